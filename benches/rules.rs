@@ -0,0 +1,51 @@
+use chrono::NaiveDate;
+use std::time::Instant;
+use swyt::{evaluate_rules, parse_rules, Verdict};
+
+const RULE_COUNT: usize = 10_000;
+const PROCESS_COUNT: usize = 2_000;
+const CYCLE_COUNT: u32 = 100;
+
+fn main() {
+    let rules_string: String = (0..RULE_COUNT)
+        .map(|i| {
+            format!(
+                "process{}={}:00~{}:30,{}:00~23:00;MO,TU,WE,TH,FR|*;SA,SU\n",
+                i,
+                i % 12,
+                i % 12,
+                12 + i % 10
+            )
+        })
+        .collect();
+
+    let start = Instant::now();
+    let rules = parse_rules(&rules_string).unwrap();
+    println!("parse_rules, {} rules: {:?}", rules.len(), start.elapsed());
+
+    let process_names: Vec<String> = (0..PROCESS_COUNT)
+        .map(|i| format!("process{}", i * 7))
+        .collect();
+    let date_time = NaiveDate::from_ymd(2020, 6, 3).and_hms(13, 15, 0);
+
+    let start = Instant::now();
+    let mut blocked_count = 0;
+    for _ in 0..CYCLE_COUNT {
+        blocked_count += process_names
+            .iter()
+            .filter(|process_name| {
+                evaluate_rules(&rules, process_name, &date_time) == Verdict::Blocked
+            })
+            .count();
+    }
+    println!(
+        "evaluate_rules, {} processes: {:?} per cycle ({} blocked)",
+        PROCESS_COUNT,
+        start.elapsed() / CYCLE_COUNT,
+        blocked_count as u32 / CYCLE_COUNT
+    );
+
+    let start = Instant::now();
+    let next_boundary = rules.next_boundary(&date_time);
+    println!("next_boundary: {:?} ({:?})", start.elapsed(), next_boundary);
+}
@@ -0,0 +1,21 @@
+fn main() {
+    #[cfg(feature = "ffi")]
+    generate_header();
+}
+
+/// Generates the C header in the build directory, building never writes to the sources. The
+/// tests of the `ffi` module check *include/swyt.h* matches it.
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_file("cbindgen.toml").expect("Couldn't read cbindgen.toml");
+    let out_dir = std::env::var("OUT_DIR").expect("Cargo sets OUT_DIR for build scripts");
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src("src/ffi.rs")
+        .generate()
+        .expect("Couldn't generate the C header")
+        .write_to_file(std::path::Path::new(&out_dir).join("swyt.h"));
+}
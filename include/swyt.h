@@ -0,0 +1,80 @@
+#ifndef SWYT_H
+#define SWYT_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum SwytVerdict {
+  SwytVerdict_Error = -1,
+  SwytVerdict_Unmatched = 0,
+  SwytVerdict_Allowed = 1,
+  SwytVerdict_Blocked = 2,
+} SwytVerdict;
+
+typedef struct SwytConfiguration SwytConfiguration;
+
+typedef struct SwytRules SwytRules;
+
+/**
+ * Parses a rules file content, returns NULL if it is invalid.
+ *
+ * # Safety
+ * `rules` must be NULL or a valid NUL-terminated string.
+ */
+struct SwytRules *swyt_parse_rules(const char *rules);
+
+/**
+ * Loads the rules file of a swyt directory, returns NULL on failure.
+ *
+ * # Safety
+ * `swyt_filepath` must be NULL or a valid NUL-terminated string.
+ */
+struct SwytRules *swyt_load_rules(const char *swyt_filepath);
+
+/**
+ * # Safety
+ * `rules` must be NULL or a pointer returned by `swyt_parse_rules` or `swyt_load_rules`
+ * that hasn't been freed yet.
+ */
+void swyt_rules_free(struct SwytRules *rules);
+
+/**
+ * Evaluates the rules for a process at the given local time, expressed in seconds since
+ * 1970-01-01 00:00:00 of the local wall clock.
+ *
+ * # Safety
+ * `rules` must be NULL or a live pointer returned by `swyt_parse_rules` or `swyt_load_rules`,
+ * `process_name` must be NULL or a valid NUL-terminated string.
+ */
+enum SwytVerdict swyt_evaluate(const struct SwytRules *rules,
+                               const char *process_name,
+                               int64_t local_time);
+
+/**
+ * Loads the configuration file of a swyt directory, returns NULL on failure.
+ *
+ * # Safety
+ * `swyt_filepath` must be NULL or a valid NUL-terminated string.
+ */
+struct SwytConfiguration *swyt_load_config(const char *swyt_filepath);
+
+/**
+ * Returns the check interval in seconds, or 0 if `config` is NULL.
+ *
+ * # Safety
+ * `config` must be NULL or a live pointer returned by `swyt_load_config`.
+ */
+uint32_t swyt_config_check_interval(const struct SwytConfiguration *config);
+
+/**
+ * # Safety
+ * `config` must be NULL or a pointer returned by `swyt_load_config` that hasn't been freed
+ * yet.
+ */
+void swyt_config_free(struct SwytConfiguration *config);
+
+#endif  /* SWYT_H */
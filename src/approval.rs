@@ -0,0 +1,552 @@
+use crate::snapshot::PolicySnapshot;
+use crate::{parse_config, parse_rules, Configuration, Rule, Rules, SwytError, WEEKDAYS};
+use log::warn;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "admin-pin")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Hash of the admin PIN, and the key derived from it signing the approvals.
+const ADMIN_PIN_FILE_NAME: &str = "admin_pin.jbb";
+const APPROVED_CONFIG_FILE_NAME: &str = "approved_config.jbb";
+const APPROVED_RULES_FILE_NAME: &str = "approved_rules.jbb";
+/// Digest of the policy whose changes were approved signed with the approval key, for the
+/// daemon to apply them.
+const CHANGES_APPROVAL_FILE_NAME: &str = "changes_approval.jbb";
+/// Salt the approval key is derived from the PIN with, along with the salt of its hash, for
+/// the key not to be the hash.
+#[cfg(feature = "admin-pin")]
+const APPROVAL_KEY_SALT: &[u8] = b"swyt approval key";
+
+/// Policy last approved, the changes loosening it being held back until approved with the
+/// admin PIN while its `loosening_approval` setting is on.
+pub struct ApprovedPolicy {
+    config_string: String,
+    configuration: Configuration,
+    rules: Rules,
+}
+
+impl ApprovedPolicy {
+    /// The configuration is the one written in `config_string`.
+    pub fn new(config_string: String, configuration: Configuration, rules: Rules) -> Self {
+        ApprovedPolicy {
+            config_string,
+            configuration,
+            rules,
+        }
+    }
+
+    pub fn configuration(&self) -> &Configuration {
+        &self.configuration
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Returns the policy to enforce from the one loaded, written in `config_string`, with the
+    /// changes loosening this policy held back, along with these changes. The settings being
+    /// changed are all held back, whether they loosen the policy can't be told. A rule removed
+    /// or loosening its approved one is held back, the approved rule being enforced instead,
+    /// while the rules tightening the policy apply right away and are approved with it.
+    ///
+    /// Nothing is held back when the policy loaded is `approved`, as checked by
+    /// `verify_changes_approval`, or when this policy has `loosening_approval` off.
+    pub fn review(
+        &mut self,
+        config_string: &str,
+        configuration: Configuration,
+        rules: Rules,
+        approved: bool,
+    ) -> (Configuration, Rules, Vec<String>) {
+        let snapshot = PolicySnapshot::new(&configuration, &rules);
+        if !self.configuration.loosening_approval() || approved {
+            *self = ApprovedPolicy::new(
+                config_string.to_string(),
+                configuration.clone(),
+                rules.clone(),
+            );
+            return (configuration, rules, vec![]);
+        }
+
+        let approved_snapshot = PolicySnapshot::new(&self.configuration, &self.rules);
+        let mut held_changes = approved_snapshot.diff_settings(&snapshot);
+        let configuration = match held_changes.is_empty() {
+            true => configuration,
+            false => self.configuration.clone(),
+        };
+
+        let redact = |rule: &Rule| self.configuration.redactor().redact(&rule.to_string());
+        let mut held_rules = vec![];
+        for approved_rule in self.rules.iter() {
+            match rules.find_written(approved_rule) {
+                None => held_changes.push(format!("Removed rule {}", redact(approved_rule))),
+                Some(rule) if loosens(approved_rule, rule) => held_changes.push(format!(
+                    "Changed rule {} to {}",
+                    redact(approved_rule),
+                    redact(rule)
+                )),
+                Some(_) => continue,
+            }
+            held_rules.push(approved_rule.clone());
+        }
+        let mut rules = rules;
+        for rule in held_rules {
+            let process_name = rule.process_name.clone();
+            if let Err(err) = rules.insert(rule) {
+                warn!(
+                    "Couldn't keep the approved rule of {}: {}",
+                    process_name, err
+                );
+            }
+        }
+        self.rules = rules.clone();
+        (configuration, rules, held_changes)
+    }
+}
+
+/// Returns whether the rule lets its processes run more than the approved one, allowing them
+/// at times the approved one doesn't, with a larger budget, without its break, or with other
+/// conditions or actions.
+pub fn loosens(approved_rule: &Rule, rule: &Rule) -> bool {
+    let longer_periods = WEEKDAYS.iter().any(|&weekday| {
+        let approved_times = approved_rule.allowed_times(weekday);
+        rule.allowed_times(weekday).iter().any(|&(begin, end)| {
+            !approved_times
+                .iter()
+                .any(|&(approved_begin, approved_end)| {
+                    approved_begin <= begin && end <= approved_end
+                })
+        })
+    });
+    let larger_budget = match (approved_rule.budget, rule.budget) {
+        (Some(approved_budget), Some(budget)) => budget.per_day() > approved_budget.per_day(),
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
+    let other_break = approved_rule.mandatory_break.is_some()
+        && rule.mandatory_break != approved_rule.mandatory_break;
+    #[cfg(feature = "timezone")]
+    let other_timezone = rule.timezone != approved_rule.timezone;
+    #[cfg(not(feature = "timezone"))]
+    let other_timezone = false;
+
+    longer_periods
+        || larger_budget
+        || other_break
+        || other_timezone
+        || rule.conditions != approved_rule.conditions
+        || rule.action != approved_rule.action
+        || rule.escalations != approved_rule.escalations
+        || rule.budget_escalations != approved_rule.budget_escalations
+}
+
+/// Reads the policy last approved, `None` when none was.
+pub fn read_approved_policy(swyt_filepath: &Path) -> Result<Option<ApprovedPolicy>, SwytError> {
+    let read = |file_name| match fs::read_to_string(swyt_filepath.join(file_name)) {
+        Ok(string) => Ok(Some(string)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(SwytError::from(err)),
+    };
+    match (
+        read(APPROVED_CONFIG_FILE_NAME)?,
+        read(APPROVED_RULES_FILE_NAME)?,
+    ) {
+        (Some(config_string), Some(rules_string)) => {
+            let configuration = parse_config(&config_string)?;
+            Ok(Some(ApprovedPolicy::new(
+                config_string,
+                configuration,
+                parse_rules(&rules_string)?,
+            )))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Records the policy as approved, its rules as they are enforced, one per line.
+pub fn write_approved_policy(
+    swyt_filepath: &Path,
+    approved_policy: &ApprovedPolicy,
+) -> Result<(), SwytError> {
+    let rules_string: String = approved_policy
+        .rules
+        .iter()
+        .map(|rule| format!("{}\n", rule))
+        .collect();
+    fs::write(
+        swyt_filepath.join(APPROVED_CONFIG_FILE_NAME),
+        &approved_policy.config_string,
+    )?;
+    fs::write(swyt_filepath.join(APPROVED_RULES_FILE_NAME), rules_string)?;
+    Ok(())
+}
+
+pub fn has_admin_pin(swyt_filepath: &Path) -> bool {
+    get_admin_pin_filepath(swyt_filepath).exists()
+}
+
+/// Sets the admin PIN approving the changes held back, `current_pin` having to be the one
+/// set before if any. Only a hash of the PIN is kept, along with the key derived from it
+/// that signs the approvals, in a file only its owner can read.
+#[cfg(feature = "admin-pin")]
+pub fn set_admin_pin(
+    swyt_filepath: &Path,
+    current_pin: Option<&str>,
+    pin: &str,
+) -> Result<(), SwytError> {
+    if has_admin_pin(swyt_filepath) {
+        verify_admin_pin(swyt_filepath, current_pin.unwrap_or_default())?;
+    }
+    if pin.is_empty() {
+        return Err(SwytError::AdminPinError("the PIN is empty".to_string()));
+    }
+
+    // The salt only has to differ from one installation to the next
+    let salt = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_le_bytes();
+    let to_admin_pin_error = |err: argon2::Error| SwytError::AdminPinError(err.to_string());
+    let hash = argon2::hash_encoded(pin.as_bytes(), &salt, &argon2::Config::default())
+        .map_err(to_admin_pin_error)?;
+    let approval_key = argon2::hash_raw(
+        pin.as_bytes(),
+        &[&salt[..], APPROVAL_KEY_SALT].concat(),
+        &argon2::Config::default(),
+    )
+    .map_err(to_admin_pin_error)?;
+    write_owner_only(
+        &get_admin_pin_filepath(swyt_filepath),
+        &format!("{}\n{}\n", hash, encode_hex(&approval_key)),
+    )
+}
+
+#[cfg(not(feature = "admin-pin"))]
+pub fn set_admin_pin(
+    _swyt_filepath: &Path,
+    _current_pin: Option<&str>,
+    _pin: &str,
+) -> Result<(), SwytError> {
+    Err(SwytError::Unsupported("set an admin PIN"))
+}
+
+/// Fails when the PIN isn't the admin PIN, or when none is set.
+#[cfg(feature = "admin-pin")]
+pub fn verify_admin_pin(swyt_filepath: &Path, pin: &str) -> Result<(), SwytError> {
+    let (hash, _) = read_admin_pin(swyt_filepath)?;
+    match argon2::verify_encoded(&hash, pin.as_bytes()) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(SwytError::AdminPinError("wrong PIN".to_string())),
+        Err(err) => Err(SwytError::AdminPinError(err.to_string())),
+    }
+}
+
+#[cfg(not(feature = "admin-pin"))]
+pub fn verify_admin_pin(_swyt_filepath: &Path, _pin: &str) -> Result<(), SwytError> {
+    Err(SwytError::Unsupported("check the admin PIN"))
+}
+
+/// Reads the hash of the admin PIN and the approval key.
+#[cfg(feature = "admin-pin")]
+fn read_admin_pin(swyt_filepath: &Path) -> Result<(String, Vec<u8>), SwytError> {
+    let admin_pin = match fs::read_to_string(get_admin_pin_filepath(swyt_filepath)) {
+        Ok(admin_pin) => admin_pin,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Err(SwytError::AdminPinError(
+                "no admin PIN is set, see swyt set-pin".to_string(),
+            ))
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let mut lines = admin_pin.lines();
+    match (lines.next(), lines.next().and_then(decode_hex)) {
+        (Some(hash), Some(approval_key)) => Ok((hash.to_string(), approval_key)),
+        _ => Err(SwytError::StateParseError),
+    }
+}
+
+/// Approves the changes held back from the policy as loaded, given the admin PIN, for the
+/// daemon to apply them. Editing the policy again before the daemon does voids the approval.
+pub fn approve_changes(
+    swyt_filepath: &Path,
+    pin: &str,
+    configuration: &Configuration,
+    rules: &Rules,
+) -> Result<(), SwytError> {
+    verify_admin_pin(swyt_filepath, pin)?;
+    let approval = sign_approval(swyt_filepath, configuration, rules)?;
+    fs::write(get_changes_approval_filepath(swyt_filepath), approval)?;
+    Ok(())
+}
+
+/// Returns whether the approval read with `read_changes_approval` approves the policy, that is
+/// it is the digest of the policy signed with the approval key. Without an admin PIN, nothing
+/// can be approved.
+pub fn verify_changes_approval(
+    swyt_filepath: &Path,
+    approval: &str,
+    configuration: &Configuration,
+    rules: &Rules,
+) -> bool {
+    match sign_approval(swyt_filepath, configuration, rules) {
+        Ok(signed_approval) => constant_time_eq(signed_approval.as_bytes(), approval.as_bytes()),
+        Err(err) => {
+            warn!("Couldn't check the approval of the changes: {}", err);
+            false
+        }
+    }
+}
+
+/// Signs the digest of the policy with the approval key, with a keyed BLAKE2b MAC.
+#[cfg(feature = "admin-pin")]
+fn sign_approval(
+    swyt_filepath: &Path,
+    configuration: &Configuration,
+    rules: &Rules,
+) -> Result<String, SwytError> {
+    let (_, approval_key) = read_admin_pin(swyt_filepath)?;
+    let digest = PolicySnapshot::new(configuration, rules).digest();
+    Ok(blake2b_simd::Params::new()
+        .key(&approval_key)
+        .hash(digest.to_string().as_bytes())
+        .to_hex()
+        .to_string())
+}
+
+#[cfg(not(feature = "admin-pin"))]
+fn sign_approval(
+    _swyt_filepath: &Path,
+    _configuration: &Configuration,
+    _rules: &Rules,
+) -> Result<String, SwytError> {
+    Err(SwytError::Unsupported("sign the approvals"))
+}
+
+/// Reads the approval of the changes of the policy, `None` when there is none.
+pub fn read_changes_approval(swyt_filepath: &Path) -> Result<Option<String>, SwytError> {
+    match fs::read_to_string(get_changes_approval_filepath(swyt_filepath)) {
+        Ok(approval) => Ok(Some(approval.trim().to_string())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Removes the approval once applied or void, does nothing when there is none.
+pub fn clear_changes_approval(swyt_filepath: &Path) -> Result<(), SwytError> {
+    match fs::remove_file(get_changes_approval_filepath(swyt_filepath)) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Writes the file, only readable and writable by its owner where permissions are supported.
+#[cfg(feature = "admin-pin")]
+fn write_owner_only(filepath: &Path, contents: &str) -> Result<(), SwytError> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to a new file
+        if filepath.exists() {
+            fs::set_permissions(filepath, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    std::io::Write::write_all(&mut options.open(filepath)?, contents.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(feature = "admin-pin")]
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(feature = "admin-pin")]
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect()
+}
+
+/// Compares in a time not depending on where the bytes differ, for the approval not to be
+/// guessed a byte at a time.
+fn constant_time_eq(bytes: &[u8], other_bytes: &[u8]) -> bool {
+    bytes.len() == other_bytes.len()
+        && bytes
+            .iter()
+            .zip(other_bytes)
+            .fold(0, |difference, (byte, other_byte)| {
+                difference | (byte ^ other_byte)
+            })
+            == 0
+}
+
+/// Returns the files of the approved policy, written by the daemon. Unlike the admin PIN,
+/// they aren't state to be removed without it.
+pub(crate) fn get_approved_policy_filepaths(swyt_filepath: &Path) -> Vec<PathBuf> {
+    [APPROVED_CONFIG_FILE_NAME, APPROVED_RULES_FILE_NAME]
+        .iter()
+        .map(|file_name| swyt_filepath.join(file_name))
+        .collect()
+}
+
+fn get_admin_pin_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(ADMIN_PIN_FILE_NAME)
+}
+
+pub(crate) fn get_changes_approval_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(CHANGES_APPROVAL_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hold_loosening_changes() {
+        let approved_rules = "game=17:00~21:00;MO budget=2h\nchess=*;SA\ntv=18:00~20:00;MO";
+        let rules = parse_rules(approved_rules).unwrap();
+        let loosened_rules = parse_rules(
+            "game=17:00~22:00;MO budget=2h\ntv=18:00~19:00;MO\nweb=*;SU\n*=*;SA fullscreen",
+        )
+        .unwrap();
+        let game = Rule::new("game".to_string(), vec![]);
+        let game_rule = |rules: &Rules| rules.find_written(&game).cloned();
+        assert!(loosens(
+            &game_rule(&rules).unwrap(),
+            &game_rule(&loosened_rules).unwrap()
+        ));
+        assert!(!loosens(
+            &game_rule(&loosened_rules).unwrap(),
+            &game_rule(&rules).unwrap()
+        ));
+        let unbudgeted_rules = parse_rules("game=17:00~21:00;MO").unwrap();
+        assert!(loosens(
+            &game_rule(&rules).unwrap(),
+            &game_rule(&unbudgeted_rules).unwrap()
+        ));
+
+        let config_string = "check_interval=60\nloosening_approval=true";
+        let mut approved_policy = ApprovedPolicy::new(
+            config_string.to_string(),
+            parse_config(config_string).unwrap(),
+            rules,
+        );
+        let loosened_config_string = "check_interval=120\nloosening_approval=false";
+        let (configuration, enforced_rules, held_changes) = approved_policy.review(
+            loosened_config_string,
+            parse_config(loosened_config_string).unwrap(),
+            loosened_rules,
+            false,
+        );
+        assert_eq!(configuration.check_interval(), 60);
+        assert!(configuration.loosening_approval());
+        assert_eq!(
+            held_changes,
+            [
+                "Changed check_interval from 60 to 120",
+                "Changed loosening_approval from true to false",
+                "Changed rule game=17:00~21:00;MO budget=2h to game=17:00~22:00;MO budget=2h",
+                "Removed rule chess=*;SA",
+            ]
+        );
+        let mut enforced_rule_lines: Vec<String> =
+            enforced_rules.iter().map(Rule::to_string).collect();
+        enforced_rule_lines.sort();
+        assert_eq!(
+            enforced_rule_lines,
+            [
+                "*=*;SA fullscreen",
+                "chess=*;SA",
+                "game=17:00~21:00;MO budget=2h",
+                "tv=18:00~19:00;MO",
+                "web=*;SU",
+            ]
+        );
+        assert_eq!(approved_policy.rules().len(), 5);
+
+        // Once approved, the changes apply
+        let loosened_rules = parse_rules("game=17:00~22:00;MO").unwrap();
+        let (configuration, enforced_rules, held_changes) = approved_policy.review(
+            loosened_config_string,
+            parse_config(loosened_config_string).unwrap(),
+            loosened_rules,
+            true,
+        );
+        assert!(held_changes.is_empty());
+        assert!(!configuration.loosening_approval());
+        assert_eq!(enforced_rules.len(), 1);
+        assert!(!approved_policy.configuration().loosening_approval());
+    }
+
+    #[test]
+    #[cfg(feature = "admin-pin")]
+    fn approve_with_admin_pin() {
+        let swyt_filepath =
+            std::env::temp_dir().join(format!("swyt-approval-{}", std::process::id()));
+        fs::create_dir_all(&swyt_filepath).unwrap();
+        let configuration = parse_config("loosening_approval=true").unwrap();
+        let rules = parse_rules("game=17:00~21:00;MO,TU budget=2h").unwrap();
+
+        assert!(read_approved_policy(&swyt_filepath).unwrap().is_none());
+        let approved_policy = ApprovedPolicy::new(
+            "loosening_approval=true".to_string(),
+            parse_config("loosening_approval=true").unwrap(),
+            rules.clone(),
+        );
+        write_approved_policy(&swyt_filepath, &approved_policy).unwrap();
+        let approved_policy = read_approved_policy(&swyt_filepath).unwrap().unwrap();
+        assert!(approved_policy.configuration().loosening_approval());
+        assert_eq!(
+            approved_policy.rules().iter().next().unwrap().to_string(),
+            "game=17:00~21:00;MO,TU budget=2h"
+        );
+
+        assert!(approve_changes(&swyt_filepath, "1234", &configuration, &rules).is_err());
+        set_admin_pin(&swyt_filepath, None, "1234").unwrap();
+        assert!(set_admin_pin(&swyt_filepath, Some("0000"), "5678").is_err());
+        assert!(approve_changes(&swyt_filepath, "0000", &configuration, &rules).is_err());
+        assert_eq!(read_changes_approval(&swyt_filepath).unwrap(), None);
+        approve_changes(&swyt_filepath, "1234", &configuration, &rules).unwrap();
+        let approval = read_changes_approval(&swyt_filepath).unwrap().unwrap();
+        assert!(verify_changes_approval(
+            &swyt_filepath,
+            &approval,
+            &configuration,
+            &rules
+        ));
+        // The digest of the policy alone doesn't approve it, nor does an approval of another one
+        let digest = PolicySnapshot::new(&configuration, &rules).digest();
+        assert!(!verify_changes_approval(
+            &swyt_filepath,
+            &digest.to_string(),
+            &configuration,
+            &rules
+        ));
+        let other_rules = parse_rules("game=*;MO").unwrap();
+        assert!(!verify_changes_approval(
+            &swyt_filepath,
+            &approval,
+            &configuration,
+            &other_rules
+        ));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = fs::metadata(get_admin_pin_filepath(&swyt_filepath)).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+        clear_changes_approval(&swyt_filepath).unwrap();
+        clear_changes_approval(&swyt_filepath).unwrap();
+        assert_eq!(read_changes_approval(&swyt_filepath).unwrap(), None);
+        fs::remove_dir_all(&swyt_filepath).unwrap();
+    }
+}
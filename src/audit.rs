@@ -0,0 +1,290 @@
+use crate::{get_config_filepath, get_rules_filepath, PolicySnapshot, SwytError};
+use chrono::NaiveDateTime;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const AUDIT_FILE_NAME: &str = "audit.jbb";
+const AUDIT_DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Who changed the policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Actor {
+    /// A user running swyt, such as `swyt init`.
+    User(String),
+    /// A front end changing the policy on behalf of someone, named by the front end.
+    Remote(String),
+    /// The owner of the policy files, edited outside of swyt, by user id.
+    FileOwner(u32),
+    Unknown,
+}
+
+impl Actor {
+    /// The user running this process, with the user who ran it through sudo.
+    pub fn current_user() -> Self {
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("LOGNAME"))
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok();
+        match (user, std::env::var("SUDO_USER").ok()) {
+            (Some(user), Some(sudo_user)) if user != sudo_user => {
+                Actor::User(format!("{} (sudo as {})", sudo_user, user))
+            }
+            (Some(user), _) | (None, Some(user)) => Actor::User(user),
+            (None, None) => Actor::Unknown,
+        }
+    }
+
+    /// The owner of the most recently modified policy file, who edited it as far as can be
+    /// told.
+    pub fn policy_owner(swyt_filepath: &Path) -> Self {
+        let modified_metadata = vec![
+            get_config_filepath(swyt_filepath),
+            get_rules_filepath(swyt_filepath),
+        ]
+        .into_iter()
+        .filter_map(|filepath| fs::metadata(filepath.ok()?).ok())
+        .max_by_key(|metadata| metadata.modified().ok());
+        match modified_metadata {
+            #[cfg(unix)]
+            Some(metadata) => Actor::FileOwner(std::os::unix::fs::MetadataExt::uid(&metadata)),
+            _ => Actor::Unknown,
+        }
+    }
+
+    fn parse(actor: &str) -> Result<Self, SwytError> {
+        match actor.split_once(':') {
+            Some(("user", user)) => Ok(Actor::User(user.to_string())),
+            Some(("remote", remote)) => Ok(Actor::Remote(remote.to_string())),
+            Some(("owner", uid)) => Ok(Actor::FileOwner(
+                u32::from_str(uid).map_err(|_| SwytError::StateParseError)?,
+            )),
+            None if actor == "unknown" => Ok(Actor::Unknown),
+            _ => Err(SwytError::StateParseError),
+        }
+    }
+
+    /// The actor as written in the audit log, which splits the entries on `;`.
+    fn to_field(&self) -> String {
+        let field = match self {
+            Actor::User(user) => format!("user:{}", user),
+            Actor::Remote(remote) => format!("remote:{}", remote),
+            Actor::FileOwner(uid) => format!("owner:{}", uid),
+            Actor::Unknown => "unknown".to_string(),
+        };
+        field.replace([';', '\n'], " ")
+    }
+}
+
+impl Display for Actor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Actor::User(user) => write!(f, "{}", user),
+            Actor::Remote(remote) => write!(f, "{} (remote)", remote),
+            Actor::FileOwner(uid) => write!(f, "owner of the files (uid {})", uid),
+            Actor::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Change of the policy recorded in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    date_time: NaiveDateTime,
+    actor: Actor,
+    /// Digest of the policy after the change, telling whether a policy was recorded.
+    policy_digest: u64,
+    change: String,
+}
+
+impl AuditEntry {
+    pub fn date_time(&self) -> &NaiveDateTime {
+        &self.date_time
+    }
+
+    pub fn actor(&self) -> &Actor {
+        &self.actor
+    }
+
+    pub fn change(&self) -> &str {
+        &self.change
+    }
+}
+
+impl Display for AuditEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}: {}",
+            self.date_time.format(AUDIT_DATE_TIME_FORMAT),
+            self.actor,
+            self.change
+        )
+    }
+}
+
+/// Appends the changes from the `previous` policy to the `current` one to the audit log,
+/// unless the current one is already the last recorded. Without a previous policy, only the
+/// policy having changed since the last entry is recorded, the first time that it is now
+/// audited.
+pub fn audit_policy(
+    swyt_filepath: &Path,
+    actor: &Actor,
+    previous: Option<&PolicySnapshot>,
+    current: &PolicySnapshot,
+    date_time: NaiveDateTime,
+) -> Result<(), SwytError> {
+    let entries = read_audit_log(swyt_filepath)?;
+    let policy_digest = current.digest();
+    if entries
+        .last()
+        .is_some_and(|entry| entry.policy_digest == policy_digest)
+    {
+        return Ok(());
+    }
+
+    let mut changes = previous.map_or(vec![], |previous| previous.diff(current));
+    if changes.is_empty() && entries.is_empty() {
+        changes.push("Started auditing the policy".to_string());
+    } else if changes.is_empty() {
+        changes.push("Changed the policy while swyt wasn't watching".to_string());
+    }
+    let mut audit = String::new();
+    for change in changes {
+        audit.push_str(&format!(
+            "{};{};{:016x};{}\n",
+            date_time.format(AUDIT_DATE_TIME_FORMAT),
+            actor.to_field(),
+            policy_digest,
+            change.replace('\n', " ")
+        ));
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_audit_filepath(swyt_filepath))?
+        .write_all(audit.as_bytes())?;
+    Ok(())
+}
+
+/// Reads the audit log, the oldest entry first, empty when nothing was recorded yet.
+pub fn read_audit_log(swyt_filepath: &Path) -> Result<Vec<AuditEntry>, SwytError> {
+    let audit_string = match fs::read_to_string(get_audit_filepath(swyt_filepath)) {
+        Ok(audit_string) => audit_string,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+
+    audit_string.lines().map(parse_audit_entry).collect()
+}
+
+/// Removes the changes recorded before `before` from the audit log, returning how many were.
+/// The latest one is kept whatever its date, telling which policy was recorded last.
+pub fn prune_audit_log(swyt_filepath: &Path, before: &NaiveDateTime) -> Result<usize, SwytError> {
+    let audit_string = match fs::read_to_string(get_audit_filepath(swyt_filepath)) {
+        Ok(audit_string) => audit_string,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+
+    let lines: Vec<&str> = audit_string.lines().collect();
+    let mut kept_audit = String::new();
+    let mut pruned = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if index + 1 < lines.len() && parse_audit_entry(line)?.date_time < *before {
+            pruned += 1;
+        } else {
+            kept_audit.push_str(line);
+            kept_audit.push('\n');
+        }
+    }
+    if pruned > 0 {
+        fs::write(get_audit_filepath(swyt_filepath), kept_audit)?;
+    }
+    Ok(pruned)
+}
+
+/// Removes every change recorded in the audit log, returning how many there were. The next
+/// change starts auditing the policy anew.
+pub fn clear_audit_log(swyt_filepath: &Path) -> Result<usize, SwytError> {
+    let cleared = read_audit_log(swyt_filepath)?.len();
+    match fs::remove_file(get_audit_filepath(swyt_filepath)) {
+        Ok(()) => Ok(cleared),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn parse_audit_entry(line: &str) -> Result<AuditEntry, SwytError> {
+    let fields: Vec<&str> = line.splitn(4, ';').collect();
+    match fields[..] {
+        [date_time, actor, policy_digest, change] => Ok(AuditEntry {
+            date_time: NaiveDateTime::parse_from_str(date_time, AUDIT_DATE_TIME_FORMAT)
+                .map_err(|_| SwytError::StateParseError)?,
+            actor: Actor::parse(actor)?,
+            policy_digest: u64::from_str_radix(policy_digest, 16)
+                .map_err(|_| SwytError::StateParseError)?,
+            change: change.to_string(),
+        }),
+        _ => Err(SwytError::StateParseError),
+    }
+}
+
+#[cfg(any(feature = "native", feature = "process"))]
+pub(crate) fn now() -> NaiveDateTime {
+    chrono::Local::now().naive_local()
+}
+
+/// Without the clock of chrono the time zone is unknown, so the time is in UTC.
+#[cfg(not(any(feature = "native", feature = "process")))]
+pub(crate) fn now() -> NaiveDateTime {
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    NaiveDateTime::from_timestamp(since_epoch.as_secs() as i64, 0)
+}
+
+pub(crate) fn get_audit_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(AUDIT_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_config, parse_rules};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn audit_log() {
+        let snapshot = PolicySnapshot::new(
+            &parse_config("check_interval=120").unwrap(),
+            &parse_rules("game=18:00~20:00;MO").unwrap(),
+        );
+        let swyt_filepath =
+            std::env::temp_dir().join(format!("swyt-audit-log-{}", std::process::id()));
+        std::fs::create_dir_all(&swyt_filepath).unwrap();
+        std::fs::copy(
+            "./test_data/audit/audit.jbb",
+            swyt_filepath.join("audit.jbb"),
+        )
+        .unwrap();
+        let entries = read_audit_log(&swyt_filepath).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].actor(), &Actor::User("alice".to_string()));
+        assert_eq!(
+            entries[2].to_string(),
+            "2020-06-02T18:00:00 owner of the files (uid 1000): Changed rule game=18:00~19:00;MO to game=18:00~20:00;MO"
+        );
+        assert!(read_audit_log(Path::new("./test_data/no_rule"))
+            .unwrap()
+            .is_empty());
+
+        // The policy is the last one recorded, so nothing is appended
+        let date_time = NaiveDate::from_ymd(2020, 6, 3).and_hms(9, 0, 0);
+        audit_policy(&swyt_filepath, &Actor::Unknown, None, &snapshot, date_time).unwrap();
+        assert_eq!(read_audit_log(&swyt_filepath).unwrap(), entries);
+        std::fs::remove_dir_all(&swyt_filepath).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+use crate::MandatoryBreak;
+use std::time::{Duration, Instant};
+#[cfg(any(feature = "native", feature = "process"))]
+use {
+    crate::{EnforcementState, Rule},
+    std::sync::PoisonError,
+};
+
+/// Time the processes of a rule have been running continuously for, and the break they are
+/// on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BreakSession {
+    running_since: Instant,
+    last_seen: Instant,
+    break_until: Option<Instant>,
+}
+
+impl BreakSession {
+    /// Returns the session after the processes of the rule were seen allowed at `now`. Not
+    /// seeing them for as long as the break lasts counts as a break, starting a new session.
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn next(
+        session: Option<&BreakSession>,
+        mandatory_break: MandatoryBreak,
+        now: Instant,
+    ) -> BreakSession {
+        let minutes = |minutes: u32| Duration::from_secs(u64::from(minutes) * 60);
+        match session {
+            Some(session) if session.is_on_break(now) => *session,
+            Some(session)
+                if session.break_until.is_none()
+                    && now.duration_since(session.last_seen)
+                        < minutes(mandatory_break.duration()) =>
+            {
+                let break_until = (now.duration_since(session.running_since)
+                    >= minutes(mandatory_break.after()))
+                .then(|| now + minutes(mandatory_break.duration()));
+                BreakSession {
+                    last_seen: now,
+                    break_until,
+                    ..*session
+                }
+            }
+            _ => BreakSession {
+                running_since: now,
+                last_seen: now,
+                break_until: None,
+            },
+        }
+    }
+
+    pub(crate) fn is_on_break(&self, now: Instant) -> bool {
+        self.break_until
+            .is_some_and(|break_until| now < break_until)
+    }
+}
+
+/// Returns whether the processes of the rule, allowed by its schedule, are on a mandatory
+/// break, tracking how long they have been running for in the enforcement state.
+#[cfg(any(feature = "native", feature = "process"))]
+pub(crate) fn is_on_break(enforcement_state: &EnforcementState, rule: &Rule) -> bool {
+    let mandatory_break = match rule.mandatory_break() {
+        Some(mandatory_break) => mandatory_break,
+        None => return false,
+    };
+
+    let now = Instant::now();
+    let mut sessions = enforcement_state
+        .break_sessions
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let previous = sessions.get(rule.process_name());
+    let was_on_break = previous.is_some_and(|session| session.is_on_break(now));
+    let session = BreakSession::next(previous, mandatory_break, now);
+    if !was_on_break && session.is_on_break(now) {
+        log::info!(
+            "The processes of {} ran for {} minutes, blocking them for a {} minute break",
+            rule.process_name(),
+            mandatory_break.after(),
+            mandatory_break.duration()
+        );
+    }
+    sessions.insert(rule.process_name().to_string(), session);
+    session.is_on_break(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_rules;
+
+    #[test]
+    fn mandatory_break() {
+        let rules = parse_rules(
+            "preset:evening=17:00~21:00;MO break=60/10\n\
+             game=*;SA break=45/15\n\
+             chess=preset:evening",
+        )
+        .unwrap();
+        let mandatory_break = rules.get_rule("game").unwrap().mandatory_break().unwrap();
+        assert_eq!(mandatory_break, MandatoryBreak::new(45, 15).unwrap());
+        assert_eq!(
+            rules.get_rule("chess").unwrap().mandatory_break(),
+            MandatoryBreak::new(60, 10)
+        );
+        assert!(rules
+            .get_rule("game")
+            .unwrap()
+            .to_string()
+            .ends_with(" break=45/15"));
+        assert!(parse_rules("game=*;SA break=45/0").is_err());
+        assert!(parse_rules("game=*;SA break=45").is_err());
+
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+        let start = Instant::now();
+        let mut session = BreakSession::next(None, mandatory_break, start);
+        for elapsed in [10, 20, 30, 40] {
+            session = BreakSession::next(Some(&session), mandatory_break, start + minutes(elapsed));
+            assert!(!session.is_on_break(start + minutes(elapsed)));
+        }
+        session = BreakSession::next(Some(&session), mandatory_break, start + minutes(45));
+        assert!(session.is_on_break(start + minutes(50)));
+        assert!(!session.is_on_break(start + minutes(60)));
+
+        // Not running for as long as the break lasts is a break
+        let mut session = BreakSession::next(None, mandatory_break, start);
+        session = BreakSession::next(Some(&session), mandatory_break, start + minutes(30));
+        session = BreakSession::next(Some(&session), mandatory_break, start + minutes(50));
+        session = BreakSession::next(Some(&session), mandatory_break, start + minutes(60));
+        assert!(!session.is_on_break(start + minutes(60)));
+    }
+}
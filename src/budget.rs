@@ -0,0 +1,305 @@
+use crate::SwytError;
+use chrono::NaiveDate;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+#[cfg(any(feature = "native", feature = "process"))]
+use {
+    crate::{EnforcementState, Rule},
+    std::sync::PoisonError,
+};
+
+const BUDGET_USAGE_FILE_NAME: &str = "budget_usage.jbb";
+const BUDGET_USAGE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Time the processes of a rule ran for on a day, counted against its budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetUsage {
+    date: NaiveDate,
+    process_name: String,
+    used: Duration,
+}
+
+impl BudgetUsage {
+    pub fn new(date: NaiveDate, process_name: String, used: Duration) -> Self {
+        BudgetUsage {
+            date,
+            process_name,
+            used,
+        }
+    }
+
+    /// The day, in the time zone of the rule.
+    pub fn date(&self) -> &NaiveDate {
+        &self.date
+    }
+
+    pub fn process_name(&self) -> &str {
+        &self.process_name
+    }
+
+    pub fn used(&self) -> Duration {
+        self.used
+    }
+
+    /// Counts the time a process seen running on `date` by the check at `checked_at` ran for
+    /// since the previous check seeing the processes of the rule running, or since its start
+    /// when later. The processes seen by a check overlapping, only the time none of them was
+    /// counted for is. A check more than twice `check_interval` after the previous one doesn't
+    /// count the time in between, the processes or the daemon having stopped then.
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn account(
+        &mut self,
+        date: NaiveDate,
+        counted: Option<CountedTime>,
+        started_at: Option<Instant>,
+        checked_at: Instant,
+        check_interval: Duration,
+    ) -> CountedTime {
+        if date != self.date {
+            self.date = date;
+            self.used = Duration::ZERO;
+        }
+        let (since, counted_from) = match counted {
+            Some(counted) if counted.checked_at == checked_at => (counted.since, counted.from),
+            Some(counted)
+                if checked_at.duration_since(counted.checked_at) <= 2 * check_interval =>
+            {
+                (Some(counted.checked_at), checked_at)
+            }
+            _ => (None, checked_at),
+        };
+        // Without a previous check, a process is counted from its start at most a check before,
+        // the processes having been blocked or the daemon stopped until then
+        let from = match started_at {
+            Some(started_at) => since
+                .or_else(|| checked_at.checked_sub(check_interval))
+                .map_or(started_at, |since| since.max(started_at))
+                .min(checked_at),
+            None => since.unwrap_or(checked_at),
+        };
+        if from < counted_from {
+            self.used += counted_from - from;
+        }
+        CountedTime {
+            since,
+            from: from.min(counted_from),
+            checked_at,
+        }
+    }
+}
+
+/// Time counted against a budget by the last check seeing the processes of its rule running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CountedTime {
+    /// When the check before saw them running, the time until then being counted by it.
+    since: Option<Instant>,
+    /// Since when the processes seen by the check were counted as running.
+    from: Instant,
+    checked_at: Instant,
+}
+
+/// Returns how long the processes of the rule, allowed by its schedule, ran for since they
+/// used its budget for `date`, `None` while they haven't. The time they have been running for
+/// is counted in the enforcement state, for the `budget@` escalations to follow it, as the
+/// check at `checked_at`, made `check_interval` after the previous one, sees one of them,
+/// started at `started_at` when known. The processes a pattern matches share the budget of
+/// its rule.
+#[cfg(any(feature = "native", feature = "process"))]
+pub(crate) fn budget_overrun(
+    enforcement_state: &EnforcementState,
+    rule: &Rule,
+    date: NaiveDate,
+    checked_at: Instant,
+    check_interval: Duration,
+    started_at: Option<Instant>,
+) -> Option<Duration> {
+    let per_day = rule.budget()?.per_day();
+
+    let mut usages = enforcement_state
+        .budget_usages
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let (usage, counted) = usages
+        .entry(rule.process_name().to_string())
+        .or_insert_with(|| {
+            let usage = BudgetUsage::new(date, rule.process_name().to_string(), Duration::ZERO);
+            (usage, None)
+        });
+    let was_over_budget = usage.date == date && usage.used >= per_day;
+    *counted = Some(usage.account(date, *counted, started_at, checked_at, check_interval));
+    if !was_over_budget && usage.used >= per_day {
+        log::info!(
+            "The processes of {} used their budget of {} minutes for today",
+            rule.process_name(),
+            per_day.as_secs() / 60
+        );
+    }
+    usage.used.checked_sub(per_day)
+}
+
+pub fn write_budget_usages(swyt_filepath: &Path, usages: &[BudgetUsage]) -> Result<(), SwytError> {
+    let usages: Vec<String> = usages
+        .iter()
+        .map(|usage| {
+            format!(
+                "{};{};{}\n",
+                usage.date.format(BUDGET_USAGE_DATE_FORMAT),
+                usage.used.as_secs(),
+                usage.process_name
+            )
+        })
+        .collect();
+    fs::write(get_budget_usage_filepath(swyt_filepath), usages.concat())?;
+    Ok(())
+}
+
+/// Reads the usages last written by the daemon, empty when none were.
+pub fn read_budget_usages(swyt_filepath: &Path) -> Result<Vec<BudgetUsage>, SwytError> {
+    let usages_string = match fs::read_to_string(get_budget_usage_filepath(swyt_filepath)) {
+        Ok(usages_string) => usages_string,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+
+    usages_string
+        .lines()
+        .map(|line| match line.splitn(3, ';').collect::<Vec<_>>()[..] {
+            [date, used, process_name] => Ok(BudgetUsage {
+                date: NaiveDate::parse_from_str(date, BUDGET_USAGE_DATE_FORMAT)
+                    .map_err(|_| SwytError::StateParseError)?,
+                process_name: process_name.to_string(),
+                used: Duration::from_secs(
+                    u64::from_str(used).map_err(|_| SwytError::StateParseError)?,
+                ),
+            }),
+            _ => Err(SwytError::StateParseError),
+        })
+        .collect()
+}
+
+pub(crate) fn get_budget_usage_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(BUDGET_USAGE_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_rules, Action};
+
+    #[test]
+    fn daily_budget() {
+        let rules = parse_rules("game=16:00~22:00;MO budget=2h\nchess=*;SA budget=90").unwrap();
+        let game_rule = rules.get_rule("game").unwrap();
+        assert_eq!(
+            game_rule.budget().unwrap().per_day(),
+            Duration::from_secs(2 * 60 * 60)
+        );
+        assert!(game_rule.to_string().ends_with(" budget=2h"));
+        assert!(rules
+            .get_rule("chess")
+            .unwrap()
+            .to_string()
+            .ends_with(" budget=90m"));
+        assert!(parse_rules("game=*;SA budget=0m").is_err());
+        assert!(parse_rules("game=*;SA budget=2d").is_err());
+        assert!(parse_rules("*=*;SA fullscreen budget=2h").is_err());
+        assert_eq!(
+            *game_rule.budget_action(Duration::from_secs(3600)),
+            Action::Kill
+        );
+
+        let rules = parse_rules(
+            "preset:limited=*;SA budget=1h budget@0=notify budget@15=kill budget@5=throttle\n\
+             game=preset:limited\n\
+             chess=preset:limited budget@10=suspend",
+        )
+        .unwrap();
+        let game_rule = rules.get_rule("game").unwrap();
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+        assert_eq!(*game_rule.budget_action(minutes(0)), Action::Notify);
+        assert_eq!(*game_rule.budget_action(minutes(14)), Action::Throttle);
+        assert_eq!(*game_rule.budget_action(minutes(15)), Action::Kill);
+        assert!(game_rule
+            .to_string()
+            .ends_with(" budget=1h budget@0=notify budget@5=throttle budget@15=kill"));
+        let chess_rule = rules.get_rule("chess").unwrap();
+        assert_eq!(*chess_rule.budget_action(minutes(0)), Action::Kill);
+        assert_eq!(*chess_rule.budget_action(minutes(10)), Action::Suspend);
+        assert!(parse_rules("game=*;SA budget=1h budget@soon=kill").is_err());
+        assert!(parse_rules("game=*;SA budget=1h budget@5=pause").is_err());
+
+        let usages = read_budget_usages(Path::new("./test_data/budget_usage")).unwrap();
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages[1].process_name(), "web browser");
+        let mut usage = usages[0].clone();
+        assert_eq!(usage.used(), Duration::from_secs(90 * 60));
+
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let seconds = Duration::from_secs;
+        let start = Instant::now();
+        // Only the time between two checks close enough is counted
+        let counted = usage.account(monday, None, None, start, seconds(60));
+        let counted = usage.account(
+            monday,
+            Some(counted),
+            None,
+            start + seconds(60),
+            seconds(60),
+        );
+        let counted = usage.account(
+            monday,
+            Some(counted),
+            None,
+            start + seconds(600),
+            seconds(60),
+        );
+        assert_eq!(usage.used(), seconds(91 * 60));
+        // A process started since the previous check is counted from its start, the processes
+        // seen by the same check once
+        let checked_at = start + seconds(660);
+        let counted = usage.account(
+            monday,
+            Some(counted),
+            Some(start + seconds(650)),
+            checked_at,
+            seconds(60),
+        );
+        let counted = usage.account(
+            monday,
+            Some(counted),
+            Some(start + seconds(630)),
+            checked_at,
+            seconds(60),
+        );
+        usage.account(monday, Some(counted), None, checked_at, seconds(60));
+        assert_eq!(usage.used(), seconds(91 * 60 + 60));
+        // Without a previous check, a process is counted from its start at most a check before
+        let counted = usage.account(
+            monday,
+            None,
+            Some(start + seconds(700)),
+            start + seconds(720),
+            seconds(60),
+        );
+        usage.account(
+            monday,
+            Some(counted),
+            Some(start),
+            start + seconds(3000),
+            seconds(60),
+        );
+        assert_eq!(usage.used(), seconds(91 * 60 + 140));
+        // A new day starts over
+        usage.account(
+            monday.succ(),
+            None,
+            Some(start),
+            start + seconds(3060),
+            seconds(60),
+        );
+        assert_eq!(usage.used(), seconds(60));
+    }
+}
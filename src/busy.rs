@@ -0,0 +1,332 @@
+use crate::{parse_days_of_week, SwytError};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const ICAL_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+const ICAL_DATE_FORMAT: &str = "%Y%m%d";
+
+/// Calendar telling when its owner is busy, read from an iCalendar file or a directory of
+/// them, as synchronized from a CalDAV server or exported from an online calendar. It is
+/// read again when any of its files changes.
+pub struct BusyCalendar {
+    path: PathBuf,
+    modified_time: Option<SystemTime>,
+    events: Vec<BusyEvent>,
+}
+
+impl BusyCalendar {
+    pub fn new(path: PathBuf) -> Self {
+        BusyCalendar {
+            path,
+            modified_time: None,
+            events: vec![],
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns whether a busy event is happening at the given local time, `utc_date_time`
+    /// being the same time in UTC, to which the events with UTC times are compared.
+    pub fn is_busy(
+        &mut self,
+        date_time: &NaiveDateTime,
+        utc_date_time: &NaiveDateTime,
+    ) -> Result<bool, SwytError> {
+        let modified_time = latest_modified_time(&self.path)?;
+        if self.modified_time.is_none() || modified_time != self.modified_time {
+            self.events = read_busy_events(&self.path)?;
+            self.modified_time = modified_time;
+        }
+
+        Ok(self.events.iter().any(|event| {
+            event.contains(if event.is_utc {
+                utc_date_time
+            } else {
+                date_time
+            })
+        }))
+    }
+}
+
+/// Event during which its owner is busy, possibly recurring daily or weekly.
+#[derive(Debug)]
+struct BusyEvent {
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    /// The times are in UTC, otherwise they are in the local time.
+    is_utc: bool,
+    recurrence: Option<Recurrence>,
+    excluded_starts: HashSet<NaiveDateTime>,
+}
+
+#[derive(Debug)]
+struct Recurrence {
+    is_weekly: bool,
+    interval: u32,
+    days_of_week: HashSet<Weekday>,
+    until: Option<NaiveDateTime>,
+    count: Option<u32>,
+}
+
+impl BusyEvent {
+    fn contains(&self, date_time: &NaiveDateTime) -> bool {
+        let duration = self.end - self.start;
+        let first_date = (*date_time - duration).date();
+        std::iter::successors(Some(first_date), |date| date.succ_opt())
+            .take_while(|date| date <= &date_time.date())
+            .map(|date| date.and_time(self.start.time()))
+            .any(|start| {
+                start <= *date_time
+                    && *date_time < start + duration
+                    && self.occurs_at(&start)
+                    && !self.excluded_starts.contains(&start)
+            })
+    }
+
+    fn occurs_at(&self, start: &NaiveDateTime) -> bool {
+        let recurrence = match &self.recurrence {
+            Some(recurrence) => recurrence,
+            None => return *start == self.start,
+        };
+        if *start < self.start || recurrence.until.is_some_and(|until| *start > until) {
+            return false;
+        }
+
+        match recurrence.count {
+            Some(count) => self
+                .occurrence_dates(recurrence)
+                .take(count as usize)
+                .any(|date| date == start.date()),
+            None => recurrence.occurs_on(self.start.date(), start.date()),
+        }
+    }
+
+    /// Returns the dates of the occurrences, from the start of the event.
+    fn occurrence_dates<'a>(
+        &'a self,
+        recurrence: &'a Recurrence,
+    ) -> impl Iterator<Item = NaiveDate> + 'a {
+        std::iter::successors(Some(self.start.date()), |date| date.succ_opt())
+            .filter(move |&date| recurrence.occurs_on(self.start.date(), date))
+    }
+}
+
+impl Recurrence {
+    fn occurs_on(&self, first_date: NaiveDate, date: NaiveDate) -> bool {
+        let interval = i64::from(self.interval.max(1));
+        if !self.is_weekly {
+            return (date - first_date).num_days() % interval == 0
+                && (self.days_of_week.is_empty() || self.days_of_week.contains(&date.weekday()));
+        }
+
+        let first_week =
+            first_date - Duration::days(first_date.weekday().num_days_from_monday().into());
+        (date - first_week).num_weeks() % interval == 0
+            && if self.days_of_week.is_empty() {
+                date.weekday() == first_date.weekday()
+            } else {
+                self.days_of_week.contains(&date.weekday())
+            }
+    }
+}
+
+/// Returns when the calendar file, or the directory or any of its calendar files, was last
+/// modified.
+fn latest_modified_time(path: &Path) -> Result<Option<SystemTime>, SwytError> {
+    let mut modified_time = fs::metadata(path)?.modified().ok();
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let entry_modified_time = entry?.metadata()?.modified().ok();
+            modified_time = modified_time.max(entry_modified_time);
+        }
+    }
+
+    Ok(modified_time)
+}
+
+fn read_busy_events(path: &Path) -> Result<Vec<BusyEvent>, SwytError> {
+    if !path.is_dir() {
+        return Ok(parse_busy_events(&fs::read_to_string(path)?));
+    }
+
+    let mut events = vec![];
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path
+            .extension()
+            .is_some_and(|extension| extension == "ics")
+        {
+            events.extend(parse_busy_events(&fs::read_to_string(entry_path)?));
+        }
+    }
+
+    Ok(events)
+}
+
+/// Parses the events of an iCalendar during which its owner is busy, the transparent and
+/// cancelled ones being left out. The time zones of the times aren't known, the times
+/// which aren't in UTC are considered local. Events that can't be parsed are skipped.
+fn parse_busy_events(calendar: &str) -> Vec<BusyEvent> {
+    let mut events = vec![];
+    let mut properties: Option<Vec<(String, String)>> = None;
+    for line in unfold_lines(calendar) {
+        match line.as_str() {
+            "BEGIN:VEVENT" => properties = Some(vec![]),
+            "END:VEVENT" => {
+                if let Some(event) = properties.take().and_then(|event| parse_busy_event(&event)) {
+                    events.push(event);
+                }
+            }
+            _ => {
+                if let (Some(properties), Some((name, value))) =
+                    (properties.as_mut(), line.split_once(':'))
+                {
+                    properties.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+    }
+
+    events
+}
+
+/// Joins the lines folded on several lines, the continuations starting with a space or a
+/// tab.
+fn unfold_lines(calendar: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in calendar.lines() {
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(continuation), Some(last_line)) => last_line.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+fn parse_busy_event(properties: &[(String, String)]) -> Option<BusyEvent> {
+    let property = |property_name: &str| {
+        properties.iter().find_map(|(name, value)| {
+            (name.split(';').next() == Some(property_name)).then_some(value.as_str())
+        })
+    };
+    if property("TRANSP") == Some("TRANSPARENT") || property("STATUS") == Some("CANCELLED") {
+        return None;
+    }
+
+    let (start, is_utc) = parse_date_time(property("DTSTART")?)?;
+    let end = match property("DTEND") {
+        Some(end) => parse_date_time(end)?.0,
+        // Without an end, an event on a whole day lasts that day
+        None if is_date(property("DTSTART")?) => start + Duration::days(1),
+        None => start,
+    };
+    let recurrence = match property("RRULE") {
+        Some(recurrence) => Some(parse_recurrence(recurrence)?),
+        None => None,
+    };
+    let excluded_starts = properties
+        .iter()
+        .filter(|(name, _)| name.split(';').next() == Some("EXDATE"))
+        .flat_map(|(_, value)| value.split(','))
+        .filter_map(parse_date_time)
+        .map(|(excluded_start, _)| excluded_start)
+        .collect();
+
+    Some(BusyEvent {
+        start,
+        end,
+        is_utc,
+        recurrence,
+        excluded_starts,
+    })
+}
+
+/// Parses a date or a date and time, also returning whether it is in UTC.
+fn parse_date_time(date_time: &str) -> Option<(NaiveDateTime, bool)> {
+    if let Some(utc_date_time) = date_time.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc_date_time, ICAL_DATE_TIME_FORMAT)
+            .ok()
+            .map(|date_time| (date_time, true));
+    }
+
+    NaiveDateTime::parse_from_str(date_time, ICAL_DATE_TIME_FORMAT)
+        .or_else(|_| {
+            NaiveDate::parse_from_str(date_time, ICAL_DATE_FORMAT).map(|date| date.and_hms(0, 0, 0))
+        })
+        .ok()
+        .map(|date_time| (date_time, false))
+}
+
+fn is_date(date_time: &str) -> bool {
+    NaiveDate::parse_from_str(date_time, ICAL_DATE_FORMAT).is_ok()
+}
+
+/// Parses a daily or weekly recurrence rule, the other ones aren't supported.
+fn parse_recurrence(recurrence: &str) -> Option<Recurrence> {
+    let mut parsed_recurrence = Recurrence {
+        is_weekly: false,
+        interval: 1,
+        days_of_week: HashSet::new(),
+        until: None,
+        count: None,
+    };
+    for part in recurrence.split(';') {
+        match part.split_once('=')? {
+            ("FREQ", "DAILY") => parsed_recurrence.is_weekly = false,
+            ("FREQ", "WEEKLY") => parsed_recurrence.is_weekly = true,
+            ("FREQ", _) => return None,
+            ("INTERVAL", interval) => parsed_recurrence.interval = interval.parse().ok()?,
+            ("BYDAY", days) => parsed_recurrence.days_of_week = parse_days_of_week(days).ok()?,
+            // An end date includes the occurrences starting on that day
+            ("UNTIL", until) if is_date(until) => {
+                parsed_recurrence.until = Some(parse_date_time(until)?.0 + Duration::days(1))
+            }
+            ("UNTIL", until) => parsed_recurrence.until = Some(parse_date_time(until)?.0),
+            ("COUNT", count) => parsed_recurrence.count = Some(count.parse().ok()?),
+            ("WKST", _) => (),
+            _ => return None,
+        }
+    }
+
+    Some(parsed_recurrence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn busy_calendar_valid() {
+        let mut busy_calendar = BusyCalendar::new(PathBuf::from("./test_data/busy_calendar"));
+        let mut is_busy = |month, day, hour, minute| {
+            let date_time = NaiveDate::from_ymd(2020, month, day).and_hms(hour, minute, 0);
+            // The local time is 2 hours ahead of UTC
+            busy_calendar
+                .is_busy(&date_time, &(date_time - chrono::Duration::hours(2)))
+                .unwrap()
+        };
+
+        assert!(is_busy(6, 1, 9, 0));
+        assert!(is_busy(6, 1, 9, 29));
+        assert!(!is_busy(6, 1, 9, 30));
+        assert!(!is_busy(6, 2, 9, 15));
+        assert!(!is_busy(6, 3, 9, 15));
+        assert!(is_busy(6, 29, 9, 15));
+        assert!(!is_busy(7, 1, 9, 15));
+        assert!(!is_busy(6, 1, 12, 30));
+        assert!(is_busy(6, 2, 16, 30));
+        assert!(!is_busy(6, 2, 14, 30));
+        assert!(is_busy(6, 5, 0, 30));
+        assert!(is_busy(6, 6, 0, 30));
+        assert!(!is_busy(6, 7, 0, 30));
+    }
+}
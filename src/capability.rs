@@ -0,0 +1,421 @@
+use crate::{Action, Condition, Configuration, Rules};
+use std::fmt::{Display, Formatter};
+#[cfg(target_os = "linux")]
+use std::fs;
+
+/// Something swyt can do depending on how it was built, the platform and the privileges of
+/// the process checking it.
+#[derive(Debug)]
+pub struct Capability {
+    name: &'static str,
+    /// Why it is unavailable and how to make it available, if it is.
+    gap: Option<String>,
+    /// Settings and rules of the policy relying on it.
+    needed_by: Vec<String>,
+}
+
+impl Capability {
+    fn new(name: &'static str, availability: Result<(), String>) -> Self {
+        Capability {
+            name,
+            gap: availability.err(),
+            needed_by: vec![],
+        }
+    }
+
+    fn with_needed_by(mut self, needed_by: Vec<String>) -> Self {
+        self.needed_by = needed_by;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    pub fn gap(&self) -> Option<&str> {
+        self.gap.as_deref()
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.gap.is_none()
+    }
+
+    pub fn needed_by(&self) -> &[String] {
+        &self.needed_by
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.gap {
+            None => write!(f, "ok      {}", self.name)?,
+            Some(gap) => write!(f, "missing {}: {}", self.name, gap)?,
+        }
+        if !self.needed_by.is_empty() {
+            write!(f, " (needed by {})", self.needed_by.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How the daemon learns about new processes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventBackend {
+    /// Listing the processes every `check_interval`.
+    Polling,
+    /// Listing them periodically and being told about each execution as well.
+    ExecEvents,
+}
+
+impl Display for EventBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventBackend::Polling => write!(f, "polling"),
+            EventBackend::ExecEvents => write!(f, "exec events"),
+        }
+    }
+}
+
+/// Features that work with this build of swyt on the current platform, whatever the
+/// privileges, so front ends can hide or degrade what doesn't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    enforcement: bool,
+    window_matching: bool,
+    gpu_usage: bool,
+    login_sessions: bool,
+    inhibitors: bool,
+    do_not_disturb: bool,
+    exec_denial: bool,
+    renamed_binaries: bool,
+    event_backend: EventBackend,
+}
+
+impl Capabilities {
+    pub fn current() -> Self {
+        Capabilities {
+            enforcement: cfg!(any(
+                all(feature = "native", any(target_os = "linux", windows)),
+                feature = "process"
+            )),
+            window_matching: cfg!(all(feature = "window", any(target_os = "linux", windows))),
+            gpu_usage: cfg!(all(feature = "gpu", target_os = "linux")),
+            login_sessions: cfg!(any(target_os = "linux", all(windows, feature = "native"))),
+            inhibitors: cfg!(target_os = "linux"),
+            do_not_disturb: cfg!(target_os = "linux"),
+            exec_denial: cfg!(all(feature = "exec-deny", target_os = "linux")),
+            renamed_binaries: cfg!(any(
+                all(feature = "native", any(target_os = "linux", windows)),
+                all(feature = "process", target_os = "linux")
+            )),
+            event_backend: if cfg!(all(feature = "exec-events", target_os = "linux")) {
+                EventBackend::ExecEvents
+            } else {
+                EventBackend::Polling
+            },
+        }
+    }
+
+    /// Whether processes can be listed and killed, without it only the rules can be evaluated.
+    pub fn enforcement(&self) -> bool {
+        self.enforcement
+    }
+
+    /// Whether the windows of processes can be found, to minimize or close them and to tell
+    /// fullscreen ones. Without it, these actions kill the processes.
+    pub fn window_matching(&self) -> bool {
+        self.window_matching
+    }
+
+    pub fn gpu_usage(&self) -> bool {
+        self.gpu_usage
+    }
+
+    /// Whether `interactive_sessions_only` can tell the sessions of the processes apart.
+    pub fn login_sessions(&self) -> bool {
+        self.login_sessions
+    }
+
+    /// Whether `inhibitor_max_deferral` can see the inhibitors of the processes.
+    pub fn inhibitors(&self) -> bool {
+        self.inhibitors
+    }
+
+    pub fn do_not_disturb(&self) -> bool {
+        self.do_not_disturb
+    }
+
+    pub fn exec_denial(&self) -> bool {
+        self.exec_denial
+    }
+
+    pub fn renamed_binaries(&self) -> bool {
+        self.renamed_binaries
+    }
+
+    pub fn event_backend(&self) -> EventBackend {
+        self.event_backend
+    }
+
+    fn features(&self) -> [(&'static str, bool); 8] {
+        [
+            ("enforcement", self.enforcement),
+            ("window matching", self.window_matching),
+            ("GPU usage", self.gpu_usage),
+            ("login sessions", self.login_sessions),
+            ("inhibitors", self.inhibitors),
+            ("Do Not Disturb", self.do_not_disturb),
+            ("execution denial", self.exec_denial),
+            ("renamed binaries", self.renamed_binaries),
+        ]
+    }
+}
+
+impl Display for Capabilities {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let features = |supported: bool| -> Vec<&str> {
+            self.features()
+                .iter()
+                .filter(|(_, is_supported)| *is_supported == supported)
+                .map(|(feature, _)| *feature)
+                .collect()
+        };
+        write!(f, "supported: {}", features(true).join(", "))?;
+        let unsupported = features(false);
+        if !unsupported.is_empty() {
+            write!(f, "; unsupported: {}", unsupported.join(", "))?;
+        }
+        write!(f, "; events: {}", self.event_backend)
+    }
+}
+
+/// Checks the capabilities of swyt on this system, beyond killing processes of the current
+/// user.
+pub fn capabilities() -> Vec<Capability> {
+    vec![
+        Capability::new("kill the processes of other users", kill_other_users()),
+        Capability::new("minimize and close windows", window_access()),
+        Capability::new("deny blocked executions", exec_denial()),
+        Capability::new("react to executions right away", exec_events()),
+    ]
+}
+
+/// Checks the capabilities the policy relies on, each with the settings and rules needing
+/// it, so the failures that would only be logged while enforcing it show up beforehand.
+pub fn required_capabilities(configuration: &Configuration, rules: &Rules) -> Vec<Capability> {
+    let rules_needing = |needs: &dyn Fn(&Action, &[Condition]) -> bool| -> Vec<String> {
+        rules
+            .iter()
+            .filter(|rule| rule.actions().any(|action| needs(action, &rule.conditions)))
+            .map(|rule| format!("rule {}", rule.process_name))
+            .collect()
+    };
+    let settings_needing = |settings: &[(&str, bool)]| -> Vec<String> {
+        settings
+            .iter()
+            .filter(|(_, needs)| *needs)
+            .map(|(setting, _)| setting.to_string())
+            .collect()
+    };
+
+    let mut window_needed_by = rules_needing(&|action, conditions| {
+        action.handles_windows()
+            || conditions
+                .iter()
+                .flat_map(Condition::leaves)
+                .any(|condition| *condition == Condition::Fullscreen)
+    });
+    window_needed_by.extend(settings_needing(&[(
+        "unsaved_work_grace",
+        configuration.unsaved_work_grace() > 0,
+    )]));
+    let mut capabilities = vec![
+        Capability::new("kill the processes of other users", kill_other_users()).with_needed_by(
+            settings_needing(&[
+                ("managed", configuration.managed()),
+                (
+                    "interactive_sessions_only",
+                    configuration.interactive_sessions_only(),
+                ),
+            ]),
+        ),
+        Capability::new("minimize and close windows", window_access())
+            .with_needed_by(window_needed_by),
+        Capability::new("read the GPU usage of processes", gpu_usage()).with_needed_by(
+            rules_needing(&|_, conditions| {
+                conditions
+                    .iter()
+                    .flat_map(Condition::leaves)
+                    .any(|condition| matches!(condition, Condition::GpuUsageAbove(_)))
+            }),
+        ),
+        Capability::new("tell login sessions apart", login_sessions()).with_needed_by(
+            settings_needing(&[(
+                "interactive_sessions_only",
+                configuration.interactive_sessions_only(),
+            )]),
+        ),
+        Capability::new("list blocking inhibitors", inhibitors()).with_needed_by(settings_needing(
+            &[(
+                "inhibitor_max_deferral",
+                configuration.inhibitor_max_deferral() > 0,
+            )],
+        )),
+        Capability::new("see Do Not Disturb", desktop_session()).with_needed_by(settings_needing(
+            &[(
+                "do_not_disturb_processes",
+                !configuration.do_not_disturb_processes().is_empty(),
+            )],
+        )),
+    ];
+    if let Some(busy_calendar) = configuration.busy_calendar() {
+        let availability = std::fs::metadata(busy_calendar)
+            .map(|_| ())
+            .map_err(|err| format!("couldn't read {}: {}", busy_calendar.display(), err));
+        capabilities.push(
+            Capability::new("read the busy calendar", availability)
+                .with_needed_by(vec!["busy_calendar".to_string()]),
+        );
+    }
+    if cfg!(all(feature = "exec-deny", target_os = "linux")) {
+        capabilities.push(
+            Capability::new("deny blocked executions", exec_denial())
+                .with_needed_by(vec!["exec-deny feature".to_string()]),
+        );
+    }
+    if cfg!(all(feature = "exec-events", target_os = "linux")) {
+        capabilities.push(
+            Capability::new("react to executions right away", exec_events())
+                .with_needed_by(vec!["exec-events feature".to_string()]),
+        );
+    }
+
+    capabilities
+        .into_iter()
+        .filter(|capability| !capability.needed_by.is_empty())
+        .collect()
+}
+
+#[cfg(all(unix, feature = "native"))]
+fn kill_other_users() -> Result<(), String> {
+    match unsafe { libc::geteuid() } {
+        0 => Ok(()),
+        _ => Err("requires running as root, e.g. as a system service".to_string()),
+    }
+}
+
+#[cfg(not(all(unix, feature = "native")))]
+fn kill_other_users() -> Result<(), String> {
+    Err("unknown on this platform, requires running as an administrator".to_string())
+}
+
+#[cfg(all(feature = "gpu", target_os = "linux"))]
+fn gpu_usage() -> Result<(), String> {
+    kill_other_users().map_err(|_| {
+        "only the processes of the current user are visible, requires running as root".to_string()
+    })
+}
+
+#[cfg(not(all(feature = "gpu", target_os = "linux")))]
+fn gpu_usage() -> Result<(), String> {
+    Err("requires the gpu feature, on Linux".to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn login_sessions() -> Result<(), String> {
+    fs::read_dir("/run/systemd/sessions")
+        .map(|_| ())
+        .map_err(|err| format!("requires systemd-logind: {}", err))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn login_sessions() -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn inhibitors() -> Result<(), String> {
+    fs::read_dir("/run/systemd/inhibit")
+        .map(|_| ())
+        .map_err(|err| format!("requires systemd-logind: {}", err))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn inhibitors() -> Result<(), String> {
+    Err("Windows power requests can't be attributed to a process".to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn desktop_session() -> Result<(), String> {
+    match std::env::var_os("DBUS_SESSION_BUS_ADDRESS") {
+        Some(_) => Ok(()),
+        None => Err("requires running in the desktop session, as a user service".to_string()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn desktop_session() -> Result<(), String> {
+    Err("Focus Assist isn't exposed on Windows".to_string())
+}
+
+#[cfg(all(feature = "window", any(target_os = "linux", windows)))]
+fn window_access() -> Result<(), String> {
+    crate::window::fullscreen_pids()
+        .map(|_| ())
+        .map_err(|err| format!("couldn't reach the window server: {}", err))
+}
+
+#[cfg(not(all(feature = "window", any(target_os = "linux", windows))))]
+fn window_access() -> Result<(), String> {
+    Err("requires the window feature, on Linux (X11) or Windows".to_string())
+}
+
+#[cfg(all(feature = "exec-deny", target_os = "linux"))]
+fn exec_denial() -> Result<(), String> {
+    crate::ExecGuard::new()
+        .map(|_| ())
+        .map_err(|err| format!("requires CAP_SYS_ADMIN: {}", err))
+}
+
+#[cfg(not(all(feature = "exec-deny", target_os = "linux")))]
+fn exec_denial() -> Result<(), String> {
+    Err("requires the exec-deny feature, on Linux".to_string())
+}
+
+#[cfg(all(feature = "exec-events", target_os = "linux"))]
+fn exec_events() -> Result<(), String> {
+    crate::ExecMonitor::new()
+        .map(|_| ())
+        .map_err(|err| format!("requires CAP_NET_ADMIN: {}", err))
+}
+
+#[cfg(not(all(feature = "exec-events", target_os = "linux")))]
+fn exec_events() -> Result<(), String> {
+    Err("requires the exec-events feature, on Linux".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_of_build() {
+        let capabilities = Capabilities::current();
+        assert_eq!(
+            capabilities.window_matching(),
+            cfg!(all(feature = "window", any(target_os = "linux", windows)))
+        );
+        assert_eq!(
+            capabilities.event_backend() == EventBackend::ExecEvents,
+            cfg!(all(feature = "exec-events", target_os = "linux"))
+        );
+        let description = capabilities.to_string();
+        assert!(description.starts_with("supported: "));
+        assert_eq!(
+            description.contains("unsupported: enforcement"),
+            !capabilities.enforcement()
+        );
+        assert!(description.ends_with(&format!("; events: {}", capabilities.event_backend())));
+    }
+}
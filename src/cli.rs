@@ -0,0 +1,343 @@
+use clap::builder::PossibleValuesParser;
+use clap::{value_parser, Arg, ArgAction, ArgMatches};
+use std::path::PathBuf;
+
+/// Hidden command making swyt idle, run by `swyt selftest` as the process to block.
+pub(crate) const SELFTEST_DUMMY_COMMAND: &str = "selftest-dummy";
+
+pub(crate) enum Command {
+    /// Runs the daemon, the command when none is given.
+    Run {
+        ephemeral: bool,
+        trace_filepath: Option<PathBuf>,
+    },
+    Replay {
+        trace_filepath: PathBuf,
+        rules_filepath: Option<PathBuf>,
+    },
+    Health,
+    Init,
+    Schedule,
+    ExportIcs {
+        filepath: Option<PathBuf>,
+    },
+    Unlock,
+    Status,
+    History {
+        count: Option<usize>,
+    },
+    Stats {
+        days: i64,
+    },
+    PurgeData {
+        days: Option<i64>,
+    },
+    Request {
+        process_name: String,
+        minutes: u32,
+        reason: String,
+    },
+    Requests,
+    Decide {
+        id: u32,
+        approved: bool,
+    },
+    SetPin,
+    ApproveChanges,
+    Selftest,
+    SelftestDummy,
+    Doctor,
+    Lint {
+        rules_filepath: Option<PathBuf>,
+    },
+    Validate,
+    Check {
+        process_name: String,
+    },
+    Rules(RulesCommand),
+    ConfinementProfile {
+        kind: String,
+    },
+    Uninstall {
+        purge: bool,
+    },
+}
+
+pub(crate) enum RulesCommand {
+    List,
+    /// Adds the rule, its attributes and conditions following the periods after a space.
+    Add(String),
+    Remove(String),
+}
+
+impl Command {
+    /// Parses the command from the arguments of swyt, printing the usage and exiting with 1
+    /// when they are invalid, or with 0 once the help is printed.
+    pub(crate) fn from_args() -> Self {
+        let matches = command().try_get_matches().unwrap_or_else(|err| {
+            let _ = err.print();
+            std::process::exit(if err.use_stderr() { 1 } else { 0 })
+        });
+        Command::from_matches(&matches)
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Self {
+        let (name, matches) = match matches.subcommand() {
+            Some(subcommand) => subcommand,
+            // `swyt run` is `swyt`, running the daemon
+            None => ("run", matches),
+        };
+        let path = |id: &str| matches.get_one::<PathBuf>(id).cloned();
+        let string = |id: &str| matches.get_one::<String>(id).cloned().unwrap_or_default();
+        match name {
+            "run" => Command::Run {
+                ephemeral: matches.get_flag("ephemeral"),
+                trace_filepath: path("record"),
+            },
+            "replay" => Command::Replay {
+                trace_filepath: path("trace file").unwrap_or_default(),
+                rules_filepath: path("rules"),
+            },
+            "health" => Command::Health,
+            "init" => Command::Init,
+            "schedule" => Command::Schedule,
+            "export-ics" => Command::ExportIcs {
+                filepath: path("file"),
+            },
+            "unlock" => Command::Unlock,
+            "status" => Command::Status,
+            "history" => Command::History {
+                count: matches.get_one("count").copied(),
+            },
+            "stats" => Command::Stats {
+                days: matches.get_one("days").copied().unwrap_or(7),
+            },
+            "purge-data" => Command::PurgeData {
+                days: matches.get_one("days").copied(),
+            },
+            "request" => Command::Request {
+                process_name: string("process"),
+                minutes: matches.get_one("duration").copied().unwrap_or_default(),
+                reason: string("reason"),
+            },
+            "requests" => Command::Requests,
+            "approve" | "deny" => Command::Decide {
+                id: matches.get_one("request").copied().unwrap_or_default(),
+                approved: name == "approve",
+            },
+            "set-pin" => Command::SetPin,
+            "approve-changes" => Command::ApproveChanges,
+            "selftest" => Command::Selftest,
+            SELFTEST_DUMMY_COMMAND => Command::SelftestDummy,
+            "doctor" => Command::Doctor,
+            "lint" => Command::Lint {
+                rules_filepath: path("rules file"),
+            },
+            "validate" => Command::Validate,
+            "check" => Command::Check {
+                process_name: string("process"),
+            },
+            "rules" => Command::Rules(match matches.subcommand() {
+                Some(("add", matches)) => {
+                    let rule = matches.get_many::<String>("rule").unwrap_or_default();
+                    RulesCommand::Add(rule.cloned().collect::<Vec<_>>().join(" "))
+                }
+                Some(("remove", matches)) => RulesCommand::Remove(
+                    matches
+                        .get_one::<String>("process")
+                        .cloned()
+                        .unwrap_or_default(),
+                ),
+                _ => RulesCommand::List,
+            }),
+            "confinement-profile" => Command::ConfinementProfile {
+                kind: string("kind"),
+            },
+            "uninstall" => Command::Uninstall {
+                purge: matches.get_flag("purge"),
+            },
+            _ => unreachable!("Unknown command: {}", name),
+        }
+    }
+}
+
+fn command() -> clap::Command {
+    let run_args = [
+        Arg::new("ephemeral")
+            .long("ephemeral")
+            .action(ArgAction::SetTrue)
+            .help("Keep the daemon state in memory and write nothing to disk"),
+        Arg::new("record")
+            .long("record")
+            .value_name("trace file")
+            .value_parser(value_parser!(PathBuf))
+            .help("Record the verdicts of the scans, for swyt replay"),
+    ];
+    let days = |help| {
+        Arg::new("days")
+            .value_parser(value_parser!(i64).range(1..))
+            .help(help)
+    };
+    let request_id = Arg::new("request")
+        .required(true)
+        .value_parser(value_parser!(u32));
+    let process = Arg::new("process").required(true);
+
+    clap::Command::new("swyt")
+        .about("Daemon killing processes based on time period rules")
+        .args(run_args.clone())
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            clap::Command::new("run")
+                .about("Run the daemon, the default")
+                .args(run_args),
+        )
+        .subcommand(
+            clap::Command::new("replay")
+                .about("Replay the scans of a trace against the rules, printing what changes")
+                .arg(
+                    Arg::new("trace file")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("rules")
+                        .long("rules")
+                        .value_name("rules file")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("Replay against the rules of this file instead of the policy"),
+                ),
+        )
+        .subcommand(clap::Command::new("health").about("Check the daemon is running and scanning"))
+        .subcommand(clap::Command::new("init").about("Create the configuration and the rules"))
+        .subcommand(
+            clap::Command::new("schedule").about("Print when the rules allow their processes"),
+        )
+        .subcommand(
+            clap::Command::new("export-ics")
+                .about("Export the allowed periods as an iCalendar")
+                .arg(Arg::new("file").value_parser(value_parser!(PathBuf))),
+        )
+        .subcommand(clap::Command::new("unlock").about("Lift the lockdown"))
+        .subcommand(
+            clap::Command::new("status").about("Print the state of the daemon after its last scan"),
+        )
+        .subcommand(
+            clap::Command::new("history")
+                .about("Print the changes of the policy")
+                .arg(
+                    Arg::new("count")
+                        .value_parser(value_parser!(usize))
+                        .help("Print the latest changes only"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("stats")
+                .about("Print the actions taken on each process")
+                .arg(days("Over the last days, 7 by default")),
+        )
+        .subcommand(
+            clap::Command::new("purge-data")
+                .about("Remove the actions taken and the changes of the policy")
+                .arg(days("Only remove those older than the days")),
+        )
+        .subcommand(
+            clap::Command::new("request")
+                .about("Request a process to be allowed for a while")
+                .arg(process.clone())
+                .arg(
+                    Arg::new("duration")
+                        .required(true)
+                        .value_parser(|duration: &str| {
+                            parse_minutes(duration).ok_or("expected a duration such as 30m or 2h")
+                        }),
+                )
+                .arg(Arg::new("reason").long("reason")),
+        )
+        .subcommand(clap::Command::new("requests").about("Print the override requests"))
+        .subcommand(
+            clap::Command::new("approve")
+                .about("Approve an override request")
+                .arg(request_id.clone()),
+        )
+        .subcommand(
+            clap::Command::new("deny")
+                .about("Deny an override request")
+                .arg(request_id),
+        )
+        .subcommand(clap::Command::new("set-pin").about("Set the admin PIN"))
+        .subcommand(
+            clap::Command::new("approve-changes")
+                .about("Approve the changes loosening the policy with the admin PIN"),
+        )
+        .subcommand(
+            clap::Command::new("selftest")
+                .about("Check a blocked process is killed on this system"),
+        )
+        .subcommand(clap::Command::new(SELFTEST_DUMMY_COMMAND).hide(true))
+        .subcommand(
+            clap::Command::new("doctor")
+                .about("Check the privileges and system support the policy relies on"),
+        )
+        .subcommand(
+            clap::Command::new("lint")
+                .about("Check the rules for what likely doesn't do what was meant")
+                .arg(Arg::new("rules file").value_parser(value_parser!(PathBuf))),
+        )
+        .subcommand(clap::Command::new("validate").about("Check the policy parses"))
+        .subcommand(
+            clap::Command::new("check")
+                .about("Print whether the rules allow a process now")
+                .arg(process.clone()),
+        )
+        .subcommand(
+            clap::Command::new("rules")
+                .about("List, add or remove the rules")
+                .subcommand_required(true)
+                .subcommand(clap::Command::new("list").about("List the rules"))
+                .subcommand(
+                    clap::Command::new("add").about("Add a rule").arg(
+                        Arg::new("rule")
+                            .required(true)
+                            .num_args(1..)
+                            .allow_hyphen_values(true),
+                    ),
+                )
+                .subcommand(
+                    clap::Command::new("remove")
+                        .about("Remove the rule of a process")
+                        .arg(process),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("confinement-profile")
+                .about("Print an AppArmor profile or an SELinux module for the daemon")
+                .arg(
+                    Arg::new("kind")
+                        .required(true)
+                        .value_parser(PossibleValuesParser::new(["apparmor", "selinux"])),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("uninstall")
+                .about("Remove the state files of the daemon")
+                .arg(
+                    Arg::new("purge")
+                        .long("purge")
+                        .action(ArgAction::SetTrue)
+                        .help("Remove the configuration directories altogether"),
+                ),
+        )
+}
+
+/// Parses a duration in minutes, such as `30m`, `2h` or `45`.
+fn parse_minutes(duration: &str) -> Option<u32> {
+    let (number, unit) = match duration.strip_suffix('h') {
+        Some(hours) => (hours, 60),
+        None => (duration.strip_suffix('m').unwrap_or(duration), 1),
+    };
+    number
+        .parse::<u32>()
+        .ok()
+        .and_then(|number| number.checked_mul(unit))
+}
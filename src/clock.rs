@@ -0,0 +1,138 @@
+use crate::SwytError;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const NTP_PORT: u16 = 123;
+const NTP_TIMEOUT: Duration = Duration::from_secs(5);
+/// Seconds from the NTP epoch, 1900, to the Unix one.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+const NTP_PACKET_LENGTH: usize = 48;
+
+/// Source of the current time, the system clock or a trusted one it is checked against.
+pub trait ClockSource {
+    fn now(&self) -> Result<SystemTime, SwytError>;
+}
+
+pub struct SystemClock;
+
+impl ClockSource for SystemClock {
+    fn now(&self) -> Result<SystemTime, SwytError> {
+        Ok(SystemTime::now())
+    }
+}
+
+/// Time of an NTP server, queried over SNTP. The server is a host name or an address, with
+/// an optional port.
+pub struct NtpClock {
+    server: String,
+}
+
+impl NtpClock {
+    pub fn new(server: String) -> Self {
+        NtpClock { server }
+    }
+}
+
+impl ClockSource for NtpClock {
+    fn now(&self) -> Result<SystemTime, SwytError> {
+        let address = match self.server.contains(':') {
+            true => self.server.clone(),
+            false => format!("{}:{}", self.server, NTP_PORT),
+        };
+        let socket = UdpSocket::bind("[::]:0").or_else(|_| UdpSocket::bind("0.0.0.0:0"))?;
+        socket.set_read_timeout(Some(NTP_TIMEOUT))?;
+        socket.connect(address)?;
+
+        // The server echoes the transmit timestamp of the request, telling its answer apart
+        let sent = Instant::now();
+        let transmit_timestamp = ntp_timestamp(SystemTime::now());
+        let mut request = [0; NTP_PACKET_LENGTH];
+        // Version 4, client mode
+        request[0] = 0x23;
+        request[40..48].copy_from_slice(&transmit_timestamp);
+        socket.send(&request)?;
+        let mut response = [0; NTP_PACKET_LENGTH];
+        let length = socket.recv(&mut response)?;
+        let server_time = parse_ntp_response(&response[..length], &transmit_timestamp)?;
+
+        // The answer is about half the round trip old
+        Ok(server_time + sent.elapsed() / 2)
+    }
+}
+
+/// Returns how far the system clock is ahead of the trusted one, negative when it is behind.
+pub fn clock_skew(
+    system_clock: &impl ClockSource,
+    trusted_clock: &impl ClockSource,
+) -> Result<chrono::Duration, SwytError> {
+    let trusted_time = trusted_clock.now()?;
+    let system_time = system_clock.now()?;
+    let skew = match system_time.duration_since(trusted_time) {
+        Ok(ahead) => chrono::Duration::from_std(ahead),
+        Err(behind) => chrono::Duration::from_std(behind.duration()).map(|behind| -behind),
+    };
+    skew.map_err(|_| SwytError::TimeFetchError)
+}
+
+/// Reads the transmit time of an SNTP server response to the request with the given
+/// transmit timestamp.
+pub(crate) fn parse_ntp_response(
+    response: &[u8],
+    transmit_timestamp: &[u8; 8],
+) -> Result<SystemTime, SwytError> {
+    let is_server = response.len() == NTP_PACKET_LENGTH && matches!(response[0] & 0x07, 4 | 5);
+    // A stratum of 0 is a kiss-of-death, the server refusing to answer
+    if !is_server || response[1] == 0 || &response[24..32] != transmit_timestamp {
+        return Err(SwytError::TimeFetchError);
+    }
+
+    let seconds = u32::from_be_bytes([response[40], response[41], response[42], response[43]]);
+    let fraction = u32::from_be_bytes([response[44], response[45], response[46], response[47]]);
+    let unix_seconds = u64::from(seconds)
+        .checked_sub(NTP_UNIX_OFFSET)
+        .ok_or(SwytError::TimeFetchError)?;
+    let nanoseconds = (u64::from(fraction) * 1_000_000_000) >> 32;
+    Ok(UNIX_EPOCH + Duration::from_secs(unix_seconds) + Duration::from_nanos(nanoseconds))
+}
+
+pub(crate) fn ntp_timestamp(time: SystemTime) -> [u8; 8] {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = (since_epoch.as_secs() + NTP_UNIX_OFFSET) as u32;
+    let fraction = ((u64::from(since_epoch.subsec_nanos()) << 32) / 1_000_000_000) as u32;
+    let mut timestamp = [0; 8];
+    timestamp[..4].copy_from_slice(&seconds.to_be_bytes());
+    timestamp[4..].copy_from_slice(&fraction.to_be_bytes());
+    timestamp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_skew_against_time_server() {
+        struct FixedClock(SystemTime);
+        impl ClockSource for FixedClock {
+            fn now(&self) -> Result<SystemTime, SwytError> {
+                Ok(self.0)
+            }
+        }
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let transmit_timestamp = ntp_timestamp(now);
+        let mut response = [0; 48];
+        response[0] = 0x24;
+        response[1] = 2;
+        response[24..32].copy_from_slice(&transmit_timestamp);
+        response[40..48].copy_from_slice(&ntp_timestamp(now + Duration::from_millis(1500)));
+        let server_time = parse_ntp_response(&response, &transmit_timestamp).unwrap();
+        let skew = clock_skew(&FixedClock(now), &FixedClock(server_time)).unwrap();
+        assert!((skew.num_milliseconds() + 1500).abs() <= 1);
+
+        // Not an answer to the request
+        assert!(parse_ntp_response(&response, &[0; 8]).is_err());
+        // A kiss-of-death
+        response[1] = 0;
+        assert!(parse_ntp_response(&response, &transmit_timestamp).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::Period;
+use chrono::NaiveDateTime;
+#[cfg(any(feature = "native", feature = "process"))]
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// Requirement on the state of a process or of the system for a rule to apply to a process,
+/// on top of its name. Conditions compose into a tree with `All` and `Any`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Fullscreen,
+    /// The process uses more than the given percentage of a GPU engine.
+    GpuUsageAbove(u32),
+    /// The system runs on battery.
+    OnBattery,
+    /// The time is in one of the periods.
+    During(Vec<Period>),
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+}
+
+impl Condition {
+    /// Evaluates the condition tree at the given time, `satisfies` telling whether the leaves
+    /// depending on the process or the system hold.
+    pub fn evaluate(
+        &self,
+        date_time: &NaiveDateTime,
+        satisfies: &mut impl FnMut(&Condition) -> bool,
+    ) -> bool {
+        match self {
+            Condition::During(periods) => periods.iter().any(|period| period.contains(date_time)),
+            Condition::All(conditions) => conditions
+                .iter()
+                .all(|condition| condition.evaluate(date_time, satisfies)),
+            Condition::Any(conditions) => conditions
+                .iter()
+                .any(|condition| condition.evaluate(date_time, satisfies)),
+            leaf => satisfies(leaf),
+        }
+    }
+
+    /// Returns the leaves of the condition tree.
+    pub fn leaves(&self) -> Vec<&Condition> {
+        match self {
+            Condition::All(conditions) | Condition::Any(conditions) => {
+                conditions.iter().flat_map(Condition::leaves).collect()
+            }
+            leaf => vec![leaf],
+        }
+    }
+
+    /// Returns whether the condition can only hold for some processes, rather than for all of
+    /// them at once like the time or the power supply.
+    pub fn depends_on_process(&self) -> bool {
+        match self {
+            Condition::Fullscreen | Condition::GpuUsageAbove(_) => true,
+            Condition::OnBattery | Condition::During(_) => false,
+            Condition::All(conditions) => conditions.iter().any(Condition::depends_on_process),
+            Condition::Any(conditions) => conditions.iter().all(Condition::depends_on_process),
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let join = |conditions: &[Condition]| {
+            conditions
+                .iter()
+                .map(Condition::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        match self {
+            Condition::Fullscreen => write!(f, "fullscreen"),
+            Condition::GpuUsageAbove(percentage) => write!(f, "gpu>{}", percentage),
+            Condition::OnBattery => write!(f, "battery"),
+            Condition::During(periods) => {
+                let periods: Vec<String> = periods.iter().map(Period::to_string).collect();
+                write!(f, "during({})", periods.join("|"))
+            }
+            Condition::All(conditions) => write!(f, "all({})", join(conditions)),
+            Condition::Any(conditions) => write!(f, "any({})", join(conditions)),
+        }
+    }
+}
+
+/// Lazily gathers the system state the conditions depend on, so it is fetched at most once
+/// per enforcement pass and only when a matching rule needs it.
+#[cfg(any(feature = "native", feature = "process"))]
+#[derive(Default)]
+pub(crate) struct ConditionProbe {
+    /// Whether the GPU usage, sampled over half a second, is left out to save battery. The
+    /// conditions on it are then never satisfied.
+    low_power: bool,
+    fullscreen_pids: Option<HashSet<u32>>,
+    gpu_usages: Option<HashMap<u32, f64>>,
+    unsaved_work_pids: Option<HashSet<u32>>,
+    inhibitor_pids: Option<HashSet<u32>>,
+    on_battery: Option<bool>,
+}
+
+#[cfg(any(feature = "native", feature = "process"))]
+impl ConditionProbe {
+    pub(crate) fn new(configuration: &crate::Configuration) -> Self {
+        ConditionProbe {
+            low_power: crate::is_low_power(configuration),
+            ..ConditionProbe::default()
+        }
+    }
+
+    /// Whether the daemon ran in low-power mode when the probe was created.
+    pub(crate) fn is_low_power(&self) -> bool {
+        self.low_power
+    }
+
+    pub(crate) fn satisfies(&mut self, pid: u32, condition: &Condition) -> bool {
+        match condition {
+            Condition::Fullscreen => self
+                .fullscreen_pids
+                .get_or_insert_with(fullscreen_pids)
+                .contains(&pid),
+            Condition::GpuUsageAbove(_) if self.low_power => false,
+            Condition::GpuUsageAbove(percentage) => self
+                .gpu_usages
+                .get_or_insert_with(gpu_usages)
+                .get(&pid)
+                .is_some_and(|&usage| usage > f64::from(*percentage)),
+            Condition::OnBattery => *self
+                .on_battery
+                .get_or_insert_with(crate::power::is_on_battery),
+            // The tree is walked by `Condition::evaluate`, which only asks for the leaves
+            Condition::During(_) | Condition::All(_) | Condition::Any(_) => false,
+        }
+    }
+
+    /// Returns whether the process seems to have unsaved work, a modal dialog or a window
+    /// whose title marks a modified document.
+    pub(crate) fn has_unsaved_work(&mut self, pid: u32) -> bool {
+        self.unsaved_work_pids
+            .get_or_insert_with(unsaved_work_pids)
+            .contains(&pid)
+    }
+
+    pub(crate) fn holds_blocking_inhibitor(&mut self, pid: u32) -> bool {
+        self.inhibitor_pids
+            .get_or_insert_with(crate::session::blocking_inhibitor_pids)
+            .contains(&pid)
+    }
+}
+
+#[cfg(all(
+    any(feature = "native", feature = "process"),
+    feature = "window",
+    any(target_os = "linux", windows)
+))]
+fn fullscreen_pids() -> HashSet<u32> {
+    crate::window::fullscreen_pids().unwrap_or_else(|err| {
+        log::trace!("Couldn't fetch fullscreen windows: {}", err);
+        HashSet::new()
+    })
+}
+
+#[cfg(all(
+    any(feature = "native", feature = "process"),
+    not(all(feature = "window", any(target_os = "linux", windows)))
+))]
+fn fullscreen_pids() -> HashSet<u32> {
+    HashSet::new()
+}
+
+#[cfg(all(
+    any(feature = "native", feature = "process"),
+    feature = "window",
+    any(target_os = "linux", windows)
+))]
+fn unsaved_work_pids() -> HashSet<u32> {
+    crate::window::unsaved_work_pids().unwrap_or_else(|err| {
+        log::trace!("Couldn't fetch windows with unsaved work: {}", err);
+        HashSet::new()
+    })
+}
+
+#[cfg(all(
+    any(feature = "native", feature = "process"),
+    not(all(feature = "window", any(target_os = "linux", windows)))
+))]
+fn unsaved_work_pids() -> HashSet<u32> {
+    HashSet::new()
+}
+
+#[cfg(all(
+    any(feature = "native", feature = "process"),
+    feature = "gpu",
+    target_os = "linux"
+))]
+fn gpu_usages() -> HashMap<u32, f64> {
+    crate::gpu::gpu_usages()
+}
+
+#[cfg(all(
+    any(feature = "native", feature = "process"),
+    not(all(feature = "gpu", target_os = "linux"))
+))]
+fn gpu_usages() -> HashMap<u32, f64> {
+    HashMap::new()
+}
@@ -0,0 +1,307 @@
+use crate::approval::get_approved_policy_filepaths;
+use crate::audit::get_audit_filepath;
+use crate::{state_filepaths, Configuration, RenamedBinaryPolicy};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+/// Mandatory access control confining the daemon, on top of its privileges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Confinement {
+    /// Confined by the AppArmor profile, with its mode, e.g. `swyt (enforce)`.
+    AppArmor(String),
+    /// Confined to the SELinux context, e.g. `system_u:system_r:swyt_t:s0`.
+    SELinux(String),
+}
+
+impl Display for Confinement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Confinement::AppArmor(profile) => write!(f, "AppArmor profile {}", profile),
+            Confinement::SELinux(context) => write!(f, "SELinux context {}", context),
+        }
+    }
+}
+
+/// Returns the confinement of the current process, `None` when it is unconfined or on
+/// platforms without AppArmor and SELinux.
+pub fn current_confinement() -> Option<Confinement> {
+    if Path::new("/sys/fs/selinux/enforce").exists() {
+        let context = read_attribute("/proc/self/attr/current")?;
+        return (!context.contains(":unconfined_t:")).then_some(Confinement::SELinux(context));
+    }
+
+    if !Path::new("/sys/module/apparmor").exists() {
+        return None;
+    }
+
+    // The interface dedicated to AppArmor doesn't clash with other security modules
+    let profile = read_attribute("/proc/self/attr/apparmor/current")
+        .or_else(|| read_attribute("/proc/self/attr/current"))?;
+    (profile != "unconfined").then_some(Confinement::AppArmor(profile))
+}
+
+fn read_attribute(path: &str) -> Option<String> {
+    let attribute = fs::read_to_string(path).ok()?;
+    let attribute = attribute.trim_end_matches(['\0', '\n']);
+    (!attribute.is_empty()).then(|| attribute.to_string())
+}
+
+/// Access the daemon needs with a policy, to be granted by a confinement profile.
+struct Requirements {
+    capabilities: Vec<&'static str>,
+    read_paths: Vec<String>,
+    write_paths: Vec<String>,
+    executables: Vec<&'static str>,
+    /// Whether the file descriptors of the other processes are read.
+    ptrace: bool,
+    x11: bool,
+    netlink: bool,
+    network: bool,
+    /// Whether the time server is queried over UDP.
+    ntp: bool,
+}
+
+impl Requirements {
+    fn new(configuration: &Configuration, swyt_filepath: &Path) -> Self {
+        let mut requirements = Requirements {
+            capabilities: vec![],
+            read_paths: vec![
+                "/proc/".to_string(),
+                "/proc/[0-9]*/stat".to_string(),
+                "/proc/[0-9]*/cmdline".to_string(),
+                "/proc/[0-9]*/cgroup".to_string(),
+                "/sys/class/power_supply/".to_string(),
+                "/sys/devices/**/power_supply/**".to_string(),
+                "/run/systemd/sessions/*".to_string(),
+                "/run/systemd/inhibit/".to_string(),
+                "/run/systemd/inhibit/*".to_string(),
+                format!("{}/", swyt_filepath.display()),
+                format!("{}/*.jbb", swyt_filepath.display()),
+            ],
+            write_paths: state_filepaths(swyt_filepath)
+                .into_iter()
+                .chain(get_approved_policy_filepaths(swyt_filepath))
+                .chain([get_audit_filepath(swyt_filepath)])
+                .map(|state_filepath| state_filepath.display().to_string())
+                .collect(),
+            executables: vec![],
+            ptrace: false,
+            x11: cfg!(feature = "window"),
+            netlink: cfg!(feature = "exec-events"),
+            network: cfg!(feature = "otlp") || configuration.report_command().is_some(),
+            ntp: configuration.time_server().is_some(),
+        };
+
+        if configuration.managed() || configuration.interactive_sessions_only() {
+            requirements.capabilities.push("kill");
+        }
+        if cfg!(feature = "exec-events") {
+            requirements.capabilities.push("net_admin");
+        }
+        if cfg!(feature = "exec-deny") {
+            requirements.capabilities.push("sys_admin");
+            requirements
+                .read_paths
+                .push("/proc/self/mounts".to_string());
+        }
+        if cfg!(feature = "gpu") {
+            requirements.capabilities.push("sys_ptrace");
+            requirements.ptrace = true;
+            requirements.read_paths.push("/proc/[0-9]*/fd/".to_string());
+            requirements
+                .read_paths
+                .push("/proc/[0-9]*/fdinfo/*".to_string());
+        }
+        if configuration.renamed_binaries() != RenamedBinaryPolicy::Off {
+            if !requirements.ptrace {
+                requirements.capabilities.push("sys_ptrace");
+                requirements.ptrace = true;
+            }
+            requirements.read_paths.push("/proc/[0-9]*/exe".to_string());
+            // The executables are hashed wherever they were copied
+            requirements.read_paths.push("/**".to_string());
+        }
+        if let Some(busy_calendar) = configuration.busy_calendar() {
+            requirements
+                .read_paths
+                .push(busy_calendar.display().to_string());
+            requirements
+                .read_paths
+                .push(format!("{}/*", busy_calendar.display()));
+        }
+        if !configuration.do_not_disturb_processes().is_empty() {
+            requirements.executables = vec!["/usr/bin/gsettings", "/usr/bin/busctl"];
+        }
+
+        requirements
+    }
+}
+
+/// Returns an AppArmor profile confining the daemon at `executable_path` to what it needs
+/// with the policy in `swyt_filepath`, to be installed in */etc/apparmor.d*.
+pub fn apparmor_profile(
+    configuration: &Configuration,
+    swyt_filepath: &Path,
+    executable_path: &Path,
+) -> String {
+    let requirements = Requirements::new(configuration, swyt_filepath);
+    let mut profile = vec![
+        "abi <abi/3.0>,".to_string(),
+        "include <tunables/global>".to_string(),
+        String::new(),
+        format!("profile swyt {} {{", executable_path.display()),
+        "  include <abstractions/base>".to_string(),
+        String::new(),
+        "  signal (send) set=(kill),".to_string(),
+    ];
+    profile.extend(
+        requirements
+            .capabilities
+            .iter()
+            .map(|capability| format!("  capability {},", capability)),
+    );
+    profile.extend(
+        requirements
+            .read_paths
+            .iter()
+            .map(|path| format!("  {} r,", path)),
+    );
+    profile.extend(
+        requirements
+            .write_paths
+            .iter()
+            .map(|path| format!("  {} rw,", path)),
+    );
+    if requirements.ptrace {
+        profile.push("  ptrace (read) peer=**,".to_string());
+    }
+    if !requirements.executables.is_empty() {
+        profile.push("  include <abstractions/dbus-session-strict>".to_string());
+        profile.push("  owner @{HOME}/.config/dconf/user r,".to_string());
+    }
+    profile.extend(
+        requirements
+            .executables
+            .iter()
+            .map(|executable| format!("  {} ix,", executable)),
+    );
+    if requirements.x11 {
+        profile.push("  include <abstractions/X>".to_string());
+    }
+    if requirements.netlink {
+        profile.push("  network netlink dgram,".to_string());
+    }
+    if requirements.network || requirements.ntp {
+        profile.push("  include <abstractions/nameservice>".to_string());
+    }
+    if requirements.network {
+        profile.push("  network inet stream,".to_string());
+        profile.push("  network inet6 stream,".to_string());
+    }
+    if requirements.ntp {
+        profile.push("  network inet dgram,".to_string());
+        profile.push("  network inet6 dgram,".to_string());
+    }
+    profile.push("}".to_string());
+
+    profile.join("\n") + "\n"
+}
+
+/// Returns an SELinux policy module confining the daemon to the `swyt_t` domain, written
+/// against the reference policy interfaces so it builds with
+/// `make -f /usr/share/selinux/devel/Makefile swyt.pp`.
+pub fn selinux_module(
+    configuration: &Configuration,
+    swyt_filepath: &Path,
+    executable_path: &Path,
+) -> String {
+    let requirements = Requirements::new(configuration, swyt_filepath);
+    let mut module = vec![
+        "policy_module(swyt, 1.0)".to_string(),
+        String::new(),
+        "type swyt_t;".to_string(),
+        "type swyt_exec_t;".to_string(),
+        "init_daemon_domain(swyt_t, swyt_exec_t)".to_string(),
+        "type swyt_etc_t;".to_string(),
+        "files_config_file(swyt_etc_t)".to_string(),
+        "type swyt_var_lib_t;".to_string(),
+        "files_type(swyt_var_lib_t)".to_string(),
+        String::new(),
+        "read_files_pattern(swyt_t, swyt_etc_t, swyt_etc_t)".to_string(),
+        "manage_files_pattern(swyt_t, swyt_etc_t, swyt_var_lib_t)".to_string(),
+        "filetrans_pattern(swyt_t, swyt_etc_t, swyt_var_lib_t, file)".to_string(),
+        "kernel_read_system_state(swyt_t)".to_string(),
+        "domain_read_all_domains_state(swyt_t)".to_string(),
+        "domain_kill_all_domains(swyt_t)".to_string(),
+        "dev_read_sysfs(swyt_t)".to_string(),
+        "systemd_read_logind_sessions_files(swyt_t)".to_string(),
+    ];
+    if !requirements.capabilities.is_empty() {
+        module.push(format!(
+            "allow swyt_t self:capability {{ {} }};",
+            requirements.capabilities.join(" ")
+        ));
+    }
+    if requirements.netlink {
+        module.push(
+            "allow swyt_t self:netlink_connector_socket { create bind read write };".to_string(),
+        );
+    }
+    if requirements.x11 {
+        module.push("xserver_stream_connect(swyt_t)".to_string());
+    }
+    if requirements.network {
+        module.push("corenet_tcp_connect_all_ports(swyt_t)".to_string());
+    }
+    if requirements.ntp {
+        module.push("allow swyt_t self:udp_socket create_socket_perms;".to_string());
+        module.push("corenet_udp_sendrecv_ntp_port(swyt_t)".to_string());
+        module.push("sysnet_dns_name_resolve(swyt_t)".to_string());
+    }
+    if !requirements.executables.is_empty() {
+        module.push("corecmd_exec_bin(swyt_t)".to_string());
+        module.push("dbus_connect_all_session_bus(swyt_t)".to_string());
+    }
+    if configuration.busy_calendar().is_some() {
+        module.push("userdom_read_user_home_content_files(swyt_t)".to_string());
+    }
+    module.push(String::new());
+    module.push(format!(
+        "# semanage fcontext -a -t swyt_exec_t '{}'",
+        executable_path.display()
+    ));
+    module.push(format!(
+        "# semanage fcontext -a -t swyt_etc_t '{}(/.*)?'",
+        swyt_filepath.display()
+    ));
+
+    module.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_config;
+
+    #[test]
+    fn confinement_profiles_valid() {
+        let configuration = load_config(Path::new("./test_data/valid_config")).unwrap();
+        let swyt_filepath = Path::new("/etc/swyt");
+        let executable_path = Path::new("/usr/bin/swyt");
+
+        let profile = apparmor_profile(&configuration, swyt_filepath, executable_path);
+        assert!(profile.contains("profile swyt /usr/bin/swyt {"));
+        assert!(profile.contains("\n  capability kill,\n"));
+        assert!(profile.contains("\n  /etc/swyt/heartbeat.jbb rw,\n"));
+        assert!(profile.contains("\n  /home/user/.calendars/work/* r,\n"));
+        assert!(profile.contains("\n  /usr/bin/gsettings ix,\n"));
+        assert!(profile.ends_with("}\n"));
+
+        let module = selinux_module(&configuration, swyt_filepath, executable_path);
+        assert!(module.starts_with("policy_module(swyt, 1.0)\n"));
+        assert!(module.contains("\nallow swyt_t self:capability { kill"));
+        assert!(module.contains("'/etc/swyt(/.*)?'"));
+        assert!(module.contains("\nuserdom_read_user_home_content_files(swyt_t)\n"));
+    }
+}
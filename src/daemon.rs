@@ -0,0 +1,314 @@
+use crate::{EnforcementState, LockdownReason, Rules, SwytError};
+use chrono::NaiveDateTime;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DAEMON_SNAPSHOT_FILE_NAME: &str = "status.json";
+const DAEMON_SNAPSHOT_DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Action taken on a process by the rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnforcementEvent {
+    date_time: NaiveDateTime,
+    process_name: String,
+    pid: u32,
+    action: String,
+    command_line: Option<String>,
+}
+
+impl EnforcementEvent {
+    pub fn new(date_time: NaiveDateTime, process_name: String, pid: u32, action: String) -> Self {
+        EnforcementEvent {
+            date_time,
+            process_name,
+            pid,
+            action,
+            command_line: None,
+        }
+    }
+
+    pub fn with_command_line(mut self, command_line: Option<String>) -> Self {
+        self.command_line = command_line;
+        self
+    }
+
+    pub fn date_time(&self) -> &NaiveDateTime {
+        &self.date_time
+    }
+
+    pub fn process_name(&self) -> &str {
+        &self.process_name
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// What was done, `kill`, `terminate`, `minimize`, `close`, `notify`, `throttle`, `suspend`
+    /// or `deny_exec`.
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// Redacted command line of the process, only read when rules match command lines.
+    pub fn command_line(&self) -> Option<&str> {
+        self.command_line.as_deref()
+    }
+
+    pub fn to_json(&self) -> String {
+        let command_line = match &self.command_line {
+            Some(command_line) => format!(",\"command_line\":{}", json_string(command_line)),
+            None => String::new(),
+        };
+        format!(
+            "{{\"date_time\":{},\"process_name\":{},\"pid\":{},\"action\":{}{}}}",
+            json_string(
+                &self
+                    .date_time
+                    .format(DAEMON_SNAPSHOT_DATE_TIME_FORMAT)
+                    .to_string()
+            ),
+            json_string(&self.process_name),
+            self.pid,
+            json_string(&self.action),
+            command_line
+        )
+    }
+}
+
+/// Temporary state of the daemon blocking or allowing processes beyond their rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Override {
+    /// Every process with a rule is blocked until an administrator lifts it.
+    Lockdown(LockdownReason),
+    /// The `busy_processes` are blocked during a busy event of the calendar.
+    Busy(Vec<String>),
+    /// The `do_not_disturb_processes` are blocked while Do Not Disturb is on.
+    DoNotDisturb(Vec<String>),
+    /// The process is allowed until then, by an approved override request.
+    Granted(String, NaiveDateTime),
+}
+
+/// Rule in force and when its processes are next allowed or blocked by its schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleStatus {
+    process_name: String,
+    rule: String,
+    next_boundary: Option<NaiveDateTime>,
+}
+
+impl RuleStatus {
+    pub fn process_name(&self) -> &str {
+        &self.process_name
+    }
+
+    /// The rule, as written in the rules file.
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// In the time zone of the rule, `None` when its schedule never changes.
+    pub fn next_boundary(&self) -> Option<&NaiveDateTime> {
+        self.next_boundary.as_ref()
+    }
+}
+
+/// State of the running daemon, written after each scan for the front ends to render.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonSnapshot {
+    pid: u32,
+    date_time: NaiveDateTime,
+    rules: Vec<RuleStatus>,
+    overrides: Vec<Override>,
+    recent_events: Vec<EnforcementEvent>,
+}
+
+impl DaemonSnapshot {
+    /// Snapshot of the daemon at `date_time`, with the actions recently recorded in its
+    /// enforcement state.
+    pub fn new(
+        rules: &Rules,
+        enforcement_state: &EnforcementState,
+        overrides: Vec<Override>,
+        date_time: NaiveDateTime,
+    ) -> Self {
+        DaemonSnapshot {
+            pid: std::process::id(),
+            date_time,
+            rules: rules
+                .iter()
+                .map(|rule| RuleStatus {
+                    process_name: rule.process_name.clone(),
+                    rule: rule.to_string(),
+                    next_boundary: rule.next_boundary(&date_time),
+                })
+                .collect(),
+            overrides,
+            recent_events: enforcement_state.recent_events(),
+        }
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn date_time(&self) -> &NaiveDateTime {
+        &self.date_time
+    }
+
+    pub fn rules(&self) -> &[RuleStatus] {
+        &self.rules
+    }
+
+    pub fn overrides(&self) -> &[Override] {
+        &self.overrides
+    }
+
+    /// The latest actions taken on processes, the oldest first.
+    pub fn recent_events(&self) -> &[EnforcementEvent] {
+        &self.recent_events
+    }
+
+    pub fn to_json(&self) -> String {
+        let date_time = |date_time: &NaiveDateTime| {
+            json_string(
+                &date_time
+                    .format(DAEMON_SNAPSHOT_DATE_TIME_FORMAT)
+                    .to_string(),
+            )
+        };
+        let processes = |process_names: &[String]| -> String {
+            let process_names: Vec<String> =
+                process_names.iter().map(|name| json_string(name)).collect();
+            format!("[{}]", process_names.join(","))
+        };
+        let rules: Vec<String> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                format!(
+                    "{{\"process_name\":{},\"rule\":{},\"next_boundary\":{}}}",
+                    json_string(&rule.process_name),
+                    json_string(&rule.rule),
+                    rule.next_boundary
+                        .as_ref()
+                        .map_or("null".to_string(), date_time)
+                )
+            })
+            .collect();
+        let overrides: Vec<String> = self
+            .overrides
+            .iter()
+            .map(|r#override| match r#override {
+                Override::Lockdown(reason) => format!(
+                    "{{\"kind\":\"lockdown\",\"reason\":{}}}",
+                    json_string(&reason.to_string())
+                ),
+                Override::Busy(process_names) => format!(
+                    "{{\"kind\":\"busy\",\"processes\":{}}}",
+                    processes(process_names)
+                ),
+                Override::DoNotDisturb(process_names) => format!(
+                    "{{\"kind\":\"do_not_disturb\",\"processes\":{}}}",
+                    processes(process_names)
+                ),
+                Override::Granted(process_name, until) => format!(
+                    "{{\"kind\":\"granted\",\"process_name\":{},\"until\":{}}}",
+                    json_string(process_name),
+                    date_time(until)
+                ),
+            })
+            .collect();
+        let recent_events: Vec<String> = self
+            .recent_events
+            .iter()
+            .map(EnforcementEvent::to_json)
+            .collect();
+
+        format!(
+            "{{\"pid\":{},\"date_time\":{},\"rules\":[{}],\"overrides\":[{}],\"recent_events\":[{}]}}",
+            self.pid,
+            date_time(&self.date_time),
+            rules.join(","),
+            overrides.join(","),
+            recent_events.join(",")
+        )
+    }
+}
+
+pub fn write_daemon_snapshot(
+    swyt_filepath: &Path,
+    snapshot: &DaemonSnapshot,
+) -> Result<(), SwytError> {
+    fs::write(
+        get_daemon_snapshot_filepath(swyt_filepath),
+        snapshot.to_json(),
+    )?;
+    Ok(())
+}
+
+/// Reads the JSON snapshot last written by the daemon.
+pub fn read_daemon_snapshot(swyt_filepath: &Path) -> Result<String, SwytError> {
+    Ok(fs::read_to_string(get_daemon_snapshot_filepath(
+        swyt_filepath,
+    ))?)
+}
+
+pub(crate) fn get_daemon_snapshot_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(DAEMON_SNAPSHOT_FILE_NAME)
+}
+
+fn json_string(string: &str) -> String {
+    let mut json_string = String::with_capacity(string.len() + 2);
+    json_string.push('"');
+    for character in string.chars() {
+        match character {
+            '"' => json_string.push_str("\\\""),
+            '\\' => json_string.push_str("\\\\"),
+            character if character.is_control() => {
+                let _ = write!(json_string, "\\u{:04x}", character as u32);
+            }
+            character => json_string.push(character),
+        }
+    }
+    json_string.push('"');
+    json_string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_rules;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn daemon_snapshot_json() {
+        let rules = parse_rules("game=18:00~20:00;MO\n*=*;SA fullscreen").unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let snapshot = DaemonSnapshot::new(
+            &rules,
+            &EnforcementState::new(),
+            vec![
+                Override::Lockdown(LockdownReason::Tampering),
+                Override::Busy(vec!["dis\"cord".to_string()]),
+            ],
+            monday.and_hms(19, 0, 0),
+        );
+
+        assert_eq!(
+            snapshot.rules()[0].next_boundary(),
+            Some(&monday.and_hms(20, 0, 1))
+        );
+        assert_eq!(
+            snapshot.rules()[1].next_boundary(),
+            Some(&NaiveDate::from_ymd(2020, 6, 6).and_hms(0, 0, 0))
+        );
+        assert!(snapshot.to_json().contains(
+            "\"date_time\":\"2020-06-01T19:00:00\",\"rules\":[{\"process_name\":\"game\",\"rule\":\"game=18:00~20:00;MO\",\"next_boundary\":\"2020-06-01T20:00:01\"}"
+        ));
+        assert!(snapshot.to_json().contains(
+            "\"overrides\":[{\"kind\":\"lockdown\",\"reason\":\"tampering\"},{\"kind\":\"busy\",\"processes\":[\"dis\\\"cord\"]}]"
+        ));
+    }
+}
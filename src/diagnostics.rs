@@ -0,0 +1,177 @@
+use crate::{format_day_of_week, Condition, Rule, Rules, WEEKDAYS};
+use chrono::NaiveTime;
+use std::fmt::{Display, Formatter};
+
+/// Rules that load but likely don't do what was meant, leading to silent non-enforcement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleWarning {
+    /// Periods of the rule overlap on the days, e.g. a period written twice.
+    OverlappingPeriods {
+        process_name: String,
+        days: Vec<&'static str>,
+    },
+    /// The period ends before it begins and allows nothing, periods don't span midnight.
+    EmptyPeriod {
+        process_name: String,
+        period: String,
+    },
+    /// The exceptions of the rule take out all its allowed periods.
+    NeverAllowed { process_name: String },
+    /// The condition or action needs a feature this build of swyt lacks.
+    Unsupported {
+        process_name: String,
+        what: String,
+        feature: &'static str,
+    },
+}
+
+impl Display for RuleWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleWarning::OverlappingPeriods { process_name, days } => write!(
+                f,
+                "overlapping periods on {} in rule {}",
+                days.join(","),
+                process_name
+            ),
+            RuleWarning::EmptyPeriod {
+                process_name,
+                period,
+            } => write!(
+                f,
+                "period {} ending before it begins in rule {}",
+                period, process_name
+            ),
+            RuleWarning::NeverAllowed { process_name } => write!(
+                f,
+                "exceptions taking out all the periods of rule {}",
+                process_name
+            ),
+            RuleWarning::Unsupported {
+                process_name,
+                what,
+                feature,
+            } => write!(
+                f,
+                "{} without the {} feature in rule {}",
+                what, feature, process_name
+            ),
+        }
+    }
+}
+
+/// Summary of the loaded rules, shared by the daemon and `swyt lint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics {
+    rule_count: usize,
+    warnings: Vec<RuleWarning>,
+}
+
+impl Diagnostics {
+    pub fn of(rules: &Rules) -> Self {
+        Diagnostics {
+            rule_count: rules.len(),
+            warnings: rules.iter().flat_map(rule_warnings).collect(),
+        }
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rule_count
+    }
+
+    pub fn warnings(&self) -> &[RuleWarning] {
+        &self.warnings
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} rule(s) loaded, {} warning(s)",
+            self.rule_count,
+            self.warnings.len()
+        )?;
+        if !self.warnings.is_empty() {
+            let warnings: Vec<String> = self.warnings.iter().map(RuleWarning::to_string).collect();
+            write!(f, ": {}", warnings.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn rule_warnings(rule: &Rule) -> Vec<RuleWarning> {
+    let process_name = || rule.process_name.clone();
+    let mut warnings = vec![];
+
+    let overlapping_days: Vec<&'static str> = WEEKDAYS
+        .iter()
+        .filter(|weekday| {
+            let mut periods: Vec<(NaiveTime, NaiveTime)> = rule
+                .allowed_periods
+                .iter()
+                .filter(|period| {
+                    period.days_of_week.contains(weekday) && period.begin_time <= period.end_time
+                })
+                .map(|period| (period.begin_time, period.end_time))
+                .collect();
+            periods.sort();
+            // Periods written back to back, such as 10:00~12:00 and 12:00~14:00, are fine
+            periods.windows(2).any(|pair| pair[1].0 < pair[0].1)
+        })
+        .map(|&weekday| format_day_of_week(weekday))
+        .collect();
+    if !overlapping_days.is_empty() {
+        warnings.push(RuleWarning::OverlappingPeriods {
+            process_name: process_name(),
+            days: overlapping_days,
+        });
+    }
+
+    for period in &rule.allowed_periods {
+        if period.begin_time > period.end_time {
+            warnings.push(RuleWarning::EmptyPeriod {
+                process_name: process_name(),
+                period: period.to_string(),
+            });
+        }
+    }
+
+    let never_allowed = WEEKDAYS
+        .iter()
+        .all(|&weekday| rule.allowed_times(weekday).is_empty());
+    if !rule.excepted_periods.is_empty() && !rule.allowed_periods.is_empty() && never_allowed {
+        warnings.push(RuleWarning::NeverAllowed {
+            process_name: process_name(),
+        });
+    }
+
+    let mut unsupported = |what: String, feature| {
+        warnings.push(RuleWarning::Unsupported {
+            process_name: process_name(),
+            what,
+            feature,
+        })
+    };
+    let has_window = cfg!(all(feature = "window", any(target_os = "linux", windows)));
+    let has_gpu = cfg!(all(feature = "gpu", target_os = "linux"));
+    for condition in rule.conditions.iter().flat_map(Condition::leaves) {
+        match condition {
+            Condition::Fullscreen if !has_window => {
+                unsupported("fullscreen condition never satisfied".to_string(), "window")
+            }
+            Condition::GpuUsageAbove(_) if !has_gpu => {
+                unsupported(format!("{} condition never satisfied", condition), "gpu")
+            }
+            _ => (),
+        }
+    }
+    for action in rule.actions() {
+        if action.handles_windows() && !has_window {
+            unsupported(format!("{} action falling back to kill", action), "window");
+        }
+    }
+
+    warnings
+}
@@ -0,0 +1,436 @@
+use crate::breaks::is_on_break;
+use crate::budget::budget_overrun;
+use crate::condition::ConditionProbe;
+#[cfg(any(
+    all(feature = "native", any(target_os = "linux", windows)),
+    all(feature = "process", target_os = "linux")
+))]
+use crate::fingerprint::{learn, renamed_copy};
+use crate::grace::{kill_deferral, termination, Termination};
+use crate::restraint::{release, restrain, restraint, Restraint};
+use crate::schedule::EvaluationTime;
+use crate::session::session_exemption;
+#[cfg(any(
+    all(feature = "native", any(target_os = "linux", windows)),
+    all(feature = "process", target_os = "linux")
+))]
+use crate::RenamedBinaryPolicy;
+use crate::{
+    evaluate_process, is_protected_from, record_enforcement, Action, Configuration,
+    EnforcementState, ProcessTarget, Rule, Rules, ScanReport, SwytError, Verdict,
+};
+use log::{info, trace, warn};
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+#[cfg(all(feature = "window", any(target_os = "linux", windows)))]
+pub(crate) use crate::window::{close_windows, minimize};
+
+/// Without window support, the `minimize` and `close` actions can't be parsed, the processes
+/// of the rules built with them are left running.
+#[cfg(not(all(feature = "window", any(target_os = "linux", windows))))]
+pub(crate) fn minimize(_pid: u32) -> Result<(), SwytError> {
+    Err(SwytError::Unsupported("minimize windows"))
+}
+
+#[cfg(not(all(feature = "window", any(target_os = "linux", windows))))]
+pub(crate) fn close_windows(_pid: u32, _title_pattern: &str) -> Result<(), SwytError> {
+    Err(SwytError::Unsupported("close windows"))
+}
+
+/// Process listed by a backend, with what the backend can read from it and do to it. The
+/// rules are enforced the same way whichever backend lists the processes.
+pub(crate) trait ProcessOps {
+    fn pid(&self) -> u32;
+    fn name(&self) -> &str;
+    async fn executable_path(&self) -> Option<String>;
+    /// The arguments of the process separated by spaces.
+    async fn command_line(&self) -> Option<String>;
+    async fn running_for(&self) -> Option<Duration>;
+    /// Returns the pid of the parent of the process when it is the same application, running
+    /// under the same name, the process being one of its helpers then, such as the renderers
+    /// of an Electron app.
+    async fn application_parent(&self) -> Option<u32>;
+    /// Asks the process to exit.
+    async fn terminate(&self) -> Result<(), SwytError>;
+    async fn kill(&self) -> Result<(), SwytError>;
+    async fn suspend(&self) -> Result<(), SwytError>;
+    async fn resume(&self) -> Result<(), SwytError>;
+    /// Gives the process the lowest priority, returning its priority before.
+    async fn throttle(&self) -> Result<i32, SwytError>;
+    async fn set_priority(&self, priority: i32) -> Result<(), SwytError>;
+}
+
+/// What the processes of a scan are checked against, the time of the scan included. The
+/// conditions are probed once per scan for all of them.
+pub(crate) struct Scan<'a> {
+    rules: &'a Rules,
+    configuration: &'a Configuration,
+    enforcement_state: &'a EnforcementState,
+    current_time: EvaluationTime,
+    checked_at: Instant,
+    /// The interval between the checks in effect, the low-power one in low-power mode.
+    check_interval: Duration,
+    condition_probe: Mutex<ConditionProbe>,
+}
+
+impl<'a> Scan<'a> {
+    pub(crate) fn new(
+        rules: &'a Rules,
+        configuration: &'a Configuration,
+        enforcement_state: &'a EnforcementState,
+    ) -> Self {
+        let condition_probe = ConditionProbe::new(configuration);
+        let check_interval = match condition_probe.is_low_power() {
+            true => configuration.low_power_check_interval(),
+            false => configuration.check_interval(),
+        };
+        Scan {
+            rules,
+            configuration,
+            enforcement_state,
+            current_time: EvaluationTime::now(configuration.dst_repeated_time()),
+            checked_at: Instant::now(),
+            check_interval: Duration::from_secs(u64::from(check_interval)),
+            condition_probe: Mutex::new(condition_probe),
+        }
+    }
+
+    pub(crate) fn configuration(&self) -> &Configuration {
+        self.configuration
+    }
+
+    pub(crate) fn current_time(&self) -> &EvaluationTime {
+        &self.current_time
+    }
+
+    fn probe(&self) -> MutexGuard<'_, ConditionProbe> {
+        self.condition_probe
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Evaluates the rules for the process and enforces them on it, recording what was done
+    /// in the report.
+    pub(crate) async fn enforce(&self, process: &impl ProcessOps, report: &mut ScanReport) {
+        let (rules, configuration, enforcement_state) =
+            (self.rules, self.configuration, self.enforcement_state);
+        let (pid, name) = (process.pid(), process.name());
+        let path = match rules.matches_paths() {
+            true => process.executable_path().await,
+            false => None,
+        };
+        let command_line = match rules.matches_command_lines() {
+            true => process.command_line().await,
+            false => None,
+        };
+        let target = ProcessTarget::named(name)
+            .with_path(path.as_deref())
+            .with_command_line(command_line.as_deref());
+        let (mut verdict, mut rule) =
+            evaluate_process(rules, &target, &self.current_time, |condition| {
+                self.probe().satisfies(pid, condition)
+            });
+        if rule.is_some_and(|rule| is_protected_from(configuration, rules, name, rule)) {
+            trace!("Skipped protected process {}", name);
+            (verdict, rule) = (Verdict::Unmatched, None);
+        }
+        // A protected process with the content of a blocked executable isn't what it claims
+        #[cfg(any(
+            all(feature = "native", any(target_os = "linux", windows)),
+            all(feature = "process", target_os = "linux")
+        ))]
+        {
+            let renamed_binaries = configuration.renamed_binaries();
+            if renamed_binaries != RenamedBinaryPolicy::Off && rules.get(name).is_some() {
+                learn(pid, name);
+            } else if renamed_binaries != RenamedBinaryPolicy::Off && verdict == Verdict::Unmatched
+            {
+                if let Some(copy) = renamed_copy(pid) {
+                    if copy.first_seen {
+                        warn!(
+                            "Process {} ({}) runs a renamed copy of {}",
+                            name, pid, copy.original_name
+                        );
+                    }
+                    if renamed_binaries == RenamedBinaryPolicy::Block {
+                        (verdict, rule) = evaluate_process(
+                            rules,
+                            &ProcessTarget::named(&copy.original_name),
+                            &self.current_time,
+                            |condition| self.probe().satisfies(pid, condition),
+                        );
+                    }
+                }
+            }
+        }
+        let mut overrun = None;
+        if let (Verdict::Allowed, Some(rule)) = (verdict, rule) {
+            let date = rule.evaluation_time(&self.current_time).date_time().date();
+            if is_on_break(enforcement_state, rule) {
+                verdict = Verdict::Blocked;
+            } else {
+                let started_at = match rule.budget() {
+                    Some(_) => process
+                        .running_for()
+                        .await
+                        .and_then(|running_for| self.checked_at.checked_sub(running_for)),
+                    None => None,
+                };
+                overrun = budget_overrun(
+                    enforcement_state,
+                    rule,
+                    date,
+                    self.checked_at,
+                    self.check_interval,
+                    started_at,
+                );
+                if overrun.is_some() {
+                    verdict = Verdict::Blocked;
+                }
+            }
+        }
+        if verdict != Verdict::Blocked {
+            if let Some(restraint) = restraint(enforcement_state, pid) {
+                release_restraint(enforcement_state, process, restraint).await;
+            }
+        }
+        report.record_scanned(name, verdict);
+        let rule = match (verdict, rule) {
+            (Verdict::Blocked, Some(rule)) => rule,
+            _ => return,
+        };
+        if let Some(exemption) = session_exemption(configuration, pid) {
+            trace!("Skipped process {} {}", name, exemption);
+            return;
+        }
+        report.record_blocked();
+        // Only the redacted command line is recorded, its secrets stay out of the statistics
+        // and the reports
+        let command_line = command_line
+            .as_deref()
+            .map(|command_line| configuration.redactor().redact(command_line));
+        let command_line = command_line.as_deref();
+
+        let action = match overrun {
+            Some(overrun) => rule.budget_action(overrun),
+            None => rule.action_at(rule.evaluation_time(&self.current_time).date_time()),
+        };
+        match action {
+            // Failing to minimize the windows mustn't turn the nudge into a kill
+            Action::Minimize => {
+                match minimize(pid) {
+                    Ok(()) => {
+                        trace!("Minimized process {}", name);
+                        record_enforcement(
+                            enforcement_state,
+                            rule,
+                            name,
+                            pid,
+                            command_line,
+                            "minimize",
+                        );
+                        report.record_minimized();
+                    }
+                    Err(err) => warn!(
+                        "Couldn't minimize process {}, leaving it running: {}",
+                        name, err
+                    ),
+                }
+                return;
+            }
+            Action::CloseWindows(title_pattern) => {
+                match close_windows(pid, title_pattern) {
+                    Ok(()) => {
+                        trace!(
+                            "Closed windows of process {} matching {}",
+                            name,
+                            title_pattern
+                        );
+                        record_enforcement(
+                            enforcement_state,
+                            rule,
+                            name,
+                            pid,
+                            command_line,
+                            "close",
+                        );
+                        report.record_closed();
+                    }
+                    Err(err) => warn!(
+                        "Couldn't close the windows of process {}, leaving it running: {}",
+                        name, err
+                    ),
+                }
+                return;
+            }
+            Action::Notify | Action::Throttle | Action::Suspend => {
+                self.restrain(process, rule, action, command_line).await;
+                report.record_restrained();
+                return;
+            }
+            Action::Kill => (),
+        }
+
+        // The helpers of an application exit with its root process, killed in their place.
+        // Those outliving it are reparented and killed as roots by the next scan
+        if let Some(parent_pid) = process.application_parent().await {
+            trace!(
+                "Left process {} to be killed with its parent {}",
+                name,
+                parent_pid
+            );
+            report.record_deferred();
+            return;
+        }
+        let deferral = kill_deferral(enforcement_state, configuration, &mut self.probe(), pid);
+        if let Some(deferral) = deferral {
+            info!("Deferred killing process {} which {}", name, deferral);
+            report.record_deferred();
+            return;
+        }
+        let termination = termination(enforcement_state, configuration, pid);
+        if termination == Some(Termination::Start) && process.terminate().await.is_ok() {
+            info!(
+                "Asked process {} to terminate, killing it in {} seconds",
+                name,
+                configuration.terminate_grace()
+            );
+            record_enforcement(
+                enforcement_state,
+                rule,
+                name,
+                pid,
+                command_line,
+                "terminate",
+            );
+            report.record_deferred();
+            return;
+        }
+        if termination == Some(Termination::Pending) {
+            trace!("Waiting for process {} to terminate", name);
+            report.record_deferred();
+            return;
+        }
+
+        // A process that couldn't be killed is reported as an error, not as killed
+        match process.kill().await {
+            Ok(()) => {
+                trace!("Killed process {}", name);
+                record_enforcement(enforcement_state, rule, name, pid, command_line, "kill");
+                release(enforcement_state, pid);
+                report.record_killed();
+            }
+            Err(err) => report.record_error(name, pid, err.to_string()),
+        }
+    }
+
+    /// Takes the `notify`, `throttle` or `suspend` action on the process, once, leaving it
+    /// running. An action the backend can't take on this platform is only warned about once,
+    /// for all processes.
+    async fn restrain(
+        &self,
+        process: &impl ProcessOps,
+        rule: &Rule,
+        action: &Action,
+        command_line: Option<&str>,
+    ) {
+        let enforcement_state = self.enforcement_state;
+        let (pid, name) = (process.pid(), process.name());
+        let restraint = restraint(enforcement_state, pid).unwrap_or_default();
+        if restraint.includes(action) || enforcement_state.is_unsupported(action) {
+            return;
+        }
+        let restrained = match action {
+            Action::Throttle => process.throttle().await.map(|priority| Restraint {
+                priority: Some(priority),
+                ..restraint
+            }),
+            Action::Suspend => process.suspend().await.map(|()| Restraint {
+                suspended: true,
+                ..restraint
+            }),
+            _ => Ok(Restraint {
+                notified: true,
+                ..restraint
+            }),
+        };
+        match restrained {
+            Ok(restraint) => {
+                restrain(enforcement_state, pid, restraint);
+                trace!("Took action {} on process {}", action, name);
+                record_enforcement(
+                    enforcement_state,
+                    rule,
+                    name,
+                    pid,
+                    command_line,
+                    &action.to_string(),
+                );
+            }
+            Err(err @ SwytError::Unsupported(_)) => {
+                warn!("{}, leaving the processes to {} running", err, action);
+                enforcement_state.record_unsupported(action);
+            }
+            Err(err) => warn!(
+                "Couldn't {} process {}, leaving it running: {}",
+                action, name, err
+            ),
+        }
+    }
+}
+
+/// Resumes a process its rule allows again and gives it its priority back, as they were
+/// before it was restrained.
+async fn release_restraint(
+    enforcement_state: &EnforcementState,
+    process: &impl ProcessOps,
+    restraint: Restraint,
+) {
+    let name = process.name();
+    if restraint.suspended {
+        if let Err(err) = process.resume().await {
+            warn!("Couldn't resume process {}: {}", name, err);
+        }
+    }
+    if let Some(priority) = restraint.priority {
+        if let Err(err) = process.set_priority(priority).await {
+            warn!("Couldn't restore the priority of process {}: {}", name, err);
+        }
+    }
+    release(enforcement_state, process.pid());
+    trace!("Released process {}", name);
+}
+
+/// Releases every process the state restrains, as when the daemon stops, `process_of`
+/// returning the process of a pid when it can still be read. Returns how many processes
+/// were resumed or given their priority back.
+pub(crate) async fn release_restraints<P: ProcessOps, F: Future<Output = Option<P>>>(
+    enforcement_state: &EnforcementState,
+    process_of: impl Fn(u32) -> F,
+) -> usize {
+    let pids: Vec<u32> = enforcement_state
+        .restraints
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .keys()
+        .copied()
+        .collect();
+    let mut released = 0;
+    for pid in pids {
+        let restraint = match restraint(enforcement_state, pid) {
+            Some(restraint) => restraint,
+            None => continue,
+        };
+        match process_of(pid).await {
+            Some(process) => release_restraint(enforcement_state, &process, restraint).await,
+            None => release(enforcement_state, pid),
+        }
+        if restraint.suspended || restraint.priority.is_some() {
+            released += 1;
+        }
+    }
+
+    released
+}
@@ -0,0 +1,184 @@
+use crate::budget::{BudgetUsage, CountedTime};
+use crate::daemon::EnforcementEvent;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+#[cfg(any(feature = "native", feature = "process"))]
+use {
+    crate::breaks::BreakSession, crate::grace::KillDeferral, crate::identity::ProcessIdentity,
+    crate::restraint::Restraint, crate::Action, chrono::Local, std::collections::BTreeSet,
+    std::time::Instant,
+};
+
+/// Actions taken on processes kept for the snapshots, the oldest being forgotten past it.
+#[cfg(any(feature = "native", feature = "process"))]
+const MAX_RECENT_EVENTS: usize = 20;
+/// Actions taken on processes kept until they are taken to be reported, the oldest being
+/// forgotten past it when nobody takes them.
+#[cfg(any(feature = "native", feature = "process"))]
+const MAX_UNREPORTED_EVENTS: usize = 1000;
+/// Messages of the rules kept until they are taken to be shown, the oldest being forgotten
+/// past it when nobody takes them.
+#[cfg(any(feature = "native", feature = "process"))]
+const MAX_UNSHOWN_MESSAGES: usize = 100;
+
+/// What the enforcement passes keep from one to the next, the passes themselves not keeping
+/// any state. The daemon keeps one for as long as it runs and passes it to `process_rules`,
+/// and to `is_execution_denied` from the thread denying executions.
+#[derive(Debug, Default)]
+pub struct EnforcementState {
+    /// Usage of the rules with a budget, by process name of the rule, with the time counted
+    /// when their processes were last seen running.
+    pub(crate) budget_usages: Mutex<BTreeMap<String, (BudgetUsage, Option<CountedTime>)>>,
+    /// Sessions of the rules with a mandatory break, by process name of the rule so
+    /// restarting a process doesn't start a new one.
+    #[cfg(any(feature = "native", feature = "process"))]
+    pub(crate) break_sessions: Mutex<BTreeMap<String, BreakSession>>,
+    /// Deadlines of the deferred kills, by process and reason.
+    #[cfg(any(feature = "native", feature = "process"))]
+    pub(crate) kill_deadlines: Mutex<BTreeMap<(ProcessIdentity, KillDeferral), Instant>>,
+    /// Deadlines of the processes asked to terminate, after which they are killed.
+    #[cfg(any(feature = "native", feature = "process"))]
+    pub(crate) termination_deadlines: Mutex<BTreeMap<ProcessIdentity, Instant>>,
+    /// What was done to the processes left running by a `notify`, `throttle` or `suspend`
+    /// action, by pid.
+    #[cfg(any(feature = "native", feature = "process"))]
+    pub(crate) restraints: Mutex<BTreeMap<u32, (ProcessIdentity, Restraint)>>,
+    /// Actions the backend couldn't take as unsupported on this platform, not to be tried
+    /// again.
+    #[cfg(any(feature = "native", feature = "process"))]
+    unsupported_actions: Mutex<BTreeSet<String>>,
+    recent_events: Mutex<VecDeque<EnforcementEvent>>,
+    unreported_events: Mutex<VecDeque<EnforcementEvent>>,
+    unshown_messages: Mutex<VecDeque<String>>,
+}
+
+impl EnforcementState {
+    pub fn new() -> Self {
+        EnforcementState::default()
+    }
+
+    /// Returns the time the processes of the rules with a budget ran for, as counted in this
+    /// state.
+    pub fn budget_usages(&self) -> Vec<BudgetUsage> {
+        self.budget_usages
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .map(|(usage, _)| usage.clone())
+            .collect()
+    }
+
+    /// Resumes counting from the given usages, as read when the daemon starts, so a restart
+    /// doesn't refill the budgets.
+    pub fn restore_budget_usages(&self, usages: Vec<BudgetUsage>) {
+        let mut current_usages = self
+            .budget_usages
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for usage in usages {
+            current_usages
+                .entry(usage.process_name().to_string())
+                .or_insert((usage, None));
+        }
+    }
+
+    /// Returns the earliest deadline of the processes asked to terminate, for the daemon to
+    /// check again then and kill the ones still running.
+    #[cfg(any(feature = "native", feature = "process"))]
+    pub fn next_termination_deadline(&self) -> Option<Instant> {
+        let now = Instant::now();
+        self.termination_deadlines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|&&deadline| deadline > now)
+            .min()
+            .copied()
+    }
+
+    #[cfg(any(feature = "native", feature = "process"))]
+    pub(crate) fn is_unsupported(&self, action: &Action) -> bool {
+        self.unsupported_actions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(&action.to_string())
+    }
+
+    #[cfg(any(feature = "native", feature = "process"))]
+    pub(crate) fn record_unsupported(&self, action: &Action) {
+        self.unsupported_actions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(action.to_string());
+    }
+
+    /// Returns the actions recently taken on processes, the oldest first.
+    pub fn recent_events(&self) -> Vec<EnforcementEvent> {
+        self.recent_events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the actions taken on processes since they were last taken, the oldest first,
+    /// for them to be reported once.
+    pub fn take_unreported_events(&self) -> Vec<EnforcementEvent> {
+        self.unreported_events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain(..)
+            .collect()
+    }
+
+    /// Returns the messages of the rules shown since they were last taken, the oldest first,
+    /// for them to be notified once.
+    pub fn take_unshown_messages(&self) -> Vec<String> {
+        self.unshown_messages
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain(..)
+            .collect()
+    }
+
+    #[cfg(any(feature = "native", feature = "process"))]
+    pub(crate) fn record_message(&self, message: String) {
+        let mut messages = self
+            .unshown_messages
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if messages.len() >= MAX_UNSHOWN_MESSAGES {
+            messages.pop_front();
+        }
+        messages.push_back(message);
+    }
+
+    /// The command line must already be redacted, the events being recorded and reported.
+    #[cfg(any(feature = "native", feature = "process"))]
+    pub(crate) fn record_event(
+        &self,
+        process_name: &str,
+        pid: u32,
+        command_line: Option<&str>,
+        action: &str,
+    ) {
+        let event = EnforcementEvent::new(
+            Local::now().naive_local(),
+            process_name.to_string(),
+            pid,
+            action.to_string(),
+        )
+        .with_command_line(command_line.map(str::to_string));
+        for (events, max_events) in [
+            (&self.recent_events, MAX_RECENT_EVENTS),
+            (&self.unreported_events, MAX_UNREPORTED_EVENTS),
+        ] {
+            let mut events = events.lock().unwrap_or_else(PoisonError::into_inner);
+            if events.len() >= max_events {
+                events.pop_front();
+            }
+            events.push_back(event.clone());
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate::schedule::EvaluationTime;
+use crate::session::session_exemption;
+use crate::{
+    evaluate_process, is_protected_from, record_enforcement, Action, Configuration,
+    EnforcementState, ProcessTarget, Rules, SwytError, Verdict,
+};
+use log::trace;
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::ptr;
+
+const EVENT_BUFFER_LENGTH: usize = 4096;
+
+/// Denies executing the programs whose rule blocks them, before they even start, using
+/// fanotify permission events, which requires CAP_SYS_ADMIN. Only the path of the executable
+/// is known at that point so the rules with conditions or matching the command line don't
+/// apply.
+pub struct ExecGuard {
+    fanotify: libc::c_int,
+}
+
+impl ExecGuard {
+    pub fn new() -> Result<Self, SwytError> {
+        let fanotify = unsafe {
+            libc::fanotify_init(
+                libc::FAN_CLASS_CONTENT | libc::FAN_CLOEXEC,
+                (libc::O_RDONLY | libc::O_LARGEFILE | libc::O_CLOEXEC) as libc::c_uint,
+            )
+        };
+        if fanotify < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let exec_guard = ExecGuard { fanotify };
+
+        exec_guard.mark_mount("/")?;
+        // Executables can live on other mounts, the ones that can't be marked, such as pseudo
+        // filesystems, are skipped
+        for mount_point in mount_points()? {
+            let _ = exec_guard.mark_mount(&mount_point);
+        }
+
+        Ok(exec_guard)
+    }
+
+    fn mark_mount(&self, mount_point: &str) -> Result<(), SwytError> {
+        let mount_point = CString::new(mount_point).map_err(|_| SwytError::ProcessFetchError)?;
+        if unsafe {
+            libc::fanotify_mark(
+                self.fanotify,
+                libc::FAN_MARK_ADD | libc::FAN_MARK_MOUNT,
+                libc::FAN_OPEN_EXEC_PERM,
+                libc::AT_FDCWD,
+                mount_point.as_ptr(),
+            )
+        } < 0
+        {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(())
+    }
+
+    /// Answers the permission requests until an error occurs. Every request must be
+    /// answered as the process executing the program waits for it. `is_denied` tells whether
+    /// the process with the given pid can't execute the program at the given path, see
+    /// `is_execution_denied`.
+    pub fn run(&self, mut is_denied: impl FnMut(&Path, u32) -> bool) -> Result<(), SwytError> {
+        let mut buffer = [0u8; EVENT_BUFFER_LENGTH];
+        loop {
+            let length = unsafe {
+                libc::read(
+                    self.fanotify,
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                )
+            };
+            if length < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error.into());
+            }
+
+            let mut offset = 0;
+            while offset + mem::size_of::<libc::fanotify_event_metadata>() <= length as usize {
+                let metadata: libc::fanotify_event_metadata = unsafe {
+                    ptr::read_unaligned(
+                        buffer[offset..].as_ptr() as *const libc::fanotify_event_metadata
+                    )
+                };
+                if (metadata.event_len as usize) < mem::size_of::<libc::fanotify_event_metadata>() {
+                    break;
+                }
+
+                self.handle_event(&metadata, &mut is_denied)?;
+                offset += metadata.event_len as usize;
+            }
+        }
+    }
+
+    fn handle_event(
+        &self,
+        metadata: &libc::fanotify_event_metadata,
+        is_denied: &mut impl FnMut(&Path, u32) -> bool,
+    ) -> Result<(), SwytError> {
+        if metadata.fd < 0 {
+            return Ok(());
+        }
+
+        let is_denied = metadata.mask & libc::FAN_OPEN_EXEC_PERM != 0
+            && fs::read_link(format!("/proc/self/fd/{}", metadata.fd))
+                .is_ok_and(|executable_path| is_denied(&executable_path, metadata.pid as u32));
+        let response = libc::fanotify_response {
+            fd: metadata.fd,
+            response: if is_denied {
+                libc::FAN_DENY
+            } else {
+                libc::FAN_ALLOW
+            },
+        };
+        let written = unsafe {
+            libc::write(
+                self.fanotify,
+                &response as *const libc::fanotify_response as *const libc::c_void,
+                mem::size_of::<libc::fanotify_response>(),
+            )
+        };
+        unsafe { libc::close(metadata.fd) };
+        if written < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ExecGuard {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fanotify) };
+    }
+}
+
+/// Returns whether the rules deny the process with the given pid to execute the program at
+/// the given path, recording the denial in the enforcement state.
+pub fn is_execution_denied(
+    rules: &Rules,
+    configuration: &Configuration,
+    enforcement_state: &EnforcementState,
+    executable_path: &Path,
+    pid: u32,
+) -> bool {
+    let executable_name = match executable_path
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+    {
+        Some(executable_name) => executable_name,
+        None => return false,
+    };
+
+    let current_time = EvaluationTime::now(configuration.dst_repeated_time());
+    let target = ProcessTarget::named(executable_name).with_path(executable_path.to_str());
+    let rule = match evaluate_process(rules, &target, &current_time, |_| false) {
+        (Verdict::Blocked, Some(rule)) => rule,
+        _ => return false,
+    };
+    if is_protected_from(configuration, rules, executable_name, rule) {
+        return false;
+    }
+    // The actions leaving the processes running let them start too
+    let action = rule.action_at(rule.evaluation_time(&current_time).date_time());
+    if matches!(action, Action::Notify | Action::Throttle | Action::Suspend) {
+        return false;
+    }
+
+    if session_exemption(configuration, pid).is_some() {
+        return false;
+    }
+
+    trace!("Denied execution of {}", executable_path.display());
+    record_enforcement(
+        enforcement_state,
+        rule,
+        executable_name,
+        pid,
+        None,
+        "deny_exec",
+    );
+    true
+}
+
+fn mount_points() -> Result<Vec<String>, SwytError> {
+    Ok(fs::read_to_string("/proc/self/mounts")?
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(String::from)
+        .collect())
+}
@@ -0,0 +1,165 @@
+use crate::SwytError;
+use std::io;
+use std::mem;
+use std::time::Duration;
+
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+const PROC_EVENT_EXEC: u32 = 2;
+
+const NLMSG_HEADER_LENGTH: usize = 16;
+const CN_MSG_HEADER_LENGTH: usize = 20;
+const PROC_EVENT_DATA_OFFSET: usize = 16;
+
+/// Listens to the kernel process connector to get notified as soon as a process calls
+/// exec, which requires CAP_NET_ADMIN.
+///
+/// The connector was chosen over an eBPF program attached to `sched_process_exec`, through
+/// aya: it is in every kernel since 2.6.15 where a portable eBPF program needs BTF and
+/// CAP_BPF or root, it only takes libc where aya would bring its own crates and a separately
+/// compiled eBPF object to the build, which can't be built offline, and it reports the same exec events, the process being checked
+/// right after either way. What eBPF would add, acting before the process runs, is done by
+/// the fanotify permission events of the `exec-deny` feature.
+pub struct ExecMonitor {
+    socket: libc::c_int,
+}
+
+impl ExecMonitor {
+    pub fn new() -> Result<Self, SwytError> {
+        let socket = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_CONNECTOR,
+            )
+        };
+        if socket < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let exec_monitor = ExecMonitor { socket };
+
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = CN_IDX_PROC;
+        address.nl_pid = std::process::id();
+        if unsafe {
+            libc::bind(
+                socket,
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        } < 0
+        {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        exec_monitor.send_listen_request()?;
+        Ok(exec_monitor)
+    }
+
+    fn send_listen_request(&self) -> Result<(), SwytError> {
+        let message_length = NLMSG_HEADER_LENGTH + CN_MSG_HEADER_LENGTH + 4;
+        let mut message = Vec::with_capacity(message_length);
+        // nlmsghdr
+        message.extend_from_slice(&(message_length as u32).to_ne_bytes());
+        message.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+        message.extend_from_slice(&0u16.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&std::process::id().to_ne_bytes());
+        // cn_msg
+        message.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        message.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&4u16.to_ne_bytes());
+        message.extend_from_slice(&0u16.to_ne_bytes());
+        message.extend_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+
+        let sent = unsafe {
+            libc::send(
+                self.socket,
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(())
+    }
+
+    /// Waits at most `timeout` for processes to call exec, returns their pids.
+    pub fn wait(&mut self, timeout: Duration) -> Result<Vec<u32>, SwytError> {
+        let mut poll_fd = libc::pollfd {
+            fd: self.socket,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        match unsafe { libc::poll(&mut poll_fd, 1, timeout) } {
+            0 => return Ok(vec![]),
+            result if result < 0 => {
+                let error = io::Error::last_os_error();
+                return match error.kind() {
+                    io::ErrorKind::Interrupted => Ok(vec![]),
+                    _ => Err(error.into()),
+                };
+            }
+            _ => (),
+        }
+
+        let mut buffer = [0u8; 4096];
+        let received = unsafe {
+            libc::recv(
+                self.socket,
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+                libc::MSG_DONTWAIT,
+            )
+        };
+        if received < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(parse_exec_events(&buffer[..received as usize]))
+    }
+}
+
+impl Drop for ExecMonitor {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.socket) };
+    }
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> Option<u32> {
+    let bytes = buffer.get(offset..offset + 4)?;
+    Some(u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn parse_exec_events(mut buffer: &[u8]) -> Vec<u32> {
+    let mut pids = vec![];
+    while let Some(message_length) = read_u32(buffer, 0) {
+        let message_length = message_length as usize;
+        if message_length < NLMSG_HEADER_LENGTH || message_length > buffer.len() {
+            break;
+        }
+
+        let proc_event_offset = NLMSG_HEADER_LENGTH + CN_MSG_HEADER_LENGTH;
+        let message = &buffer[..message_length];
+        if read_u32(message, proc_event_offset) == Some(PROC_EVENT_EXEC) {
+            // exec_proc_event is { process_pid, process_tgid }, the tgid is the process id
+            if let Some(tgid) = read_u32(message, proc_event_offset + PROC_EVENT_DATA_OFFSET + 4) {
+                pids.push(tgid);
+            }
+        }
+
+        // Netlink messages are aligned on 4 bytes
+        let aligned_length = (message_length + 3) & !3;
+        buffer = &buffer[aligned_length.min(buffer.len())..];
+    }
+
+    pids
+}
@@ -0,0 +1,201 @@
+use crate::{evaluate_rules, load_config, load_rules, parse_rules, Configuration, Rules, Verdict};
+use chrono::NaiveDateTime;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::Path;
+use std::ptr;
+
+pub struct SwytRules(Rules);
+
+pub struct SwytConfiguration(Configuration);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwytVerdict {
+    Error = -1,
+    Unmatched = 0,
+    Allowed = 1,
+    Blocked = 2,
+}
+
+impl From<Verdict> for SwytVerdict {
+    fn from(verdict: Verdict) -> Self {
+        match verdict {
+            Verdict::Unmatched => SwytVerdict::Unmatched,
+            Verdict::Allowed => SwytVerdict::Allowed,
+            Verdict::Blocked => SwytVerdict::Blocked,
+        }
+    }
+}
+
+unsafe fn str_from_ptr<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(string).to_str().ok()
+}
+
+/// Parses a rules file content, returns NULL if it is invalid.
+///
+/// # Safety
+/// `rules` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn swyt_parse_rules(rules: *const c_char) -> *mut SwytRules {
+    match str_from_ptr(rules).map(parse_rules) {
+        Some(Ok(rules)) => Box::into_raw(Box::new(SwytRules(rules))),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Loads the rules file of a swyt directory, returns NULL on failure.
+///
+/// # Safety
+/// `swyt_filepath` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn swyt_load_rules(swyt_filepath: *const c_char) -> *mut SwytRules {
+    match str_from_ptr(swyt_filepath).map(|path| load_rules(Path::new(path))) {
+        Some(Ok(rules)) => Box::into_raw(Box::new(SwytRules(rules))),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `rules` must be NULL or a pointer returned by `swyt_parse_rules` or `swyt_load_rules`
+/// that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn swyt_rules_free(rules: *mut SwytRules) {
+    if !rules.is_null() {
+        drop(Box::from_raw(rules));
+    }
+}
+
+/// Evaluates the rules for a process at the given local time, expressed in seconds since
+/// 1970-01-01 00:00:00 of the local wall clock.
+///
+/// # Safety
+/// `rules` must be NULL or a live pointer returned by `swyt_parse_rules` or `swyt_load_rules`,
+/// `process_name` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn swyt_evaluate(
+    rules: *const SwytRules,
+    process_name: *const c_char,
+    local_time: i64,
+) -> SwytVerdict {
+    let process_name = match str_from_ptr(process_name) {
+        Some(process_name) => process_name,
+        None => return SwytVerdict::Error,
+    };
+    let date_time = match NaiveDateTime::from_timestamp_opt(local_time, 0) {
+        Some(date_time) => date_time,
+        None => return SwytVerdict::Error,
+    };
+
+    match rules.as_ref() {
+        Some(SwytRules(rules)) => evaluate_rules(rules, process_name, &date_time).into(),
+        None => SwytVerdict::Error,
+    }
+}
+
+/// Loads the configuration file of a swyt directory, returns NULL on failure.
+///
+/// # Safety
+/// `swyt_filepath` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn swyt_load_config(swyt_filepath: *const c_char) -> *mut SwytConfiguration {
+    match str_from_ptr(swyt_filepath).map(|path| load_config(Path::new(path))) {
+        Some(Ok(config)) => Box::into_raw(Box::new(SwytConfiguration(config))),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Returns the check interval in seconds, or 0 if `config` is NULL.
+///
+/// # Safety
+/// `config` must be NULL or a live pointer returned by `swyt_load_config`.
+#[no_mangle]
+pub unsafe extern "C" fn swyt_config_check_interval(config: *const SwytConfiguration) -> u32 {
+    config
+        .as_ref()
+        .map_or(0, |SwytConfiguration(config)| config.check_interval())
+}
+
+/// # Safety
+/// `config` must be NULL or a pointer returned by `swyt_load_config` that hasn't been freed
+/// yet.
+#[no_mangle]
+pub unsafe extern "C" fn swyt_config_free(config: *mut SwytConfiguration) {
+    if !config.is_null() {
+        drop(Box::from_raw(config));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn header_up_to_date() {
+        assert_eq!(
+            include_str!(concat!(env!("OUT_DIR"), "/swyt.h")),
+            include_str!("../include/swyt.h"),
+            "include/swyt.h is outdated, regenerate it with cbindgen"
+        );
+    }
+
+    #[test]
+    fn parse_and_evaluate() {
+        let rules_string = CString::new("game=18:00~20:00;MO").unwrap();
+        let process_name = CString::new("game").unwrap();
+        // Monday 2020-06-01, at 19:00 then 09:00
+        let (evening, morning) = (1_591_038_000, 1_591_002_000);
+        unsafe {
+            let rules = swyt_parse_rules(rules_string.as_ptr());
+            assert!(!rules.is_null());
+            assert_eq!(
+                swyt_evaluate(rules, process_name.as_ptr(), evening),
+                SwytVerdict::Allowed
+            );
+            assert_eq!(
+                swyt_evaluate(rules, process_name.as_ptr(), morning),
+                SwytVerdict::Blocked
+            );
+            assert_eq!(
+                swyt_evaluate(rules, ptr::null(), evening),
+                SwytVerdict::Error
+            );
+            assert_eq!(
+                swyt_evaluate(rules, process_name.as_ptr(), i64::MAX),
+                SwytVerdict::Error
+            );
+            swyt_rules_free(rules);
+        }
+    }
+
+    #[test]
+    fn null_and_invalid_input() {
+        let invalid_utf8 = CString::new(vec![b'g', 0xff, b'=', b'*']).unwrap();
+        let invalid_rules = CString::new("game=25:00~26:00;MO").unwrap();
+        let process_name = CString::new("game").unwrap();
+        unsafe {
+            assert!(swyt_parse_rules(ptr::null()).is_null());
+            assert!(swyt_parse_rules(invalid_utf8.as_ptr()).is_null());
+            assert!(swyt_parse_rules(invalid_rules.as_ptr()).is_null());
+            assert!(swyt_load_rules(ptr::null()).is_null());
+            assert!(swyt_load_rules(invalid_utf8.as_ptr()).is_null());
+            assert_eq!(
+                swyt_evaluate(ptr::null(), process_name.as_ptr(), 0),
+                SwytVerdict::Error
+            );
+            assert_eq!(
+                swyt_evaluate(ptr::null(), invalid_utf8.as_ptr(), 0),
+                SwytVerdict::Error
+            );
+            assert!(swyt_load_config(ptr::null()).is_null());
+            assert_eq!(swyt_config_check_interval(ptr::null()), 0);
+            swyt_rules_free(ptr::null_mut());
+            swyt_config_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::identity::ProcessIdentity;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+/// Executables seen running under the names of the rules, to recognize their renamed copies.
+static FINGERPRINTS: Mutex<Fingerprints> = Mutex::new(Fingerprints {
+    by_path: BTreeMap::new(),
+    rule_executables: BTreeMap::new(),
+    alerted: BTreeSet::new(),
+});
+/// Processes alerted about that are remembered, the oldest pids being forgotten past it.
+const MAX_ALERTED_PROCESSES: usize = 4096;
+
+/// Content of an executable, the size telling most of them apart without reading them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Fingerprint {
+    size: u64,
+    hash: u64,
+}
+
+struct Fingerprints {
+    /// Fingerprints of the executables, with the modification time they were computed at.
+    by_path: BTreeMap<PathBuf, (SystemTime, Fingerprint)>,
+    /// Name of the rule and path of the executables run under it, by fingerprint.
+    rule_executables: BTreeMap<Fingerprint, (String, BTreeSet<PathBuf>)>,
+    alerted: BTreeSet<ProcessIdentity>,
+}
+
+/// Copy of an executable run under the name of a rule, under another name.
+pub(crate) struct RenamedCopy {
+    pub(crate) original_name: String,
+    /// Whether the process wasn't reported as a renamed copy before.
+    pub(crate) first_seen: bool,
+}
+
+/// Remembers the executable of the process, running under the name of a rule.
+pub(crate) fn learn(pid: u32, process_name: &str) {
+    let path = match executable_path(pid) {
+        Some(path) => path,
+        None => return,
+    };
+    if let Some(fingerprint) = fingerprint(&path, |_| true) {
+        let mut fingerprints = FINGERPRINTS.lock().unwrap_or_else(PoisonError::into_inner);
+        fingerprints
+            .rule_executables
+            .entry(fingerprint)
+            .or_insert_with(|| (process_name.to_string(), BTreeSet::new()))
+            .1
+            .insert(path);
+    }
+}
+
+/// Returns what the process is a renamed copy of, if its executable has the content of one
+/// run under the name of a rule from another path. The same executable run under several
+/// names, such as a multi-call binary, isn't a copy.
+pub(crate) fn renamed_copy(pid: u32) -> Option<RenamedCopy> {
+    let path = executable_path(pid)?;
+    // Only the executables of the size of a known one are read
+    let fingerprint = fingerprint(&path, |size| {
+        FINGERPRINTS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .rule_executables
+            .keys()
+            .any(|fingerprint| fingerprint.size == size)
+    })?;
+
+    let mut fingerprints = FINGERPRINTS.lock().unwrap_or_else(PoisonError::into_inner);
+    let original_name = match fingerprints.rule_executables.get(&fingerprint) {
+        Some((original_name, paths)) if !paths.contains(&path) => original_name.clone(),
+        _ => return None,
+    };
+    if fingerprints.alerted.len() >= MAX_ALERTED_PROCESSES {
+        fingerprints.alerted.clear();
+    }
+    Some(RenamedCopy {
+        original_name,
+        first_seen: fingerprints.alerted.insert(ProcessIdentity::of(pid)),
+    })
+}
+
+/// Returns the fingerprint of the executable, computed again when it was modified since, if
+/// `wanted` accepts its size.
+fn fingerprint(path: &Path, wanted: impl Fn(u64) -> bool) -> Option<Fingerprint> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified_time = metadata.modified().ok()?;
+    if let Some(&(fingerprint_time, fingerprint)) = FINGERPRINTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .by_path
+        .get(path)
+    {
+        if fingerprint_time == modified_time {
+            return Some(fingerprint);
+        }
+    }
+    if !wanted(metadata.len()) {
+        return None;
+    }
+
+    let fingerprint = Fingerprint {
+        size: metadata.len(),
+        hash: hash_file(path).ok()?,
+    };
+    FINGERPRINTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .by_path
+        .insert(path.to_path_buf(), (modified_time, fingerprint));
+    Some(fingerprint)
+}
+
+fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer)? {
+            0 => return Ok(hasher.finish()),
+            length => hasher.write(&buffer[..length]),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn executable_path(pid: u32) -> Option<PathBuf> {
+    fs::read_link(format!("/proc/{}/exe", pid)).ok()
+}
+
+#[cfg(windows)]
+fn executable_path(pid: u32) -> Option<PathBuf> {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStringExt;
+    use winapi::shared::minwindef::{DWORD, MAX_PATH};
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::OpenProcess;
+    use winapi::um::winbase::QueryFullProcessImageNameW;
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return None;
+        }
+        let mut path = [0u16; MAX_PATH];
+        let mut length = path.len() as DWORD;
+        let queried = QueryFullProcessImageNameW(process, 0, path.as_mut_ptr(), &mut length);
+        CloseHandle(process);
+        (queried != 0).then(|| PathBuf::from(OsString::from_wide(&path[..length as usize])))
+    }
+}
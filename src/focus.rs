@@ -0,0 +1,45 @@
+#[cfg(target_os = "linux")]
+use std::process::Command;
+
+/// Returns whether Do Not Disturb is on in the desktop session of the daemon, as set in
+/// GNOME or reported through the `Inhibited` property of the freedesktop notification
+/// server, as KDE Plasma does. It is considered off on other platforms, and when the daemon
+/// can't reach the session.
+#[cfg(target_os = "linux")]
+pub fn is_do_not_disturb_on() -> bool {
+    let gnome_do_not_disturb = command_output(
+        "gsettings",
+        &["get", "org.gnome.desktop.notifications", "show-banners"],
+    )
+    .is_some_and(|show_banners| show_banners == "false");
+
+    gnome_do_not_disturb
+        || command_output(
+            "busctl",
+            &[
+                "--user",
+                "get-property",
+                "org.freedesktop.Notifications",
+                "/org/freedesktop/Notifications",
+                "org.freedesktop.Notifications",
+                "Inhibited",
+            ],
+        )
+        .is_some_and(|inhibited| inhibited == "b true")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_do_not_disturb_on() -> bool {
+    false
+}
+
+/// Returns the trimmed standard output of a command that succeeded.
+#[cfg(target_os = "linux")]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
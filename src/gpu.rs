@@ -0,0 +1,110 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Duration between the two samples the GPU usage is computed from.
+const SAMPLE_DURATION: Duration = Duration::from_millis(500);
+
+const CLIENT_ID_KEY: &str = "drm-client-id";
+const DEVICE_KEY: &str = "drm-pdev";
+const ENGINE_KEY_PREFIX: &str = "drm-engine-";
+
+/// Returns the GPU usage percentage of the processes using a GPU, that is the usage of the
+/// busiest engine they use. It is computed from the time the engines spent on each DRM
+/// client, reported in the fdinfo of the DRM file descriptors.
+pub fn gpu_usages() -> HashMap<u32, f64> {
+    let begin_instant = Instant::now();
+    let begin_sample = sample();
+    thread::sleep(SAMPLE_DURATION);
+    let end_sample = sample();
+    let elapsed_nanoseconds = begin_instant.elapsed().as_nanos() as f64;
+
+    end_sample
+        .into_iter()
+        .map(|(pid, engine_times)| {
+            let usage = engine_times
+                .iter()
+                .map(|(engine, &end_time)| {
+                    let begin_time = begin_sample
+                        .get(&pid)
+                        .and_then(|engine_times| engine_times.get(engine))
+                        .copied()
+                        .unwrap_or(end_time);
+                    end_time.saturating_sub(begin_time) as f64 * 100.0 / elapsed_nanoseconds
+                })
+                .fold(0.0, f64::max);
+            (pid, usage)
+        })
+        .collect()
+}
+
+/// Returns the busy time in nanoseconds of each engine, per process.
+fn sample() -> HashMap<u32, HashMap<String, u64>> {
+    let mut samples = HashMap::new();
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return samples,
+    };
+
+    for entry in entries.flatten() {
+        let pid = match entry.file_name().to_str().map(str::parse::<u32>) {
+            Some(Ok(pid)) => pid,
+            _ => continue,
+        };
+
+        let engine_times = process_sample(pid);
+        if !engine_times.is_empty() {
+            samples.insert(pid, engine_times);
+        }
+    }
+
+    samples
+}
+
+fn process_sample(pid: u32) -> HashMap<String, u64> {
+    let mut engine_times = HashMap::new();
+    let fdinfo_entries = match fs::read_dir(format!("/proc/{}/fdinfo", pid)) {
+        Ok(fdinfo_entries) => fdinfo_entries,
+        Err(_) => return engine_times,
+    };
+
+    // Several file descriptors can refer to the same DRM client
+    let mut clients = HashSet::new();
+    for fdinfo_entry in fdinfo_entries.flatten() {
+        let fdinfo = match fs::read_to_string(fdinfo_entry.path()) {
+            Ok(fdinfo) => fdinfo,
+            Err(_) => continue,
+        };
+
+        let fields: Vec<(&str, &str)> = fdinfo
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+        let field = |key| fields.iter().find(|&&(field_key, _)| field_key == key);
+        let client = match (field(DEVICE_KEY), field(CLIENT_ID_KEY)) {
+            (Some(&(_, device)), Some(&(_, client_id))) => (device, client_id),
+            (None, Some(&(_, client_id))) => ("", client_id),
+            _ => continue,
+        };
+        if !clients.insert((client.0.to_string(), client.1.to_string())) {
+            continue;
+        }
+
+        for &(key, value) in &fields {
+            let engine = match key.strip_prefix(ENGINE_KEY_PREFIX) {
+                Some(engine) => engine,
+                None => continue,
+            };
+            let time = value
+                .strip_suffix("ns")
+                .and_then(|time| time.trim().parse::<u64>().ok());
+            if let Some(time) = time {
+                *engine_times.entry(engine.to_string()).or_insert(0) += time;
+            }
+        }
+    }
+
+    engine_times
+}
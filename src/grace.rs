@@ -0,0 +1,156 @@
+use crate::condition::ConditionProbe;
+use crate::identity::ProcessIdentity;
+use crate::{Configuration, EnforcementState};
+use std::collections::btree_map::Entry;
+use std::fmt::{Display, Formatter};
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+
+/// Where a blocked process is in its graceful termination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Termination {
+    /// The process is to be asked to terminate now.
+    Start,
+    /// The process was asked to terminate and has until its deadline to.
+    Pending,
+}
+
+/// Why killing a process is deferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum KillDeferral {
+    /// The process seems to have unsaved work, so the user can save it.
+    UnsavedWork,
+    /// The process holds a blocking systemd-logind inhibitor, for a critical operation.
+    Inhibitor,
+}
+
+impl Display for KillDeferral {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KillDeferral::UnsavedWork => write!(f, "seems to have unsaved work"),
+            KillDeferral::Inhibitor => write!(f, "holds a blocking inhibitor"),
+        }
+    }
+}
+
+/// Returns why killing the process is deferred, if it is. The process is killed once the
+/// reason doesn't hold anymore, or when the deferral configured for it elapses, as tracked in
+/// the enforcement state.
+pub(crate) fn kill_deferral(
+    enforcement_state: &EnforcementState,
+    configuration: &Configuration,
+    condition_probe: &mut ConditionProbe,
+    pid: u32,
+) -> Option<KillDeferral> {
+    let deferrals = [
+        (
+            KillDeferral::UnsavedWork,
+            configuration.unsaved_work_grace(),
+        ),
+        (
+            KillDeferral::Inhibitor,
+            configuration.inhibitor_max_deferral(),
+        ),
+    ];
+    let longest_deferral = deferrals.iter().map(|&(_, seconds)| seconds).max()?;
+    if longest_deferral == 0 {
+        return None;
+    }
+
+    let now = Instant::now();
+    let mut identity = None;
+    let mut kill_deadlines = enforcement_state
+        .kill_deadlines
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    // The deadlines long past belong to processes that were killed or exited since
+    kill_deadlines
+        .retain(|_, deadline| *deadline + Duration::from_secs(u64::from(longest_deferral)) > now);
+    for (deferral, seconds) in deferrals {
+        let applies = match deferral {
+            KillDeferral::UnsavedWork => seconds > 0 && condition_probe.has_unsaved_work(pid),
+            KillDeferral::Inhibitor => seconds > 0 && condition_probe.holds_blocking_inhibitor(pid),
+        };
+        if !applies {
+            continue;
+        }
+
+        // A process reusing the pid of a previous one gets its own deadline
+        let identity = *identity.get_or_insert_with(|| ProcessIdentity::of(pid));
+        let deadline = *kill_deadlines
+            .entry((identity, deferral))
+            .or_insert(now + Duration::from_secs(u64::from(seconds)));
+        if now < deadline {
+            return Some(deferral);
+        }
+    }
+
+    None
+}
+
+/// Returns where the blocked process is in its graceful termination, `None` when it is to
+/// be killed, `terminate_grace` being 0 or having elapsed since it was asked to terminate.
+pub(crate) fn termination(
+    enforcement_state: &EnforcementState,
+    configuration: &Configuration,
+    pid: u32,
+) -> Option<Termination> {
+    let grace = Duration::from_secs(u64::from(configuration.terminate_grace()));
+    if grace.is_zero() {
+        return None;
+    }
+
+    let now = Instant::now();
+    let mut termination_deadlines = enforcement_state
+        .termination_deadlines
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    // A process that outlived its deadline is killed again until then, being asked to
+    // terminate anew afterwards
+    termination_deadlines.retain(|_, deadline| *deadline + grace > now);
+    match termination_deadlines.entry(ProcessIdentity::of(pid)) {
+        Entry::Occupied(entry) if now < *entry.get() => Some(Termination::Pending),
+        Entry::Occupied(_) => None,
+        Entry::Vacant(entry) => {
+            entry.insert(now + grace);
+            Some(Termination::Start)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_config;
+
+    #[test]
+    fn graceful_termination() {
+        let enforcement_state = EnforcementState::new();
+        let pid = std::process::id();
+        let configuration = parse_config("terminate_grace=0").unwrap();
+        assert_eq!(termination(&enforcement_state, &configuration, pid), None);
+
+        let configuration = parse_config("terminate_grace=10").unwrap();
+        assert_eq!(
+            termination(&enforcement_state, &configuration, pid),
+            Some(Termination::Start)
+        );
+        assert_eq!(
+            termination(&enforcement_state, &configuration, pid),
+            Some(Termination::Pending)
+        );
+
+        // Nothing is probed without a deferral configured
+        let configuration = parse_config("unsaved_work_grace=0\ninhibitor_max_deferral=0").unwrap();
+        let mut condition_probe = ConditionProbe::new(&configuration);
+        assert_eq!(
+            kill_deferral(
+                &enforcement_state,
+                &configuration,
+                &mut condition_probe,
+                pid
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::SwytError;
+use chrono::NaiveDateTime;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const HEARTBEAT_FILE_NAME: &str = "heartbeat.jbb";
+const EPHEMERAL_HEARTBEAT_DIRECTORY_NAME: &str = "swyt";
+const HEARTBEAT_DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// State the daemon records after each scan, so its health can be checked from outside.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heartbeat {
+    pid: u32,
+    last_scan: NaiveDateTime,
+}
+
+impl Heartbeat {
+    pub fn new(pid: u32, last_scan: NaiveDateTime) -> Self {
+        Heartbeat { pid, last_scan }
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn last_scan(&self) -> &NaiveDateTime {
+        &self.last_scan
+    }
+}
+
+pub fn write_heartbeat(swyt_filepath: &Path, heartbeat: &Heartbeat) -> Result<(), SwytError> {
+    fs::write(
+        get_heartbeat_filepath(swyt_filepath),
+        format!(
+            "pid={}\nlast_scan={}\n",
+            heartbeat.pid,
+            heartbeat.last_scan.format(HEARTBEAT_DATE_TIME_FORMAT)
+        ),
+    )?;
+    Ok(())
+}
+
+pub fn read_heartbeat(swyt_filepath: &Path) -> Result<Heartbeat, SwytError> {
+    let heartbeat_string = fs::read_to_string(get_heartbeat_filepath(swyt_filepath))?;
+    let mut pid = None;
+    let mut last_scan = None;
+    for line in heartbeat_string.lines() {
+        match line.split_once('=') {
+            Some(("pid", value)) => {
+                pid = Some(u32::from_str(value).map_err(|_| SwytError::StateParseError)?)
+            }
+            Some(("last_scan", value)) => {
+                last_scan = Some(
+                    NaiveDateTime::parse_from_str(value, HEARTBEAT_DATE_TIME_FORMAT)
+                        .map_err(|_| SwytError::StateParseError)?,
+                )
+            }
+            _ => (),
+        }
+    }
+
+    Ok(Heartbeat {
+        pid: pid.ok_or(SwytError::StateParseError)?,
+        last_scan: last_scan.ok_or(SwytError::StateParseError)?,
+    })
+}
+
+/// Returns the directory an ephemeral daemon writes its heartbeat to, in the runtime
+/// directory of the user which lives in memory, `None` when there is none, as outside Linux.
+pub fn ephemeral_heartbeat_directory() -> Option<PathBuf> {
+    dirs::runtime_dir().map(|runtime_dir| runtime_dir.join(EPHEMERAL_HEARTBEAT_DIRECTORY_NAME))
+}
+
+pub(crate) fn get_heartbeat_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(HEARTBEAT_FILE_NAME)
+}
+
+/// Returns whether a file can be created in the directory.
+pub fn is_directory_writable(directory: &Path) -> bool {
+    let probe_filepath = directory.join(".swyt-write-probe");
+    let is_writable = fs::write(&probe_filepath, b"").is_ok();
+    let _ = fs::remove_file(&probe_filepath);
+    is_writable
+}
+
+/// Returns whether a process is running, or `None` when it can't be told on this platform.
+#[cfg(all(unix, feature = "native"))]
+pub fn is_process_running(pid: u32) -> Option<bool> {
+    // Signal 0 only checks the process exists, EPERM meaning it belongs to another user
+    let is_running = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0
+        || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+    Some(is_running)
+}
+
+#[cfg(all(windows, feature = "native"))]
+pub fn is_process_running(pid: u32) -> Option<bool> {
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{GetExitCodeProcess, OpenProcess};
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+    const STILL_ACTIVE: u32 = 259;
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return Some(false);
+        }
+
+        let mut exit_code = 0;
+        let has_exit_code = GetExitCodeProcess(process, &mut exit_code) != 0;
+        CloseHandle(process);
+        Some(has_exit_code && exit_code == STILL_ACTIVE)
+    }
+}
+
+#[cfg(not(all(any(unix, windows), feature = "native")))]
+pub fn is_process_running(_pid: u32) -> Option<bool> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn read_heartbeat_valid() {
+        let heartbeat = read_heartbeat(Path::new("./test_data/heartbeat")).unwrap();
+        assert_eq!(heartbeat.pid(), 4242);
+        assert_eq!(
+            heartbeat.last_scan(),
+            &NaiveDate::from_ymd(2020, 6, 1).and_hms(10, 30, 0)
+        );
+        assert!(read_heartbeat(Path::new("./test_data/no_rule")).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{format_day_of_week, Period, Rules, WEEKDAYS};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+
+const ICAL_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Exports the allowed periods of the rules as an iCalendar, one weekly recurring event per
+/// period starting from `start_date`. The times are floating, as the rules follow the local
+/// time wherever the calendar is viewed. `timestamp` is the UTC creation time of the
+/// calendar.
+pub fn export_ics(rules: &Rules, start_date: NaiveDate, timestamp: &NaiveDateTime) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//swyt//swyt//EN".to_string(),
+    ];
+    for (rule_index, rule) in rules.rules.iter().enumerate() {
+        for (period_index, period) in rule.allowed_periods.iter().enumerate() {
+            let first_date = match first_date(period, start_date) {
+                Some(first_date) => first_date,
+                None => continue,
+            };
+
+            lines.extend(vec![
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:{}-{}@swyt", rule_index, period_index),
+                format!("DTSTAMP:{}Z", timestamp.format(ICAL_DATE_TIME_FORMAT)),
+                format!(
+                    "DTSTART:{}",
+                    first_date
+                        .and_time(period.begin_time)
+                        .format(ICAL_DATE_TIME_FORMAT)
+                ),
+                format!(
+                    "DTEND:{}",
+                    first_date
+                        .and_time(period.end_time)
+                        .format(ICAL_DATE_TIME_FORMAT)
+                ),
+                format!("RRULE:{}", weekly_rrule(period)),
+                format!("SUMMARY:{} allowed", escape_text(&rule.process_name)),
+                "END:VEVENT".to_string(),
+            ]);
+        }
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| format!("{}\r\n", line)).collect()
+}
+
+/// Returns the recurrence rule repeating a period on its days of the week.
+fn weekly_rrule(period: &Period) -> String {
+    let days: Vec<&str> = WEEKDAYS
+        .iter()
+        .filter(|weekday| period.days_of_week.contains(weekday))
+        .map(|&weekday| format_day_of_week(weekday))
+        .collect();
+    format!("FREQ=WEEKLY;BYDAY={}", days.join(","))
+}
+
+/// Returns the first day of the period from the start date, the first occurrence of an event
+/// being its start.
+fn first_date(period: &Period, start_date: NaiveDate) -> Option<NaiveDate> {
+    (0..7)
+        .map(|days| start_date + Duration::days(days))
+        .find(|date| period.days_of_week.contains(&date.weekday()))
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+}
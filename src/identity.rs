@@ -0,0 +1,127 @@
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(all(feature = "native", any(target_os = "linux", windows)))]
+use std::time::Duration;
+
+/// Identity of a process across scans, its pid being reused by other processes once it
+/// exits. The start time is unknown on the platforms where it can't be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct ProcessIdentity {
+    pid: u32,
+    start_time: Option<u64>,
+}
+
+impl ProcessIdentity {
+    pub(crate) fn new(pid: u32, start_time: Option<u64>) -> Self {
+        ProcessIdentity { pid, start_time }
+    }
+
+    pub(crate) fn of(pid: u32) -> Self {
+        ProcessIdentity::new(pid, start_time(pid))
+    }
+
+    pub(crate) fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub(crate) fn start_time(&self) -> Option<u64> {
+        self.start_time
+    }
+}
+
+/// Returns the name of the process as truncated by the kernel, the pid of its parent and its
+/// start time in clock ticks since boot, read from `/proc/<pid>/stat`.
+#[cfg(target_os = "linux")]
+pub(crate) fn stat(pid: u32) -> Option<(String, u32, u64)> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The name is between parentheses and can contain any character, parentheses included
+    let name_start = stat.find('(')? + 1;
+    let name_end = stat.rfind(')')?;
+    // The fields after the name start from the 3rd, the parent being the 4th and the start
+    // time the 22nd
+    let fields: Vec<&str> = stat.get(name_end + 1..)?.split_whitespace().collect();
+    let parent_pid = fields.get(1)?.parse().ok()?;
+    let start_time = fields.get(19)?.parse().ok()?;
+
+    Some((
+        stat.get(name_start..name_end)?.to_string(),
+        parent_pid,
+        start_time,
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn start_time(pid: u32) -> Option<u64> {
+    stat(pid).map(|(_, _, start_time)| start_time)
+}
+
+/// Returns how long the process has been running for, from its start time and the uptime.
+#[cfg(all(target_os = "linux", feature = "native"))]
+pub(crate) fn running_for(pid: u32) -> Option<Duration> {
+    let uptime: f64 = fs::read_to_string("/proc/uptime")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_second <= 0 {
+        return None;
+    }
+    let started = start_time(pid)? as f64 / ticks_per_second as f64;
+    Duration::try_from_secs_f64(uptime - started).ok()
+}
+
+/// Returns the creation time of the process, in 100 nanoseconds since 1601.
+#[cfg(all(windows, feature = "native"))]
+fn start_time(pid: u32) -> Option<u64> {
+    use std::mem;
+    use winapi::shared::minwindef::FILETIME;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{GetProcessTimes, OpenProcess};
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return None;
+        }
+
+        let mut creation_time: FILETIME = mem::zeroed();
+        let mut exit_time: FILETIME = mem::zeroed();
+        let mut kernel_time: FILETIME = mem::zeroed();
+        let mut user_time: FILETIME = mem::zeroed();
+        let has_times = GetProcessTimes(
+            process,
+            &mut creation_time,
+            &mut exit_time,
+            &mut kernel_time,
+            &mut user_time,
+        ) != 0;
+        CloseHandle(process);
+
+        has_times.then(|| {
+            u64::from(creation_time.dwHighDateTime) << 32 | u64::from(creation_time.dwLowDateTime)
+        })
+    }
+}
+
+/// Returns how long the process has been running for, from its creation time.
+#[cfg(all(windows, feature = "native"))]
+pub(crate) fn running_for(pid: u32) -> Option<Duration> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Seconds from 1601 to 1970.
+    const UNIX_EPOCH_SECONDS: u64 = 11_644_473_600;
+    let start_time = start_time(pid)?;
+    let started = Duration::from_secs(start_time / 10_000_000)
+        + Duration::from_nanos(start_time % 10_000_000 * 100);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?
+        + Duration::from_secs(UNIX_EPOCH_SECONDS);
+    now.checked_sub(started)
+}
+
+#[cfg(not(any(target_os = "linux", all(windows, feature = "native"))))]
+fn start_time(_pid: u32) -> Option<u64> {
+    None
+}
@@ -1,47 +1,1094 @@
+mod approval;
+mod audit;
+mod breaks;
+mod budget;
+mod busy;
+mod capability;
+mod clock;
+mod condition;
+pub mod confinement;
+mod daemon;
+mod diagnostics;
+#[cfg(any(feature = "native", feature = "process"))]
+mod enforce;
+mod enforcement;
+#[cfg(all(feature = "exec-deny", target_os = "linux"))]
+mod exec_guard;
+#[cfg(all(feature = "exec-events", target_os = "linux"))]
+mod exec_monitor;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(any(
+    all(feature = "native", any(target_os = "linux", windows)),
+    all(feature = "process", target_os = "linux")
+))]
+mod fingerprint;
+pub mod focus;
+#[cfg(all(feature = "gpu", target_os = "linux"))]
+mod gpu;
+#[cfg(any(feature = "native", feature = "process"))]
+mod grace;
+mod health;
+mod ical;
+#[cfg(any(feature = "native", feature = "process"))]
+mod identity;
+mod lockdown;
+mod matcher;
+#[cfg(all(feature = "native", any(target_os = "linux", windows)))]
+mod native;
+mod outbox;
+pub mod power;
+#[cfg(feature = "process")]
+mod process;
+mod redact;
+#[cfg(all(feature = "registry", windows))]
+mod registry;
+mod replay;
+mod report;
+mod request;
+#[cfg(any(feature = "native", feature = "process"))]
+mod restraint;
+mod schedule;
+pub mod semantics;
+pub mod session;
+mod snapshot;
+mod stats;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(all(feature = "window", any(target_os = "linux", windows)))]
+mod window;
+
+pub use approval::{
+    approve_changes, clear_changes_approval, has_admin_pin, loosens, read_approved_policy,
+    read_changes_approval, set_admin_pin, verify_admin_pin, verify_changes_approval,
+    write_approved_policy, ApprovedPolicy,
+};
+pub use audit::{
+    audit_policy, clear_audit_log, prune_audit_log, read_audit_log, Actor, AuditEntry,
+};
+pub use budget::{read_budget_usages, write_budget_usages, BudgetUsage};
+pub use busy::BusyCalendar;
+pub use capability::{capabilities, required_capabilities, Capabilities, Capability, EventBackend};
+pub use clock::{clock_skew, ClockSource, NtpClock, SystemClock};
+pub use condition::Condition;
+pub use daemon::{
+    read_daemon_snapshot, write_daemon_snapshot, DaemonSnapshot, EnforcementEvent, Override,
+    RuleStatus,
+};
+pub use diagnostics::{Diagnostics, RuleWarning};
+pub use enforcement::EnforcementState;
+pub use health::{
+    ephemeral_heartbeat_directory, is_directory_writable, is_process_running, read_heartbeat,
+    write_heartbeat, Heartbeat,
+};
+pub use ical::export_ics;
+pub use lockdown::{
+    clear_lockdown, read_lockdown, write_lockdown, ClockWatch, Lockdown, LockdownReason,
+};
+pub use outbox::{
+    clear_queued_reports, deliver_reports, queue_reports, read_queued_reports, QueuedReport,
+};
+pub use power::is_low_power;
+pub use redact::Redactor;
+pub use replay::{append_scan_trace, read_scan_traces, ScanTrace, VerdictChange};
+pub use report::{ScanError, ScanReport};
+pub use request::{
+    decide_override_request, read_override_requests, request_override, OverrideRequest,
+    RequestStatus,
+};
+pub use snapshot::PolicySnapshot;
+#[cfg(feature = "sqlite")]
+pub use stats::SqliteStatsStore;
+pub use stats::{
+    open_configured_stats_store, open_stats_store, AnonymousStatsStore, DefaultStatsStore,
+    FlatFileStatsStore, StatsStore,
+};
+
+#[cfg(all(feature = "exec-deny", target_os = "linux"))]
+pub use exec_guard::{is_execution_denied, ExecGuard};
+#[cfg(all(feature = "exec-events", target_os = "linux"))]
+pub use exec_monitor::ExecMonitor;
+#[cfg(all(feature = "exec-events", target_os = "linux"))]
+pub use native::process_rules_for_pids;
+#[cfg(all(feature = "native", any(target_os = "linux", windows)))]
+pub use native::{process_rules, release_restraints};
+#[cfg(all(
+    feature = "sync",
+    not(all(feature = "native", any(target_os = "linux", windows)))
+))]
+pub use process::{process_rules, release_restraints};
+#[cfg(feature = "process")]
+pub use process::{process_rules_async, release_restraints_async};
+#[cfg(all(feature = "registry", windows))]
+pub use registry::load_registry_policy;
+#[cfg(any(feature = "native", feature = "process"))]
+pub use restraint::{restore_restraints, write_restraint_journal};
+
 use chrono::prelude::*;
-use futures::StreamExt;
-use log::{info, trace};
+use log::{info, warn};
+pub(crate) use matcher::ProcessTarget;
+use matcher::{normalize_process_name, Matcher, RuleId};
+use schedule::{EvaluationTime, Scheduler, WeekSchedule};
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::fs::File;
-use std::io::{BufRead, BufReader, Error};
-use std::path::PathBuf;
+use std::io::Error;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::SystemTime;
 
-const SWYT_DIRECTORY_NAME: &'static str = "swyt";
-const CONFIG_FILE_NAME: &'static str = "config.jbb";
-const RULES_FILE_NAME: &'static str = "rules.jbb";
+const SWYT_DIRECTORY_NAME: &str = "swyt";
+const CONFIG_FILE_NAME: &str = "config.jbb";
+const RULES_FILE_NAME: &str = "rules.jbb";
 
 const DEFAULT_CHECK_INTERVAL: u32 = 60;
+const DEFAULT_LOW_POWER_CHECK_INTERVAL: u32 = 300;
+const DEFAULT_OVERRIDE_REQUESTS_PER_DAY: u32 = 3;
+const DEFAULT_OVERRIDE_REQUEST_EXPIRY: u32 = 60;
+const DEFAULT_MAX_CLOCK_SKEW: u32 = 300;
+
+/// Process name of the rules applying to any process satisfying their conditions.
+const ANY_PROCESS_NAME: &str = "*";
+/// Days of the week, in the order they are written in the rules.
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+/// Shared and system processes, such as package managers, backup jobs and antiviruses,
+/// that only a rule naming them exactly can target.
+const PROTECTED_PROCESSES: [&str; 28] = [
+    "swyt",
+    "apt",
+    "apt-get",
+    "dpkg",
+    "unattended-upgrade",
+    "packagekitd",
+    "dnf",
+    "yum",
+    "rpm",
+    "pacman",
+    "zypper",
+    "snapd",
+    "flatpak",
+    "fwupd",
+    "restic",
+    "borg",
+    "rsync",
+    "duplicity",
+    "timeshift",
+    "clamd",
+    "clamscan",
+    "freshclam",
+    "msiexec",
+    "TrustedInstaller",
+    "TiWorker",
+    "wuauclt",
+    "MsMpEng",
+    "MpCmdRun",
+];
+/// Prefix of the names of the presets, periods and attributes that rules can inherit.
+const PRESET_PREFIX: &str = "preset:";
+/// Prefix of the names of the day sets that periods can use as their days, and of the
+/// parameters of the templates.
+const DAY_SET_PREFIX: char = '$';
+/// Prefix of the definitions of the templates, expanded into rules.
+const TEMPLATE_PREFIX: &str = "template ";
+
+#[derive(Default, Clone)]
+pub struct Rules {
+    rules: Vec<Rule>,
+    matcher: Matcher,
+    any_process_rule_ids: Vec<RuleId>,
+    scheduler: Scheduler,
+}
+
+impl Rules {
+    pub fn new() -> Self {
+        Rules::default()
+    }
+
+    /// Inserts the rule, replacing the one written with the same process name or pattern.
+    /// Fails when its pattern is invalid.
+    pub fn insert(&mut self, rule: Rule) -> Result<(), SwytError> {
+        match self.find_written_id(&rule) {
+            Some(rule_id) => {
+                self.rules[rule_id] = rule;
+                self.scheduler = Scheduler::default();
+                for rule in self.rules.iter().filter(|rule| !rule.has_timezone()) {
+                    self.scheduler.insert(&rule.schedule);
+                }
+            }
+            None => {
+                if rule.process_name == ANY_PROCESS_NAME {
+                    self.any_process_rule_ids.push(self.rules.len());
+                } else {
+                    self.matcher.insert(&rule.process_name, self.rules.len())?;
+                }
+                if !rule.has_timezone() {
+                    self.scheduler.insert(&rule.schedule);
+                }
+                self.rules.push(rule);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the rule written with the same process name or pattern as the given one, or
+    /// with the same conditions for the `*` rules, which the given one would replace.
+    pub(crate) fn find_written(&self, rule: &Rule) -> Option<&Rule> {
+        self.find_written_id(rule)
+            .map(|rule_id| &self.rules[rule_id])
+    }
+
+    fn find_written_id(&self, rule: &Rule) -> Option<RuleId> {
+        if rule.process_name == ANY_PROCESS_NAME {
+            self.any_process_rule_ids
+                .iter()
+                .copied()
+                .find(|&rule_id| self.rules[rule_id].conditions == rule.conditions)
+        } else {
+            self.matcher.find_written(&rule.process_name)
+        }
+    }
+
+    pub fn get(&self, process_name: &str) -> Option<&Vec<Period>> {
+        self.get_rule(process_name)
+            .map(|rule| &rule.allowed_periods)
+    }
+
+    /// Returns the rule applying to the process with the given name, the one named after it
+    /// or else the first one whose pattern matches its name.
+    pub fn get_rule(&self, process_name: &str) -> Option<&Rule> {
+        self.find_rule(&ProcessTarget::named(process_name))
+    }
+
+    /// Returns the rule named after the process, or else the first one whose pattern matches
+    /// it.
+    pub(crate) fn find_rule(&self, process: &ProcessTarget) -> Option<&Rule> {
+        self.matcher
+            .find(process)
+            .map(|rule_id| &self.rules[rule_id])
+    }
 
-type Rules = HashMap<String, Vec<Period>>;
+    /// Returns whether rules match the path of the executable of the processes, which then
+    /// has to be read.
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn matches_paths(&self) -> bool {
+        self.matcher.matches_paths()
+    }
+
+    /// Returns whether rules match the command line of the processes, which then has to be
+    /// read.
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn matches_command_lines(&self) -> bool {
+        self.matcher.matches_command_lines()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter()
+    }
+
+    pub fn process_names(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|rule| rule.process_name.as_str())
+    }
+
+    pub fn next_boundary(&self, date_time: &NaiveDateTime) -> Option<NaiveDateTime> {
+        self.scheduler.next_boundary(date_time)
+    }
+
+    /// Returns the same rules without any allowed period, blocking their processes at any
+    /// time.
+    pub fn locked(&self) -> Rules {
+        let mut locked_rules = self.clone();
+        for rule in &mut locked_rules.rules {
+            *rule = Rule::new(rule.process_name.clone(), vec![])
+                .with_conditions(rule.conditions.clone())
+                .with_action(rule.action.clone())
+                .with_escalations(rule.escalations.clone());
+        }
+        locked_rules.scheduler = Scheduler::default();
+
+        locked_rules
+    }
+
+    /// Returns the same rules with the given processes allowed at any time, as granted by an
+    /// administrator.
+    pub fn with_grants(&self, process_names: &[String]) -> Rules {
+        let mut granted_rules = self.clone();
+        let every_day = Period {
+            days_of_week: WEEKDAYS.iter().copied().collect(),
+            begin_time: NaiveTime::from_hms(0, 0, 0),
+            end_time: NaiveTime::from_hms(23, 59, 59),
+        };
+        for process_name in process_names {
+            if let Err(err) =
+                granted_rules.insert(Rule::new(process_name.clone(), vec![every_day.clone()]))
+            {
+                warn!("Couldn't grant {}: {}", process_name, err);
+            }
+        }
+
+        granted_rules
+    }
+
+    /// Returns the same rules with the given processes blocked at any time.
+    pub fn with_lockdown(&self, process_names: &[String]) -> Rules {
+        let mut lockdown_rules = self.clone();
+        for process_name in process_names {
+            if let Err(err) = lockdown_rules.insert(Rule::new(process_name.clone(), vec![])) {
+                warn!("Couldn't lock {} down: {}", process_name, err);
+            }
+        }
+
+        lockdown_rules
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
 
+#[derive(Clone)]
 pub struct Rule {
     process_name: String,
     allowed_periods: Vec<Period>,
+    /// Periods taken out of the allowed ones.
+    excepted_periods: Vec<Period>,
+    conditions: Vec<Condition>,
+    action: Action,
+    /// Actions replacing `action` once the rule has been blocking for the given minutes.
+    escalations: Vec<(u32, Action)>,
+    /// Text shown when the rule acts on a process, see `message_for`.
+    message: Option<String>,
+    mandatory_break: Option<MandatoryBreak>,
+    budget: Option<Budget>,
+    /// Actions replacing `action` once the budget has been used for the given minutes.
+    budget_escalations: Vec<(u32, Action)>,
+    /// Time zone the periods are in, instead of the local one.
+    #[cfg(feature = "timezone")]
+    timezone: Option<chrono_tz::Tz>,
+    schedule: WeekSchedule,
+}
+
+impl Rule {
+    pub fn new(process_name: String, allowed_periods: Vec<Period>) -> Self {
+        let schedule = WeekSchedule::new(&allowed_periods, &[]);
+        Rule {
+            process_name,
+            allowed_periods,
+            excepted_periods: vec![],
+            conditions: vec![],
+            action: Action::Kill,
+            escalations: vec![],
+            message: None,
+            mandatory_break: None,
+            budget: None,
+            budget_escalations: vec![],
+            #[cfg(feature = "timezone")]
+            timezone: None,
+            schedule,
+        }
+    }
+
+    pub fn process_name(&self) -> &str {
+        &self.process_name
+    }
+
+    /// Returns when the rule allows its processes on the given day, the overlapping periods
+    /// being merged.
+    pub fn allowed_times(&self, weekday: Weekday) -> Vec<(NaiveTime, NaiveTime)> {
+        self.schedule.day_periods(weekday)
+    }
+
+    /// Returns the first time after `date_time` the schedule of the rule may begin or stop
+    /// allowing its processes, in the time zone of the rule.
+    pub fn next_boundary(&self, date_time: &NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut boundaries: Vec<u32> = self.schedule.boundaries().collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        semantics::next_boundary(&boundaries, date_time)
+    }
+
+    pub fn with_exceptions(mut self, excepted_periods: Vec<Period>) -> Self {
+        self.schedule = WeekSchedule::new(&self.allowed_periods, &excepted_periods);
+        self.excepted_periods = excepted_periods;
+        self
+    }
+
+    pub fn excepted_periods(&self) -> &[Period] {
+        &self.excepted_periods
+    }
+
+    pub fn with_conditions(mut self, conditions: Vec<Condition>) -> Self {
+        self.conditions = conditions;
+        self
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    pub fn with_action(mut self, action: Action) -> Self {
+        self.action = action;
+        self
+    }
+
+    pub fn action(&self) -> &Action {
+        &self.action
+    }
+
+    pub fn with_escalations(mut self, mut escalations: Vec<(u32, Action)>) -> Self {
+        escalations.sort_by_key(|&(minutes, _)| minutes);
+        self.escalations = escalations;
+        self
+    }
+
+    pub fn with_message(mut self, message: Option<String>) -> Self {
+        self.message = message;
+        self
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn with_mandatory_break(mut self, mandatory_break: Option<MandatoryBreak>) -> Self {
+        self.mandatory_break = mandatory_break;
+        self
+    }
+
+    pub fn mandatory_break(&self) -> Option<MandatoryBreak> {
+        self.mandatory_break
+    }
+
+    pub fn with_budget(mut self, budget: Option<Budget>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn budget(&self) -> Option<Budget> {
+        self.budget
+    }
+
+    pub fn with_budget_escalations(mut self, mut budget_escalations: Vec<(u32, Action)>) -> Self {
+        budget_escalations.sort_by_key(|&(minutes, _)| minutes);
+        self.budget_escalations = budget_escalations;
+        self
+    }
+
+    #[cfg(feature = "timezone")]
+    pub fn with_timezone(mut self, timezone: Option<chrono_tz::Tz>) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    #[cfg(feature = "timezone")]
+    pub fn timezone(&self) -> Option<chrono_tz::Tz> {
+        self.timezone
+    }
+
+    fn has_timezone(&self) -> bool {
+        #[cfg(feature = "timezone")]
+        return self.timezone.is_some();
+        #[cfg(not(feature = "timezone"))]
+        false
+    }
+
+    /// Returns the time in the time zone of the rule.
+    pub(crate) fn evaluation_time(&self, time: &EvaluationTime) -> EvaluationTime {
+        #[cfg(feature = "timezone")]
+        if let Some(timezone) = &self.timezone {
+            return time.in_timezone(timezone);
+        }
+        *time
+    }
+
+    /// Returns the message of the rule for the given process and action, `{process}` and
+    /// `{action}` being replaced with them.
+    pub fn message_for(&self, process_name: &str, action: &str) -> Option<String> {
+        self.message.as_ref().map(|message| {
+            message
+                .replace("{process}", process_name)
+                .replace("{action}", action)
+        })
+    }
+
+    /// Returns the action to take on a process blocked at the given time, escalated with how
+    /// long the rule has been blocking it.
+    pub fn action_at(&self, date_time: &NaiveDateTime) -> &Action {
+        // Without any allowed period in the past week, the rule has been blocking for long
+        let blocked_minutes = self
+            .schedule
+            .last_allowed(date_time)
+            .map(|last_allowed| (*date_time - last_allowed).num_minutes());
+        self.escalations
+            .iter()
+            .rev()
+            .find(|&&(minutes, _)| {
+                blocked_minutes.is_none_or(|blocked_minutes| blocked_minutes >= i64::from(minutes))
+            })
+            .map_or(&self.action, |(_, action)| action)
+    }
+
+    /// Returns the action to take on a process whose budget has been used, escalated with how
+    /// long its processes ran for since.
+    pub fn budget_action(&self, overrun: std::time::Duration) -> &Action {
+        let overrun_minutes = overrun.as_secs() / 60;
+        self.budget_escalations
+            .iter()
+            .rev()
+            .find(|&&(minutes, _)| overrun_minutes >= u64::from(minutes))
+            .map_or(&self.action, |(_, action)| action)
+    }
+
+    /// Returns the actions the rule can take, its escalations included.
+    pub(crate) fn actions(&self) -> impl Iterator<Item = &Action> {
+        std::iter::once(&self.action).chain(
+            self.escalations
+                .iter()
+                .chain(&self.budget_escalations)
+                .map(|(_, action)| action),
+        )
+    }
+
+    fn evaluate(
+        &self,
+        time: &EvaluationTime,
+        satisfies: &mut impl FnMut(&Condition) -> bool,
+    ) -> Verdict {
+        let time = self.evaluation_time(time);
+        if !self
+            .conditions
+            .iter()
+            .all(|condition| condition.evaluate(time.date_time(), satisfies))
+        {
+            Verdict::Unmatched
+        } else if self.schedule.contains(&time) {
+            Verdict::Allowed
+        } else {
+            Verdict::Blocked
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let periods: Vec<String> = self.allowed_periods.iter().map(Period::to_string).collect();
+        write!(f, "{}={}", self.process_name, periods.join("|"))?;
+        if !self.excepted_periods.is_empty() {
+            let excepted_periods: Vec<String> = self
+                .excepted_periods
+                .iter()
+                .map(Period::to_string)
+                .collect();
+            write!(f, " except={}", excepted_periods.join("|"))?;
+        }
+        for condition in &self.conditions {
+            write!(f, " {}", condition)?;
+        }
+        if self.action != Action::Kill {
+            write!(f, " action={}", self.action)?;
+        }
+        for (minutes, action) in &self.escalations {
+            write!(f, " action@{}={}", minutes, action)?;
+        }
+        if let Some(message) = &self.message {
+            write!(f, " message=\"{}\"", message)?;
+        }
+        if let Some(mandatory_break) = &self.mandatory_break {
+            write!(f, " {}", mandatory_break)?;
+        }
+        if let Some(budget) = &self.budget {
+            write!(f, " {}", budget)?;
+        }
+        for (minutes, action) in &self.budget_escalations {
+            write!(f, " budget@{}={}", minutes, action)?;
+        }
+        #[cfg(feature = "timezone")]
+        if let Some(timezone) = &self.timezone {
+            write!(f, " tz={}", timezone.name())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Break the processes of a rule are blocked for once they have been running continuously for
+/// a while, even during its allowed periods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MandatoryBreak {
+    after: u32,
+    duration: u32,
+}
+
+impl MandatoryBreak {
+    /// A break of `duration` minutes after `after` minutes, both being positive.
+    pub fn new(after: u32, duration: u32) -> Option<Self> {
+        (after > 0 && duration > 0).then_some(MandatoryBreak { after, duration })
+    }
+
+    /// Minutes of continuous running after which the break starts.
+    pub fn after(&self) -> u32 {
+        self.after
+    }
+
+    /// Minutes the break lasts.
+    pub fn duration(&self) -> u32 {
+        self.duration
+    }
+}
+
+impl Display for MandatoryBreak {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "break={}/{}", self.after, self.duration)
+    }
+}
+
+/// Time the processes of a rule can run for each day within its allowed periods, being
+/// blocked for the rest of the day once they have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    per_day: std::time::Duration,
+}
+
+impl Budget {
+    /// A budget of at least a minute a day, `None` otherwise.
+    pub fn new(per_day: std::time::Duration) -> Option<Self> {
+        (per_day.as_secs() >= 60).then_some(Budget { per_day })
+    }
+
+    pub fn per_day(&self) -> std::time::Duration {
+        self.per_day
+    }
+}
+
+impl Display for Budget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.per_day.as_secs() / 60 {
+            minutes if minutes % 60 == 0 => write!(f, "budget={}h", minutes / 60),
+            minutes => write!(f, "budget={}m", minutes),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+/// What is done to a process running outside of its allowed periods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Kill,
+    /// Minimizes the windows of the process, again on every check while it stays blocked.
+    Minimize,
+    /// Closes the windows of the process whose title contains the pattern, ignoring case,
+    /// leaving the process alive.
+    CloseWindows(String),
+    /// Only shows the message of the rule and records the action, leaving the process alone.
+    Notify,
+    /// Lowers the priority of the process to the lowest, restored once its rule allows it
+    /// again.
+    Throttle,
+    /// Pauses the process, resumed once its rule allows it again.
+    Suspend,
+}
+
+impl Action {
+    /// Whether the action needs to handle the windows of the process.
+    pub(crate) fn handles_windows(&self) -> bool {
+        matches!(self, Action::Minimize | Action::CloseWindows(_))
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Kill => write!(f, "kill"),
+            Action::Minimize => write!(f, "minimize"),
+            Action::CloseWindows(title_pattern) if title_pattern.contains(char::is_whitespace) => {
+                write!(f, "close:\"{}\"", title_pattern)
+            }
+            Action::CloseWindows(title_pattern) => write!(f, "close:{}", title_pattern),
+            Action::Notify => write!(f, "notify"),
+            Action::Throttle => write!(f, "throttle"),
+            Action::Suspend => write!(f, "suspend"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Period {
     days_of_week: HashSet<Weekday>,
     begin_time: NaiveTime,
     end_time: NaiveTime,
 }
 
+impl Period {
+    pub fn contains(&self, date_time: &NaiveDateTime) -> bool {
+        self.days_of_week.contains(&date_time.date().weekday())
+            && semantics::period_contains(self.begin_time, self.end_time, date_time.time())
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.begin_time == NaiveTime::from_hms(0, 0, 0)
+            && self.end_time == NaiveTime::from_hms(23, 59, 59)
+        {
+            write!(f, "*")?;
+        } else {
+            write!(
+                f,
+                "{}~{}",
+                self.begin_time.format("%H:%M"),
+                self.end_time.format("%H:%M")
+            )?;
+        }
+
+        let days_of_week: Vec<&str> = WEEKDAYS
+            .iter()
+            .filter(|weekday| self.days_of_week.contains(weekday))
+            .map(|&weekday| format_day_of_week(weekday))
+            .collect();
+        write!(f, ";{}", days_of_week.join(","))
+    }
+}
+
+#[derive(Clone)]
 pub struct Configuration {
     check_interval: u32,
+    interactive_sessions_only: bool,
+    managed: bool,
+    on_reload_error: ReloadErrorBehavior,
+    on_scan_error: ScanErrorBehavior,
+    scan_workers: u32,
+    low_power: LowPowerMode,
+    low_power_check_interval: u32,
+    dst_repeated_time: RepeatedTimePolicy,
+    renamed_binaries: RenamedBinaryPolicy,
+    lockdown_processes: Vec<String>,
+    unsaved_work_grace: u32,
+    terminate_grace: u32,
+    inhibitor_max_deferral: u32,
+    busy_calendar: Option<PathBuf>,
+    busy_processes: Vec<String>,
+    do_not_disturb_processes: Vec<String>,
+    protected_processes: Vec<String>,
+    override_requests_per_day: u32,
+    override_request_expiry: u32,
+    override_cooldown: u32,
+    time_server: Option<String>,
+    max_clock_skew: u32,
+    report_command: Option<String>,
+    notify_command: Option<String>,
+    stats_retention: u32,
+    anonymous_stats: bool,
+    audit_retention: u32,
+    loosening_approval: bool,
+    redactor: Redactor,
+}
+
+/// What the daemon does when the policy changed but can't be loaded anymore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReloadErrorBehavior {
+    /// Keeps enforcing the last valid policy.
+    Keep,
+    /// Stops the daemon, nothing is enforced anymore.
+    Halt,
+    /// Blocks all the processes of the last valid policy at any time.
+    FailsafeLock,
+}
+
+impl Display for ReloadErrorBehavior {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReloadErrorBehavior::Keep => write!(f, "keep"),
+            ReloadErrorBehavior::Halt => write!(f, "halt"),
+            ReloadErrorBehavior::FailsafeLock => write!(f, "failsafe_lock"),
+        }
+    }
+}
+
+/// What a scan does when a process can't be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanErrorBehavior {
+    /// Skips the process, counting it in the scan report.
+    Skip,
+    /// Aborts the scan, the rules are enforced again on the next one.
+    Abort,
+}
+
+impl Display for ScanErrorBehavior {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanErrorBehavior::Skip => write!(f, "skip"),
+            ScanErrorBehavior::Abort => write!(f, "abort"),
+        }
+    }
+}
+
+/// When the daemon trades enforcement latency for battery life, see `power::is_low_power`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LowPowerMode {
+    /// On while the system runs on battery.
+    Auto,
+    On,
+    Off,
+}
+
+impl Display for LowPowerMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LowPowerMode::Auto => write!(f, "auto"),
+            LowPowerMode::On => write!(f, "on"),
+            LowPowerMode::Off => write!(f, "off"),
+        }
+    }
+}
+
+/// Which occurrence of the local times repeated when the clocks fall back the ends of the
+/// periods happen at, see `semantics::period_contains_repeated`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatedTimePolicy {
+    /// Both, the periods are in force on each pass of the repeated times.
+    Both,
+    Earliest,
+    Latest,
+}
+
+impl Display for RepeatedTimePolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepeatedTimePolicy::Both => write!(f, "both"),
+            RepeatedTimePolicy::Earliest => write!(f, "earliest"),
+            RepeatedTimePolicy::Latest => write!(f, "latest"),
+        }
+    }
+}
+
+/// What is done to a process running a renamed copy of the executable of a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenamedBinaryPolicy {
+    Off,
+    /// Logs it, once per process.
+    Alert,
+    /// Logs it and enforces the rule of the original executable on it.
+    Block,
+}
+
+impl Display for RenamedBinaryPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenamedBinaryPolicy::Off => write!(f, "off"),
+            RenamedBinaryPolicy::Alert => write!(f, "alert"),
+            RenamedBinaryPolicy::Block => write!(f, "block"),
+        }
+    }
 }
 
 impl Configuration {
     pub fn check_interval(&self) -> u32 {
         self.check_interval
     }
+
+    pub fn interactive_sessions_only(&self) -> bool {
+        self.interactive_sessions_only
+    }
+
+    pub fn managed(&self) -> bool {
+        self.managed
+    }
+
+    pub fn on_reload_error(&self) -> ReloadErrorBehavior {
+        self.on_reload_error
+    }
+
+    pub fn on_scan_error(&self) -> ScanErrorBehavior {
+        self.on_scan_error
+    }
+
+    /// Threads evaluating and enforcing the rules on the listed processes, at least 1.
+    pub fn scan_workers(&self) -> u32 {
+        self.scan_workers
+    }
+
+    pub fn low_power(&self) -> LowPowerMode {
+        self.low_power
+    }
+
+    /// Seconds between two checks in low-power mode, at least `check_interval`.
+    pub fn low_power_check_interval(&self) -> u32 {
+        self.low_power_check_interval.max(self.check_interval)
+    }
+
+    pub fn dst_repeated_time(&self) -> RepeatedTimePolicy {
+        self.dst_repeated_time
+    }
+
+    pub fn renamed_binaries(&self) -> RenamedBinaryPolicy {
+        self.renamed_binaries
+    }
+
+    /// High-risk processes blocked at any time while the daemon is locked down.
+    pub fn lockdown_processes(&self) -> &[String] {
+        &self.lockdown_processes
+    }
+
+    /// Seconds a process that seems to have unsaved work is given before being killed, 0
+    /// killing it right away.
+    pub fn unsaved_work_grace(&self) -> u32 {
+        self.unsaved_work_grace
+    }
+
+    /// Seconds a blocked process is given to exit after being asked to before being killed, 0
+    /// killing it right away.
+    pub fn terminate_grace(&self) -> u32 {
+        self.terminate_grace
+    }
+
+    /// Seconds killing a process holding a blocking inhibitor can be deferred for, 0 not
+    /// deferring it.
+    pub fn inhibitor_max_deferral(&self) -> u32 {
+        self.inhibitor_max_deferral
+    }
+
+    /// iCalendar file, or directory of them, whose busy events block `busy_processes`.
+    pub fn busy_calendar(&self) -> Option<&Path> {
+        self.busy_calendar.as_deref()
+    }
+
+    /// Distracting processes blocked at any time during the busy events of `busy_calendar`.
+    pub fn busy_processes(&self) -> &[String] {
+        &self.busy_processes
+    }
+
+    /// Distracting processes blocked at any time while the desktop is in Do Not Disturb.
+    pub fn do_not_disturb_processes(&self) -> &[String] {
+        &self.do_not_disturb_processes
+    }
+
+    /// Override requests the monitored user can make over 24 hours, 0 disabling them.
+    pub fn override_requests_per_day(&self) -> u32 {
+        self.override_requests_per_day
+    }
+
+    /// Minutes an override request waits for a decision before expiring.
+    pub fn override_request_expiry(&self) -> u32 {
+        self.override_request_expiry
+    }
+
+    /// Hours after an approved override request ends before its process can be requested
+    /// again, so overrides can't be chained.
+    pub fn override_cooldown(&self) -> u32 {
+        self.override_cooldown
+    }
+
+    /// NTP server the system clock is checked against, `None` not to check it.
+    pub fn time_server(&self) -> Option<&str> {
+        self.time_server.as_deref()
+    }
+
+    /// Seconds the system clock can differ from `time_server` before the daemon locks down.
+    pub fn max_clock_skew(&self) -> u32 {
+        self.max_clock_skew
+    }
+
+    /// Command the actions taken on processes are reported to, one JSON object on its
+    /// standard input each, `None` not to report them. They are queued in the outbox until
+    /// the command succeeds.
+    pub fn report_command(&self) -> Option<&str> {
+        self.report_command.as_deref()
+    }
+
+    /// Command the messages of the rules are shown with, run through the shell with `{}`
+    /// replaced by the message, `None` to only log them.
+    pub fn notify_command(&self) -> Option<&str> {
+        self.notify_command.as_deref()
+    }
+
+    /// Days the actions taken on processes are kept in the statistics, 0 to keep them forever.
+    pub fn stats_retention(&self) -> u32 {
+        self.stats_retention
+    }
+
+    /// Whether the statistics only keep how many times each action was taken on each process
+    /// each day, without the times, the pids nor the command lines, see `AnonymousStatsStore`.
+    pub fn anonymous_stats(&self) -> bool {
+        self.anonymous_stats
+    }
+
+    /// Days the changes to the policy are kept in the audit log, 0 to keep them forever.
+    pub fn audit_retention(&self) -> u32 {
+        self.audit_retention
+    }
+
+    /// Whether the changes loosening the policy are held back until approved with the admin
+    /// PIN, see `ApprovedPolicy`.
+    pub fn loosening_approval(&self) -> bool {
+        self.loosening_approval
+    }
+
+    /// Redacts the secrets in the command lines recorded and in the changes to the policy.
+    pub fn redactor(&self) -> &Redactor {
+        &self.redactor
+    }
+
+    /// Processes protected in addition to the built-in ones.
+    pub fn protected_processes(&self) -> &[String] {
+        &self.protected_processes
+    }
+
+    /// Returns whether the process is only targeted by a rule naming it exactly, never by a
+    /// `*` rule.
+    pub fn is_protected(&self, process_name: &str) -> bool {
+        let process_name = normalize_process_name(process_name);
+        PROTECTED_PROCESSES
+            .iter()
+            .copied()
+            .chain(self.protected_processes.iter().map(String::as_str))
+            .any(|protected_process| normalize_process_name(protected_process) == process_name)
+    }
 }
 
 impl Default for Configuration {
     fn default() -> Self {
         Configuration {
             check_interval: DEFAULT_CHECK_INTERVAL,
+            interactive_sessions_only: false,
+            managed: false,
+            on_reload_error: ReloadErrorBehavior::Keep,
+            on_scan_error: ScanErrorBehavior::Skip,
+            scan_workers: 1,
+            low_power: LowPowerMode::Auto,
+            low_power_check_interval: DEFAULT_LOW_POWER_CHECK_INTERVAL,
+            dst_repeated_time: RepeatedTimePolicy::Both,
+            renamed_binaries: RenamedBinaryPolicy::Off,
+            lockdown_processes: vec![],
+            unsaved_work_grace: 0,
+            terminate_grace: 0,
+            inhibitor_max_deferral: 0,
+            busy_calendar: None,
+            busy_processes: vec![],
+            do_not_disturb_processes: vec![],
+            protected_processes: vec![],
+            override_requests_per_day: DEFAULT_OVERRIDE_REQUESTS_PER_DAY,
+            override_request_expiry: DEFAULT_OVERRIDE_REQUEST_EXPIRY,
+            override_cooldown: 0,
+            time_server: None,
+            max_clock_skew: DEFAULT_MAX_CLOCK_SKEW,
+            report_command: None,
+            notify_command: None,
+            stats_retention: 0,
+            anonymous_stats: false,
+            audit_retention: 0,
+            loosening_approval: false,
+            redactor: Redactor::default(),
         }
     }
 }
@@ -51,8 +1098,24 @@ pub enum SwytError {
     ConfigFileNotFound,
     ConfigParseError,
     RuleParseError,
+    /// A time of the rules, as written, out of the day.
+    TimeParseError(String),
     ProcessFetchError,
     ProcessKillError,
+    ManagedPolicyPermissionError,
+    StateParseError,
+    WindowFetchError,
+    TimeFetchError,
+    /// What this build of swyt can't do on the current platform, see `Capabilities`.
+    Unsupported(&'static str),
+    /// Why an override request couldn't be made or decided on.
+    OverrideRequestError(String),
+    /// Why a report couldn't be delivered.
+    ReportDeliveryError(String),
+    /// Why the statistics couldn't be read or written, other than an I/O error.
+    StatsStoreError(String),
+    /// Why the admin PIN couldn't be checked or set.
+    AdminPinError(String),
     IoError(std::io::Error),
 }
 
@@ -62,8 +1125,33 @@ impl Display for SwytError {
             SwytError::ConfigFileNotFound => write!(f, "Couldn't find config file"),
             SwytError::ConfigParseError => write!(f, "Couldn't parse config file"),
             SwytError::RuleParseError => write!(f, "Couldn't parse rule"),
+            SwytError::TimeParseError(ref time) => write!(
+                f,
+                "Couldn't parse time {}, expected HH:MM from 00:00 to 23:59, or 24:00 to end a period",
+                time
+            ),
             SwytError::ProcessFetchError => write!(f, "Couldn't fetch process"),
             SwytError::ProcessKillError => write!(f, "Couldn't kill process"),
+            SwytError::StateParseError => write!(f, "Couldn't parse state file"),
+            SwytError::ManagedPolicyPermissionError => write!(
+                f,
+                "Managed policy files must only be writable by root or the Administrators"
+            ),
+            SwytError::WindowFetchError => write!(f, "Couldn't fetch windows"),
+            SwytError::TimeFetchError => write!(f, "Couldn't fetch the time from the time server"),
+            SwytError::Unsupported(what) => write!(f, "Can't {} on this platform", what),
+            SwytError::OverrideRequestError(ref reason) => {
+                write!(f, "Couldn't handle the override request: {}", reason)
+            }
+            SwytError::ReportDeliveryError(ref reason) => {
+                write!(f, "Couldn't deliver the report: {}", reason)
+            }
+            SwytError::StatsStoreError(ref reason) => {
+                write!(f, "Couldn't access the statistics: {}", reason)
+            }
+            SwytError::AdminPinError(ref reason) => {
+                write!(f, "Couldn't check the admin PIN: {}", reason)
+            }
             SwytError::IoError(ref err) => std::fmt::Display::fmt(err, f),
         }
     }
@@ -75,51 +1163,278 @@ impl From<std::io::Error> for SwytError {
     }
 }
 
-pub fn process_rules(rules: &Rules) -> Result<(), SwytError> {
-    trace!("Process rules...");
-    let current_date_time = Local::now();
-    let mut processes = heim::process::processes();
-    while let Ok(process_result) =
-        futures::executor::block_on(processes.next()).ok_or(SwytError::ProcessFetchError)
-    {
-        if let Ok(process) = process_result {
-            let process_name = futures::executor::block_on(process.name())
-                .map_err(|_| SwytError::ProcessFetchError)?;
-            if let Some(periods) = rules.get(&process_name) {
-                if !periods.iter().any(|p| {
-                    p.days_of_week.contains(&current_date_time.date().weekday())
-                        && current_date_time.time() >= p.begin_time
-                        && current_date_time.time() <= p.end_time
-                }) {
-                    trace!("Killed process {}", process_name);
-                    let _ = futures::executor::block_on(process.kill())
-                        .map_err(|_| SwytError::ProcessKillError);
-                }
-            }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Unmatched,
+    Allowed,
+    Blocked,
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Unmatched => write!(f, "unmatched"),
+            Verdict::Allowed => write!(f, "allowed"),
+            Verdict::Blocked => write!(f, "blocked"),
+        }
+    }
+}
+
+/// Returns whether the rule is kept from targeting the process, protected as it is and not
+/// named by it exactly.
+#[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+pub(crate) fn is_protected_from(
+    configuration: &Configuration,
+    rules: &Rules,
+    process_name: &str,
+    rule: &Rule,
+) -> bool {
+    configuration.is_protected(process_name)
+        && !rules
+            .matcher
+            .find_name(process_name)
+            .is_some_and(|rule_id| std::ptr::eq(&rules.rules[rule_id], rule))
+}
+
+/// Evaluates the rules for a process known only by its name, rules with conditions never
+/// apply to it. The time is taken as is in the time zones of the rules.
+pub fn evaluate_rules(rules: &Rules, process_name: &str, date_time: &NaiveDateTime) -> Verdict {
+    evaluate_rules_with_conditions(rules, process_name, date_time, |_| false)
+}
+
+/// Evaluates the rules like `evaluate_rules` at an instant, converted to the time zones of
+/// the rules, the repeated times of a day the clocks fall back being handled with `policy`.
+pub fn evaluate_rules_at<Tz: TimeZone>(
+    rules: &Rules,
+    process_name: &str,
+    date_time: &DateTime<Tz>,
+    policy: RepeatedTimePolicy,
+) -> Verdict {
+    evaluate_process(
+        rules,
+        &ProcessTarget::named(process_name),
+        &EvaluationTime::at(date_time, policy),
+        |_| false,
+    )
+    .0
+}
+
+/// Evaluates the rules for a process, `satisfies` tells whether the process satisfies a
+/// condition. The rule named after the process takes precedence, the rules applying to any
+/// process are only considered when it doesn't apply, and block the process if any of them
+/// does.
+pub fn evaluate_rules_with_conditions(
+    rules: &Rules,
+    process_name: &str,
+    date_time: &NaiveDateTime,
+    satisfies: impl FnMut(&Condition) -> bool,
+) -> Verdict {
+    evaluate_process(
+        rules,
+        &ProcessTarget::named(process_name),
+        &EvaluationTime::new(*date_time),
+        satisfies,
+    )
+    .0
+}
+
+/// Evaluates the rules for a process like `evaluate_rules_with_conditions`, also returning
+/// the rule the verdict stems from.
+#[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", name = "evaluate", skip(rules, time, satisfies))
+)]
+pub(crate) fn evaluate_process<'a>(
+    rules: &'a Rules,
+    process: &ProcessTarget,
+    time: &EvaluationTime,
+    mut satisfies: impl FnMut(&Condition) -> bool,
+) -> (Verdict, Option<&'a Rule>) {
+    if let Some(rule) = rules.find_rule(process) {
+        match rule.evaluate(time, &mut satisfies) {
+            Verdict::Unmatched => (),
+            verdict => return (verdict, Some(rule)),
         }
     }
 
-    Ok(())
+    let mut result = (Verdict::Unmatched, None);
+    for &rule_id in &rules.any_process_rule_ids {
+        let rule = &rules.rules[rule_id];
+        match rule.evaluate(time, &mut satisfies) {
+            Verdict::Blocked => return (Verdict::Blocked, Some(rule)),
+            Verdict::Allowed => result = (Verdict::Allowed, Some(rule)),
+            Verdict::Unmatched => (),
+        }
+    }
+
+    result
+}
+
+/// Shows the message of the rule acting on a process, logging it and queuing it for the
+/// daemon to pass to its `notify_command`, and records the action.
+#[cfg(any(feature = "native", feature = "process"))]
+pub(crate) fn record_enforcement(
+    enforcement_state: &EnforcementState,
+    rule: &Rule,
+    process_name: &str,
+    pid: u32,
+    command_line: Option<&str>,
+    action: &str,
+) {
+    if let Some(message) = rule.message_for(process_name, action) {
+        info!("{}", message);
+        enforcement_state.record_message(message);
+    }
+    trace_enforcement(process_name, pid, action);
+    enforcement_state.record_event(process_name, pid, command_line, action);
+}
+
+/// Records an action taken on a process in the current trace, and counts it in the
+/// `swyt.enforcements` metric.
+#[cfg(feature = "tracing")]
+#[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+fn trace_enforcement(process_name: &str, pid: u32, action: &str) {
+    tracing::info!(
+        monotonic_counter.swyt.enforcements = 1u64,
+        process_name,
+        pid,
+        action,
+        "Enforced rule"
+    );
 }
 
-pub fn load_rules(swyt_filepath: &PathBuf) -> Result<Rules, SwytError> {
-    let rules_filepath = get_rules_filepath(swyt_filepath)?;
+#[cfg(not(feature = "tracing"))]
+#[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+fn trace_enforcement(_process_name: &str, _pid: u32, _action: &str) {}
+
+/// Records the outcome of a scan in the current trace, counting the scanned processes and
+/// the enforcement errors in the `swyt.scanned_processes` and `swyt.enforcement_errors`
+/// metrics.
+#[cfg(feature = "tracing")]
+#[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+pub(crate) fn record_scan(report: &ScanReport) {
+    tracing::debug!(
+        monotonic_counter.swyt.scanned_processes = u64::from(report.scanned()),
+        monotonic_counter.swyt.enforcement_errors = report.errors().len() as u64,
+        blocked = report.blocked(),
+        killed = report.killed(),
+        minimized = report.minimized(),
+        closed = report.closed(),
+        restrained = report.restrained(),
+        deferred = report.deferred(),
+        "Scanned processes"
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+#[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+pub(crate) fn record_scan(_report: &ScanReport) {}
+
+/// Loads the rules from the swyt directory, given as any kind of path.
+pub fn load_rules(swyt_filepath: impl AsRef<Path>) -> Result<Rules, SwytError> {
+    let rules_filepath = get_rules_filepath(swyt_filepath.as_ref())?;
     parse_rules_file(rules_filepath)
 }
 
-pub fn load_config(swyt_filepath: &PathBuf) -> Result<Configuration, SwytError> {
-    let config_filepath = get_config_filepath(swyt_filepath)?;
+/// Loads the configuration from the swyt directory, given as any kind of path.
+pub fn load_config(swyt_filepath: impl AsRef<Path>) -> Result<Configuration, SwytError> {
+    let config_filepath = get_config_filepath(swyt_filepath.as_ref())?;
     parse_config_file(config_filepath)
 }
 
-fn get_config_filepath(swyt_filepath: &PathBuf) -> Result<PathBuf, SwytError> {
-    let mut config_directory = swyt_filepath.clone();
+/// Reads the configuration and the rules as written, empty when they don't exist yet.
+pub fn read_policy(swyt_filepath: &Path) -> Result<(String, String), SwytError> {
+    let read = |filepath: PathBuf| match std::fs::read_to_string(filepath) {
+        Ok(string) => Ok(string),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(SwytError::from(err)),
+    };
+    Ok((
+        read(get_config_filepath(swyt_filepath)?)?,
+        read(get_rules_filepath(swyt_filepath)?)?,
+    ))
+}
+
+/// Writes the configuration and the rules, replacing the current ones, as the current user.
+/// Nothing is written when one of them can't be parsed.
+pub fn write_policy(
+    swyt_filepath: &Path,
+    config_string: &str,
+    rules_string: &str,
+) -> Result<(), SwytError> {
+    write_policy_as(
+        swyt_filepath,
+        config_string,
+        rules_string,
+        &Actor::current_user(),
+    )
+}
+
+/// Writes the configuration and the rules like `write_policy`, recording the changes in the
+/// audit log on behalf of the actor. Nothing is written when they can't be recorded.
+pub fn write_policy_as(
+    swyt_filepath: &Path,
+    config_string: &str,
+    rules_string: &str,
+    actor: &Actor,
+) -> Result<(), SwytError> {
+    let current = PolicySnapshot::new(&parse_config(config_string)?, &parse_rules(rules_string)?);
+    let previous = match (load_config(swyt_filepath), load_rules(swyt_filepath)) {
+        (Ok(configuration), Ok(rules)) => Some(PolicySnapshot::new(&configuration, &rules)),
+        _ => None,
+    };
+    audit_policy(
+        swyt_filepath,
+        actor,
+        previous.as_ref(),
+        &current,
+        audit::now(),
+    )?;
+    std::fs::write(get_config_filepath(swyt_filepath)?, config_string)?;
+    std::fs::write(get_rules_filepath(swyt_filepath)?, rules_string)?;
+    Ok(())
+}
+
+/// Returns the last time the configuration or the rules were modified, so the daemon can
+/// reload them when they change.
+pub fn policy_modified_time(swyt_filepath: &Path) -> Option<SystemTime> {
+    let config_filepath = get_config_filepath(swyt_filepath).ok()?;
+    let rules_filepath = get_rules_filepath(swyt_filepath).ok()?;
+    [config_filepath, rules_filepath]
+        .iter()
+        .filter_map(|filepath| std::fs::metadata(filepath).ok()?.modified().ok())
+        .max()
+}
+
+/// Reloads the configuration and the rules when they were modified since `modified_time`,
+/// returning `None` otherwise, for embedders to pick up edits of the policy files like the
+/// daemon does on each check. `modified_time` is updated even when they can't be loaded, so
+/// they are only tried again once modified again.
+pub fn reload(
+    swyt_filepath: &Path,
+    modified_time: &mut Option<SystemTime>,
+) -> Result<Option<(Configuration, Rules)>, SwytError> {
+    let current_modified_time = policy_modified_time(swyt_filepath);
+    if current_modified_time == *modified_time {
+        return Ok(None);
+    }
+
+    *modified_time = current_modified_time;
+    Ok(Some((
+        load_config(swyt_filepath)?,
+        load_rules(swyt_filepath)?,
+    )))
+}
+
+fn get_config_filepath(swyt_filepath: &Path) -> Result<PathBuf, SwytError> {
+    let mut config_directory = swyt_filepath.to_path_buf();
     config_directory.push(CONFIG_FILE_NAME);
     Ok(config_directory)
 }
 
-fn get_rules_filepath(swyt_filepath: &PathBuf) -> Result<PathBuf, SwytError> {
-    let mut rules_filepath = swyt_filepath.clone();
+fn get_rules_filepath(swyt_filepath: &Path) -> Result<PathBuf, SwytError> {
+    let mut rules_filepath = swyt_filepath.to_path_buf();
     rules_filepath.push(RULES_FILE_NAME);
     Ok(rules_filepath)
 }
@@ -130,6 +1445,92 @@ pub fn find_swyt_filepath() -> Result<PathBuf, SwytError> {
     Ok(config_directory)
 }
 
+/// Returns the files the daemon writes its own state to, as opposed to the policy.
+pub fn state_filepaths(swyt_filepath: &Path) -> Vec<PathBuf> {
+    let state_filepaths = vec![
+        health::get_heartbeat_filepath(swyt_filepath),
+        lockdown::get_lockdown_filepath(swyt_filepath),
+        daemon::get_daemon_snapshot_filepath(swyt_filepath),
+        request::get_override_requests_filepath(swyt_filepath),
+        request::get_override_grants_filepath(swyt_filepath),
+        budget::get_budget_usage_filepath(swyt_filepath),
+        outbox::get_outbox_filepath(swyt_filepath),
+        stats::get_events_filepath(swyt_filepath),
+    ]
+    .into_iter()
+    .chain(stats::get_stats_database_filepaths(swyt_filepath))
+    .chain([approval::get_changes_approval_filepath(swyt_filepath)])
+    .collect::<Vec<_>>();
+    #[cfg(any(feature = "native", feature = "process"))]
+    let state_filepaths = [
+        state_filepaths,
+        vec![restraint::get_restraint_journal_filepath(swyt_filepath)],
+    ]
+    .concat();
+    state_filepaths
+}
+
+/// Returns the system-wide swyt directory when the installation is managed, that is when
+/// its configuration sets `managed=true`. The policy must then only be loaded from there,
+/// the user configuration being ignored.
+pub fn find_managed_swyt_filepath() -> Result<Option<PathBuf>, SwytError> {
+    let managed_swyt_filepath = match managed_swyt_directory() {
+        Some(managed_swyt_filepath) => managed_swyt_filepath,
+        None => return Ok(None),
+    };
+    if !get_config_filepath(&managed_swyt_filepath)?.exists()
+        || !load_config(&managed_swyt_filepath)?.managed()
+    {
+        return Ok(None);
+    }
+
+    check_managed_permissions(&managed_swyt_filepath)?;
+    Ok(Some(managed_swyt_filepath))
+}
+
+/// Returns the system-wide swyt directory, whether the installation is managed or not.
+#[cfg(unix)]
+pub fn managed_swyt_directory() -> Option<PathBuf> {
+    Some(Path::new("/etc").join(SWYT_DIRECTORY_NAME))
+}
+
+#[cfg(windows)]
+pub fn managed_swyt_directory() -> Option<PathBuf> {
+    std::env::var_os("ProgramData")
+        .map(|program_data| Path::new(&program_data).join(SWYT_DIRECTORY_NAME))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn managed_swyt_directory() -> Option<PathBuf> {
+    None
+}
+
+/// Checks the managed policy can't be edited by local users. On Windows, the permissions
+/// of the directory are left to the administrator deploying it.
+#[cfg(unix)]
+fn check_managed_permissions(managed_swyt_filepath: &Path) -> Result<(), SwytError> {
+    use std::os::unix::fs::MetadataExt;
+
+    let policy_filepaths = [
+        managed_swyt_filepath.to_path_buf(),
+        get_config_filepath(managed_swyt_filepath)?,
+        get_rules_filepath(managed_swyt_filepath)?,
+    ];
+    for policy_filepath in policy_filepaths.iter().filter(|filepath| filepath.exists()) {
+        let metadata = std::fs::metadata(policy_filepath)?;
+        if metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+            return Err(SwytError::ManagedPolicyPermissionError);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_managed_permissions(_managed_swyt_filepath: &Path) -> Result<(), SwytError> {
+    Ok(())
+}
+
 fn parse_rules_file(rules_filepath: PathBuf) -> Result<Rules, SwytError> {
     if !rules_filepath.exists() {
         info!(
@@ -142,37 +1543,479 @@ fn parse_rules_file(rules_filepath: PathBuf) -> Result<Rules, SwytError> {
         return Ok(Rules::new());
     }
 
+    parse_rules(&std::fs::read_to_string(&rules_filepath)?)
+}
+
+pub fn parse_rules(rules_string: &str) -> Result<Rules, SwytError> {
+    validate_rules(rules_string).map_err(|(_, err)| err)
+}
+
+/// Parses the rules like `parse_rules`, an error coming with the number of the line it is
+/// on, from 1.
+pub fn validate_rules(rules_string: &str) -> Result<Rules, (usize, SwytError)> {
+    // Day sets and templates are defined before the lines using them, which are expanded
+    let mut day_sets = HashMap::new();
+    let mut templates = HashMap::new();
+    let mut lines = vec![];
+    for (index, line) in rules_string.lines().enumerate() {
+        let mut expand_line = || -> Result<(), SwytError> {
+            if let Some(day_set) = line.strip_prefix(DAY_SET_PREFIX) {
+                let (name, days) = day_set.split_once('=').ok_or(SwytError::RuleParseError)?;
+                day_sets.insert(name.to_string(), parse_day_set(days, &day_sets)?);
+            } else if let Some(template) = line.strip_prefix(TEMPLATE_PREFIX) {
+                let (name, template) = parse_template(template)?;
+                templates.insert(name, template);
+            } else if let Some((name, arguments)) = parse_template_instance(line) {
+                let template = templates.get(name).ok_or(SwytError::RuleParseError)?;
+                let rule = template.instantiate(&arguments)?;
+                lines.push((index + 1, resolve_day_sets(&rule, &day_sets)?));
+            } else {
+                lines.push((index + 1, resolve_day_sets(line, &day_sets)?));
+            }
+            Ok(())
+        };
+        expand_line().map_err(|err| (index + 1, err))?;
+    }
+
+    // Presets can be defined after the rules inheriting them, but can't inherit themselves
+    let mut presets = HashMap::new();
+    for (line_number, line) in lines
+        .iter()
+        .filter(|(_, line)| line.starts_with(PRESET_PREFIX))
+    {
+        let preset = parse_rule(line, &HashMap::new()).map_err(|err| (*line_number, err))?;
+        presets.insert(preset.process_name.clone(), preset);
+    }
+
     let mut rules = Rules::new();
-    let rules_file = File::open(&rules_filepath)?;
-    let reader = BufReader::new(rules_file);
-    for line in reader.lines() {
-        let rule = parse_rule(&line?)?;
-        rules.insert(rule.process_name, rule.allowed_periods);
+    for (line_number, line) in lines
+        .iter()
+        .filter(|(_, line)| !line.starts_with(PRESET_PREFIX))
+    {
+        rules
+            .insert(parse_rule(line, &presets).map_err(|err| (*line_number, err))?)
+            .map_err(|err| (*line_number, err))?;
     }
 
     Ok(rules)
 }
 
-fn parse_rule(rule: &str) -> Result<Rule, SwytError> {
-    let mut split_rule = rule.split("=");
+/// Returns the rules without the lines of the rule of the process, `None` when there is none.
+/// The rules generated by templates are left as they are.
+pub fn remove_rule(rules_string: &str, process_name: &str) -> Option<String> {
+    let is_rule_of_process = |line: &str| {
+        line.split_once('=')
+            .is_some_and(|(rule_process_name, _)| rule_process_name.trim() == process_name)
+    };
+    if !rules_string.lines().any(is_rule_of_process) {
+        return None;
+    }
+
+    Some(
+        rules_string
+            .lines()
+            .filter(|line| !is_rule_of_process(line))
+            .map(|line| format!("{}\n", line))
+            .collect(),
+    )
+}
+
+/// Rule written with parameters, instantiated for several processes.
+struct Template {
+    parameters: Vec<String>,
+    rule: String,
+}
+
+impl Template {
+    /// Returns the rule with its parameters replaced with the arguments, in the same order.
+    fn instantiate(&self, arguments: &[&str]) -> Result<String, SwytError> {
+        if arguments.len() != self.parameters.len() {
+            return Err(SwytError::RuleParseError);
+        }
+
+        let mut rule = String::with_capacity(self.rule.len());
+        let mut rest = self.rule.as_str();
+        while let Some(index) = rest.find(DAY_SET_PREFIX) {
+            rule.push_str(&rest[..index]);
+            rest = &rest[index + DAY_SET_PREFIX.len_utf8()..];
+            let name_length = rest
+                .find(|character: char| !is_name_character(character))
+                .unwrap_or(rest.len());
+            let (name, remaining) = rest.split_at(name_length);
+            // Other names are day sets
+            match self
+                .parameters
+                .iter()
+                .position(|parameter| parameter == name)
+            {
+                Some(position) => rule.push_str(arguments[position]),
+                None => {
+                    rule.push(DAY_SET_PREFIX);
+                    rule.push_str(name);
+                }
+            }
+            rest = remaining;
+        }
+        rule.push_str(rest);
+
+        Ok(rule)
+    }
+}
+
+fn is_name_character(character: char) -> bool {
+    character.is_alphanumeric() || character == '_' || character == '-'
+}
+
+/// Parses a template definition following `template `, e.g. `streaming($p)=$p=18:00~21:00;FR`.
+fn parse_template(template: &str) -> Result<(String, Template), SwytError> {
+    let (signature, rule) = template.split_once('=').ok_or(SwytError::RuleParseError)?;
+    let (name, parameters) =
+        parse_template_instance(signature.trim()).ok_or(SwytError::RuleParseError)?;
+    let parameters = parameters
+        .iter()
+        .map(|parameter| {
+            parameter
+                .strip_prefix(DAY_SET_PREFIX)
+                .filter(|name| !name.is_empty() && name.chars().all(is_name_character))
+                .map(str::to_string)
+                .ok_or(SwytError::RuleParseError)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((
+        name.to_string(),
+        Template {
+            parameters,
+            rule: rule.trim().to_string(),
+        },
+    ))
+}
+
+/// Parses the name and the comma separated arguments of `name(arguments)`, a line that isn't
+/// a rule as it has no `=`.
+fn parse_template_instance(line: &str) -> Option<(&str, Vec<&str>)> {
+    if line.contains('=') {
+        return None;
+    }
+    let (name, arguments) = line.strip_suffix(')')?.split_once('(')?;
+    Some((name, arguments.split(',').map(str::trim).collect()))
+}
+
+/// Parses the days of a day set, days and day sets separated by commas, optionally followed
+/// by ` minus ` and the days taken out of them.
+fn parse_day_set(
+    day_set: &str,
+    day_sets: &HashMap<String, HashSet<Weekday>>,
+) -> Result<HashSet<Weekday>, SwytError> {
+    let parse_days = |days: &str| -> Result<HashSet<Weekday>, SwytError> {
+        let mut parsed_days = HashSet::new();
+        for day in days.trim().split(',') {
+            match day.strip_prefix(DAY_SET_PREFIX) {
+                Some(name) => parsed_days.extend(
+                    day_sets
+                        .get(name)
+                        .ok_or(SwytError::RuleParseError)?
+                        .iter()
+                        .copied(),
+                ),
+                None => {
+                    parsed_days.insert(parse_day_of_week(day)?);
+                }
+            }
+        }
+        Ok(parsed_days)
+    };
+
+    match day_set.split_once(" minus ") {
+        Some((days, excluded_days)) => {
+            let excluded_days = parse_days(excluded_days)?;
+            Ok(parse_days(days)?
+                .difference(&excluded_days)
+                .copied()
+                .collect())
+        }
+        None => parse_days(day_set),
+    }
+}
+
+/// Replaces the day sets used as the days of the periods of a rule with their days.
+fn resolve_day_sets(
+    rule: &str,
+    day_sets: &HashMap<String, HashSet<Weekday>>,
+) -> Result<String, SwytError> {
+    let mut resolved_rule = String::with_capacity(rule.len());
+    let mut characters = rule.chars().peekable();
+    let mut previous_character = None;
+    while let Some(character) = characters.next() {
+        // Elsewhere, such as in a message, it is left as is
+        if character != DAY_SET_PREFIX || !matches!(previous_character, Some(';') | Some(',')) {
+            resolved_rule.push(character);
+            previous_character = Some(character);
+            continue;
+        }
+
+        let mut name = String::new();
+        while let Some(&character) = characters.peek() {
+            if !is_name_character(character) {
+                break;
+            }
+            name.push(character);
+            characters.next();
+        }
+        let days = day_sets.get(&name).ok_or(SwytError::RuleParseError)?;
+        let days: Vec<&str> = WEEKDAYS
+            .iter()
+            .filter(|weekday| days.contains(weekday))
+            .map(|&weekday| format_day_of_week(weekday))
+            .collect();
+        resolved_rule.push_str(&days.join(","));
+        previous_character = Some('$');
+    }
+
+    Ok(resolved_rule)
+}
+
+fn parse_rule(rule: &str, presets: &HashMap<String, Rule>) -> Result<Rule, SwytError> {
+    let mut split_rule = rule.splitn(2, "=");
     let process_name = split_rule
         .next()
         .ok_or(SwytError::RuleParseError)?
         .to_string();
-    let periods_string = split_rule.next().ok_or(SwytError::RuleParseError)?;
+    let rule_value = split_rule.next().ok_or(SwytError::RuleParseError)?.trim();
+    let (periods_string, attributes) = rule_value
+        .split_once(char::is_whitespace)
+        .unwrap_or((rule_value, ""));
+    let mut allowed_periods = vec![];
+    let mut inherited_preset = None;
+    for periods in periods_string.split("|") {
+        if periods.starts_with(PRESET_PREFIX) {
+            let preset = presets.get(periods).ok_or(SwytError::RuleParseError)?;
+            allowed_periods.extend(preset.allowed_periods.iter().cloned());
+            inherited_preset = inherited_preset.or(Some(preset));
+        } else {
+            allowed_periods.extend(parse_periods(periods)?);
+        }
+    }
+
+    // The attributes of the rule override the ones of the preset it inherits
+    let mut conditions = vec![];
+    let mut action = None;
+    let mut escalations = vec![];
+    let mut message = None;
+    let mut mandatory_break = None;
+    let mut budget = None;
+    let mut budget_escalations = vec![];
+    let mut excepted_periods = None;
+    #[cfg(feature = "timezone")]
+    let mut timezone = None;
+    for attribute in split_rule_attributes(attributes)? {
+        #[cfg(feature = "timezone")]
+        if let Some(timezone_name) = attribute.strip_prefix("tz=") {
+            timezone = Some(
+                chrono_tz::Tz::from_str(timezone_name).map_err(|_| SwytError::RuleParseError)?,
+            );
+            continue;
+        }
+        if let Some(action_string) = attribute.strip_prefix("action=") {
+            action = Some(parse_action(action_string)?);
+        } else if let Some(excepted_periods_string) = attribute.strip_prefix("except=") {
+            excepted_periods = Some(parse_excepted_periods(excepted_periods_string)?);
+        } else if let Some(escalation) = attribute.strip_prefix("action@") {
+            escalations.push(parse_escalation(escalation)?);
+        } else if let Some(message_string) = attribute.strip_prefix("message=") {
+            if message_string.is_empty() {
+                return Err(SwytError::RuleParseError);
+            }
+            message = Some(message_string.to_string());
+        } else if let Some(mandatory_break_string) = attribute.strip_prefix("break=") {
+            mandatory_break = Some(parse_mandatory_break(mandatory_break_string)?);
+        } else if let Some(budget_string) = attribute.strip_prefix("budget=") {
+            budget = Some(parse_budget(budget_string)?);
+        } else if let Some(escalation) = attribute.strip_prefix("budget@") {
+            budget_escalations.push(parse_escalation(escalation)?);
+        } else {
+            conditions.push(parse_condition(&attribute)?);
+        }
+    }
+    if let Some(preset) = inherited_preset {
+        if conditions.is_empty() {
+            conditions = preset.conditions.clone();
+        }
+        if action.is_none() && escalations.is_empty() {
+            escalations = preset.escalations.clone();
+        }
+        action = action.or_else(|| Some(preset.action.clone()));
+        message = message.or_else(|| preset.message.clone());
+        mandatory_break = mandatory_break.or(preset.mandatory_break);
+        budget = budget.or(preset.budget);
+        if budget_escalations.is_empty() {
+            budget_escalations = preset.budget_escalations.clone();
+        }
+        excepted_periods = excepted_periods.or_else(|| Some(preset.excepted_periods.clone()));
+        #[cfg(feature = "timezone")]
+        {
+            timezone = timezone.or(preset.timezone);
+        }
+    }
+    // A rule applying to any process regardless of its state would kill the whole system
+    if process_name == ANY_PROCESS_NAME && !conditions.iter().any(Condition::depends_on_process) {
+        return Err(SwytError::RuleParseError);
+    }
+    // The processes of a `*` rule have nothing in common to share a daily budget
+    if process_name == ANY_PROCESS_NAME && budget.is_some() {
+        return Err(SwytError::RuleParseError);
+    }
+
+    let action = action.unwrap_or(Action::Kill);
+    let rule = Rule::new(process_name, allowed_periods)
+        .with_conditions(conditions)
+        .with_action(action)
+        .with_escalations(escalations)
+        .with_message(message)
+        .with_mandatory_break(mandatory_break)
+        .with_budget(budget)
+        .with_budget_escalations(budget_escalations)
+        .with_exceptions(excepted_periods.unwrap_or_default());
+    #[cfg(feature = "timezone")]
+    let rule = rule.with_timezone(timezone);
+    Ok(rule)
+}
+
+/// Splits the attributes following the periods of a rule on whitespace, double quotes
+/// allowing values to contain some.
+fn split_rule_attributes(attributes: &str) -> Result<Vec<String>, SwytError> {
+    let mut split_attributes = vec![];
+    let mut attribute = String::new();
+    let mut is_quoted = false;
+    for character in attributes.chars() {
+        match character {
+            '"' => is_quoted = !is_quoted,
+            _ if character.is_whitespace() && !is_quoted => {
+                if !attribute.is_empty() {
+                    split_attributes.push(std::mem::take(&mut attribute));
+                }
+            }
+            _ => attribute.push(character),
+        }
+    }
+
+    if is_quoted {
+        return Err(SwytError::RuleParseError);
+    }
+
+    if !attribute.is_empty() {
+        split_attributes.push(attribute);
+    }
+
+    Ok(split_attributes)
+}
+
+fn parse_action(action: &str) -> Result<Action, SwytError> {
+    if let Some(title_pattern) = action.strip_prefix("close:") {
+        if title_pattern.is_empty() {
+            return Err(SwytError::RuleParseError);
+        }
+        if !cfg!(all(feature = "window", any(target_os = "linux", windows))) {
+            return Err(SwytError::Unsupported("close windows"));
+        }
+
+        return Ok(Action::CloseWindows(title_pattern.to_string()));
+    }
+
+    match action {
+        "kill" => Ok(Action::Kill),
+        // Minimizing is a nudge, it isn't replaced with a kill when windows can't be handled
+        "minimize" if cfg!(all(feature = "window", any(target_os = "linux", windows))) => {
+            Ok(Action::Minimize)
+        }
+        "minimize" => Err(SwytError::Unsupported("minimize windows")),
+        "notify" => Ok(Action::Notify),
+        "throttle" => Ok(Action::Throttle),
+        "suspend" => Ok(Action::Suspend),
+        _ => Err(SwytError::RuleParseError),
+    }
+}
+
+/// Parses the minutes after which an escalation applies and its action, as in
+/// `action@60=kill`.
+fn parse_escalation(escalation: &str) -> Result<(u32, Action), SwytError> {
+    let (minutes, action) = escalation
+        .split_once('=')
+        .ok_or(SwytError::RuleParseError)?;
+    let minutes = u32::from_str(minutes).map_err(|_| SwytError::RuleParseError)?;
+    Ok((minutes, parse_action(action)?))
+}
+
+/// Parses a mandatory break, such as `45/15` for a 15 minute break every 45 minutes.
+fn parse_mandatory_break(mandatory_break: &str) -> Result<MandatoryBreak, SwytError> {
+    let (after, duration) = mandatory_break
+        .split_once('/')
+        .ok_or(SwytError::RuleParseError)?;
+    let after = u32::from_str(after).map_err(|_| SwytError::RuleParseError)?;
+    let duration = u32::from_str(duration).map_err(|_| SwytError::RuleParseError)?;
+    MandatoryBreak::new(after, duration).ok_or(SwytError::RuleParseError)
+}
+
+/// Parses a daily budget in minutes, or in hours with `h`, such as `90m` or `2h`.
+fn parse_budget(budget: &str) -> Result<Budget, SwytError> {
+    let (number, unit) = match budget.strip_suffix('h') {
+        Some(hours) => (hours, 60 * 60),
+        None => (budget.strip_suffix('m').unwrap_or(budget), 60),
+    };
+    let number = u64::from_str(number).map_err(|_| SwytError::RuleParseError)?;
+    Budget::new(std::time::Duration::from_secs(number * unit)).ok_or(SwytError::RuleParseError)
+}
+
+fn parse_condition(condition: &str) -> Result<Condition, SwytError> {
+    if let Some(percentage) = condition.strip_prefix("gpu>") {
+        let percentage = u32::from_str(percentage).map_err(|_| SwytError::RuleParseError)?;
+        return Ok(Condition::GpuUsageAbove(percentage));
+    }
+    if let Some((operator, operands)) = condition
+        .strip_suffix(')')
+        .and_then(|condition| condition.split_once('('))
+    {
+        return match operator {
+            "during" => Ok(Condition::During(
+                operands
+                    .split('|')
+                    .map(parse_periods)
+                    .collect::<Result<Vec<_>, _>>()?
+                    .concat(),
+            )),
+            "all" => Ok(Condition::All(parse_condition_operands(operands)?)),
+            "any" => Ok(Condition::Any(parse_condition_operands(operands)?)),
+            _ => Err(SwytError::RuleParseError),
+        };
+    }
+
+    match condition {
+        "fullscreen" => Ok(Condition::Fullscreen),
+        "battery" => Ok(Condition::OnBattery),
+        _ => Err(SwytError::RuleParseError),
+    }
+}
+
+/// Parses the conditions combined by `all` or `any`, separated by commas outside of the
+/// parentheses of the nested ones.
+fn parse_condition_operands(operands: &str) -> Result<Vec<Condition>, SwytError> {
+    let mut conditions = vec![];
+    let mut depth = 0usize;
+    let mut operand_start = 0;
+    for (index, character) in operands.char_indices() {
+        match character {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1).ok_or(SwytError::RuleParseError)?,
+            ',' if depth == 0 => {
+                conditions.push(parse_condition(&operands[operand_start..index])?);
+                operand_start = index + 1;
+            }
+            _ => (),
+        }
+    }
+    conditions.push(parse_condition(&operands[operand_start..])?);
 
-    let allowed_periods: Vec<Period> = periods_string
-        .split("|")
-        .map(parse_periods)
-        .collect::<Result<Vec<Vec<Period>>, SwytError>>()?
-        .iter()
-        .flatten()
-        .map(|p| p.clone())
-        .collect();
-    Ok(Rule {
-        process_name,
-        allowed_periods,
-    })
+    Ok(conditions)
 }
 
 fn parse_periods(period: &str) -> Result<Vec<Period>, SwytError> {
@@ -192,11 +2035,27 @@ fn parse_periods(period: &str) -> Result<Vec<Period>, SwytError> {
         .collect())
 }
 
+/// Parses the periods of an `except` attribute, those without days applying to every day.
+fn parse_excepted_periods(excepted_periods: &str) -> Result<Vec<Period>, SwytError> {
+    let mut periods = vec![];
+    for excepted_period in excepted_periods.split('|') {
+        match excepted_period.contains(';') {
+            true => periods.extend(parse_periods(excepted_period)?),
+            false => periods.extend(parse_period_times(excepted_period)?.into_iter().map(
+                |(begin_time, end_time)| Period {
+                    days_of_week: WEEKDAYS.iter().copied().collect(),
+                    begin_time,
+                    end_time,
+                },
+            )),
+        }
+    }
+
+    Ok(periods)
+}
+
 fn parse_period_times(period_times: &str) -> Result<Vec<(NaiveTime, NaiveTime)>, SwytError> {
-    Ok(period_times
-        .split(",")
-        .map(parse_period_time)
-        .collect::<Result<_, SwytError>>()?)
+    period_times.split(",").map(parse_period_time).collect()
 }
 
 fn parse_period_time(period_time: &str) -> Result<(NaiveTime, NaiveTime), SwytError> {
@@ -208,7 +2067,11 @@ fn parse_period_time(period_time: &str) -> Result<(NaiveTime, NaiveTime), SwytEr
         _ => {
             let mut split_time = period_time.split("~");
             let begin_time = parse_time(split_time.next().ok_or(SwytError::RuleParseError)?)?;
-            let end_time = parse_time(split_time.next().ok_or(SwytError::RuleParseError)?)?;
+            let end_time = match split_time.next().ok_or(SwytError::RuleParseError)? {
+                // The end of the day, included like with `*`
+                "24:00" => NaiveTime::from_hms(23, 59, 59),
+                end_time => parse_time(end_time)?,
+            };
             Ok((begin_time, end_time))
         }
     }
@@ -221,14 +2084,12 @@ fn parse_time(time: &str) -> Result<NaiveTime, SwytError> {
     let minutes = u32::from_str(split_time.next().ok_or(SwytError::RuleParseError)?)
         .map_err(|_| SwytError::RuleParseError)?;
 
-    Ok(NaiveTime::from_hms(hours, minutes, 0))
+    NaiveTime::from_hms_opt(hours, minutes, 0)
+        .ok_or_else(|| SwytError::TimeParseError(time.to_string()))
 }
 
 fn parse_days_of_week(days_of_week: &str) -> Result<HashSet<Weekday>, SwytError> {
-    Ok(days_of_week
-        .split(",")
-        .map(parse_day_of_week)
-        .collect::<Result<HashSet<Weekday>, SwytError>>()?)
+    days_of_week.split(",").map(parse_day_of_week).collect()
 }
 
 fn parse_day_of_week(day_of_week: &str) -> Result<Weekday, SwytError> {
@@ -244,6 +2105,18 @@ fn parse_day_of_week(day_of_week: &str) -> Result<Weekday, SwytError> {
     })
 }
 
+fn format_day_of_week(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
 fn parse_config_file(config_filepath: PathBuf) -> Result<Configuration, SwytError> {
     if !config_filepath.exists() {
         info!(
@@ -256,11 +2129,19 @@ fn parse_config_file(config_filepath: PathBuf) -> Result<Configuration, SwytErro
         return Ok(Configuration::default());
     }
 
+    parse_config(&std::fs::read_to_string(&config_filepath)?)
+}
+
+pub fn parse_config(config_string: &str) -> Result<Configuration, SwytError> {
+    validate_config(config_string).map_err(|(_, err)| err)
+}
+
+/// Parses the configuration like `parse_config`, an error coming with the number of the line
+/// it is on, from 1.
+pub fn validate_config(config_string: &str) -> Result<Configuration, (usize, SwytError)> {
     let mut config = Configuration::default();
-    let config_file = File::open(&config_filepath)?;
-    let reader = BufReader::new(config_file);
-    for line in reader.lines() {
-        parse_config_line(line?, &mut config)?;
+    for (index, line) in config_string.lines().enumerate() {
+        parse_config_line(line.to_string(), &mut config).map_err(|err| (index + 1, err))?;
     }
 
     Ok(config)
@@ -273,52 +2154,319 @@ fn parse_config_line(line: String, config: &mut Configuration) -> Result<(), Swy
 
     match config_identifier {
         "check_interval" => {
-            let value = u32::from_str(&config_value).unwrap_or(DEFAULT_CHECK_INTERVAL);
+            let value = u32::from_str(config_value).unwrap_or(DEFAULT_CHECK_INTERVAL);
             config.check_interval = value
         }
+        "interactive_sessions_only" => {
+            config.interactive_sessions_only = bool::from_str(config_value).unwrap_or(false)
+        }
+        "managed" => config.managed = bool::from_str(config_value).unwrap_or(false),
+        "on_reload_error" => {
+            config.on_reload_error = match config_value {
+                "halt" => ReloadErrorBehavior::Halt,
+                "failsafe_lock" => ReloadErrorBehavior::FailsafeLock,
+                _ => ReloadErrorBehavior::Keep,
+            }
+        }
+        "on_scan_error" => {
+            config.on_scan_error = match config_value {
+                "abort" => ScanErrorBehavior::Abort,
+                _ => ScanErrorBehavior::Skip,
+            }
+        }
+        "scan_workers" => {
+            config.scan_workers = match u32::from_str(config_value) {
+                Ok(value) if value > 0 => value,
+                _ => 1,
+            }
+        }
+        "low_power" => {
+            config.low_power = match config_value {
+                "on" => LowPowerMode::On,
+                "off" => LowPowerMode::Off,
+                _ => LowPowerMode::Auto,
+            }
+        }
+        "low_power_check_interval" => {
+            config.low_power_check_interval =
+                u32::from_str(config_value).unwrap_or(DEFAULT_LOW_POWER_CHECK_INTERVAL)
+        }
+        "dst_repeated_time" => {
+            config.dst_repeated_time = match config_value {
+                "earliest" => RepeatedTimePolicy::Earliest,
+                "latest" => RepeatedTimePolicy::Latest,
+                _ => RepeatedTimePolicy::Both,
+            }
+        }
+        "renamed_binaries" => {
+            config.renamed_binaries = match config_value {
+                "alert" => RenamedBinaryPolicy::Alert,
+                "block" => RenamedBinaryPolicy::Block,
+                _ => RenamedBinaryPolicy::Off,
+            }
+        }
+        "unsaved_work_grace" => {
+            config.unsaved_work_grace = u32::from_str(config_value).unwrap_or(0)
+        }
+        "terminate_grace" => config.terminate_grace = u32::from_str(config_value).unwrap_or(0),
+        "inhibitor_max_deferral" => {
+            config.inhibitor_max_deferral = u32::from_str(config_value).unwrap_or(0)
+        }
+        "lockdown_processes" => config.lockdown_processes = parse_process_names(config_value),
+        "busy_calendar" if !config_value.is_empty() => {
+            config.busy_calendar = Some(PathBuf::from(config_value))
+        }
+        "busy_processes" => config.busy_processes = parse_process_names(config_value),
+        "do_not_disturb_processes" => {
+            config.do_not_disturb_processes = parse_process_names(config_value)
+        }
+        "protected_processes" => config.protected_processes = parse_process_names(config_value),
+        "override_requests_per_day" => {
+            config.override_requests_per_day =
+                u32::from_str(config_value).unwrap_or(DEFAULT_OVERRIDE_REQUESTS_PER_DAY)
+        }
+        "override_request_expiry" => {
+            config.override_request_expiry = match u32::from_str(config_value) {
+                Ok(override_request_expiry) if override_request_expiry > 0 => {
+                    override_request_expiry
+                }
+                _ => DEFAULT_OVERRIDE_REQUEST_EXPIRY,
+            }
+        }
+        "override_cooldown" => config.override_cooldown = u32::from_str(config_value).unwrap_or(0),
+        "time_server" if !config_value.is_empty() => {
+            config.time_server = Some(config_value.to_string())
+        }
+        // The command can have arguments with `=` in them
+        "report_command" if !config_value.is_empty() => {
+            config.report_command = line
+                .split_once('=')
+                .map(|(_, command)| command.trim().to_string())
+        }
+        "notify_command" if !config_value.is_empty() => {
+            config.notify_command = line
+                .split_once('=')
+                .map(|(_, command)| command.trim().to_string())
+        }
+        // The key can be repeated, each line adding a regular expression with `=` in it maybe
+        "redact_pattern" if !config_value.is_empty() => {
+            if let Some((_, pattern)) = line.split_once('=') {
+                config.redactor.insert_pattern(pattern.trim())?;
+            }
+        }
+        "stats_retention" => config.stats_retention = u32::from_str(config_value).unwrap_or(0),
+        "anonymous_stats" => config.anonymous_stats = bool::from_str(config_value).unwrap_or(false),
+        "audit_retention" => config.audit_retention = u32::from_str(config_value).unwrap_or(0),
+        "loosening_approval" => {
+            config.loosening_approval = bool::from_str(config_value).unwrap_or(false)
+        }
+        "max_clock_skew" => {
+            config.max_clock_skew = match u32::from_str(config_value) {
+                Ok(max_clock_skew) if max_clock_skew > 0 => max_clock_skew,
+                _ => DEFAULT_MAX_CLOCK_SKEW,
+            }
+        }
         _ => (),
     }
 
     Ok(())
 }
 
+/// Parses a comma separated list of process names, `*` being left out as blocking any
+/// process at any time would kill the whole system.
+fn parse_process_names(process_names: &str) -> Vec<String> {
+    process_names
+        .split(',')
+        .map(str::trim)
+        .filter(|process_name| !process_name.is_empty() && *process_name != ANY_PROCESS_NAME)
+        .map(String::from)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const VALID_CONFIG_SWYT_PATH: &'static str = "./test_data/valid_config";
-    const MISSING_VALUE_CONFIG_SWYT_PATH: &'static str = "./test_data/missing_value_config";
-    const INVALID_CONFIG_SWYT_PATH: &'static str = "./test_data/invalid_config";
-    const VALID_RULES_SWYT_PATH: &'static str = "./test_data/valid_rules";
-    const NO_RULE_SWYT_PATH: &'static str = "./test_data/no_rule";
-    const INVALID_RULES_SWYT_PATH: &'static str = "./test_data/invalid_rules";
+    const VALID_CONFIG_SWYT_PATH: &str = "./test_data/valid_config";
+    const MISSING_VALUE_CONFIG_SWYT_PATH: &str = "./test_data/missing_value_config";
+    const INVALID_CONFIG_SWYT_PATH: &str = "./test_data/invalid_config";
+    const VALID_RULES_SWYT_PATH: &str = "./test_data/valid_rules";
+    const NO_RULE_SWYT_PATH: &str = "./test_data/no_rule";
+    const INVALID_RULES_SWYT_PATH: &str = "./test_data/invalid_rules";
 
     #[test]
     pub fn load_config_valid() {
-        let config = load_config(&VALID_CONFIG_SWYT_PATH.into()).unwrap();
+        let config = load_config(Path::new(VALID_CONFIG_SWYT_PATH)).unwrap();
         assert_eq!(config.check_interval(), 120);
+        assert!(config.interactive_sessions_only());
+        assert!(config.managed());
+        assert_eq!(config.on_reload_error(), ReloadErrorBehavior::FailsafeLock);
+        assert_eq!(config.on_scan_error(), ScanErrorBehavior::Abort);
+        assert_eq!(config.scan_workers(), 4);
+        assert_eq!(config.low_power(), LowPowerMode::Off);
+        assert_eq!(config.low_power_check_interval(), 600);
+        assert_eq!(config.dst_repeated_time(), RepeatedTimePolicy::Earliest);
+        assert_eq!(config.renamed_binaries(), RenamedBinaryPolicy::Alert);
+        assert_eq!(config.lockdown_processes(), ["steam", "discord"]);
+        assert_eq!(config.unsaved_work_grace(), 300);
+        assert_eq!(config.terminate_grace(), 10);
+        assert_eq!(config.inhibitor_max_deferral(), 1800);
+        assert_eq!(
+            config.busy_calendar(),
+            Some(Path::new("/home/user/.calendars/work"))
+        );
+        assert_eq!(config.busy_processes(), ["discord"]);
+        assert_eq!(config.do_not_disturb_processes(), ["discord", "steam"]);
+        assert_eq!(config.protected_processes(), ["backup-job"]);
+        assert_eq!(config.override_requests_per_day(), 5);
+        assert_eq!(config.override_request_expiry(), 90);
+        assert_eq!(config.override_cooldown(), 8);
+        assert_eq!(config.time_server(), Some("pool.ntp.org"));
+        assert_eq!(config.max_clock_skew(), 120);
+        assert_eq!(
+            config.report_command(),
+            Some("curl -fsS --data-binary @- https://example.com/swyt?source=swyt")
+        );
+        assert_eq!(
+            config.notify_command(),
+            Some("notify-send --urgency=critical Swyt {}")
+        );
+        assert_eq!(config.stats_retention(), 90);
+        assert!(config.anonymous_stats());
+        assert_eq!(config.audit_retention(), 365);
+        assert!(config.loosening_approval());
+    }
+
+    #[test]
+    fn reload_modified_policy() {
+        let swyt_filepath = Path::new("./test_data/reload");
+        let mut modified_time = None;
+        let (config, rules) = reload(swyt_filepath, &mut modified_time).unwrap().unwrap();
+        assert_eq!(config.check_interval(), 30);
+        assert_eq!(rules.len(), 1);
+        assert_eq!(modified_time, policy_modified_time(swyt_filepath));
+        assert!(reload(swyt_filepath, &mut modified_time).unwrap().is_none());
     }
 
     #[test]
     pub fn load_config_missing_value() {
-        let config = load_config(&MISSING_VALUE_CONFIG_SWYT_PATH.into()).unwrap();
+        let config = load_config(Path::new(MISSING_VALUE_CONFIG_SWYT_PATH)).unwrap();
         assert_eq!(config.check_interval(), 60);
     }
 
     #[test]
     pub fn load_config_bad_value() {
-        let config = load_config(&INVALID_CONFIG_SWYT_PATH.into()).unwrap();
+        let config = load_config(Path::new(INVALID_CONFIG_SWYT_PATH)).unwrap();
         assert_eq!(config.check_interval(), 60);
+        assert!(!config.interactive_sessions_only());
+        assert_eq!(config.on_reload_error(), ReloadErrorBehavior::Keep);
+        assert_eq!(config.scan_workers(), 1);
+        assert_eq!(config.low_power(), LowPowerMode::Auto);
+        assert_eq!(config.low_power_check_interval(), 300);
+        assert_eq!(config.dst_repeated_time(), RepeatedTimePolicy::Both);
+        assert_eq!(config.renamed_binaries(), RenamedBinaryPolicy::Off);
+        assert_eq!(config.terminate_grace(), 0);
+        assert_eq!(config.override_requests_per_day(), 3);
+        assert_eq!(config.override_request_expiry(), 60);
+        assert_eq!(config.override_cooldown(), 0);
+        assert_eq!(config.time_server(), None);
+        assert_eq!(config.max_clock_skew(), 300);
+        assert_eq!(config.report_command(), None);
+        assert_eq!(config.notify_command(), None);
+        assert_eq!(config.stats_retention(), 0);
+        assert!(!config.anonymous_stats());
+        assert_eq!(config.audit_retention(), 0);
+        assert!(!config.loosening_approval());
+    }
+
+    #[test]
+    #[cfg(all(feature = "window", any(target_os = "linux", windows)))]
+    pub fn required_capabilities_valid() {
+        let configuration = parse_config("unsaved_work_grace=60\nmanaged=true").unwrap();
+        let rules = parse_rules(
+            "game=*;SA action=minimize action@30=kill\n\
+             chat=*;SU\n\
+             *=*;SU gpu>50",
+        )
+        .unwrap();
+        let capabilities = required_capabilities(&configuration, &rules);
+        let needed_by: Vec<(&str, &[String])> = capabilities
+            .iter()
+            .map(|capability| (capability.name(), capability.needed_by()))
+            .collect();
+
+        assert_eq!(
+            needed_by[..3],
+            [
+                (
+                    "kill the processes of other users",
+                    &["managed".to_string()][..]
+                ),
+                (
+                    "minimize and close windows",
+                    &["rule game".to_string(), "unsaved_work_grace".to_string()][..]
+                ),
+                (
+                    "read the GPU usage of processes",
+                    &["rule *".to_string()][..]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn scan_report_display() {
+        let mut report = ScanReport::default();
+        report.record_scanned("game", Verdict::Blocked);
+        report.record_scanned("chat", Verdict::Blocked);
+        report.record_scanned("bash", Verdict::Unmatched);
+        report.record_blocked();
+        report.record_blocked();
+        report.record_minimized();
+        report
+            .record_unreadable(ScanErrorBehavior::Skip, SwytError::ProcessFetchError)
+            .unwrap();
+        assert!(report
+            .record_unreadable(ScanErrorBehavior::Abort, SwytError::ProcessFetchError)
+            .is_err());
+        report.record_error("game", 42, "Operation not permitted".to_string());
+
+        assert_eq!(
+            report.to_string(),
+            "Scanned 3 process(es), 2 blocked: 0 killed, 1 minimized, 0 closed, 0 restrained, 0 deferred, 1 unreadable, 1 error(s)"
+        );
+        assert_eq!(
+            report.errors()[0].to_string(),
+            "Couldn't enforce the rule on process game (42): Operation not permitted"
+        );
+    }
+
+    #[test]
+    pub fn export_ics_valid() {
+        let rules = load_rules(Path::new(VALID_RULES_SWYT_PATH)).unwrap();
+        let ics = export_ics(
+            &rules,
+            NaiveDate::from_ymd(2020, 6, 3),
+            &NaiveDate::from_ymd(2020, 6, 1).and_hms(10, 30, 0),
+        );
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 5);
+        assert!(ics.contains(
+            "DTSTART:20200603T100000\r\nDTEND:20200603T110000\r\nRRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE\r\nSUMMARY:process1 allowed\r\n"
+        ));
+        assert!(ics.contains("DTSTART:20200606T000000\r\n"));
     }
 
     #[test]
     pub fn load_rules_valid() {
-        let rules = load_rules(&VALID_RULES_SWYT_PATH.into()).unwrap();
+        let rules = load_rules(Path::new(VALID_RULES_SWYT_PATH)).unwrap();
 
         assert_eq!(rules.len(), 3);
 
         let process0_rules = rules.get("process0").unwrap();
-        let process0_rule0 = process0_rules.get(0).unwrap();
+        let process0_rule0 = process0_rules.first().unwrap();
         assert_eq!(process0_rule0.begin_time, NaiveTime::from_hms(18, 00, 00));
         assert_eq!(process0_rule0.end_time, NaiveTime::from_hms(20, 00, 00));
         assert!(process0_rule0.days_of_week.contains(&Weekday::Mon));
@@ -338,7 +2486,7 @@ mod tests {
         assert!(process0_rule2.days_of_week.contains(&Weekday::Sun));
 
         let process1_rules = rules.get("process1").unwrap();
-        let process1_rule0 = process1_rules.get(0).unwrap();
+        let process1_rule0 = process1_rules.first().unwrap();
         assert_eq!(process1_rule0.begin_time, NaiveTime::from_hms(10, 00, 00));
         assert_eq!(process1_rule0.end_time, NaiveTime::from_hms(11, 00, 00));
         assert!(process1_rule0.days_of_week.contains(&Weekday::Mon));
@@ -346,7 +2494,7 @@ mod tests {
         assert!(process1_rule0.days_of_week.contains(&Weekday::Wed));
 
         let process2_rules = rules.get("process2").unwrap();
-        let process2_rule0 = process2_rules.get(0).unwrap();
+        let process2_rule0 = process2_rules.first().unwrap();
         assert_eq!(process2_rule0.begin_time, NaiveTime::from_hms(12, 00, 00));
         assert_eq!(process2_rule0.end_time, NaiveTime::from_hms(15, 00, 00));
         assert!(process2_rule0.days_of_week.contains(&Weekday::Mon));
@@ -356,15 +2504,849 @@ mod tests {
 
     #[test]
     fn load_invalid_rules() {
-        match load_rules(&INVALID_RULES_SWYT_PATH.into()) {
-            Err(SwytError::RuleParseError) => assert!(true),
-            _ => assert!(false),
-        }
+        assert!(matches!(
+            load_rules(Path::new(INVALID_RULES_SWYT_PATH)),
+            Err(SwytError::RuleParseError)
+        ));
     }
 
     #[test]
     fn load_no_rule() {
-        let rules = load_rules(&NO_RULE_SWYT_PATH.into()).unwrap();
+        let rules = load_rules(Path::new(NO_RULE_SWYT_PATH)).unwrap();
         assert_eq!(rules.len(), 0);
     }
+
+    #[test]
+    fn evaluate_rules_verdicts() {
+        let rules = load_rules(Path::new(VALID_RULES_SWYT_PATH)).unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+
+        assert_eq!(
+            evaluate_rules(&rules, "process1", &monday.and_hms(10, 30, 0)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "process1", &monday.and_hms(12, 0, 0)),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "firefox", &monday.and_hms(12, 0, 0)),
+            Verdict::Unmatched
+        );
+    }
+
+    #[test]
+    fn evaluate_rules_patterns() {
+        let rules = parse_rules(
+            "chrome=08:00~18:00;MO\nchrom*=08:00~09:00;MO\nchrom*=08:00~10:00;MO\n\
+             path:/usr/bin/steam*=08:00~09:00;MO\ncmd:* --server*=08:00~09:00;MO",
+        )
+        .unwrap();
+        let noon = EvaluationTime::new(NaiveDate::from_ymd(2020, 6, 1).and_hms(12, 0, 0));
+        let evaluate =
+            |process: ProcessTarget| evaluate_process(&rules, &process, &noon, |_| false).0;
+
+        assert_eq!(rules.len(), 4);
+        // The rule named after the process takes precedence over the patterns
+        assert_eq!(evaluate(ProcessTarget::named("chrome")), Verdict::Allowed);
+        assert_eq!(evaluate(ProcessTarget::named("chromium")), Verdict::Blocked);
+        assert_eq!(evaluate(ProcessTarget::named("steam")), Verdict::Unmatched);
+        assert_eq!(
+            evaluate(ProcessTarget::named("steam").with_path(Some("/usr/bin/steam"))),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate(
+                ProcessTarget::named("java").with_command_line(Some("java -jar mc.jar --server"))
+            ),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate(ProcessTarget::named("java").with_command_line(Some("java -jar mc.jar"))),
+            Verdict::Unmatched
+        );
+        assert!(parse_rules("path:=08:00~09:00;MO").is_err());
+
+        #[cfg(feature = "regex")]
+        {
+            let rules = parse_rules("re:^minecraft.*=08:00~09:00;MO").unwrap();
+            let evaluate = |name| evaluate_rules(&rules, name, noon.date_time());
+            assert_eq!(evaluate("minecraft-launcher"), Verdict::Blocked);
+            assert_eq!(evaluate("not-minecraft"), Verdict::Unmatched);
+            assert!(parse_rules("re:(minecraft=08:00~09:00;MO").is_err());
+        }
+    }
+
+    #[test]
+    fn evaluate_rules_overlapping_periods() {
+        let rules = parse_rules("process0=08:00~18:00,09:00~10:00,12:00~13:00;MO").unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+
+        assert_eq!(
+            evaluate_rules(&rules, "process0", &monday.and_hms(17, 0, 0)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "process0", &monday.and_hms(7, 59, 0)),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "process0", &monday.succ().and_hms(9, 30, 0)),
+            Verdict::Blocked
+        );
+    }
+
+    #[test]
+    fn period_contains_repeated_times() {
+        use semantics::RepeatedPass::{First, Second};
+        let time = |hour, minute| NaiveTime::from_hms(hour, minute, 0);
+        // The clocks fall back from 03:00 to 02:00
+        let repeated_times = (time(2, 0), NaiveTime::from_hms(2, 59, 59));
+        let contains = |period: (NaiveTime, NaiveTime), at, pass, policy| {
+            semantics::period_contains_repeated(
+                period.0,
+                period.1,
+                at,
+                repeated_times,
+                pass,
+                policy,
+            )
+        };
+
+        let ending = (time(1, 0), time(2, 30));
+        let beginning = (time(2, 30), time(4, 0));
+        let around = (time(1, 0), time(5, 0));
+        for (period, at, pass, policy, expected) in [
+            (ending, time(2, 15), First, RepeatedTimePolicy::Both, true),
+            (ending, time(2, 15), Second, RepeatedTimePolicy::Both, true),
+            (ending, time(2, 45), First, RepeatedTimePolicy::Both, false),
+            (
+                ending,
+                time(2, 15),
+                Second,
+                RepeatedTimePolicy::Earliest,
+                false,
+            ),
+            (ending, time(2, 45), First, RepeatedTimePolicy::Latest, true),
+            (
+                ending,
+                time(2, 45),
+                Second,
+                RepeatedTimePolicy::Latest,
+                false,
+            ),
+            (
+                beginning,
+                time(2, 15),
+                First,
+                RepeatedTimePolicy::Earliest,
+                false,
+            ),
+            (
+                beginning,
+                time(2, 15),
+                Second,
+                RepeatedTimePolicy::Earliest,
+                true,
+            ),
+            (
+                beginning,
+                time(2, 45),
+                First,
+                RepeatedTimePolicy::Latest,
+                false,
+            ),
+            (
+                beginning,
+                time(2, 45),
+                Second,
+                RepeatedTimePolicy::Latest,
+                true,
+            ),
+            (
+                around,
+                time(2, 15),
+                First,
+                RepeatedTimePolicy::Earliest,
+                true,
+            ),
+            (
+                around,
+                time(2, 15),
+                Second,
+                RepeatedTimePolicy::Latest,
+                true,
+            ),
+        ] {
+            assert_eq!(contains(period, at, pass, policy), expected);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "timezone")]
+    fn evaluate_rules_timezone() {
+        let rules = parse_rules(
+            "game=17:00~21:00;MO tz=Asia/Tokyo\n\
+             browser=preset:paris|17:00~21:00;MO\n\
+             preset:paris=01:00~02:30;SU tz=Europe/Paris",
+        )
+        .unwrap();
+        assert!(parse_rules("game=*;MO tz=Mars/Olympus_Mons").is_err());
+        assert_eq!(
+            rules.get_rule("game").unwrap().to_string(),
+            "game=17:00~21:00;MO tz=Asia/Tokyo"
+        );
+        assert_eq!(
+            rules.get_rule("browser").unwrap().timezone(),
+            Some(chrono_tz::Europe::Paris)
+        );
+
+        // 18:00 in Tokyo
+        let monday = Utc.ymd(2020, 6, 1).and_hms(9, 0, 0);
+        let verdict = |process_name, date_time, policy| {
+            evaluate_rules_at(&rules, process_name, date_time, policy)
+        };
+        assert_eq!(
+            verdict("game", &monday, RepeatedTimePolicy::Both),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            verdict("browser", &monday, RepeatedTimePolicy::Both),
+            Verdict::Blocked
+        );
+
+        // 02:45 in Paris on the first pass, the clocks falling back at 03:00
+        let sunday = Utc.ymd(2020, 10, 25).and_hms(0, 45, 0);
+        assert_eq!(
+            verdict("browser", &sunday, RepeatedTimePolicy::Both),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            verdict("browser", &sunday, RepeatedTimePolicy::Latest),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            verdict(
+                "browser",
+                &(sunday + chrono::Duration::minutes(45)),
+                RepeatedTimePolicy::Earliest
+            ),
+            Verdict::Blocked
+        );
+    }
+
+    #[test]
+    fn evaluate_rules_same_minute() {
+        let rules = parse_rules("game=17:00~21:00;MO").unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+
+        for _ in 0..2 {
+            assert_eq!(
+                evaluate_rules(&rules, "game", &monday.and_hms(18, 0, 10)),
+                Verdict::Allowed
+            );
+            assert_eq!(
+                evaluate_rules(&rules, "game", &monday.and_hms(18, 0, 50)),
+                Verdict::Allowed
+            );
+            assert_eq!(
+                evaluate_rules(&rules, "game", &monday.and_hms(21, 0, 0)),
+                Verdict::Allowed
+            );
+            assert_eq!(
+                evaluate_rules(&rules, "game", &monday.and_hms(21, 0, 30)),
+                Verdict::Blocked
+            );
+            assert_eq!(
+                evaluate_rules(&rules, "game", &monday.and_hms(16, 59, 59)),
+                Verdict::Blocked
+            );
+            assert_eq!(
+                evaluate_rules(&rules, "game", &monday.succ().and_hms(18, 0, 10)),
+                Verdict::Blocked
+            );
+        }
+    }
+
+    #[test]
+    fn evaluate_rules_conditions() {
+        let rules = parse_rules(
+            "*=00:00~23:00;MO fullscreen\n\
+             mpv=*;MO fullscreen",
+        )
+        .unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let fullscreen = |condition: &Condition| *condition == Condition::Fullscreen;
+
+        assert_eq!(
+            evaluate_rules_with_conditions(&rules, "game", &monday.and_hms(23, 30, 0), fullscreen),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate_rules_with_conditions(&rules, "mpv", &monday.and_hms(23, 30, 0), fullscreen),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            evaluate_rules_with_conditions(&rules, "game", &monday.and_hms(23, 30, 0), |_| false),
+            Verdict::Unmatched
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "game", &monday.and_hms(23, 30, 0)),
+            Verdict::Unmatched
+        );
+        assert!(parse_rules("*=*;MO").is_err());
+        assert!(parse_rules("*=*;MO gpu>").is_err());
+
+        let rules = parse_rules("*=08:00~17:00;MO gpu>50").unwrap();
+        assert_eq!(
+            evaluate_rules_with_conditions(
+                &rules,
+                "game",
+                &monday.and_hms(18, 0, 0),
+                |condition| { *condition == Condition::GpuUsageAbove(50) }
+            ),
+            Verdict::Blocked
+        );
+    }
+
+    #[test]
+    fn evaluate_rules_condition_tree() {
+        let rules =
+            parse_rules("*=*;SA all(fullscreen,any(battery,during(18:00~23:00;MO|*;TU)))").unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let fullscreen = |condition: &Condition| *condition == Condition::Fullscreen;
+        let on_battery = |condition: &Condition| {
+            matches!(condition, Condition::Fullscreen | Condition::OnBattery)
+        };
+
+        assert_eq!(
+            evaluate_rules_with_conditions(&rules, "game", &monday.and_hms(19, 0, 0), fullscreen),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate_rules_with_conditions(&rules, "game", &monday.and_hms(10, 0, 0), fullscreen),
+            Verdict::Unmatched
+        );
+        assert_eq!(
+            evaluate_rules_with_conditions(&rules, "game", &monday.and_hms(10, 0, 0), on_battery),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            rules.iter().next().unwrap().to_string(),
+            "*=*;SA all(fullscreen,any(battery,during(18:00~23:00;MO|*;TU)))"
+        );
+
+        assert!(parse_rules("*=*;MO any(fullscreen,battery)").is_err());
+        assert!(parse_rules("*=*;MO all(fullscreen,battery)").is_ok());
+        assert!(parse_rules("game=*;MO any(fullscreen").is_err());
+        assert!(parse_rules("game=*;MO all(fullscreen))").is_err());
+        assert!(parse_rules("game=*;MO during(25:00~26:00;MO)").is_err());
+    }
+
+    #[test]
+    fn protected_processes() {
+        let rules = parse_rules("*=*;SA fullscreen\ndpkg=*;SA").unwrap();
+        let config = load_config(Path::new(VALID_CONFIG_SWYT_PATH)).unwrap();
+        let any_process_rule = rules.iter().next().unwrap();
+        let dpkg_rule = rules.iter().nth(1).unwrap();
+
+        assert!(!is_protected_from(
+            &config,
+            &rules,
+            "game",
+            any_process_rule
+        ));
+        assert!(is_protected_from(&config, &rules, "apt", any_process_rule));
+        assert!(is_protected_from(
+            &config,
+            &rules,
+            "backup-job",
+            any_process_rule
+        ));
+        assert!(!is_protected_from(&config, &rules, "dpkg", dpkg_rule));
+        assert!(is_protected_from(&config, &rules, "dpkg", any_process_rule));
+        assert!(!Configuration::default().is_protected("backup-job"));
+    }
+
+    #[test]
+    fn evaluate_rules_exceptions() {
+        let rules = parse_rules(
+            "game=*;MO,TU,WE,TH,FR except=22:00~06:00|*;FR\n\
+             browser=preset:school|*;SA\n\
+             preset:school=08:00~18:00;MO except=12:00~13:00;MO",
+        )
+        .unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let friday = NaiveDate::from_ymd(2020, 6, 5);
+        let game = rules.get_rule("game").unwrap();
+
+        for (date_time, verdict) in [
+            (monday.and_hms(5, 59, 59), Verdict::Blocked),
+            (monday.and_hms(6, 0, 0), Verdict::Allowed),
+            (monday.and_hms(21, 59, 59), Verdict::Allowed),
+            (monday.and_hms(22, 0, 0), Verdict::Blocked),
+            (friday.and_hms(12, 0, 0), Verdict::Blocked),
+        ] {
+            assert_eq!(evaluate_rules(&rules, "game", &date_time), verdict);
+        }
+        assert_eq!(
+            game.allowed_times(Weekday::Mon),
+            [(
+                NaiveTime::from_hms(6, 0, 0),
+                NaiveTime::from_hms(21, 59, 59)
+            )]
+        );
+        assert!(game.allowed_times(Weekday::Fri).is_empty());
+        assert_eq!(
+            game.to_string(),
+            "game=*;MO,TU,WE,TH,FR except=22:00~06:00;MO,TU,WE,TH,FR,SA,SU|*;FR"
+        );
+
+        assert_eq!(
+            evaluate_rules(&rules, "browser", &monday.and_hms(12, 30, 0)),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "browser", &monday.and_hms(13, 0, 0)),
+            Verdict::Allowed
+        );
+        assert!(parse_rules("game=*;MO except=").is_err());
+    }
+
+    #[test]
+    fn parse_rules_day_sets() {
+        let rules = parse_rules(
+            "$weekdays=MO,TU,WE,TH,FR\n\
+             $schooldays=$weekdays minus WE\n\
+             game=17:00~21:00;$schooldays|*;SA message=\"Costs $5\"",
+        )
+        .unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let wednesday = NaiveDate::from_ymd(2020, 6, 3);
+        let game = rules.get_rule("game").unwrap();
+
+        assert_eq!(
+            evaluate_rules(&rules, "game", &monday.and_hms(18, 0, 0)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "game", &wednesday.and_hms(18, 0, 0)),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            game.to_string(),
+            "game=17:00~21:00;MO,TU,TH,FR|*;SA message=\"Costs $5\""
+        );
+        assert!(parse_rules("game=*;$holidays").is_err());
+        assert!(parse_rules("$none=MO minus MO\ngame=*;$none").is_err());
+        assert!(parse_rules("$days=MO,$days").is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "window", any(target_os = "linux", windows)))]
+    fn parse_rules_templates() {
+        let rules = parse_rules(
+            "$weekdays=MO,TU,WE,TH,FR\n\
+             template streaming($p, $action) = $p=18:00~21:00;$weekdays|*;SA,SU action=$action\n\
+             streaming(netflix, minimize)\n\
+             streaming(twitch, kill)",
+        )
+        .unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            rules.get_rule("netflix").unwrap().to_string(),
+            "netflix=18:00~21:00;MO,TU,WE,TH,FR|*;SA,SU action=minimize"
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "twitch", &monday.and_hms(17, 0, 0)),
+            Verdict::Blocked
+        );
+        assert!(parse_rules("streaming(netflix)").is_err());
+        assert!(parse_rules("template streaming($p)=$p=*;MO\nstreaming(netflix, twitch)").is_err());
+        assert!(parse_rules("template streaming(p)=p=*;MO").is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "window", any(target_os = "linux", windows)))]
+    fn parse_rules_actions() {
+        let rules = parse_rules(
+            "game=*;SA,SU action=minimize\n\
+             *=00:00~23:00;MO fullscreen action=kill\n\
+             process0=*;MO",
+        )
+        .unwrap();
+
+        assert_eq!(rules.get_rule("game").unwrap().action(), &Action::Minimize);
+        assert_eq!(rules.get_rule("process0").unwrap().action(), &Action::Kill);
+        assert!(parse_rules("game=*;SA,SU action=close").is_err());
+
+        let rules = parse_rules("firefox=*;SA,SU action=close:\"Twitch - \" fullscreen").unwrap();
+        let rule = rules.get_rule("firefox").unwrap();
+        assert_eq!(
+            rule.action(),
+            &Action::CloseWindows("Twitch - ".to_string())
+        );
+        assert_eq!(rule.conditions(), &[Condition::Fullscreen]);
+        assert!(parse_rules("firefox=*;SA,SU action=close:\"Twitch").is_err());
+    }
+
+    #[test]
+    fn parse_rules_times() {
+        for rules_string in [
+            "game=25:00~26:00;MO",
+            "game=17:60~18:00;MO",
+            "game=24:00~24:00;MO",
+        ] {
+            assert!(matches!(
+                parse_rules(rules_string),
+                Err(SwytError::TimeParseError(_))
+            ));
+        }
+
+        let rules = parse_rules("game=22:00~24:00;MO").unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        assert_eq!(
+            evaluate_rules(&rules, "game", &monday.and_hms(23, 59, 30)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "game", &monday.and_hms(21, 59, 59)),
+            Verdict::Blocked
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "window", any(target_os = "linux", windows)))]
+    fn parse_rules_presets() {
+        let rules = parse_rules(
+            "game=preset:teen|10:00~12:00;SA\n\
+             browser=preset:teen action=close:YouTube\n\
+             preset:teen=17:00~21:00;MO,TU,WE,TH,FR action=minimize",
+        )
+        .unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let saturday = NaiveDate::from_ymd(2020, 6, 6);
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            evaluate_rules(&rules, "game", &monday.and_hms(18, 0, 0)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "game", &saturday.and_hms(11, 0, 0)),
+            Verdict::Allowed
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "browser", &saturday.and_hms(11, 0, 0)),
+            Verdict::Blocked
+        );
+        assert_eq!(rules.get_rule("game").unwrap().action(), &Action::Minimize);
+        assert_eq!(
+            rules.get_rule("browser").unwrap().action(),
+            &Action::CloseWindows("YouTube".to_string())
+        );
+        assert!(parse_rules("game=preset:unknown").is_err());
+        assert!(parse_rules("preset:a=*;SA\npreset:b=preset:a").is_err());
+    }
+
+    #[test]
+    fn rules_diagnostics() {
+        let rules = parse_rules(
+            "game=10:00~12:00,11:00~13:00;MO|22:00~06:00;TU\n\
+             mpv=*;MO except=*;MO\n\
+             browser=10:00~12:00,12:00~14:00;MO",
+        )
+        .unwrap();
+        let diagnostics = Diagnostics::of(&rules);
+
+        assert_eq!(diagnostics.rule_count(), 3);
+        assert_eq!(
+            diagnostics.warnings(),
+            [
+                RuleWarning::OverlappingPeriods {
+                    process_name: "game".to_string(),
+                    days: vec!["MO"],
+                },
+                RuleWarning::EmptyPeriod {
+                    process_name: "game".to_string(),
+                    period: "22:00~06:00;TU".to_string(),
+                },
+                RuleWarning::NeverAllowed {
+                    process_name: "mpv".to_string(),
+                },
+            ]
+        );
+        assert!(diagnostics
+            .to_string()
+            .starts_with("3 rule(s) loaded, 3 warning(s): overlapping periods on MO in rule game"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "window", any(target_os = "linux", windows)))]
+    fn policy_snapshot_diff() {
+        let snapshot = PolicySnapshot::new(
+            &parse_config("check_interval=60").unwrap(),
+            &parse_rules(
+                "game=17:00~21:00;MO,TU|*;SA,SU\n\
+                 chat=12:00~14:00;MO\n\
+                 *=00:00~23:00;MO fullscreen",
+            )
+            .unwrap(),
+        );
+        let reloaded_snapshot = PolicySnapshot::new(
+            &parse_config("check_interval=30").unwrap(),
+            &parse_rules(
+                "game=17:00~22:00;MO,TU|*;SA,SU action=minimize\n\
+                 *=00:00~23:00;MO fullscreen\n\
+                 browser=*;SU action=close:\"Twitch - \"",
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(
+            snapshot.diff(&reloaded_snapshot),
+            vec![
+                "Changed check_interval from 60 to 30",
+                "Changed rule game=17:00~21:00;MO,TU|*;SA,SU to game=17:00~22:00;MO,TU|*;SA,SU action=minimize",
+                "Added rule browser=*;SU action=close:\"Twitch - \"",
+                "Removed rule chat=12:00~14:00;MO",
+            ]
+        );
+        assert!(snapshot.diff(&snapshot).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn policy_snapshot_redacted() {
+        let snapshot = PolicySnapshot::new(
+            &parse_config("check_interval=60").unwrap(),
+            &parse_rules("game=17:00~21:00;MO").unwrap(),
+        );
+        let reloaded_snapshot = PolicySnapshot::new(
+            &parse_config("redact_pattern=hunter\\d").unwrap(),
+            &parse_rules("cmd:*--token:abc*=08:00~09:00;MO\ncmd:*hunter2*=*;MO").unwrap(),
+        );
+
+        assert_eq!(
+            snapshot.diff(&reloaded_snapshot),
+            vec![
+                "Changed redact_pattern from  to hunter\\d",
+                "Added rule cmd:*--token:[REDACTED]",
+                "Added rule cmd:*[REDACTED]*=*;MO",
+                "Removed rule game=17:00~21:00;MO",
+            ]
+        );
+        assert!(matches!(
+            validate_config("check_interval=60\nredact_pattern=("),
+            Err((2, SwytError::ConfigParseError))
+        ));
+    }
+
+    #[test]
+    #[cfg(all(feature = "window", any(target_os = "linux", windows)))]
+    fn rule_escalations() {
+        let rules = parse_rules(
+            "game=17:00~21:00;MO action=minimize action@60=kill action@15=close:Game\n\
+             chat=17:00~21:00;MO",
+        )
+        .unwrap();
+        let game = rules.get_rule("game").unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let tuesday = NaiveDate::from_ymd(2020, 6, 2);
+
+        assert_eq!(
+            game.action_at(&monday.and_hms(21, 10, 0)),
+            &Action::Minimize
+        );
+        assert_eq!(
+            game.action_at(&monday.and_hms(21, 30, 0)),
+            &Action::CloseWindows("Game".to_string())
+        );
+        assert_eq!(game.action_at(&tuesday.and_hms(9, 0, 0)), &Action::Kill);
+        assert_eq!(
+            game.to_string(),
+            "game=17:00~21:00;MO action=minimize action@15=close:Game action@60=kill"
+        );
+        assert_eq!(
+            rules
+                .get_rule("chat")
+                .unwrap()
+                .action_at(&tuesday.and_hms(9, 0, 0)),
+            &Action::Kill
+        );
+        assert!(parse_rules("game=*;SA action@later=kill").is_err());
+    }
+
+    #[test]
+    fn rule_message() {
+        let rules = parse_rules(
+            "preset:evening=17:00~21:00;MO message=\"Time is up for {process}\"\n\
+             game=preset:evening message=\"{process} was {action}ed\"\n\
+             chat=preset:evening\n\
+             mail=*;SA",
+        )
+        .unwrap();
+        let game = rules.get_rule("game").unwrap();
+
+        assert_eq!(
+            game.message_for("game.exe", "kill"),
+            Some("game.exe was killed".to_string())
+        );
+        assert_eq!(
+            rules.get_rule("chat").unwrap().message_for("chat", "kill"),
+            Some("Time is up for chat".to_string())
+        );
+        assert_eq!(rules.get_rule("mail").unwrap().message(), None);
+        assert_eq!(
+            game.to_string(),
+            "game=17:00~21:00;MO message=\"{process} was {action}ed\""
+        );
+        assert!(parse_rules("game=*;SA message=").is_err());
+    }
+
+    #[test]
+    fn rule_allowed_times() {
+        let rules = parse_rules("game=17:00~21:00,20:00~22:00,9:00~10:00;MO|*;SU").unwrap();
+        let game = rules.iter().next().unwrap();
+
+        assert_eq!(game.process_name(), "game");
+        assert_eq!(
+            game.allowed_times(Weekday::Mon),
+            vec![
+                (NaiveTime::from_hms(9, 0, 0), NaiveTime::from_hms(10, 0, 0)),
+                (NaiveTime::from_hms(17, 0, 0), NaiveTime::from_hms(22, 0, 0)),
+            ]
+        );
+        assert!(game.allowed_times(Weekday::Tue).is_empty());
+        assert_eq!(game.allowed_times(Weekday::Sun).len(), 1);
+    }
+
+    #[test]
+    fn rules_locked() {
+        let rules = load_rules(Path::new(VALID_RULES_SWYT_PATH))
+            .unwrap()
+            .locked();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+
+        assert_eq!(
+            evaluate_rules(&rules, "process1", &monday.and_hms(10, 30, 0)),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "firefox", &monday.and_hms(10, 30, 0)),
+            Verdict::Unmatched
+        );
+        assert_eq!(rules.next_boundary(&monday.and_hms(10, 30, 0)), None);
+    }
+
+    #[test]
+    fn rules_with_lockdown() {
+        let rules = load_rules(Path::new(VALID_RULES_SWYT_PATH))
+            .unwrap()
+            .with_lockdown(&["process1".to_string(), "steam".to_string()]);
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+
+        assert_eq!(
+            evaluate_rules(&rules, "process1", &monday.and_hms(10, 30, 0)),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "steam", &monday.and_hms(10, 30, 0)),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate_rules(&rules, "process2", &monday.and_hms(12, 30, 0)),
+            Verdict::Allowed
+        );
+    }
+
+    #[test]
+    fn rules_next_boundary() {
+        let rules = load_rules(Path::new(VALID_RULES_SWYT_PATH)).unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let sunday = NaiveDate::from_ymd(2020, 6, 7);
+
+        assert_eq!(
+            rules.next_boundary(&monday.and_hms(9, 0, 0)),
+            Some(monday.and_hms(10, 0, 0))
+        );
+        assert_eq!(
+            rules.next_boundary(&monday.and_hms(10, 0, 0)),
+            Some(monday.and_hms(11, 0, 1))
+        );
+        assert_eq!(
+            rules.next_boundary(&sunday.and_hms(12, 0, 0)),
+            Some(sunday.succ().and_hms(0, 0, 0))
+        );
+        assert_eq!(Rules::new().next_boundary(&monday.and_hms(9, 0, 0)), None);
+    }
+
+    #[test]
+    fn audit_log_retention() {
+        let swyt_filepath = std::env::temp_dir().join(format!("swyt-audit-{}", std::process::id()));
+        std::fs::create_dir_all(&swyt_filepath).unwrap();
+        std::fs::copy(
+            "./test_data/audit/audit.jbb",
+            swyt_filepath.join("audit.jbb"),
+        )
+        .unwrap();
+        let entries = read_audit_log(&swyt_filepath).unwrap();
+
+        let june_2 = NaiveDate::from_ymd(2020, 6, 2).and_hms(0, 0, 0);
+        assert_eq!(prune_audit_log(&swyt_filepath, &june_2).unwrap(), 1);
+        assert_eq!(read_audit_log(&swyt_filepath).unwrap(), entries[1..]);
+        // The latest change is kept whatever its date
+        let june_3 = NaiveDate::from_ymd(2020, 6, 3).and_hms(0, 0, 0);
+        assert_eq!(prune_audit_log(&swyt_filepath, &june_3).unwrap(), 1);
+        assert_eq!(read_audit_log(&swyt_filepath).unwrap(), entries[2..]);
+
+        assert_eq!(clear_audit_log(&swyt_filepath).unwrap(), 1);
+        assert!(read_audit_log(&swyt_filepath).unwrap().is_empty());
+        assert_eq!(prune_audit_log(&swyt_filepath, &june_3).unwrap(), 0);
+        std::fs::remove_dir_all(&swyt_filepath).unwrap();
+    }
+
+    #[test]
+    fn validate_policy_line_numbers() {
+        assert!(matches!(
+            validate_config("check_interval=60\nmanaged"),
+            Err((2, SwytError::ConfigParseError))
+        ));
+        assert!(matches!(
+            validate_rules("$weekend=SA,SU\ngame=18:00~20:00;$weekend\nchat=25:00~26:00;MO"),
+            Err((3, SwytError::TimeParseError(_)))
+        ));
+        // The presets are parsed after the rules inheriting them, but the line still counts
+        assert!(matches!(
+            validate_rules("game=18:00~20:00;MO\npreset:evening=18:00"),
+            Err((2, SwytError::RuleParseError))
+        ));
+        assert!(validate_rules("game=18:00~20:00;MO").is_ok());
+        #[cfg(not(all(feature = "window", any(target_os = "linux", windows))))]
+        assert!(matches!(
+            validate_rules("game=18:00~20:00;MO\ngame=*;SA action=minimize"),
+            Err((2, SwytError::Unsupported(_)))
+        ));
+        #[cfg(not(all(feature = "window", any(target_os = "linux", windows))))]
+        assert!(matches!(
+            validate_rules("browser=*;SA action=close:YouTube"),
+            Err((1, SwytError::Unsupported(_)))
+        ));
+    }
+
+    #[test]
+    fn remove_rule_lines() {
+        let rules_string = "game=18:00~20:00;MO\nchat=12:00~14:00;TU\n*=*;SA fullscreen\n";
+        assert_eq!(
+            remove_rule(rules_string, "game").as_deref(),
+            Some("chat=12:00~14:00;TU\n*=*;SA fullscreen\n")
+        );
+        assert_eq!(
+            remove_rule(rules_string, "*").as_deref(),
+            Some("game=18:00~20:00;MO\nchat=12:00~14:00;TU\n")
+        );
+        assert_eq!(remove_rule(rules_string, "gam"), None);
+    }
 }
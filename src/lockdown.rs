@@ -0,0 +1,189 @@
+use crate::{verify_admin_pin, SwytError};
+use chrono::NaiveDateTime;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+const LOCKDOWN_FILE_NAME: &str = "lockdown.jbb";
+const LOCKDOWN_DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// How far the wall clock can go back before it is considered manipulated, clock
+/// synchronization corrections being much smaller.
+const CLOCK_BACKWARD_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+/// Why the daemon locked down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockdownReason {
+    /// The managed policy was made writable by other users.
+    Tampering,
+    /// The policy couldn't be reloaded several times in a row.
+    ReloadFailures,
+    /// The wall clock was set back.
+    ClockManipulation,
+    /// The wall clock differs from the one of the time server by more than `max_clock_skew`.
+    ClockSkew,
+}
+
+impl LockdownReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LockdownReason::Tampering => "tampering",
+            LockdownReason::ReloadFailures => "reload_failures",
+            LockdownReason::ClockManipulation => "clock_manipulation",
+            LockdownReason::ClockSkew => "clock_skew",
+        }
+    }
+
+    fn parse(reason: &str) -> Result<Self, SwytError> {
+        match reason {
+            "tampering" => Ok(LockdownReason::Tampering),
+            "reload_failures" => Ok(LockdownReason::ReloadFailures),
+            "clock_manipulation" => Ok(LockdownReason::ClockManipulation),
+            "clock_skew" => Ok(LockdownReason::ClockSkew),
+            _ => Err(SwytError::StateParseError),
+        }
+    }
+}
+
+impl Display for LockdownReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// State recorded while the daemon is locked down, so the lockdown outlives restarts until
+/// an administrator lifts it by removing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lockdown {
+    reason: LockdownReason,
+    since: NaiveDateTime,
+}
+
+impl Lockdown {
+    pub fn new(reason: LockdownReason, since: NaiveDateTime) -> Self {
+        Lockdown { reason, since }
+    }
+
+    pub fn reason(&self) -> LockdownReason {
+        self.reason
+    }
+
+    pub fn since(&self) -> &NaiveDateTime {
+        &self.since
+    }
+}
+
+pub fn write_lockdown(swyt_filepath: &Path, lockdown: &Lockdown) -> Result<(), SwytError> {
+    fs::write(
+        get_lockdown_filepath(swyt_filepath),
+        format!(
+            "reason={}\nsince={}\n",
+            lockdown.reason,
+            lockdown.since.format(LOCKDOWN_DATE_TIME_FORMAT)
+        ),
+    )?;
+    Ok(())
+}
+
+/// Reads the lockdown state, `None` meaning the daemon isn't locked down.
+pub fn read_lockdown(swyt_filepath: &Path) -> Result<Option<Lockdown>, SwytError> {
+    let lockdown_filepath = get_lockdown_filepath(swyt_filepath);
+    if !lockdown_filepath.exists() {
+        return Ok(None);
+    }
+
+    let lockdown_string = fs::read_to_string(lockdown_filepath)?;
+    let mut reason = None;
+    let mut since = None;
+    for line in lockdown_string.lines() {
+        match line.split_once('=') {
+            Some(("reason", value)) => reason = Some(LockdownReason::parse(value)?),
+            Some(("since", value)) => {
+                since = Some(
+                    NaiveDateTime::parse_from_str(value, LOCKDOWN_DATE_TIME_FORMAT)
+                        .map_err(|_| SwytError::StateParseError)?,
+                )
+            }
+            _ => (),
+        }
+    }
+
+    Ok(Some(Lockdown {
+        reason: reason.ok_or(SwytError::StateParseError)?,
+        since: since.ok_or(SwytError::StateParseError)?,
+    }))
+}
+
+/// Lifts the lockdown given the admin PIN, does nothing when the daemon isn't locked down.
+pub fn clear_lockdown(swyt_filepath: &Path, admin_pin: Option<&str>) -> Result<(), SwytError> {
+    let lockdown_filepath = get_lockdown_filepath(swyt_filepath);
+    if lockdown_filepath.exists() {
+        verify_admin_pin(swyt_filepath, admin_pin.unwrap_or_default())?;
+        fs::remove_file(lockdown_filepath)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn get_lockdown_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(LOCKDOWN_FILE_NAME)
+}
+
+/// Detects the wall clock being set back by comparing it to the monotonic clock. The clock
+/// going forward can't be told apart from the system resuming from sleep, so it isn't
+/// detected.
+pub struct ClockWatch {
+    instant: Instant,
+    system_time: SystemTime,
+}
+
+impl ClockWatch {
+    pub fn new() -> Self {
+        ClockWatch {
+            instant: Instant::now(),
+            system_time: SystemTime::now(),
+        }
+    }
+
+    /// Returns whether the wall clock was set back since the previous call.
+    pub fn has_gone_back(&mut self) -> bool {
+        let (instant, system_time) = (Instant::now(), SystemTime::now());
+        let expected_system_time = self.system_time + instant.duration_since(self.instant);
+        self.instant = instant;
+        self.system_time = system_time;
+        system_time + CLOCK_BACKWARD_TOLERANCE < expected_system_time
+    }
+}
+
+impl Default for ClockWatch {
+    fn default() -> Self {
+        ClockWatch::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn read_lockdown_valid() {
+        let lockdown = read_lockdown(Path::new("./test_data/lockdown"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(lockdown.reason(), LockdownReason::ClockManipulation);
+        assert_eq!(
+            lockdown.since(),
+            &NaiveDate::from_ymd(2020, 6, 1).and_hms(10, 30, 0)
+        );
+        assert!(read_lockdown(Path::new("./test_data/no_rule"))
+            .unwrap()
+            .is_none());
+        // Lifting it takes the admin PIN, which isn't set
+        assert!(clear_lockdown(Path::new("./test_data/lockdown"), Some("1234")).is_err());
+        assert!(read_lockdown(Path::new("./test_data/lockdown"))
+            .unwrap()
+            .is_some());
+    }
+}
@@ -1,6 +1,48 @@
-use log::{error, info};
-use std::time::Duration;
-use swyt::{find_swyt_filepath, load_config, load_rules, process_rules, SwytError};
+use chrono::{Local, Utc, Weekday};
+use cli::{RulesCommand, SELFTEST_DUMMY_COMMAND};
+use log::{debug, error, info, warn};
+#[cfg(feature = "otlp")]
+use opentelemetry::trace::TracerProvider;
+#[cfg(feature = "otlp")]
+use opentelemetry_otlp::{MetricExporter, SpanExporter};
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::{metrics::SdkMeterProvider, trace::SdkTracerProvider, Resource};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use swyt::confinement::{apparmor_profile, current_confinement, selinux_module};
+use swyt::focus::is_do_not_disturb_on;
+#[cfg(all(feature = "registry", windows))]
+use swyt::load_registry_policy;
+use swyt::{
+    append_scan_trace, approve_changes, audit_policy, capabilities, clear_audit_log,
+    clear_changes_approval, clear_lockdown, clear_queued_reports, clock_skew,
+    decide_override_request, deliver_reports, ephemeral_heartbeat_directory, evaluate_rules,
+    export_ics, find_managed_swyt_filepath, find_swyt_filepath, has_admin_pin,
+    is_directory_writable, is_low_power, is_process_running, load_config, load_rules,
+    managed_swyt_directory, open_configured_stats_store, parse_config, parse_rules,
+    policy_modified_time, process_rules, prune_audit_log, queue_reports, read_approved_policy,
+    read_audit_log, read_changes_approval, read_daemon_snapshot, read_heartbeat, read_lockdown,
+    read_override_requests, read_policy, read_scan_traces, release_restraints, remove_rule,
+    request_override, required_capabilities, restore_restraints, set_admin_pin, state_filepaths,
+    validate_config, validate_rules, verify_admin_pin, verify_changes_approval,
+    write_approved_policy, write_daemon_snapshot, write_heartbeat, write_lockdown, write_policy,
+    write_restraint_journal, Actor, ApprovedPolicy, BusyCalendar, Capabilities, Capability,
+    ClockWatch, Configuration, DaemonSnapshot, Diagnostics, EnforcementEvent, EnforcementState,
+    Heartbeat, Lockdown, LockdownReason, LowPowerMode, NtpClock, Override, PolicySnapshot,
+    ReloadErrorBehavior, RequestStatus, Rule, Rules, ScanReport, StatsStore, SwytError,
+    SystemClock, Verdict,
+};
+#[cfg(all(feature = "exec-deny", target_os = "linux"))]
+use swyt::{is_execution_denied, ExecGuard};
+#[cfg(all(feature = "exec-events", target_os = "linux"))]
+use swyt::{process_rules_for_pids, ExecMonitor};
+
+mod cli;
 
 macro_rules! fatal {
     ($($tt:tt)*) => {{
@@ -9,12 +51,870 @@ macro_rules! fatal {
     }}
 }
 
-fn main() -> Result<(), SwytError> {
+/// Slack given to the daemon to complete a scan before it is considered unhealthy.
+const HEALTH_GRACE_PERIOD_SECONDS: i64 = 10;
+/// Number of policy reloads failing in a row after which the daemon locks down.
+const LOCKDOWN_RELOAD_FAILURES: u32 = 3;
+/// How often the system clock is checked against the time server.
+const TIME_SERVER_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How often the statistics and the audit log are pruned past their retention.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How long the report command can run for before its delivery is taken to have failed.
+const REPORT_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+/// Name of the copy of swyt run by `swyt selftest`, matching no other process.
+const SELFTEST_DUMMY_NAME: &str = "swyt-selftest";
+/// How long `swyt selftest` waits for the dummy process to be killed.
+const SELFTEST_TIMEOUT: Duration = Duration::from_secs(3);
+/// How often the daemon checks whether it was asked to stop while it waits for its next scan.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Set once the daemon is asked to stop, for it to release the processes it restrains before
+/// exiting.
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+fn main() {
     env_logger::init();
+    let exit_code = match cli::Command::from_args() {
+        cli::Command::Run {
+            ephemeral,
+            trace_filepath,
+        } => run(ephemeral, trace_filepath),
+        cli::Command::Replay {
+            trace_filepath,
+            rules_filepath,
+        } => replay(&trace_filepath, rules_filepath.as_deref()),
+        cli::Command::Health => check_health(),
+        cli::Command::Init => init(),
+        cli::Command::Schedule => print_schedule(),
+        cli::Command::ExportIcs { filepath } => export_calendar(filepath.as_deref()),
+        cli::Command::Unlock => unlock(),
+        cli::Command::Status => print_status(),
+        cli::Command::History { count } => print_history(count),
+        cli::Command::Stats { days } => print_stats(days),
+        cli::Command::PurgeData { days } => purge_data(days),
+        cli::Command::Request {
+            process_name,
+            minutes,
+            reason,
+        } => request(&process_name, minutes, &reason),
+        cli::Command::Requests => print_requests(),
+        cli::Command::Decide { id, approved } => decide(id, approved),
+        cli::Command::SetPin => set_pin(),
+        cli::Command::ApproveChanges => approve_loosening(),
+        cli::Command::Selftest => selftest(),
+        cli::Command::SelftestDummy => idle(),
+        cli::Command::Doctor => doctor(),
+        cli::Command::Lint { rules_filepath } => lint(rules_filepath.as_deref()),
+        cli::Command::Validate => validate(),
+        cli::Command::Check { process_name } => check(&process_name),
+        cli::Command::Rules(command) => edit_rules(command),
+        cli::Command::ConfinementProfile { kind } => print_confinement_profile(&kind),
+        cli::Command::Uninstall { purge } => uninstall(purge),
+    };
+    std::process::exit(exit_code)
+}
+
+/// Runs the daemon, enforcing the rules on every check, with the state in memory only when
+/// `ephemeral` and the verdicts of the scans recorded in the trace file if any.
+fn run(ephemeral: bool, trace_filepath: Option<PathBuf>) -> i32 {
+    #[cfg(feature = "otlp")]
+    let _telemetry_providers = init_otlp()
+        .map_err(|err| info!("Couldn't export telemetry: {}", err))
+        .ok();
 
     info!("Swyt is starting...");
-    let swyt_filepath = find_swyt_filepath().unwrap_or_else(|e| fatal!(e));
+    // The heartbeat of an ephemeral daemon goes to the runtime directory, in memory
+    let ephemeral_filepath = match ephemeral {
+        true => {
+            info!(
+                "Ephemeral mode, the daemon state is kept in memory and nothing is written to disk"
+            );
+            let heartbeat_filepath = ephemeral_heartbeat_directory();
+            match &heartbeat_filepath {
+                Some(heartbeat_filepath) => {
+                    if let Err(err) = std::fs::create_dir_all(heartbeat_filepath) {
+                        debug!("Couldn't create the heartbeat directory: {}", err);
+                    }
+                }
+                None => info!("No runtime directory, swyt health can't check this daemon"),
+            }
+            heartbeat_filepath
+        }
+        false => None,
+    };
+    let (mut swyt_filepath, configuration, rules) = match ephemeral {
+        true => load_policy_read_only(),
+        false => load_policy(),
+    }
+    .unwrap_or_else(|e| fatal!(e));
+    log_diagnostics(&rules);
+    let mut last_modified_time = policy_modified_time(&swyt_filepath);
+    let snapshot = PolicySnapshot::new(&configuration, &rules);
+    let mut approved_policy = read_approved_policy(&swyt_filepath).unwrap_or_else(|err| {
+        error!("Couldn't read the approved policy: {}", err);
+        None
+    });
+    let (configuration, rules) = review_policy(
+        &swyt_filepath,
+        &mut approved_policy,
+        configuration,
+        rules,
+        ephemeral,
+    );
+    let policy = Policy::new(configuration, rules, snapshot);
+    if !ephemeral {
+        audit_changes(&swyt_filepath, None, &policy.snapshot);
+    }
+    let mut stats_store = match ephemeral {
+        true => None,
+        false => open_stats(&swyt_filepath, &policy.configuration),
+    };
+    let mut stats_filepath = swyt_filepath.clone();
+    let mut anonymous_stats = policy.configuration.anonymous_stats();
+    let enforcement_state = Arc::new(EnforcementState::new());
+    match stats_store.as_deref().map(StatsStore::read_budget_usages) {
+        Some(Ok(usages)) => enforcement_state.restore_budget_usages(usages),
+        Some(Err(err)) => warn!(
+            "Couldn't read the budget usage, counting from zero: {}",
+            err
+        ),
+        None => (),
+    }
+    // What a daemon stopped before releasing it, as when killed, left restrained is
+    // released, the journal of an ephemeral daemon being in the runtime directory
+    let journal_filepath = match ephemeral {
+        true => ephemeral_filepath.as_deref(),
+        false => Some(swyt_filepath.as_path()),
+    };
+    if let Some(journal_filepath) = journal_filepath {
+        match restore_restraints(&enforcement_state, journal_filepath) {
+            Ok(()) => match release_restraints(&enforcement_state) {
+                0 => (),
+                released => info!("Released {} process(es) restrained before", released),
+            },
+            Err(err) => warn!("Couldn't read the journal of the restraints: {}", err),
+        }
+        journal_restraints(journal_filepath, &enforcement_state);
+    }
+    let policy = Arc::new(RwLock::new(policy));
+    let mut locked_down = match read_lockdown(&swyt_filepath) {
+        Ok(Some(lockdown)) => {
+            error!(
+                "Locked down since {} because of {}, run swyt unlock to lift it",
+                lockdown.since(),
+                lockdown.reason()
+            );
+            true
+        }
+        Ok(None) => false,
+        Err(err) => {
+            error!("Couldn't read the lockdown state, locking down: {}", err);
+            true
+        }
+    };
+    if locked_down {
+        apply_lockdown(&policy);
+    }
+    let mut clock_watch = ClockWatch::new();
+    let mut last_time_server_check: Option<Instant> = None;
+    let mut last_prune: Option<Instant> = None;
+    let mut reload_failures = 0;
+    let mut busy_calendar = None;
+    let mut busy = false;
+    let mut low_power = false;
+    let mut do_not_disturb = false;
+    let mut seen_requests = HashSet::new();
+
+    #[cfg(all(feature = "exec-deny", target_os = "linux"))]
+    match ExecGuard::new() {
+        Ok(exec_guard) => {
+            let policy = Arc::clone(&policy);
+            let enforcement_state = Arc::clone(&enforcement_state);
+            std::thread::spawn(move || {
+                let result = exec_guard.run(|executable_path, pid| {
+                    let policy = policy.read().expect("Couldn't lock the policy");
+                    is_execution_denied(
+                        &policy.rules,
+                        &policy.configuration,
+                        &enforcement_state,
+                        executable_path,
+                        pid,
+                    )
+                });
+                if let Err(err) = result {
+                    info!("Stopped denying blocked executions: {}", err);
+                }
+            });
+        }
+        Err(err) => info!("Couldn't deny blocked executions: {}", err),
+    }
+
+    handle_stop_requests();
+    #[cfg(all(feature = "exec-events", target_os = "linux"))]
+    let mut exec_monitor = match ExecMonitor::new() {
+        Ok(exec_monitor) => Some(exec_monitor),
+        Err(err) => {
+            info!("Couldn't listen to exec events, polling only: {}", err);
+            None
+        }
+    };
+
+    while !STOP_REQUESTED.load(Ordering::SeqCst) {
+        // An ephemeral lockdown isn't recorded and lasts until the daemon stops
+        let lockdown_lifted =
+            locked_down && !ephemeral && matches!(read_lockdown(&swyt_filepath), Ok(None));
+        if lockdown_lifted {
+            info!("The lockdown was lifted");
+            locked_down = false;
+            reload_failures = 0;
+        }
+
+        let mut lockdown_reason = None;
+        let modified_time = policy_modified_time(&swyt_filepath);
+        let changes_approved =
+            !ephemeral && matches!(read_changes_approval(&swyt_filepath), Ok(Some(_)));
+        if lockdown_lifted || changes_approved || modified_time != last_modified_time {
+            last_modified_time = modified_time;
+            match reload_policy(&policy, &mut approved_policy, &mut swyt_filepath, ephemeral) {
+                Ok(()) => reload_failures = 0,
+                Err(SwytError::ManagedPolicyPermissionError) => {
+                    lockdown_reason = Some(LockdownReason::Tampering)
+                }
+                Err(_) => {
+                    reload_failures += 1;
+                    if reload_failures >= LOCKDOWN_RELOAD_FAILURES {
+                        lockdown_reason = Some(LockdownReason::ReloadFailures);
+                    }
+                }
+            }
+            if locked_down {
+                apply_lockdown(&policy);
+            }
+        }
+        if clock_watch.has_gone_back() {
+            lockdown_reason = Some(LockdownReason::ClockManipulation);
+        }
+        let (time_server, max_clock_skew) = {
+            let policy = policy.read().expect("Couldn't lock the policy");
+            let configuration = &policy.configuration;
+            (
+                configuration.time_server().map(str::to_string),
+                configuration.max_clock_skew(),
+            )
+        };
+        let time_server_check_due = last_time_server_check
+            .is_none_or(|last_check| last_check.elapsed() >= TIME_SERVER_CHECK_INTERVAL);
+        if let (Some(time_server), true) = (time_server, time_server_check_due) {
+            last_time_server_check = Some(Instant::now());
+            match clock_skew(&SystemClock, &NtpClock::new(time_server.clone())) {
+                Ok(skew) if skew.num_seconds().unsigned_abs() > u64::from(max_clock_skew) => {
+                    error!(
+                        "The system clock is {} seconds off the time of {}",
+                        skew.num_seconds(),
+                        time_server
+                    );
+                    lockdown_reason = Some(LockdownReason::ClockSkew);
+                }
+                Ok(skew) => debug!(
+                    "The system clock is {} ms off the time of {}",
+                    skew.num_milliseconds(),
+                    time_server
+                ),
+                // The time server being unreachable, as when offline, isn't held against the clock
+                Err(err) => info!("Couldn't check the clock against {}: {}", time_server, err),
+            }
+        }
+        if let (false, Some(lockdown_reason)) = (locked_down, lockdown_reason) {
+            lock_down(&policy, &swyt_filepath, lockdown_reason, ephemeral);
+            locked_down = true;
+        }
+
+        let policy = policy.read().expect("Couldn't lock the policy");
+        let (configuration, rules) = (&policy.configuration, &policy.rules);
+        let was_low_power = std::mem::replace(&mut low_power, is_low_power(configuration));
+        match (was_low_power, low_power) {
+            (false, true) => info!(
+                "Low-power mode on, checking every {} seconds",
+                configuration.low_power_check_interval()
+            ),
+            (true, false) => info!("Low-power mode off"),
+            _ => (),
+        }
+        let was_busy = std::mem::replace(&mut busy, is_busy(&mut busy_calendar, configuration));
+        match (was_busy, busy) {
+            (false, true) => info!(
+                "Busy according to the calendar, blocking {}",
+                configuration.busy_processes().join(", ")
+            ),
+            (true, false) => info!("No longer busy according to the calendar"),
+            _ => (),
+        }
+        let was_do_not_disturb = std::mem::replace(
+            &mut do_not_disturb,
+            !configuration.do_not_disturb_processes().is_empty() && is_do_not_disturb_on(),
+        );
+        match (was_do_not_disturb, do_not_disturb) {
+            (false, true) => info!(
+                "Do Not Disturb is on, blocking {}",
+                configuration.do_not_disturb_processes().join(", ")
+            ),
+            (true, false) => info!("Do Not Disturb is off"),
+            _ => (),
+        }
+
+        let mut focus_processes = vec![];
+        if busy {
+            focus_processes.extend_from_slice(configuration.busy_processes());
+        }
+        if do_not_disturb {
+            focus_processes.extend_from_slice(configuration.do_not_disturb_processes());
+        }
+        let focus_rules =
+            (!focus_processes.is_empty()).then(|| rules.with_lockdown(&focus_processes));
+        let rules = focus_rules.as_ref().unwrap_or(rules);
+        let grants =
+            match read_override_requests(&swyt_filepath, configuration, Local::now().naive_local())
+            {
+                Ok(requests) => requests,
+                Err(err) => {
+                    debug!("Couldn't read the override requests: {}", err);
+                    vec![]
+                }
+            }
+            .into_iter()
+            .filter_map(|request| match request.status() {
+                RequestStatus::Pending => {
+                    if seen_requests.insert(request.id()) {
+                        warn!(
+                            "Override requested: {}, run swyt approve {} or swyt deny {}",
+                            request,
+                            request.id(),
+                            request.id()
+                        );
+                    }
+                    None
+                }
+                RequestStatus::Approved { until } if !locked_down => {
+                    Some((request.process_name().to_string(), until))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let granted_processes: Vec<String> = grants.iter().map(|(name, _)| name.clone()).collect();
+        let granted_rules =
+            (!granted_processes.is_empty()).then(|| rules.with_grants(&granted_processes));
+        let rules = granted_rules.as_ref().unwrap_or(rules);
+        match process_rules(rules, configuration, &enforcement_state) {
+            Ok(report) => {
+                log_scan_report(&report);
+                if let Some(trace_filepath) = &trace_filepath {
+                    if let Err(err) = append_scan_trace(trace_filepath, &report) {
+                        error!("Couldn't record the scan: {}", err);
+                    }
+                }
+            }
+            Err(err) => error!("Couldn't scan the processes: {}", err),
+        }
+
+        // The journal and the heartbeat of an ephemeral daemon go to the runtime directory
+        let state_filepath = match ephemeral {
+            true => ephemeral_filepath.as_deref(),
+            false => Some(swyt_filepath.as_path()),
+        };
+        if let Some(state_filepath) = state_filepath {
+            journal_restraints(state_filepath, &enforcement_state);
+        }
+        let heartbeat = Heartbeat::new(std::process::id(), Local::now().naive_local());
+        if let Some(state_filepath) = state_filepath {
+            if let Err(err) = write_heartbeat(state_filepath, &heartbeat) {
+                debug!("Couldn't write heartbeat: {}", err);
+            }
+        }
+        // The statistics follow the policy when it moves to the managed directory, and their
+        // store the configuration
+        let stats_moved =
+            stats_filepath != swyt_filepath || anonymous_stats != configuration.anonymous_stats();
+        if !ephemeral && stats_moved {
+            stats_store = open_stats(&swyt_filepath, configuration);
+            stats_filepath = swyt_filepath.clone();
+            anonymous_stats = configuration.anonymous_stats();
+        }
+        let usages = enforcement_state.budget_usages();
+        let events = enforcement_state.take_unreported_events();
+        if let Some(stats_store) = stats_store.as_mut() {
+            if !usages.is_empty() {
+                if let Err(err) = stats_store.write_budget_usages(&usages) {
+                    warn!("Couldn't write the budget usage: {}", err);
+                }
+            }
+            if let Err(err) = stats_store.record_events(&events) {
+                warn!("Couldn't record the actions taken: {}", err);
+            }
+        }
+        let prune_due = last_prune.is_none_or(|last_prune| last_prune.elapsed() >= PRUNE_INTERVAL);
+        if !ephemeral && prune_due {
+            last_prune = Some(Instant::now());
+            prune_data(&swyt_filepath, stats_store.as_mut(), configuration);
+        }
+        match configuration.report_command() {
+            Some(report_command) if ephemeral => report_events_now(report_command, &events),
+            Some(report_command) => report_events(&swyt_filepath, report_command, &events),
+            None => (),
+        }
+        notify_messages(configuration, &enforcement_state);
+        let mut overrides = vec![];
+        if let (true, Ok(Some(lockdown))) = (locked_down, read_lockdown(&swyt_filepath)) {
+            overrides.push(Override::Lockdown(lockdown.reason()));
+        }
+        if busy {
+            overrides.push(Override::Busy(configuration.busy_processes().to_vec()));
+        }
+        if do_not_disturb {
+            overrides.push(Override::DoNotDisturb(
+                configuration.do_not_disturb_processes().to_vec(),
+            ));
+        }
+        for (process_name, until) in grants {
+            overrides.push(Override::Granted(process_name, until));
+        }
+        let snapshot = DaemonSnapshot::new(
+            &policy.rules,
+            &enforcement_state,
+            overrides,
+            *heartbeat.last_scan(),
+        );
+        if !ephemeral {
+            if let Err(err) = write_daemon_snapshot(&swyt_filepath, &snapshot) {
+                debug!("Couldn't write the daemon snapshot: {}", err);
+            }
+        }
+
+        let check_interval = Duration::from_secs(match low_power {
+            true => configuration.low_power_check_interval(),
+            false => configuration.check_interval(),
+        } as u64);
+        let now = Local::now().naive_local();
+        let sleep_duration = match rules.next_boundary(&now) {
+            Some(next_boundary) => (next_boundary - now)
+                .to_std()
+                .map_or(check_interval, |until_boundary| {
+                    until_boundary.min(check_interval)
+                }),
+            None => check_interval,
+        };
+        // The processes asked to terminate are killed once their grace period elapses
+        let sleep_duration = enforcement_state
+            .next_termination_deadline()
+            .map_or(sleep_duration, |deadline| {
+                sleep_duration.min(deadline.saturating_duration_since(Instant::now()))
+            });
+
+        #[cfg(all(feature = "exec-events", target_os = "linux"))]
+        if let Some(monitor) = exec_monitor.as_mut() {
+            let result = watch_exec_events(
+                monitor,
+                rules,
+                configuration,
+                &enforcement_state,
+                state_filepath,
+                sleep_duration,
+            );
+            if let Err(err) = result {
+                info!("Stopped listening to exec events, polling only: {}", err);
+                exec_monitor = None;
+            }
+            continue;
+        }
+
+        sleep_unless_stopped(sleep_duration);
+    }
+
+    // Stopping cleanly leaves no process suspended nor throttled, nor anything to undo
+    let released = release_restraints(&enforcement_state);
+    let state_filepath = match ephemeral {
+        true => ephemeral_filepath.as_deref(),
+        false => Some(swyt_filepath.as_path()),
+    };
+    if let Some(state_filepath) = state_filepath {
+        journal_restraints(state_filepath, &enforcement_state);
+    }
+    info!("Swyt stopped, {} process(es) released", released);
+    0
+}
+
+/// Sleeps for the duration, waking up early when the daemon is asked to stop.
+fn sleep_unless_stopped(duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        if STOP_REQUESTED.load(Ordering::SeqCst) {
+            return;
+        }
+        std::thread::sleep(remaining.min(STOP_CHECK_INTERVAL));
+    }
+}
+
+/// Makes SIGTERM and SIGINT ask the daemon to stop after its current scan, instead of
+/// killing it.
+#[cfg(all(feature = "native", unix))]
+fn handle_stop_requests() {
+    extern "C" fn request_stop(_signal: libc::c_int) {
+        STOP_REQUESTED.store(true, Ordering::SeqCst);
+    }
+
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        let handler = request_stop as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        if unsafe { libc::signal(signal, handler) } == libc::SIG_ERR {
+            warn!(
+                "Couldn't handle signal {}: {}",
+                signal,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+/// Makes Ctrl+C and the console closing ask the daemon to stop after its current scan,
+/// instead of killing it.
+#[cfg(all(feature = "native", windows))]
+fn handle_stop_requests() {
+    use winapi::shared::minwindef::{BOOL, DWORD, TRUE};
+    use winapi::um::consoleapi::SetConsoleCtrlHandler;
+
+    unsafe extern "system" fn request_stop(_control_type: DWORD) -> BOOL {
+        STOP_REQUESTED.store(true, Ordering::SeqCst);
+        TRUE
+    }
+
+    if unsafe { SetConsoleCtrlHandler(Some(request_stop), TRUE) } == 0 {
+        warn!(
+            "Couldn't handle the console controls: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+/// Without the native backend, the signals kill the daemon, leaving the processes it
+/// restrains to be released when it starts again.
+#[cfg(not(all(feature = "native", any(unix, windows))))]
+fn handle_stop_requests() {}
+
+#[cfg(all(feature = "exec-events", target_os = "linux"))]
+fn watch_exec_events(
+    exec_monitor: &mut ExecMonitor,
+    rules: &Rules,
+    configuration: &Configuration,
+    enforcement_state: &EnforcementState,
+    journal_filepath: Option<&Path>,
+    duration: Duration,
+) -> Result<(), SwytError> {
+    let deadline = Instant::now() + duration;
+    while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+        if STOP_REQUESTED.load(Ordering::SeqCst) {
+            break;
+        }
+        let pids = exec_monitor.wait(timeout.min(STOP_CHECK_INTERVAL))?;
+        if !pids.is_empty() {
+            log_scan_report(&process_rules_for_pids(
+                rules,
+                configuration,
+                enforcement_state,
+                &pids,
+            )?);
+            notify_messages(configuration, enforcement_state);
+            if let Some(journal_filepath) = journal_filepath {
+                journal_restraints(journal_filepath, enforcement_state);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes what the daemon suspended or throttled to the journal, to be undone when it
+/// starts again if it stops before releasing them.
+fn journal_restraints(journal_filepath: &Path, enforcement_state: &EnforcementState) {
+    if let Err(err) = write_restraint_journal(journal_filepath, enforcement_state) {
+        warn!("Couldn't journal the restraints: {}", err);
+    }
+}
+
+fn log_scan_report(report: &ScanReport) {
+    debug!("{}", report);
+    for error in report.errors() {
+        error!("{}", error);
+    }
+}
+
+/// Policy the daemon enforces, shared with the thread denying blocked executions.
+struct Policy {
+    configuration: Configuration,
+    rules: Rules,
+    /// State of the policy as loaded, before the changes waiting for approval are held back
+    /// and before any lockdown changes its rules.
+    snapshot: PolicySnapshot,
+}
+
+impl Policy {
+    fn new(configuration: Configuration, rules: Rules, snapshot: PolicySnapshot) -> Self {
+        Policy {
+            configuration,
+            rules,
+            snapshot,
+        }
+    }
+}
+
+/// Replaces the policy with the one currently deployed. When it can't be loaded, the
+/// `on_reload_error` setting of the last valid configuration tells what to do and the
+/// error is returned. An `ephemeral` daemon doesn't record the changes in the audit log.
+fn reload_policy(
+    policy: &RwLock<Policy>,
+    approved_policy: &mut Option<ApprovedPolicy>,
+    swyt_filepath: &mut PathBuf,
+    ephemeral: bool,
+) -> Result<(), SwytError> {
+    let mut policy = policy.write().expect("Couldn't lock the policy");
+    let reloaded = match ephemeral {
+        true => load_policy_read_only(),
+        false => load_policy(),
+    };
+    let err = match reloaded {
+        Ok((reloaded_swyt_filepath, configuration, rules)) => {
+            let snapshot = PolicySnapshot::new(&configuration, &rules);
+            let changes = policy.snapshot.diff(&snapshot);
+            info!("Reloaded the policy, {} change(s)", changes.len());
+            for change in changes {
+                info!("{}", change);
+            }
+            log_diagnostics(&rules);
+            if !ephemeral {
+                audit_changes(&reloaded_swyt_filepath, Some(&policy.snapshot), &snapshot);
+            }
+            let (configuration, rules) = review_policy(
+                &reloaded_swyt_filepath,
+                approved_policy,
+                configuration,
+                rules,
+                ephemeral,
+            );
+            *swyt_filepath = reloaded_swyt_filepath;
+            *policy = Policy::new(configuration, rules, snapshot);
+            return Ok(());
+        }
+        Err(err) => err,
+    };
+
+    match policy.configuration.on_reload_error() {
+        ReloadErrorBehavior::Keep => {
+            error!(
+                "Couldn't reload the policy, keeping the last valid one: {}",
+                err
+            )
+        }
+        ReloadErrorBehavior::Halt => {
+            fatal!(format!("Couldn't reload the policy, halting: {}", err))
+        }
+        ReloadErrorBehavior::FailsafeLock => {
+            error!(
+                "Couldn't reload the policy, blocking the processes of the last valid one: {}",
+                err
+            );
+            policy.rules = policy.rules.locked();
+        }
+    }
+    Err(err)
+}
+
+/// Returns the policy to enforce from the one loaded, with the changes loosening the approved
+/// policy held back until approved with the admin PIN. The approval of the changes is used
+/// up, the policy approved then being the one returned. An `ephemeral` daemon only reads the
+/// approved policy.
+///
+/// Without an approved policy, the policy loaded is approved, unless an admin PIN is set: the
+/// approved policy was then removed or damaged, and every change is held back, the processes
+/// of the rules being blocked with the default settings until the policy is approved.
+fn review_policy(
+    swyt_filepath: &Path,
+    approved_policy: &mut Option<ApprovedPolicy>,
+    configuration: Configuration,
+    rules: Rules,
+    ephemeral: bool,
+) -> (Configuration, Rules) {
+    let config_string = match read_policy(swyt_filepath) {
+        Ok((config_string, _)) => config_string,
+        Err(err) => {
+            error!("Couldn't read the configuration to approve it: {}", err);
+            return (configuration, rules);
+        }
+    };
+    let approval = match ephemeral {
+        true => None,
+        false => read_changes_approval(swyt_filepath).unwrap_or_else(|err| {
+            error!("Couldn't read the approval of the changes: {}", err);
+            None
+        }),
+    };
+    let approved = approval.as_deref().is_some_and(|approval| {
+        verify_changes_approval(swyt_filepath, approval, &configuration, &rules)
+    });
+    if approved_policy.is_none() && !approved && has_admin_pin(swyt_filepath) {
+        error!(
+            "The approved policy is missing, blocking the processes of the rules until the policy is approved with swyt approve-changes"
+        );
+        if approval.is_some() {
+            warn!("The approval doesn't match the policy, approve it again");
+            if let Err(err) = clear_changes_approval(swyt_filepath) {
+                error!("Couldn't clear the approval of the changes: {}", err);
+            }
+        }
+        return (Configuration::default(), rules.locked());
+    }
+    let approved_policy = approved_policy.get_or_insert_with(|| {
+        ApprovedPolicy::new(config_string.clone(), configuration.clone(), rules.clone())
+    });
+    let (configuration, rules, held_changes) =
+        approved_policy.review(&config_string, configuration, rules, approved);
+    if approval.is_some() {
+        if !held_changes.is_empty() {
+            warn!("The policy changed since its changes were approved, approve them again");
+        }
+        if let Err(err) = clear_changes_approval(swyt_filepath) {
+            error!("Couldn't clear the approval of the changes: {}", err);
+        }
+    }
+    for held_change in held_changes {
+        warn!(
+            "Held back until approved with swyt approve-changes: {}",
+            held_change
+        );
+    }
+    if !ephemeral {
+        if let Err(err) = write_approved_policy(swyt_filepath, approved_policy) {
+            error!("Couldn't record the approved policy: {}", err);
+        }
+    }
+    (configuration, rules)
+}
+
+/// Records the changes of the policy in the audit log, unless swyt already did as it changed
+/// them. The files were edited directly, so the changes are attributed to their owner.
+fn audit_changes(
+    swyt_filepath: &Path,
+    previous: Option<&PolicySnapshot>,
+    current: &PolicySnapshot,
+) {
+    let actor = Actor::policy_owner(swyt_filepath);
+    let now = Local::now().naive_local();
+    if let Err(err) = audit_policy(swyt_filepath, &actor, previous, current, now) {
+        error!("Couldn't record the changes of the policy: {}", err);
+    }
+}
+
+/// Returns whether the calendar of the configuration has a busy event happening, the
+/// calendar being read again when it changes or when the configuration points to another
+/// one.
+fn is_busy(busy_calendar: &mut Option<BusyCalendar>, configuration: &Configuration) -> bool {
+    let path = match configuration.busy_calendar() {
+        Some(path) => path,
+        None => {
+            *busy_calendar = None;
+            return false;
+        }
+    };
+    if busy_calendar.as_ref().map(BusyCalendar::path) != Some(path) {
+        *busy_calendar = Some(BusyCalendar::new(path.to_path_buf()));
+    }
+
+    let now = Local::now();
+    busy_calendar
+        .as_mut()
+        .map_or(Ok(false), |busy_calendar| {
+            busy_calendar.is_busy(&now.naive_local(), &now.naive_utc())
+        })
+        .unwrap_or_else(|err| {
+            error!("Couldn't read the busy calendar: {}", err);
+            false
+        })
+}
+
+/// Locks the daemon down, blocking the high-risk processes of the configuration at any time
+/// until an administrator runs `swyt unlock`, or until it stops when `ephemeral`.
+fn lock_down(
+    policy: &RwLock<Policy>,
+    swyt_filepath: &Path,
+    reason: LockdownReason,
+    ephemeral: bool,
+) {
+    error!("Locking down because of {}", reason);
+    let lockdown = Lockdown::new(reason, Local::now().naive_local());
+    if !ephemeral {
+        if let Err(err) = write_lockdown(swyt_filepath, &lockdown) {
+            error!(
+                "Couldn't record the lockdown, it won't outlive a restart: {}",
+                err
+            );
+        }
+    }
+    apply_lockdown(policy);
+}
+
+fn apply_lockdown(policy: &RwLock<Policy>) {
+    let mut policy = policy.write().expect("Couldn't lock the policy");
+    let lockdown_rules = policy
+        .rules
+        .with_lockdown(policy.configuration.lockdown_processes());
+    policy.rules = lockdown_rules;
+}
+
+/// Loads the policy from the registry, the managed directory or the user directory, in
+/// that order of precedence. Also returns the directory where the daemon state is kept.
+fn load_policy() -> Result<(PathBuf, Configuration, Rules), SwytError> {
+    #[cfg(all(feature = "registry", windows))]
+    if let Some((configuration, rules)) = load_registry_policy()? {
+        info!("Loaded the policy from the registry, local edits are disabled");
+        let swyt_filepath = find_swyt_filepath()?;
+        create_swyt_directory(&swyt_filepath)?;
+        return Ok((swyt_filepath, configuration, rules));
+    }
+
+    let swyt_filepath = find_policy_filepath()?;
+    create_swyt_directory(&swyt_filepath)?;
+    let configuration = load_config(&swyt_filepath)?;
+    let rules = load_rules(&swyt_filepath)?;
+    Ok((swyt_filepath, configuration, rules))
+}
 
+/// Loads the policy like `load_policy`, without creating the directory or the files
+/// missing, for the filesystem may be read-only.
+fn load_policy_read_only() -> Result<(PathBuf, Configuration, Rules), SwytError> {
+    #[cfg(all(feature = "registry", windows))]
+    if let Some((configuration, rules)) = load_registry_policy()? {
+        info!("Loaded the policy from the registry, local edits are disabled");
+        return Ok((find_swyt_filepath()?, configuration, rules));
+    }
+
+    let swyt_filepath = find_policy_filepath()?;
+    let (config_string, rules_string) = read_policy(&swyt_filepath)?;
+    let configuration = parse_config(&config_string)?;
+    let rules = parse_rules(&rules_string)?;
+    Ok((swyt_filepath, configuration, rules))
+}
+
+/// Returns the directory the policy is loaded from, the managed one if any.
+fn find_policy_filepath() -> Result<PathBuf, SwytError> {
+    match find_managed_swyt_filepath()? {
+        Some(managed_swyt_filepath) => {
+            info!(
+                "Managed installation, the policy is only loaded from {} and local edits are disabled",
+                managed_swyt_filepath.display()
+            );
+            Ok(managed_swyt_filepath)
+        }
+        None => find_swyt_filepath(),
+    }
+}
+
+fn create_swyt_directory(swyt_filepath: &Path) -> Result<(), SwytError> {
     if !swyt_filepath.exists() {
         info!(
             "Swyt configuration directory doesn't exist, creating: {}",
@@ -22,19 +922,1110 @@ fn main() -> Result<(), SwytError> {
                 .to_str()
                 .expect("Couldn't convert swyt filepath to str")
         );
+        std::fs::create_dir_all(swyt_filepath)?;
+    }
+
+    Ok(())
+}
+
+/// Creates the user configuration and its first rules interactively, checking each rule as it
+/// is typed. Returns the exit code of the command.
+fn init() -> i32 {
+    match create_policy() {
+        Ok(()) => 0,
+        Err(err) => {
+            error!("Couldn't set up swyt: {}", err);
+            1
+        }
+    }
+}
+
+fn create_policy() -> Result<(), SwytError> {
+    let swyt_filepath = find_swyt_filepath()?;
+    if policy_modified_time(&swyt_filepath).is_some()
+        && !prompt("A configuration already exists, replace it? [y/N] ")?.eq_ignore_ascii_case("y")
+    {
+        return Ok(());
+    }
+
+    let check_interval = loop {
+        let answer = prompt(&format!(
+            "Seconds between two process checks [{}]: ",
+            Configuration::default().check_interval()
+        ))?;
+        if answer.is_empty() {
+            break Configuration::default().check_interval();
+        }
+        match answer.parse::<u32>() {
+            Ok(check_interval) if check_interval > 0 => break check_interval,
+            _ => println!("Enter a positive number of seconds"),
+        }
+    };
+
+    println!("Enter the rules, e.g. my_chat_app=12:00~14:00;MO,TU,WE,TH,FR|*;SA,SU");
+    let mut rules_string = String::new();
+    loop {
+        let rule = prompt("Rule (leave empty to finish): ")?;
+        if rule.is_empty() {
+            break;
+        }
+        match parse_rules(&rule) {
+            Ok(_) => {
+                rules_string.push_str(&rule);
+                rules_string.push('\n');
+            }
+            Err(err) => println!("{}, the format is described in the README", err),
+        }
+    }
+
+    create_swyt_directory(&swyt_filepath)?;
+    write_policy(
+        &swyt_filepath,
+        &format!("check_interval={}\n", check_interval),
+        &rules_string,
+    )?;
+    println!(
+        "Wrote the configuration and the rules in {}, start the daemon with swyt",
+        swyt_filepath.display()
+    );
+    Ok(())
+}
+
+/// Asks for the admin PIN and checks it when one is set, the commands lifting the limits
+/// being reserved to whoever knows it.
+fn check_admin_pin(swyt_filepath: &Path) -> Result<(), SwytError> {
+    match has_admin_pin(swyt_filepath) {
+        true => verify_admin_pin(swyt_filepath, &prompt("Admin PIN: ")?),
+        false => Ok(()),
+    }
+}
+
+/// Asks a question on the terminal and returns the trimmed answer, empty at the end of input.
+fn prompt(question: &str) -> Result<String, SwytError> {
+    print!("{}", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+/// Idles as the process `swyt selftest` blocks, until it is killed. Returns the exit code of
+/// the command.
+fn idle() -> i32 {
+    std::thread::sleep(SELFTEST_TIMEOUT * 10);
+    0
+}
+
+/// Checks that a blocked process is detected and killed on this system, then reports the
+/// capabilities swyt lacks. Returns the exit code of the command.
+fn selftest() -> i32 {
+    let result = check_kill();
+    match &result {
+        Ok(()) => println!("ok      detect and kill a blocked process"),
+        Err(err) => println!("failed  detect and kill a blocked process: {}", err),
+    }
+    for capability in capabilities() {
+        println!("{}", capability);
+    }
+
+    if result.is_ok() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Checks the privileges and system support the settings and rules of the policy rely on,
+/// explaining what is missing. Returns the exit code of the command.
+fn doctor() -> i32 {
+    let (_, configuration, rules) = match load_policy() {
+        Ok(policy) => policy,
+        Err(err) => {
+            eprintln!("Couldn't load the policy: {}", err);
+            return 1;
+        }
+    };
+
+    println!("Capabilities of this build: {}", Capabilities::current());
+    if let Some(time_server) = configuration.time_server() {
+        match clock_skew(&SystemClock, &NtpClock::new(time_server.to_string())) {
+            Ok(skew) => println!(
+                "The system clock is {} ms off the time of {}",
+                skew.num_milliseconds(),
+                time_server
+            ),
+            Err(err) => println!("Couldn't check the clock against {}: {}", time_server, err),
+        }
+    }
+    let confinement = current_confinement();
+    if let Some(confinement) = &confinement {
+        println!("Confined by the {}", confinement);
+    }
+    let capabilities = required_capabilities(&configuration, &rules);
+    if capabilities.is_empty() {
+        println!(
+            "The policy needs no other privileges than killing the processes of the current user"
+        );
+    }
+    for capability in &capabilities {
+        println!("{}", capability);
+    }
+    if confinement.is_some() && !capabilities.iter().all(Capability::is_available) {
+        println!(
+            "The confinement may deny what is missing, see swyt confinement-profile for the access the policy needs"
+        );
+    }
+
+    if capabilities.iter().all(Capability::is_available) {
+        0
+    } else {
+        1
+    }
+}
+
+/// Blocks a copy of swyt idling under a name no other process has, and waits for the rules
+/// to kill it.
+fn check_kill() -> Result<(), String> {
+    let dummy_filepath = std::env::temp_dir().join(format!(
+        "{}{}",
+        SELFTEST_DUMMY_NAME,
+        std::env::consts::EXE_SUFFIX
+    ));
+    let current_exe = std::env::current_exe().map_err(|err| err.to_string())?;
+    std::fs::copy(current_exe, &dummy_filepath).map_err(|err| {
+        format!(
+            "couldn't copy swyt to {}: {}",
+            dummy_filepath.display(),
+            err
+        )
+    })?;
+    let result = Command::new(&dummy_filepath)
+        .arg(SELFTEST_DUMMY_COMMAND)
+        .spawn()
+        .map_err(|err| format!("couldn't start {}: {}", dummy_filepath.display(), err))
+        .and_then(|mut dummy| {
+            let result = wait_for_kill(&mut dummy, &dummy_filepath);
+            let _ = dummy.kill();
+            let _ = dummy.wait();
+            result
+        });
+    let _ = std::fs::remove_file(&dummy_filepath);
+    result
+}
+
+fn wait_for_kill(dummy: &mut Child, dummy_filepath: &Path) -> Result<(), String> {
+    let process_name = dummy_filepath
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut rules = Rules::new();
+    rules
+        .insert(Rule::new(process_name, vec![]))
+        .map_err(|err| err.to_string())?;
+
+    // The dummy only gets its name once it has started, the rules are applied until then
+    let (configuration, enforcement_state) = (Configuration::default(), EnforcementState::new());
+    let deadline = Instant::now() + SELFTEST_TIMEOUT;
+    while Instant::now() < deadline {
+        let report = process_rules(&rules, &configuration, &enforcement_state)
+            .map_err(|err| err.to_string())?;
+        if let Some(error) = report.errors().first() {
+            return Err(error.reason.clone());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+        if dummy.try_wait().map_err(|err| err.to_string())?.is_some() {
+            return Ok(());
+        }
+    }
+
+    Err("the blocked process is still running".to_string())
+}
+
+fn open_stats(swyt_filepath: &Path, configuration: &Configuration) -> Option<Box<dyn StatsStore>> {
+    open_configured_stats_store(swyt_filepath, configuration)
+        .map_err(|err| warn!("Couldn't open the statistics, they won't be kept: {}", err))
+        .ok()
+}
 
-        if let Err(err) = std::fs::create_dir(&swyt_filepath) {
-            fatal!(err);
+/// Removes the actions taken on processes and the changes to the policy recorded before
+/// their retention.
+fn prune_data(
+    swyt_filepath: &Path,
+    stats_store: Option<&mut Box<dyn StatsStore>>,
+    configuration: &Configuration,
+) {
+    let now = Local::now().naive_local();
+    let retention_start = |retention: u32| now - chrono::Duration::days(i64::from(retention));
+    let stats_retention = configuration.stats_retention();
+    if let (Some(stats_store), true) = (stats_store, stats_retention > 0) {
+        match stats_store.prune_events(&retention_start(stats_retention)) {
+            Ok(0) => (),
+            Ok(pruned) => info!("Pruned {} action(s) past the statistics retention", pruned),
+            Err(err) => warn!("Couldn't prune the statistics: {}", err),
         }
     }
-    let configuration = load_config(&swyt_filepath).unwrap_or_else(|e| fatal!(e));
-    let rules = load_rules(&swyt_filepath).unwrap_or_else(|e| fatal!(e));
+    let audit_retention = configuration.audit_retention();
+    if audit_retention > 0 {
+        match prune_audit_log(swyt_filepath, &retention_start(audit_retention)) {
+            Ok(0) => (),
+            Ok(pruned) => info!("Pruned {} change(s) past the audit retention", pruned),
+            Err(err) => warn!("Couldn't prune the audit log: {}", err),
+        }
+    }
+}
+
+/// Removes the actions taken on processes and the changes to the policy recorded more than
+/// the given days ago, or all of them along with the reports waiting to be delivered. The
+/// budget usage is kept, the daemon enforcing the budgets from it. Returns the exit code of
+/// the command.
+fn purge_data(days: Option<i64>) -> i32 {
+    let swyt_filepath = match find_policy_filepath() {
+        Ok(swyt_filepath) => swyt_filepath,
+        Err(err) => {
+            eprintln!("Couldn't find the policy: {}", err);
+            return 1;
+        }
+    };
+    let purged = load_config(&swyt_filepath)
+        .and_then(|configuration| open_configured_stats_store(&swyt_filepath, &configuration))
+        .and_then(|mut stats_store| match days {
+            Some(days) => {
+                let before = (Local::now() - chrono::Duration::days(days)).naive_local();
+                Ok((
+                    stats_store.prune_events(&before)?,
+                    prune_audit_log(&swyt_filepath, &before)?,
+                    0,
+                ))
+            }
+            None => Ok((
+                stats_store.clear_events()?,
+                clear_audit_log(&swyt_filepath)?,
+                clear_queued_reports(&swyt_filepath)?,
+            )),
+        });
+    match purged {
+        Ok((events, changes, reports)) => {
+            println!(
+                "Purged {} action(s), {} change(s) to the policy and {} queued report(s)",
+                events, changes, reports
+            );
+            0
+        }
+        Err(err) => {
+            eprintln!("Couldn't purge the data: {}", err);
+            1
+        }
+    }
+}
+
+/// Prints how many times each action was taken on each process over the last days, 7 by
+/// default. Returns the exit code of the command.
+fn print_stats(days: i64) -> i32 {
+    let since = (Local::now() - chrono::Duration::days(days)).naive_local();
+    let events = find_policy_filepath()
+        .and_then(|swyt_filepath| {
+            let configuration = load_config(&swyt_filepath)?;
+            open_configured_stats_store(&swyt_filepath, &configuration)
+        })
+        .and_then(|stats_store| stats_store.events_since(&since));
+    let events = match events {
+        Ok(events) => events,
+        Err(err) => {
+            eprintln!("Couldn't read the statistics: {}", err);
+            return 1;
+        }
+    };
 
+    let mut counts: BTreeMap<&str, BTreeMap<&str, u32>> = BTreeMap::new();
+    for event in &events {
+        *counts
+            .entry(event.process_name())
+            .or_default()
+            .entry(event.action())
+            .or_default() += 1;
+    }
+    println!("Over the last {} day(s):", days);
+    for (process_name, actions) in counts {
+        let actions: Vec<String> = actions
+            .iter()
+            .map(|(action, count)| format!("{} {}", count, action))
+            .collect();
+        println!("{}: {}", process_name, actions.join(", "));
+    }
+    0
+}
+
+/// Queues the events in the outbox and delivers the reports due to the report command, the
+/// outbox keeping them while it fails, such as when offline.
+fn report_events(swyt_filepath: &Path, report_command: &str, events: &[EnforcementEvent]) {
+    let now = Local::now().naive_local();
+    let reports: Vec<String> = events.iter().map(EnforcementEvent::to_json).collect();
+    if let Err(err) = queue_reports(swyt_filepath, &reports, now) {
+        warn!("Couldn't queue the reports: {}", err);
+    }
+    match deliver_reports(swyt_filepath, now, |report| {
+        run_report_command(report_command, report)
+    }) {
+        Ok(0) => (),
+        Ok(delivered) => debug!("Delivered {} report(s)", delivered),
+        Err(err) => warn!("Couldn't deliver the reports: {}", err),
+    }
+}
+
+/// Delivers the reports right away, without an outbox to retry them, those that fail being
+/// dropped.
+fn report_events_now(report_command: &str, events: &[EnforcementEvent]) {
+    for event in events {
+        if let Err(err) = run_report_command(report_command, &event.to_json()) {
+            warn!("Couldn't deliver a report, dropping it: {}", err);
+        }
+    }
+}
+
+/// Shows the messages of the rules acting on processes with the notify command, they are
+/// only logged without one.
+fn notify_messages(configuration: &Configuration, enforcement_state: &EnforcementState) {
+    let messages = enforcement_state.take_unshown_messages();
+    if let Some(notify_command) = configuration.notify_command() {
+        for message in messages {
+            if let Err(err) = run_notify_command(notify_command, &message) {
+                warn!("Couldn't notify \"{}\": {}", message, err);
+            }
+        }
+    }
+}
+
+/// Runs the command with `{}` replaced by the message, passed in the `SWYT_MESSAGE`
+/// environment variable for the shell not to interpret it, as the process names in it are
+/// chosen by whoever starts the processes. The command is waited for on its own thread so
+/// a slow notifier doesn't hold the scans up.
+fn run_notify_command(notify_command: &str, message: &str) -> Result<(), SwytError> {
+    // cmd expands the variables between `!` once the command is parsed with /V:ON
+    let (shell, args, variable) = match cfg!(windows) {
+        true => ("cmd", ["/V:ON", "/C"].as_slice(), "\"!SWYT_MESSAGE!\""),
+        false => ("sh", ["-c"].as_slice(), "\"$SWYT_MESSAGE\""),
+    };
+    let mut child = Command::new(shell)
+        .args(args)
+        .arg(notify_command.replace("{}", variable))
+        .env("SWYT_MESSAGE", message)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => {
+            warn!("The notify command exited with {}", status)
+        }
+        Ok(_) => (),
+        Err(err) => warn!("Couldn't wait for the notify command: {}", err),
+    });
+    Ok(())
+}
+
+/// Runs the command with the report on its standard input, killing it past
+/// `REPORT_COMMAND_TIMEOUT` so an unresponsive endpoint doesn't hold the scans up.
+fn run_report_command(report_command: &str, report: &str) -> Result<(), SwytError> {
+    let mut command = match cfg!(windows) {
+        true => Command::new("cmd"),
+        false => Command::new("sh"),
+    };
+    let mut child = command
+        .args([if cfg!(windows) { "/C" } else { "-c" }, report_command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Closing the standard input once written ends the report
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(format!("{}\n", report).as_bytes())?;
+    }
+
+    let deadline = Instant::now() + REPORT_COMMAND_TIMEOUT;
     loop {
-        if let Err(err) = process_rules(&rules) {
-            fatal!(err);
+        if let Some(status) = child.try_wait()? {
+            return match status.success() {
+                true => Ok(()),
+                false => Err(SwytError::ReportDeliveryError(format!(
+                    "the report command exited with {}",
+                    status
+                ))),
+            };
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(SwytError::ReportDeliveryError(
+                "the report command timed out".to_string(),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Prints when each rule allows its processes over the week. Returns the exit code of the
+/// command.
+fn print_schedule() -> i32 {
+    let rules = match load_policy() {
+        Ok((_, _, rules)) => rules,
+        Err(err) => {
+            error!("Couldn't load the rules: {}", err);
+            return 1;
+        }
+    };
+
+    for rule in rules.iter() {
+        let mut header = rule.process_name().to_string();
+        for condition in rule.conditions() {
+            header.push_str(&format!(" {}", condition));
+        }
+        println!("{}", header);
+        for weekday in
+            std::iter::successors(Some(Weekday::Mon), |weekday| Some(weekday.succ())).take(7)
+        {
+            let allowed_times: Vec<String> = rule
+                .allowed_times(weekday)
+                .iter()
+                .map(|(begin_time, end_time)| {
+                    format!(
+                        "{}~{}",
+                        begin_time.format("%H:%M"),
+                        end_time.format("%H:%M")
+                    )
+                })
+                .collect();
+            let allowed_times = if allowed_times.is_empty() {
+                "blocked".to_string()
+            } else {
+                allowed_times.join(", ")
+            };
+            println!("  {:?} {}", weekday, allowed_times);
+        }
+    }
+
+    0
+}
+
+/// Writes the allowed periods of the rules as an iCalendar to the given file, or prints it.
+/// Returns the exit code of the command.
+fn export_calendar(filepath: Option<&Path>) -> i32 {
+    let result = load_policy().and_then(|(_, _, rules)| {
+        let ics = export_ics(
+            &rules,
+            Local::now().naive_local().date(),
+            &Utc::now().naive_utc(),
+        );
+        match filepath {
+            Some(filepath) => std::fs::write(filepath, ics)?,
+            None => print!("{}", ics),
+        }
+        Ok(())
+    });
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            error!("Couldn't export the calendar: {}", err);
+            1
+        }
+    }
+}
+
+fn log_diagnostics(rules: &Rules) {
+    let diagnostics = Diagnostics::of(rules);
+    match diagnostics.warnings().is_empty() {
+        true => info!("{}", diagnostics),
+        false => warn!("{}", diagnostics),
+    }
+}
+
+/// Checks the rules of the given file, or of the policy, printing what likely doesn't do
+/// what was meant. Returns 0 when there is nothing to report, 1 when the rules can't be
+/// loaded and 2 when there are warnings.
+fn lint(rules_filepath: Option<&Path>) -> i32 {
+    let rules = match rules_filepath {
+        Some(rules_filepath) => std::fs::read_to_string(rules_filepath)
+            .map_err(SwytError::from)
+            .and_then(|rules_string| parse_rules(&rules_string)),
+        None => load_policy().map(|(_, _, rules)| rules),
+    };
+    let rules = match rules {
+        Ok(rules) => rules,
+        Err(err) => {
+            println!("error: {}", err);
+            return 1;
+        }
+    };
+
+    let diagnostics = Diagnostics::of(&rules);
+    for warning in diagnostics.warnings() {
+        println!("warning: {}", warning);
+    }
+    println!(
+        "{} rule(s) loaded, {} warning(s)",
+        diagnostics.rule_count(),
+        diagnostics.warnings().len()
+    );
+    match diagnostics.warnings().is_empty() {
+        true => 0,
+        false => 2,
+    }
+}
+
+/// Parses the configuration and the rules of the policy, printing the errors with the line
+/// they are on, then the warnings of `swyt lint`. Returns 0 when the policy is valid, 1 when
+/// it can't be loaded and 2 when there are warnings.
+fn validate() -> i32 {
+    let (config_string, rules_string) =
+        match find_policy_filepath().and_then(|swyt_filepath| read_policy(&swyt_filepath)) {
+            Ok(policy) => policy,
+            Err(err) => {
+                println!("error: {}", err);
+                return 1;
+            }
+        };
+
+    let config_result = validate_config(&config_string);
+    if let Err((line_number, err)) = &config_result {
+        println!("error: config.jbb line {}: {}", line_number, err);
+    }
+    let rules = match validate_rules(&rules_string) {
+        Ok(rules) => rules,
+        Err((line_number, err)) => {
+            println!("error: rules.jbb line {}: {}", line_number, err);
+            return 1;
+        }
+    };
+    if config_result.is_err() {
+        return 1;
+    }
+
+    let diagnostics = Diagnostics::of(&rules);
+    for warning in diagnostics.warnings() {
+        println!("warning: {}", warning);
+    }
+    println!(
+        "The policy is valid, {} rule(s) loaded, {} warning(s)",
+        diagnostics.rule_count(),
+        diagnostics.warnings().len()
+    );
+    match diagnostics.warnings().is_empty() {
+        true => 0,
+        false => 2,
+    }
+}
+
+/// Prints whether the rules allow the process now, the overrides of the daemon aside.
+/// Returns 0 when it is allowed, 1 when the rules can't be loaded and 2 when it is blocked.
+fn check(process_name: &str) -> i32 {
+    let rules = match load_policy() {
+        Ok((_, _, rules)) => rules,
+        Err(err) => {
+            error!("Couldn't load the rules: {}", err);
+            return 1;
+        }
+    };
+
+    let now = Local::now().naive_local();
+    let next_boundary = rules
+        .get_rule(process_name)
+        .and_then(|rule| rule.next_boundary(&now));
+    let (verdict, exit_code) = match evaluate_rules(&rules, process_name, &now) {
+        Verdict::Unmatched => ("allowed, no rule applies to it", 0),
+        Verdict::Allowed => ("allowed", 0),
+        Verdict::Blocked => ("blocked", 2),
+    };
+    match next_boundary {
+        Some(next_boundary) => println!(
+            "{} is {} (its schedule changes next at {})",
+            process_name,
+            verdict,
+            next_boundary.format("%Y-%m-%d %H:%M")
+        ),
+        None => println!("{} is {}", process_name, verdict),
+    }
+    exit_code
+}
+
+/// Lists, adds or removes the rules of the policy, the changes being recorded in the audit
+/// log. Returns the exit code of the command.
+fn edit_rules(command: RulesCommand) -> i32 {
+    let swyt_filepath = match find_policy_filepath() {
+        Ok(swyt_filepath) => swyt_filepath,
+        Err(err) => {
+            error!("Couldn't find the policy: {}", err);
+            return 1;
+        }
+    };
+    let (config_string, mut rules_string) = match read_policy(&swyt_filepath) {
+        Ok(policy) => policy,
+        Err(err) => {
+            error!("Couldn't read the policy: {}", err);
+            return 1;
+        }
+    };
+
+    let rules_string = match command {
+        RulesCommand::List => {
+            for line in rules_string.lines().filter(|line| !line.trim().is_empty()) {
+                println!("{}", line);
+            }
+            return 0;
+        }
+        RulesCommand::Add(rule) => {
+            let process_name = rule.split_once('=').map_or("", |(name, _)| name.trim());
+            // Rules for any process with different conditions add up
+            if process_name != "*" && remove_rule(&rules_string, process_name).is_some() {
+                eprintln!(
+                    "A rule for {} already exists, remove it first",
+                    process_name
+                );
+                return 1;
+            }
+            if !rules_string.is_empty() && !rules_string.ends_with('\n') {
+                rules_string.push('\n');
+            }
+            rules_string.push_str(&rule);
+            rules_string.push('\n');
+            if let Err((_, err)) = validate_rules(&rules_string) {
+                eprintln!("Invalid rule: {}", err);
+                return 1;
+            }
+            rules_string
+        }
+        RulesCommand::Remove(process_name) => match remove_rule(&rules_string, &process_name) {
+            Some(rules_string) => rules_string,
+            None => {
+                eprintln!("There is no rule for {}", process_name);
+                return 1;
+            }
+        },
+    };
+
+    match write_policy(&swyt_filepath, &config_string, &rules_string) {
+        Ok(()) => 0,
+        Err(err) => {
+            error!("Couldn't write the rules: {}", err);
+            1
+        }
+    }
+}
+
+/// Prints an AppArmor profile or an SELinux policy module granting the daemon the access
+/// the policy needs. Returns the exit code of the command.
+fn print_confinement_profile(kind: &str) -> i32 {
+    let result = load_policy().and_then(|(swyt_filepath, configuration, _)| {
+        let executable_path = std::env::current_exe()?;
+        match kind {
+            "selinux" => print!(
+                "{}",
+                selinux_module(&configuration, &swyt_filepath, &executable_path)
+            ),
+            _ => print!(
+                "{}",
+                apparmor_profile(&configuration, &swyt_filepath, &executable_path)
+            ),
+        }
+        Ok(())
+    });
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            error!("Couldn't generate the confinement profile: {}", err);
+            1
+        }
+    }
+}
+
+/// Evaluates the rules, those of a rules file given with `--rules` or those of the policy,
+/// for the processes of the scans recorded in a trace, printing the processes they treat
+/// differently. Returns the exit code of the command.
+fn replay(trace_filepath: &Path, rules_filepath: Option<&Path>) -> i32 {
+    let rules = match rules_filepath {
+        Some(rules_filepath) => std::fs::read_to_string(rules_filepath)
+            .map_err(SwytError::from)
+            .and_then(|rules_string| parse_rules(&rules_string)),
+        None => load_policy().map(|(_, _, rules)| rules),
+    };
+    let result = rules.and_then(|rules| {
+        let scan_traces = read_scan_traces(trace_filepath)?;
+        let mut change_count = 0;
+        for scan_trace in &scan_traces {
+            for change in scan_trace.replay(&rules) {
+                println!(
+                    "{} {}: {} -> {}",
+                    scan_trace.date_time().format("%Y-%m-%dT%H:%M:%S"),
+                    change.process_name,
+                    change.recorded,
+                    change.replayed
+                );
+                change_count += 1;
+            }
+        }
+        println!(
+            "Replayed {} scan(s), {} verdict(s) changed",
+            scan_traces.len(),
+            change_count
+        );
+        Ok(())
+    });
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            error!("Couldn't replay the scans: {}", err);
+            1
+        }
+    }
+}
+
+/// Prints the state the daemon recorded after its last scan, as JSON. Returns the exit code
+/// of the command.
+fn print_status() -> i32 {
+    // Only read, the directory and the files missing aren't created
+    let swyt_filepath = find_policy_filepath().or_else(|_| find_swyt_filepath());
+    match swyt_filepath.and_then(|swyt_filepath| read_daemon_snapshot(&swyt_filepath)) {
+        Ok(snapshot) => {
+            println!("{}", snapshot);
+            0
+        }
+        Err(err) => {
+            eprintln!("Couldn't read the state of the daemon: {}", err);
+            1
+        }
+    }
+}
+
+/// Prints the changes of the policy recorded in the audit log, the `count` latest ones or all
+/// of them, with who made them. Returns the exit code of the command.
+fn print_history(count: Option<usize>) -> i32 {
+    let count = count.unwrap_or(usize::MAX);
+    // Only read, the directory and the files missing aren't created
+    let swyt_filepath = find_policy_filepath().or_else(|_| find_swyt_filepath());
+    match swyt_filepath.and_then(|swyt_filepath| read_audit_log(&swyt_filepath)) {
+        Ok(entries) => {
+            for entry in &entries[entries.len().saturating_sub(count)..] {
+                println!("{}", entry);
+            }
+            0
+        }
+        Err(err) => {
+            eprintln!("Couldn't read the audit log: {}", err);
+            1
         }
+    }
+}
 
-        std::thread::sleep(Duration::from_secs(configuration.check_interval() as u64))
+/// Queues a request to allow a process for a while, for the administrator to approve with
+/// `swyt approve`. Returns the exit code of the command.
+fn request(process_name: &str, minutes: u32, reason: &str) -> i32 {
+    let result = load_policy().and_then(|(swyt_filepath, configuration, _)| {
+        request_override(
+            &swyt_filepath,
+            &configuration,
+            process_name,
+            minutes,
+            reason,
+            Local::now().naive_local(),
+        )
+    });
+    match result {
+        Ok(request) => {
+            println!("Requested {}", request);
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
     }
 }
+
+/// Prints the override requests with their status. Returns the exit code of the command.
+fn print_requests() -> i32 {
+    let result = load_policy().and_then(|(swyt_filepath, configuration, _)| {
+        read_override_requests(&swyt_filepath, &configuration, Local::now().naive_local())
+    });
+    match result {
+        Ok(requests) => {
+            for request in requests {
+                println!("{}", request);
+            }
+            0
+        }
+        Err(err) => {
+            eprintln!("Couldn't read the override requests: {}", err);
+            1
+        }
+    }
+}
+
+/// Approves or denies the pending override request, approving it taking the admin PIN.
+/// Returns the exit code of the command.
+fn decide(id: u32, approved: bool) -> i32 {
+    let result = load_policy().and_then(|(swyt_filepath, configuration, _)| {
+        let admin_pin = match approved && has_admin_pin(&swyt_filepath) {
+            true => Some(prompt("Admin PIN: ")?),
+            false => None,
+        };
+        decide_override_request(
+            &swyt_filepath,
+            &configuration,
+            id,
+            approved,
+            admin_pin.as_deref(),
+            Local::now().naive_local(),
+        )
+    });
+    match result {
+        Ok(request) => {
+            println!("{}", request);
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+/// Sets the admin PIN approving the changes loosening the policy, asking for the current one
+/// first if any. Returns the exit code of the command.
+fn set_pin() -> i32 {
+    let result = load_policy().and_then(|(swyt_filepath, _, _)| {
+        let current_pin = match has_admin_pin(&swyt_filepath) {
+            true => Some(prompt("Current admin PIN: ")?),
+            false => None,
+        };
+        let pin = prompt("New admin PIN: ")?;
+        set_admin_pin(&swyt_filepath, current_pin.as_deref(), &pin)
+    });
+    match result {
+        Ok(()) => {
+            println!("The admin PIN is set");
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+/// Prints the changes held back until approved, then approves them with the admin PIN for
+/// the daemon to apply them. Returns the exit code of the command.
+fn approve_loosening() -> i32 {
+    let result = load_policy().and_then(|(swyt_filepath, configuration, rules)| {
+        let approved_policy = read_approved_policy(&swyt_filepath).unwrap_or_else(|err| {
+            eprintln!("Couldn't read the approved policy: {}", err);
+            None
+        });
+        let held_changes = match approved_policy {
+            Some(mut approved_policy) => {
+                let (config_string, _) = read_policy(&swyt_filepath)?;
+                let (configuration, rules) = (configuration.clone(), rules.clone());
+                approved_policy
+                    .review(&config_string, configuration, rules, false)
+                    .2
+            }
+            // The daemon holds every change back
+            None if has_admin_pin(&swyt_filepath) => {
+                vec!["The approved policy is missing, approving the whole policy".to_string()]
+            }
+            None => vec![],
+        };
+        if held_changes.is_empty() {
+            println!("No change is held back");
+            return Ok(false);
+        }
+        for held_change in &held_changes {
+            println!("{}", held_change);
+        }
+        let pin = prompt("Admin PIN: ")?;
+        approve_changes(&swyt_filepath, &pin, &configuration, &rules)?;
+        Ok(true)
+    });
+    match result {
+        Ok(true) => {
+            println!("The changes are approved, the daemon applies them at its next check");
+            0
+        }
+        Ok(false) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+/// Lifts the lockdown given the admin PIN once the policy loads again, the daemon then
+/// reloads it. Returns the exit code of the command.
+fn unlock() -> i32 {
+    let result = load_policy().and_then(|(swyt_filepath, _, _)| {
+        let admin_pin = match has_admin_pin(&swyt_filepath) {
+            true => Some(prompt("Admin PIN: ")?),
+            false => None,
+        };
+        clear_lockdown(&swyt_filepath, admin_pin.as_deref())
+    });
+    match result {
+        Ok(()) => {
+            info!("The lockdown is lifted");
+            0
+        }
+        Err(err) => {
+            error!("Couldn't lift the lockdown: {}", err);
+            1
+        }
+    }
+}
+
+/// Removes the state files of the daemon from the user and the system-wide directories, and
+/// these directories altogether with `--purge`, given the admin PIN if any, printing what was
+/// removed. Swyt doesn't
+/// register itself as a service nor change the system configuration, so there is nothing
+/// else to revert. Returns the exit code of the command.
+fn uninstall(purge: bool) -> i32 {
+    let swyt_filepaths: Vec<PathBuf> = find_swyt_filepath()
+        .ok()
+        .into_iter()
+        .chain(managed_swyt_directory())
+        .filter(|swyt_filepath| swyt_filepath.exists())
+        .collect();
+
+    // A running daemon would write its state again
+    let daemon_running = swyt_filepaths.iter().any(|swyt_filepath| {
+        read_heartbeat(swyt_filepath)
+            .is_ok_and(|heartbeat| is_process_running(heartbeat.pid()) == Some(true))
+    });
+    if daemon_running {
+        error!("The daemon is running, stop it before uninstalling");
+        return 1;
+    }
+    // The admin PIN and the approved policy are only removed along with the directories
+    if purge {
+        for swyt_filepath in &swyt_filepaths {
+            if let Err(err) = check_admin_pin(swyt_filepath) {
+                error!("Couldn't purge {}: {}", swyt_filepath.display(), err);
+                return 1;
+            }
+        }
+    }
+
+    let mut exit_code = 0;
+    for swyt_filepath in &swyt_filepaths {
+        let result = if purge {
+            std::fs::remove_dir_all(swyt_filepath).map(|_| vec![swyt_filepath.clone()])
+        } else {
+            state_filepaths(swyt_filepath)
+                .into_iter()
+                .filter(|state_filepath| state_filepath.exists())
+                .map(|state_filepath| std::fs::remove_file(&state_filepath).map(|_| state_filepath))
+                .collect()
+        };
+        match result {
+            Ok(removed_filepaths) => {
+                for removed_filepath in removed_filepaths {
+                    println!("Removed {}", removed_filepath.display());
+                }
+            }
+            Err(err) => {
+                error!("Couldn't clean up {}: {}", swyt_filepath.display(), err);
+                exit_code = 1;
+            }
+        }
+    }
+
+    exit_code
+}
+
+/// Checks the daemon is running and scanning, its policy loads and its state can be
+/// written, unless it is ephemeral, printing the result of each check as JSON. Returns the
+/// exit code of the command.
+fn check_health() -> i32 {
+    let ephemeral_heartbeat = ephemeral_heartbeat_directory()
+        .and_then(|heartbeat_filepath| read_heartbeat(&heartbeat_filepath).ok())
+        .filter(|heartbeat| is_process_running(heartbeat.pid()) != Some(false));
+    let ephemeral = ephemeral_heartbeat.is_some();
+    let policy = match ephemeral {
+        true => load_policy_read_only(),
+        false => load_policy(),
+    };
+    let config_loaded = policy.is_ok();
+    let (swyt_filepath, check_interval) = match policy {
+        // The daemon may be in low-power mode, checking less often
+        Ok((swyt_filepath, configuration, _)) => match configuration.low_power() {
+            LowPowerMode::Off => (Some(swyt_filepath), configuration.check_interval()),
+            _ => (
+                Some(swyt_filepath),
+                configuration.low_power_check_interval(),
+            ),
+        },
+        Err(_) => (
+            find_swyt_filepath().ok(),
+            Configuration::default().check_interval(),
+        ),
+    };
+
+    let heartbeat = ephemeral_heartbeat.or_else(|| {
+        swyt_filepath
+            .as_deref()
+            .and_then(|swyt_filepath| read_heartbeat(swyt_filepath).ok())
+    });
+    let last_scan_recent = heartbeat.as_ref().is_some_and(|heartbeat| {
+        let since_last_scan = Local::now().naive_local() - *heartbeat.last_scan();
+        since_last_scan.num_seconds() <= 2 * i64::from(check_interval) + HEALTH_GRACE_PERIOD_SECONDS
+    });
+    // When processes can't be checked on this platform, a recent scan tells the daemon runs
+    let daemon_running = heartbeat
+        .as_ref()
+        .is_some_and(|heartbeat| is_process_running(heartbeat.pid()).unwrap_or(last_scan_recent));
+    let persistence_writable = swyt_filepath.as_deref().is_some_and(is_directory_writable);
+
+    // An ephemeral daemon doesn't write its state, the filesystem may well be read-only
+    let healthy =
+        daemon_running && config_loaded && last_scan_recent && (ephemeral || persistence_writable);
+    let last_scan = heartbeat.map_or("null".to_string(), |heartbeat| {
+        format!("\"{}\"", heartbeat.last_scan().format("%Y-%m-%dT%H:%M:%S"))
+    });
+    println!(
+        "{{\"healthy\":{},\"daemon_running\":{},\"config_loaded\":{},\"last_scan_recent\":{},\"persistence_writable\":{},\"ephemeral\":{},\"last_scan\":{}}}",
+        healthy,
+        daemon_running,
+        config_loaded,
+        last_scan_recent,
+        persistence_writable,
+        ephemeral,
+        last_scan
+    );
+
+    if healthy {
+        0
+    } else {
+        1
+    }
+}
+
+/// Exports the traces and metrics of the daemon over OTLP/HTTP, configured through the
+/// standard `OTEL_EXPORTER_OTLP_*` environment variables. The returned providers must be
+/// kept alive for the export to go on.
+#[cfg(feature = "otlp")]
+fn init_otlp() -> Result<(SdkTracerProvider, SdkMeterProvider), Box<dyn std::error::Error>> {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let resource = Resource::builder().with_service_name("swyt").build();
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(SpanExporter::builder().with_http().build()?)
+        .with_resource(resource.clone())
+        .build();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(MetricExporter::builder().with_http().build()?)
+        .with_resource(resource)
+        .build();
+
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("swyt")))
+        .with(tracing_opentelemetry::MetricsLayer::new(
+            meter_provider.clone(),
+        ));
+    tracing::subscriber::set_global_default(subscriber)?;
+    Ok((tracer_provider, meter_provider))
+}
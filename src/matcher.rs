@@ -0,0 +1,369 @@
+use crate::SwytError;
+#[cfg(feature = "regex")]
+use log::error;
+use std::borrow::Cow;
+use std::collections::HashMap;
+#[cfg(feature = "regex")]
+use std::sync::OnceLock;
+
+pub(crate) type RuleId = usize;
+
+/// Prefix of the patterns matching the full path of the executable of the processes.
+const PATH_PREFIX: &str = "path:";
+/// Prefix of the patterns matching the command line of the processes.
+const COMMAND_LINE_PREFIX: &str = "cmd:";
+/// Prefix of the regular expressions matching the names of the processes.
+const REGEX_PREFIX: &str = "re:";
+/// Size a compiled regular expression may reach, the default of the `regex` crate.
+#[cfg(feature = "regex")]
+const REGEX_SIZE_LIMIT: usize = 10 * (1 << 20);
+
+/// What a rule designates its processes with, as written before the `=` of the rule, once
+/// compiled. Globs support `*`, any characters, and `?`, a single one.
+#[derive(Debug, Clone)]
+enum ProcessPattern {
+    Name(Glob),
+    /// Index of the regular expression among the ones of the matcher.
+    #[cfg(feature = "regex")]
+    NameRegex(usize),
+    Path(Glob),
+    CommandLine(Glob),
+}
+
+/// What the rules can tell a process apart with. The path of its executable and its command
+/// line are only read when rules match on them, see `Matcher::matches_paths`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ProcessTarget<'a> {
+    name: &'a str,
+    path: Option<&'a str>,
+    command_line: Option<&'a str>,
+}
+
+impl<'a> ProcessTarget<'a> {
+    pub(crate) fn named(name: &'a str) -> Self {
+        ProcessTarget {
+            name,
+            path: None,
+            command_line: None,
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn with_path(mut self, path: Option<&'a str>) -> Self {
+        self.path = path;
+        self
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn with_command_line(mut self, command_line: Option<&'a str>) -> Self {
+        self.command_line = command_line;
+        self
+    }
+}
+
+/// Maps process names to the rules targeting them, built once when the rules are loaded so
+/// matching a process only costs a hash lookup. The rules with a pattern are only tried, in
+/// order, when no rule is named after the process.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Matcher {
+    names: HashMap<Box<str>, RuleId>,
+    patterns: Vec<(Box<str>, ProcessPattern, RuleId)>,
+    #[cfg(feature = "regex")]
+    regexes: Vec<Box<str>>,
+    /// The regular expressions compiled together, on the first match after an insertion, so a
+    /// process name is only scanned once whatever their number.
+    #[cfg(feature = "regex")]
+    regex_set: OnceLock<Option<regex::RegexSet>>,
+}
+
+impl Matcher {
+    /// Inserts the rule of the process name or pattern, failing when the pattern is invalid.
+    pub(crate) fn insert(&mut self, process_name: &str, rule_id: RuleId) -> Result<(), SwytError> {
+        let pattern = if let Some(path_glob) = process_name.strip_prefix(PATH_PREFIX) {
+            ProcessPattern::Path(Glob::new(&normalize_path(path_glob))?)
+        } else if let Some(command_line_glob) = process_name.strip_prefix(COMMAND_LINE_PREFIX) {
+            ProcessPattern::CommandLine(Glob::new(command_line_glob)?)
+        } else if let Some(name_regex) = process_name.strip_prefix(REGEX_PREFIX) {
+            self.insert_regex(name_regex)?
+        } else if process_name.contains(['*', '?']) {
+            ProcessPattern::Name(Glob::new(&normalize_process_name(process_name))?)
+        } else {
+            self.names
+                .insert(normalize_process_name(process_name).into(), rule_id);
+            return Ok(());
+        };
+
+        self.patterns.push((process_name.into(), pattern, rule_id));
+        Ok(())
+    }
+
+    #[cfg(feature = "regex")]
+    fn insert_regex(&mut self, name_regex: &str) -> Result<ProcessPattern, SwytError> {
+        regex::Regex::new(name_regex).map_err(|_| SwytError::RuleParseError)?;
+        self.regexes.push(name_regex.into());
+        self.regex_set = OnceLock::new();
+        Ok(ProcessPattern::NameRegex(self.regexes.len() - 1))
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn insert_regex(&mut self, _name_regex: &str) -> Result<ProcessPattern, SwytError> {
+        Err(SwytError::Unsupported("match regular expressions"))
+    }
+
+    /// Returns the rule written with the process name or pattern, to replace it.
+    pub(crate) fn find_written(&self, process_name: &str) -> Option<RuleId> {
+        self.find_name(process_name).or_else(|| {
+            self.patterns
+                .iter()
+                .find(|(pattern, ..)| pattern.as_ref() == process_name)
+                .map(|&(_, _, rule_id)| rule_id)
+        })
+    }
+
+    /// Returns the rule named after the process, the patterns aside.
+    pub(crate) fn find_name(&self, process_name: &str) -> Option<RuleId> {
+        self.names
+            .get(normalize_process_name(process_name).as_ref())
+            .copied()
+    }
+
+    pub(crate) fn find(&self, process: &ProcessTarget) -> Option<RuleId> {
+        let name = normalize_process_name(process.name);
+        if let Some(&rule_id) = self.names.get(name.as_ref()) {
+            return Some(rule_id);
+        }
+
+        #[cfg(feature = "regex")]
+        let mut regex_matches = None;
+        self.patterns
+            .iter()
+            .find(|(_, pattern, _)| match pattern {
+                ProcessPattern::Name(glob) => glob.matches(&name),
+                #[cfg(feature = "regex")]
+                ProcessPattern::NameRegex(index) => regex_matches
+                    .get_or_insert_with(|| self.regex_matches(&name))
+                    .as_ref()
+                    .is_some_and(|matches| matches.matched(*index)),
+                ProcessPattern::Path(glob) => process
+                    .path
+                    .is_some_and(|path| glob.matches(&normalize_path(path))),
+                ProcessPattern::CommandLine(glob) => process
+                    .command_line
+                    .is_some_and(|command_line| glob.matches(command_line)),
+            })
+            .map(|&(_, _, rule_id)| rule_id)
+    }
+
+    #[cfg(feature = "regex")]
+    fn regex_matches(&self, name: &str) -> Option<regex::SetMatches> {
+        self.regex_set
+            .get_or_init(|| {
+                // Each expression was checked against the default limit when inserted
+                regex::RegexSetBuilder::new(self.regexes.iter().map(AsRef::<str>::as_ref))
+                    .size_limit(self.regexes.len() * REGEX_SIZE_LIMIT)
+                    .build()
+                    .map_err(|err| {
+                        error!(
+                            "Couldn't compile the regular expressions of the rules: {}",
+                            err
+                        )
+                    })
+                    .ok()
+            })
+            .as_ref()
+            .map(|regex_set| regex_set.matches(name))
+    }
+
+    /// Returns whether rules match the path of the executable of the processes.
+    pub(crate) fn matches_paths(&self) -> bool {
+        self.patterns
+            .iter()
+            .any(|(_, pattern, _)| matches!(pattern, ProcessPattern::Path(_)))
+    }
+
+    /// Returns whether rules match the command line of the processes.
+    pub(crate) fn matches_command_lines(&self) -> bool {
+        self.patterns
+            .iter()
+            .any(|(_, pattern, _)| matches!(pattern, ProcessPattern::CommandLine(_)))
+    }
+}
+
+/// Glob split once into the literal parts between its wildcards, so matching a text neither
+/// allocates nor decodes the glob again.
+#[derive(Debug, Clone)]
+struct Glob {
+    tokens: Vec<GlobToken>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum GlobToken {
+    Literal(Box<str>),
+    AnyCharacter,
+    AnyCharacters,
+}
+
+impl Glob {
+    fn new(glob: &str) -> Result<Self, SwytError> {
+        if glob.is_empty() {
+            return Err(SwytError::RuleParseError);
+        }
+
+        let mut tokens = vec![];
+        let mut literal_start = None;
+        for (index, character) in glob.char_indices() {
+            let token = match character {
+                '*' => GlobToken::AnyCharacters,
+                '?' => GlobToken::AnyCharacter,
+                _ => {
+                    literal_start.get_or_insert(index);
+                    continue;
+                }
+            };
+            if let Some(start) = literal_start.take() {
+                tokens.push(GlobToken::Literal(glob[start..index].into()));
+            }
+            // Consecutive `*` match the same as a single one
+            if token != GlobToken::AnyCharacters || tokens.last() != Some(&token) {
+                tokens.push(token);
+            }
+        }
+        if let Some(start) = literal_start {
+            tokens.push(GlobToken::Literal(glob[start..].into()));
+        }
+
+        Ok(Glob { tokens })
+    }
+
+    /// Returns whether the whole text matches the glob.
+    fn matches(&self, text: &str) -> bool {
+        let (mut token_index, mut rest) = (0, text);
+        // Where to resume from when a match fails after a `*`, making it match one more character
+        let mut backtrack = None;
+        loop {
+            let matched_rest = match self.tokens.get(token_index) {
+                None if rest.is_empty() => return true,
+                None => None,
+                Some(GlobToken::AnyCharacters) if token_index + 1 == self.tokens.len() => {
+                    return true
+                }
+                Some(GlobToken::AnyCharacters) => {
+                    token_index += 1;
+                    backtrack = Some((token_index, rest));
+                    continue;
+                }
+                Some(GlobToken::AnyCharacter) => skip_character(rest),
+                Some(GlobToken::Literal(literal)) => rest.strip_prefix(literal.as_ref()),
+            };
+
+            match (matched_rest, backtrack) {
+                (Some(matched_rest), _) => {
+                    token_index += 1;
+                    rest = matched_rest;
+                }
+                (None, Some((star_token_index, star_rest))) => match skip_character(star_rest) {
+                    Some(star_rest) => {
+                        token_index = star_token_index;
+                        rest = star_rest;
+                        backtrack = Some((star_token_index, star_rest));
+                    }
+                    None => return false,
+                },
+                (None, None) => return false,
+            }
+        }
+    }
+}
+
+/// Returns the text without its first character, `None` when it is empty.
+fn skip_character(text: &str) -> Option<&str> {
+    text.chars()
+        .next()
+        .map(|character| &text[character.len_utf8()..])
+}
+
+/// Process names are case insensitive on Windows and reported with their extension, so
+/// `Firefox.exe` and `firefox` designate the same process there.
+#[cfg(windows)]
+pub(crate) fn normalize_process_name(process_name: &str) -> Cow<'_, str> {
+    let process_name = if process_name.chars().any(char::is_uppercase) {
+        Cow::Owned(process_name.to_lowercase())
+    } else {
+        Cow::Borrowed(process_name)
+    };
+
+    match process_name {
+        Cow::Borrowed(process_name) => {
+            Cow::Borrowed(process_name.strip_suffix(".exe").unwrap_or(process_name))
+        }
+        Cow::Owned(process_name) => match process_name.strip_suffix(".exe") {
+            Some(stripped_process_name) => Cow::Owned(stripped_process_name.to_string()),
+            None => Cow::Owned(process_name),
+        },
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn normalize_process_name(process_name: &str) -> Cow<'_, str> {
+    Cow::Borrowed(process_name)
+}
+
+/// Paths are case insensitive on Windows.
+#[cfg(windows)]
+fn normalize_path(path: &str) -> Cow<'_, str> {
+    Cow::Owned(path.to_lowercase())
+}
+
+#[cfg(not(windows))]
+fn normalize_path(path: &str) -> Cow<'_, str> {
+    Cow::Borrowed(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_matches() {
+        let glob = |glob: &str| Glob::new(glob).unwrap();
+        assert!(glob("*").matches(""));
+        assert!(glob("steam*").matches("steamwebhelper"));
+        assert!(glob("*craft*").matches("minecraft-launcher"));
+        assert!(glob("a*b*c").matches("aXbYbZc"));
+        assert!(glob("?é?").matches("dés"));
+        assert!(glob("**.sh").matches("run.sh"));
+        assert!(!glob("steam*").matches("nosteam"));
+        assert!(!glob("a*b*c").matches("aXbYbZ"));
+        assert!(!glob("?").matches(""));
+        assert!(!glob("game").matches("games"));
+        assert!(Glob::new("").is_err());
+    }
+
+    #[test]
+    fn matcher_insert() {
+        let mut matcher = Matcher::default();
+        matcher.insert("steam", 0).unwrap();
+        matcher.insert("steam*", 1).unwrap();
+        matcher.insert("path:/opt/games/*", 2).unwrap();
+        assert!(matcher.insert("path:", 3).is_err());
+        #[cfg(feature = "regex")]
+        {
+            matcher.insert("re:^mine(craft)?$", 3).unwrap();
+            assert_eq!(matcher.find(&ProcessTarget::named("minecraft")), Some(3));
+            matcher.insert("re:^mine", 4).unwrap();
+            assert_eq!(matcher.find(&ProcessTarget::named("minesweeper")), Some(4));
+            assert!(matcher.insert("re:(", 5).is_err());
+        }
+        #[cfg(not(feature = "regex"))]
+        assert!(matcher.insert("re:^mine", 3).is_err());
+
+        assert_eq!(matcher.find(&ProcessTarget::named("steam")), Some(0));
+        assert_eq!(
+            matcher.find(&ProcessTarget::named("steamwebhelper")),
+            Some(1)
+        );
+        let game = ProcessTarget::named("game").with_path(Some("/opt/games/game"));
+        assert_eq!(matcher.find(&game), Some(2));
+        assert_eq!(matcher.find(&ProcessTarget::named("game")), None);
+    }
+}
@@ -0,0 +1,533 @@
+use crate::enforce::{ProcessOps, Scan};
+use crate::identity::running_for;
+use crate::{record_scan, Configuration, EnforcementState, Rules, ScanReport, SwytError};
+use log::trace;
+use std::future::Future;
+use std::panic;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    /// The pid and the name of the parent of the process, when it was listed along with it.
+    pub parent: Option<(u32, String)>,
+}
+
+#[cfg(target_os = "linux")]
+use linux::{command_line, executable_path, resume, set_priority, suspend, throttle};
+#[cfg(target_os = "linux")]
+pub use linux::{kill, processes, terminate};
+#[cfg(windows)]
+use windows::{command_line, executable_path, resume, set_priority, suspend, throttle};
+#[cfg(windows)]
+pub use windows::{kill, processes, terminate};
+
+impl ProcessOps for ProcessInfo {
+    fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn executable_path(&self) -> Option<String> {
+        executable_path(self.pid)
+    }
+
+    async fn command_line(&self) -> Option<String> {
+        command_line(self.pid)
+    }
+
+    async fn running_for(&self) -> Option<Duration> {
+        running_for(self.pid)
+    }
+
+    async fn application_parent(&self) -> Option<u32> {
+        let (parent_pid, parent_name) = self.parent.as_ref()?;
+        (*parent_name == self.name).then_some(*parent_pid)
+    }
+
+    async fn terminate(&self) -> Result<(), SwytError> {
+        terminate(self.pid)
+    }
+
+    async fn kill(&self) -> Result<(), SwytError> {
+        kill(self.pid)
+    }
+
+    async fn suspend(&self) -> Result<(), SwytError> {
+        suspend(self.pid)
+    }
+
+    async fn resume(&self) -> Result<(), SwytError> {
+        resume(self.pid)
+    }
+
+    async fn throttle(&self) -> Result<i32, SwytError> {
+        throttle(self.pid)
+    }
+
+    async fn set_priority(&self, priority: i32) -> Result<(), SwytError> {
+        set_priority(self.pid, priority)
+    }
+}
+
+/// Resumes the processes the daemon suspended and gives the ones it throttled their
+/// priority back, as when it stops. Returns how many processes were released.
+pub fn release_restraints(enforcement_state: &EnforcementState) -> usize {
+    complete(crate::enforce::release_restraints(
+        enforcement_state,
+        |pid| async move {
+            Some(ProcessInfo {
+                pid,
+                name: pid.to_string(),
+                parent: None,
+            })
+        },
+    ))
+}
+
+/// Runs a future of the native backend, whose operations are synchronous so it completes
+/// on its first poll.
+fn complete<T>(future: impl Future<Output = T>) -> T {
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("The native backend never waits"),
+    }
+}
+
+/// Enforces the rules on the running processes, reading them from the system synchronously,
+/// without heim.
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "scan", skip_all))]
+pub fn process_rules(
+    rules: &Rules,
+    configuration: &Configuration,
+    enforcement_state: &EnforcementState,
+) -> Result<ScanReport, SwytError> {
+    trace!("Process rules...");
+    enforce_rules(rules, configuration, enforcement_state, processes()?)
+}
+
+#[cfg(all(feature = "exec-events", target_os = "linux"))]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "scan_exec", skip(rules, configuration, enforcement_state))
+)]
+pub fn process_rules_for_pids(
+    rules: &Rules,
+    configuration: &Configuration,
+    enforcement_state: &EnforcementState,
+    pids: &[u32],
+) -> Result<ScanReport, SwytError> {
+    trace!("Process rules for pids {:?}...", pids);
+    let processes = pids.iter().filter_map(|&pid| linux::process(pid)).map(Ok);
+    enforce_rules(rules, configuration, enforcement_state, processes)
+}
+
+/// Enforces the rules on the listed processes, split between `scan_workers` threads. The
+/// conditions are probed once per scan for all of them.
+fn enforce_rules(
+    rules: &Rules,
+    configuration: &Configuration,
+    enforcement_state: &EnforcementState,
+    processes: impl IntoIterator<Item = Result<ProcessInfo, SwytError>>,
+) -> Result<ScanReport, SwytError> {
+    let scan = Scan::new(rules, configuration, enforcement_state);
+    let mut processes = processes.into_iter().collect::<Vec<_>>();
+    let workers = (configuration.scan_workers() as usize).clamp(1, processes.len().max(1));
+    let chunk_size = processes.len().div_ceil(workers);
+    let mut chunks = vec![];
+    while processes.len() > chunk_size {
+        chunks.push(processes.split_off(processes.len() - chunk_size));
+    }
+    chunks.push(processes);
+
+    let reports = thread::scope(|scope| {
+        let workers = chunks
+            .into_iter()
+            .map(|chunk| {
+                let scan = &scan;
+                scope.spawn(move || enforce_rules_on(scan, chunk))
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+            })
+            .collect::<Vec<_>>()
+    });
+    let mut report = ScanReport::default();
+    report.record_date_time(*scan.current_time().date_time());
+    for worker_report in reports {
+        report.merge(worker_report?);
+    }
+
+    record_scan(&report);
+    Ok(report)
+}
+
+fn enforce_rules_on(
+    scan: &Scan,
+    processes: Vec<Result<ProcessInfo, SwytError>>,
+) -> Result<ScanReport, SwytError> {
+    let mut report = ScanReport::default();
+    for process in processes {
+        match process {
+            Ok(process) => complete(scan.enforce(&process, &mut report)),
+            Err(err) => report.record_unreadable(scan.configuration().on_scan_error(), err)?,
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::ProcessInfo;
+    use crate::identity::{stat, ProcessIdentity};
+    use crate::SwytError;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+    use std::sync::{Mutex, PoisonError};
+
+    /// Length after which the kernel truncates `comm`.
+    const COMM_MAX_LENGTH: usize = 15;
+    /// Highest niceness, the lowest priority.
+    const LOWEST_PRIORITY: i32 = 19;
+
+    /// Full names of the processes whose `comm` is truncated, read from their command line
+    /// once. They are kept by identity so a process reusing the pid isn't given the name.
+    static FULL_NAMES: Mutex<BTreeMap<ProcessIdentity, String>> = Mutex::new(BTreeMap::new());
+
+    /// Lists the processes, the ones that couldn't be read being errors.
+    pub fn processes() -> Result<Vec<Result<ProcessInfo, SwytError>>, SwytError> {
+        let mut processes = vec![];
+        let mut listed = vec![];
+        let mut identities = BTreeSet::new();
+        for entry in fs::read_dir("/proc")? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    processes.push(Err(err.into()));
+                    continue;
+                }
+            };
+            let pid = match entry.file_name().to_str().map(str::parse::<u32>) {
+                Some(Ok(pid)) => pid,
+                _ => continue,
+            };
+
+            // The process may have exited since the directory was listed
+            if let Some((process, identity, parent_pid)) = process_with_identity(pid) {
+                listed.push((process, parent_pid));
+                identities.insert(identity);
+            }
+        }
+        let names: BTreeMap<u32, String> = listed
+            .iter()
+            .map(|(process, _)| (process.pid, process.name.clone()))
+            .collect();
+        for (mut process, parent_pid) in listed {
+            process.parent = names
+                .get(&parent_pid)
+                .map(|parent_name| (parent_pid, parent_name.clone()));
+            processes.push(Ok(process));
+        }
+
+        // The names of the processes that exited since the previous scan are forgotten
+        FULL_NAMES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|identity, _| identities.contains(identity));
+
+        Ok(processes)
+    }
+
+    #[cfg(feature = "exec-events")]
+    pub fn process(pid: u32) -> Option<ProcessInfo> {
+        let (mut process, _, parent_pid) = process_with_identity(pid)?;
+        process.parent =
+            process_with_identity(parent_pid).map(|(parent, _, _)| (parent_pid, parent.name));
+        Some(process)
+    }
+
+    /// Reads the process, returning it with its identity and the pid of its parent.
+    fn process_with_identity(pid: u32) -> Option<(ProcessInfo, ProcessIdentity, u32)> {
+        let (comm, parent_pid, start_time) = stat(pid)?;
+        let identity = ProcessIdentity::new(pid, Some(start_time));
+        let name = match comm.len() < COMM_MAX_LENGTH {
+            true => comm,
+            false => FULL_NAMES
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(identity)
+                .or_insert_with(|| full_name(pid, &comm))
+                .clone(),
+        };
+        let process = ProcessInfo {
+            pid,
+            name,
+            parent: None,
+        };
+        Some((process, identity, parent_pid))
+    }
+
+    /// Returns the name of the executable of the process when `comm` is a truncation of it.
+    fn full_name(pid: u32, comm: &str) -> String {
+        let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+        let executable_name = cmdline
+            .split(|&byte| byte == 0)
+            .next()
+            .and_then(|executable| std::str::from_utf8(executable).ok())
+            .and_then(|executable| Path::new(executable).file_name())
+            .and_then(|file_name| file_name.to_str());
+        match executable_name {
+            Some(executable_name) if executable_name.starts_with(comm) => {
+                executable_name.to_string()
+            }
+            _ => comm.to_string(),
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
+    pub fn kill(pid: u32) -> Result<(), SwytError> {
+        signal(pid, libc::SIGKILL)
+    }
+
+    pub fn terminate(pid: u32) -> Result<(), SwytError> {
+        signal(pid, libc::SIGTERM)
+    }
+
+    pub(super) fn suspend(pid: u32) -> Result<(), SwytError> {
+        signal(pid, libc::SIGSTOP)
+    }
+
+    pub(super) fn resume(pid: u32) -> Result<(), SwytError> {
+        signal(pid, libc::SIGCONT)
+    }
+
+    /// Gives the process the lowest priority, returning its niceness before.
+    pub(super) fn throttle(pid: u32) -> Result<i32, SwytError> {
+        // -1 is both a niceness and the error, told apart by errno
+        unsafe { *libc::__errno_location() = 0 };
+        let priority = unsafe { libc::getpriority(libc::PRIO_PROCESS, pid as libc::id_t) };
+        let error = io::Error::last_os_error();
+        if priority == -1 && error.raw_os_error() != Some(0) {
+            return Err(error.into());
+        }
+        set_priority(pid, LOWEST_PRIORITY)?;
+        Ok(priority)
+    }
+
+    /// Sets the niceness of the process, only root being able to lower it.
+    pub(super) fn set_priority(pid: u32, priority: i32) -> Result<(), SwytError> {
+        match unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, priority) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error().into()),
+        }
+    }
+
+    /// Returns the path of the executable of the process, which can't be read for the
+    /// processes of other users without privileges.
+    pub(super) fn executable_path(pid: u32) -> Option<String> {
+        let path = fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+        path.to_str().map(str::to_string)
+    }
+
+    /// Returns the command line of the process, its arguments separated by spaces.
+    pub(super) fn command_line(pid: u32) -> Option<String> {
+        let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+        let arguments: Vec<_> = cmdline
+            .split(|&byte| byte == 0)
+            .filter(|argument| !argument.is_empty())
+            .map(String::from_utf8_lossy)
+            .collect();
+        Some(arguments.join(" "))
+    }
+
+    fn signal(pid: u32, signal: libc::c_int) -> Result<(), SwytError> {
+        match unsafe { libc::kill(pid as libc::pid_t, signal) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error().into()),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::ProcessInfo;
+    use crate::SwytError;
+    use std::collections::BTreeMap;
+    use std::ffi::OsString;
+    use std::io;
+    use std::mem;
+    use std::os::windows::ffi::OsStringExt;
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::processthreadsapi::{
+        GetPriorityClass, OpenProcess, SetPriorityClass, TerminateProcess,
+    };
+    use winapi::um::tlhelp32::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+    use winapi::um::winbase::{QueryFullProcessImageNameW, IDLE_PRIORITY_CLASS};
+    use winapi::um::winnt::{
+        PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SET_INFORMATION, PROCESS_TERMINATE,
+    };
+
+    /// Longest path of an executable, long paths included.
+    const MAX_EXECUTABLE_PATH_LENGTH: usize = 32_768;
+
+    pub fn processes() -> Result<Vec<Result<ProcessInfo, SwytError>>, SwytError> {
+        let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(SwytError::ProcessFetchError);
+        }
+
+        let mut listed = vec![];
+        let mut entry: PROCESSENTRY32W = unsafe { mem::zeroed() };
+        entry.dwSize = mem::size_of::<PROCESSENTRY32W>() as u32;
+        let mut has_entry = unsafe { Process32FirstW(snapshot, &mut entry) } != 0;
+        while has_entry {
+            let name_length = entry
+                .szExeFile
+                .iter()
+                .position(|&character| character == 0)
+                .unwrap_or(entry.szExeFile.len());
+            let process = ProcessInfo {
+                pid: entry.th32ProcessID,
+                name: OsString::from_wide(&entry.szExeFile[..name_length])
+                    .to_string_lossy()
+                    .into_owned(),
+                parent: None,
+            };
+            listed.push((process, entry.th32ParentProcessID));
+            has_entry = unsafe { Process32NextW(snapshot, &mut entry) } != 0;
+        }
+
+        unsafe { CloseHandle(snapshot) };
+        let names: BTreeMap<u32, String> = listed
+            .iter()
+            .map(|(process, _)| (process.pid, process.name.clone()))
+            .collect();
+        let processes = listed
+            .into_iter()
+            .map(|(mut process, parent_pid)| {
+                process.parent = names
+                    .get(&parent_pid)
+                    .map(|parent_name| (parent_pid, parent_name.clone()));
+                Ok(process)
+            })
+            .collect();
+        Ok(processes)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
+    pub fn kill(pid: u32) -> Result<(), SwytError> {
+        unsafe {
+            let process = OpenProcess(PROCESS_TERMINATE, 0, pid);
+            if process.is_null() {
+                return Err(io::Error::last_os_error().into());
+            }
+
+            let terminated = TerminateProcess(process, 1) != 0;
+            let error = io::Error::last_os_error();
+            CloseHandle(process);
+            if terminated {
+                Ok(())
+            } else {
+                Err(error.into())
+            }
+        }
+    }
+
+    /// Asks the process to exit by closing its windows, there being no termination signal.
+    pub fn terminate(pid: u32) -> Result<(), SwytError> {
+        crate::enforce::close_windows(pid, "")
+    }
+
+    /// Suspending a process takes suspending each of its threads, it isn't done.
+    pub(super) fn suspend(_pid: u32) -> Result<(), SwytError> {
+        Err(SwytError::Unsupported("suspend processes"))
+    }
+
+    pub(super) fn resume(_pid: u32) -> Result<(), SwytError> {
+        Err(SwytError::Unsupported("resume processes"))
+    }
+
+    /// Gives the process the idle priority class, returning its priority class before.
+    pub(super) fn throttle(pid: u32) -> Result<i32, SwytError> {
+        unsafe {
+            let process = OpenProcess(
+                PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_SET_INFORMATION,
+                0,
+                pid,
+            );
+            if process.is_null() {
+                return Err(io::Error::last_os_error().into());
+            }
+
+            let priority_class = GetPriorityClass(process);
+            let throttled =
+                priority_class != 0 && SetPriorityClass(process, IDLE_PRIORITY_CLASS) != 0;
+            let error = io::Error::last_os_error();
+            CloseHandle(process);
+            if throttled {
+                Ok(priority_class as i32)
+            } else {
+                Err(error.into())
+            }
+        }
+    }
+
+    pub(super) fn set_priority(pid: u32, priority_class: i32) -> Result<(), SwytError> {
+        unsafe {
+            let process = OpenProcess(PROCESS_SET_INFORMATION, 0, pid);
+            if process.is_null() {
+                return Err(io::Error::last_os_error().into());
+            }
+
+            let set = SetPriorityClass(process, priority_class as u32) != 0;
+            let error = io::Error::last_os_error();
+            CloseHandle(process);
+            if set {
+                Ok(())
+            } else {
+                Err(error.into())
+            }
+        }
+    }
+
+    pub(super) fn executable_path(pid: u32) -> Option<String> {
+        unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if process.is_null() {
+                return None;
+            }
+
+            let mut path = vec![0; MAX_EXECUTABLE_PATH_LENGTH];
+            let mut length = path.len() as u32;
+            let queried = QueryFullProcessImageNameW(process, 0, path.as_mut_ptr(), &mut length);
+            CloseHandle(process);
+            (queried != 0).then(|| {
+                OsString::from_wide(&path[..length as usize])
+                    .to_string_lossy()
+                    .into_owned()
+            })
+        }
+    }
+
+    /// The command line of another process is only found in its memory, it isn't read.
+    pub(super) fn command_line(_pid: u32) -> Option<String> {
+        None
+    }
+}
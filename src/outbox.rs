@@ -0,0 +1,214 @@
+use crate::SwytError;
+use chrono::{Duration, NaiveDateTime};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const OUTBOX_FILE_NAME: &str = "outbox.jbb";
+const OUTBOX_DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+/// Reports kept at most, the oldest being dropped past it so an endpoint unreachable for
+/// long doesn't fill the disk.
+const MAX_QUEUED_REPORTS: usize = 10_000;
+const MIN_RETRY_DELAY_SECONDS: i64 = 30;
+const MAX_RETRY_DELAY_SECONDS: i64 = 60 * 60;
+
+/// Report waiting in the outbox to be delivered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedReport {
+    attempts: u32,
+    next_attempt: NaiveDateTime,
+    report: String,
+}
+
+impl QueuedReport {
+    /// Number of failed deliveries.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn next_attempt(&self) -> &NaiveDateTime {
+        &self.next_attempt
+    }
+
+    pub fn report(&self) -> &str {
+        &self.report
+    }
+}
+
+/// Queues the reports, one line each, to be delivered from `now`.
+pub fn queue_reports(
+    swyt_filepath: &Path,
+    reports: &[String],
+    now: NaiveDateTime,
+) -> Result<(), SwytError> {
+    if reports.is_empty() {
+        return Ok(());
+    }
+
+    let mut queued_reports = read_queued_reports(swyt_filepath)?;
+    queued_reports.extend(reports.iter().map(|report| QueuedReport {
+        attempts: 0,
+        next_attempt: now,
+        report: report.replace('\n', " "),
+    }));
+    let dropped = queued_reports.len().saturating_sub(MAX_QUEUED_REPORTS);
+    if dropped > 0 {
+        log::warn!(
+            "Dropped {} undelivered report(s), the outbox is full",
+            dropped
+        );
+    }
+    write_queued_reports(swyt_filepath, &queued_reports[dropped..])
+}
+
+/// Delivers the queued reports due at `now` in order, returning how many were delivered. A
+/// failure is taken for the endpoint being unreachable: the delivery stops there, to be
+/// retried after a delay doubling with each failure, up to an hour.
+pub fn deliver_reports(
+    swyt_filepath: &Path,
+    now: NaiveDateTime,
+    mut deliver: impl FnMut(&str) -> Result<(), SwytError>,
+) -> Result<usize, SwytError> {
+    let mut queued_reports = read_queued_reports(swyt_filepath)?;
+    // The reports are delivered in order, so they are due once the first one is
+    match queued_reports.first() {
+        Some(queued_report) if queued_report.next_attempt <= now => (),
+        _ => return Ok(0),
+    }
+
+    let mut delivered = 0;
+    for queued_report in &mut queued_reports {
+        if let Err(err) = deliver(&queued_report.report) {
+            queued_report.attempts += 1;
+            queued_report.next_attempt = now + retry_delay(queued_report.attempts);
+            log::debug!(
+                "Couldn't deliver a report, retrying at {}: {}",
+                queued_report.next_attempt,
+                err
+            );
+            break;
+        }
+        delivered += 1;
+    }
+    write_queued_reports(swyt_filepath, &queued_reports[delivered..])?;
+    Ok(delivered)
+}
+
+/// Reads the reports waiting to be delivered, the oldest first.
+pub fn read_queued_reports(swyt_filepath: &Path) -> Result<Vec<QueuedReport>, SwytError> {
+    let outbox_string = match fs::read_to_string(get_outbox_filepath(swyt_filepath)) {
+        Ok(outbox_string) => outbox_string,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+
+    outbox_string
+        .lines()
+        .map(|line| match line.splitn(3, ';').collect::<Vec<_>>()[..] {
+            [attempts, next_attempt, report] => Ok(QueuedReport {
+                attempts: u32::from_str(attempts).map_err(|_| SwytError::StateParseError)?,
+                next_attempt: NaiveDateTime::parse_from_str(next_attempt, OUTBOX_DATE_TIME_FORMAT)
+                    .map_err(|_| SwytError::StateParseError)?,
+                report: report.to_string(),
+            }),
+            _ => Err(SwytError::StateParseError),
+        })
+        .collect()
+}
+
+/// Removes the reports waiting to be delivered, returning how many there were.
+pub fn clear_queued_reports(swyt_filepath: &Path) -> Result<usize, SwytError> {
+    let cleared = read_queued_reports(swyt_filepath)?.len();
+    match fs::remove_file(get_outbox_filepath(swyt_filepath)) {
+        Ok(()) => Ok(cleared),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn write_queued_reports(
+    swyt_filepath: &Path,
+    queued_reports: &[QueuedReport],
+) -> Result<(), SwytError> {
+    let queued_reports: Vec<String> = queued_reports
+        .iter()
+        .map(|queued_report| {
+            format!(
+                "{};{};{}\n",
+                queued_report.attempts,
+                queued_report.next_attempt.format(OUTBOX_DATE_TIME_FORMAT),
+                queued_report.report
+            )
+        })
+        .collect();
+    fs::write(get_outbox_filepath(swyt_filepath), queued_reports.concat())?;
+    Ok(())
+}
+
+fn retry_delay(attempts: u32) -> Duration {
+    // The delay reaches the maximum well before the shift could overflow
+    let exponent = attempts.saturating_sub(1).min(16);
+    Duration::seconds((MIN_RETRY_DELAY_SECONDS << exponent).min(MAX_RETRY_DELAY_SECONDS))
+}
+
+pub(crate) fn get_outbox_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(OUTBOX_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn outbox_delivery_with_backoff() {
+        let swyt_filepath =
+            std::env::temp_dir().join(format!("swyt-outbox-{}", std::process::id()));
+        std::fs::create_dir_all(&swyt_filepath).unwrap();
+        let now = NaiveDate::from_ymd(2020, 6, 1).and_hms(18, 0, 0);
+        let reports = vec!["{\"pid\":1}".to_string(), "{\"pid\":2}".to_string()];
+        queue_reports(&swyt_filepath, &reports, now).unwrap();
+
+        let unreachable = |_: &str| Err(SwytError::ReportDeliveryError("offline".to_string()));
+        assert_eq!(
+            deliver_reports(&swyt_filepath, now, unreachable).unwrap(),
+            0
+        );
+        assert_eq!(
+            deliver_reports(&swyt_filepath, now, unreachable).unwrap(),
+            0
+        );
+        let queued_reports = read_queued_reports(&swyt_filepath).unwrap();
+        assert_eq!(queued_reports.len(), 2);
+        assert_eq!(queued_reports[0].attempts(), 1);
+        assert_eq!(
+            queued_reports[0].next_attempt(),
+            &(now + Duration::seconds(30))
+        );
+
+        // Delivered in order once the endpoint is back, a failure stopping the delivery
+        let retry = now + Duration::seconds(30);
+        let mut delivered = vec![];
+        let mut deliver_first = |report: &str| {
+            if !delivered.is_empty() {
+                return Err(SwytError::ReportDeliveryError("offline".to_string()));
+            }
+            delivered.push(report.to_string());
+            Ok(())
+        };
+        assert_eq!(
+            deliver_reports(&swyt_filepath, retry, &mut deliver_first).unwrap(),
+            1
+        );
+        assert_eq!(delivered, ["{\"pid\":1}"]);
+        let queued_reports = read_queued_reports(&swyt_filepath).unwrap();
+        assert_eq!(queued_reports[0].report(), "{\"pid\":2}");
+        assert_eq!(
+            queued_reports[0].next_attempt(),
+            &(retry + Duration::seconds(30))
+        );
+
+        std::fs::remove_dir_all(&swyt_filepath).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{Configuration, LowPowerMode};
+#[cfg(target_os = "linux")]
+use std::fs;
+
+/// Returns whether the daemon runs in low-power mode, `low_power=auto` enabling it while
+/// the system runs on battery.
+pub fn is_low_power(configuration: &Configuration) -> bool {
+    match configuration.low_power() {
+        LowPowerMode::On => true,
+        LowPowerMode::Off => false,
+        LowPowerMode::Auto => is_on_battery(),
+    }
+}
+
+/// Returns whether the system runs on battery, that is it has a battery and no power supply
+/// is online. The batteries of peripherals, such as wireless mice, are left out.
+#[cfg(target_os = "linux")]
+pub fn is_on_battery() -> bool {
+    let power_supplies = match fs::read_dir("/sys/class/power_supply") {
+        Ok(power_supplies) => power_supplies,
+        Err(_) => return false,
+    };
+
+    let mut has_battery = false;
+    for power_supply in power_supplies.flatten() {
+        let path = power_supply.path();
+        let read = |attribute: &str| {
+            fs::read_to_string(path.join(attribute)).map(|value| value.trim().to_string())
+        };
+        if read("scope").is_ok_and(|scope| scope == "Device") {
+            continue;
+        }
+
+        match read("type").as_deref() {
+            Ok("Battery") => has_battery = true,
+            Ok(_) if read("online").is_ok_and(|online| online == "1") => return false,
+            _ => (),
+        }
+    }
+
+    has_battery
+}
+
+#[cfg(all(windows, feature = "native"))]
+pub fn is_on_battery() -> bool {
+    use std::mem;
+    use winapi::um::winbase::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    const AC_LINE_OFFLINE: u8 = 0;
+
+    unsafe {
+        let mut power_status: SYSTEM_POWER_STATUS = mem::zeroed();
+        GetSystemPowerStatus(&mut power_status) != 0 && power_status.ACLineStatus == AC_LINE_OFFLINE
+    }
+}
+
+#[cfg(not(any(target_os = "linux", all(windows, feature = "native"))))]
+pub fn is_on_battery() -> bool {
+    false
+}
@@ -0,0 +1,164 @@
+#[cfg(windows)]
+use crate::enforce::close_windows;
+use crate::enforce::{ProcessOps, Scan};
+use crate::{record_scan, Configuration, EnforcementState, Rules, ScanReport, SwytError};
+use futures::StreamExt;
+use heim::units::time::second;
+use log::trace;
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Process listed by heim, with its name read.
+struct HeimProcess {
+    process: heim::process::Process,
+    pid: u32,
+    name: String,
+}
+
+fn to_swyt_error(error: heim::process::ProcessError) -> SwytError {
+    SwytError::IoError(io::Error::other(error.to_string()))
+}
+
+impl ProcessOps for HeimProcess {
+    fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn executable_path(&self) -> Option<String> {
+        let path = self.process.exe().await.ok()?;
+        path.to_str().map(str::to_string)
+    }
+
+    async fn command_line(&self) -> Option<String> {
+        let command = self.process.command().await.ok()?;
+        Some(command.to_os_string().to_string_lossy().into_owned())
+    }
+
+    async fn running_for(&self) -> Option<Duration> {
+        let create_time = self.process.create_time().await.ok()?;
+        let created = Duration::try_from_secs_f64(create_time.get::<second>()).ok()?;
+        SystemTime::now().duration_since(UNIX_EPOCH + created).ok()
+    }
+
+    /// The parent is read when the process is enforced, one killed before by the same scan
+    /// isn't found anymore.
+    async fn application_parent(&self) -> Option<u32> {
+        let parent = self.process.parent().await.ok()?;
+        (parent.name().await.ok()? == self.name).then(|| parent.pid() as u32)
+    }
+
+    #[cfg(not(windows))]
+    async fn terminate(&self) -> Result<(), SwytError> {
+        self.process.terminate().await.map_err(to_swyt_error)
+    }
+
+    /// heim kills the process on Windows, its windows are closed instead.
+    #[cfg(windows)]
+    async fn terminate(&self) -> Result<(), SwytError> {
+        close_windows(self.pid, "")
+    }
+
+    async fn kill(&self) -> Result<(), SwytError> {
+        self.process.kill().await.map_err(to_swyt_error)
+    }
+
+    async fn suspend(&self) -> Result<(), SwytError> {
+        self.process.suspend().await.map_err(to_swyt_error)
+    }
+
+    async fn resume(&self) -> Result<(), SwytError> {
+        self.process.resume().await.map_err(to_swyt_error)
+    }
+
+    /// heim can't read nor set the priority of the processes.
+    async fn throttle(&self) -> Result<i32, SwytError> {
+        Err(SwytError::Unsupported("throttle processes"))
+    }
+
+    async fn set_priority(&self, _priority: i32) -> Result<(), SwytError> {
+        Err(SwytError::Unsupported("set the priority of processes"))
+    }
+}
+
+/// Runs `process_rules_async` to completion on the current thread, so heim is still used and
+/// its futures are only blocked on. Enable the `native` feature for a scan that is
+/// synchronous throughout, without heim, on Linux and Windows.
+#[cfg(all(
+    feature = "sync",
+    not(all(feature = "native", any(target_os = "linux", windows)))
+))]
+pub fn process_rules(
+    rules: &Rules,
+    configuration: &Configuration,
+    enforcement_state: &EnforcementState,
+) -> Result<ScanReport, SwytError> {
+    futures::executor::block_on(process_rules_async(rules, configuration, enforcement_state))
+}
+
+/// Runs `release_restraints_async` to completion on the current thread.
+#[cfg(all(
+    feature = "sync",
+    not(all(feature = "native", any(target_os = "linux", windows)))
+))]
+pub fn release_restraints(enforcement_state: &EnforcementState) -> usize {
+    futures::executor::block_on(release_restraints_async(enforcement_state))
+}
+
+/// Resumes the processes the daemon suspended, as when it stops. Returns how many processes
+/// were released.
+pub async fn release_restraints_async(enforcement_state: &EnforcementState) -> usize {
+    crate::enforce::release_restraints(enforcement_state, |pid| async move {
+        let process = heim::process::get(pid as heim::process::Pid).await.ok()?;
+        let name = process.name().await.ok()?;
+        Some(HeimProcess { process, pid, name })
+    })
+    .await
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "scan", skip_all))]
+pub async fn process_rules_async(
+    rules: &Rules,
+    configuration: &Configuration,
+    enforcement_state: &EnforcementState,
+) -> Result<ScanReport, SwytError> {
+    trace!("Process rules...");
+    let scan = Scan::new(rules, configuration, enforcement_state);
+    let mut report = ScanReport::default();
+    report.record_date_time(*scan.current_time().date_time());
+    let mut processes = heim::process::processes();
+    while let Some(process_result) = processes.next().await {
+        let process = match process_result {
+            Ok(process) => process,
+            Err(_) => {
+                report.record_unreadable(
+                    configuration.on_scan_error(),
+                    SwytError::ProcessFetchError,
+                )?;
+                continue;
+            }
+        };
+        let name = match process.name().await {
+            Ok(name) => name,
+            Err(_) => {
+                report.record_unreadable(
+                    configuration.on_scan_error(),
+                    SwytError::ProcessFetchError,
+                )?;
+                continue;
+            }
+        };
+        let process = HeimProcess {
+            pid: process.pid() as u32,
+            process,
+            name,
+        };
+        scan.enforce(&process, &mut report).await;
+    }
+
+    record_scan(&report);
+    Ok(report)
+}
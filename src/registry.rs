@@ -0,0 +1,120 @@
+use crate::{parse_config, parse_rules, Configuration, Rules, SwytError};
+use std::ffi::OsStr;
+use std::io;
+use std::iter;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+use winapi::shared::minwindef::{DWORD, HKEY};
+use winapi::shared::winerror::{ERROR_FILE_NOT_FOUND, ERROR_NO_MORE_ITEMS, ERROR_SUCCESS};
+use winapi::um::winnt::{KEY_READ, REG_DWORD, REG_MULTI_SZ, REG_SZ};
+use winapi::um::winreg::{RegCloseKey, RegEnumValueW, RegOpenKeyExW, HKEY_LOCAL_MACHINE};
+
+const POLICY_KEY: &str = r"Software\Policies\Swyt";
+const RULES_VALUE_NAME: &str = "Rules";
+
+/// Longest value name the registry allows, in characters.
+const MAX_VALUE_NAME_LENGTH: usize = 16383;
+const MAX_VALUE_DATA_LENGTH: usize = 64 * 1024;
+
+/// Loads the policy deployed through Group Policy in `HKLM\Software\Policies\Swyt`. Each
+/// value is a configuration key, except `Rules`, a multi-string value holding one rule per
+/// string. Returns `None` when the key doesn't exist.
+pub fn load_registry_policy() -> Result<Option<(Configuration, Rules)>, SwytError> {
+    let policy_key_name: Vec<u16> = OsStr::new(POLICY_KEY)
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect();
+    let mut policy_key: HKEY = ptr::null_mut();
+    let status = unsafe {
+        RegOpenKeyExW(
+            HKEY_LOCAL_MACHINE,
+            policy_key_name.as_ptr(),
+            0,
+            KEY_READ,
+            &mut policy_key,
+        )
+    };
+    match status as DWORD {
+        ERROR_SUCCESS => (),
+        ERROR_FILE_NOT_FOUND => return Ok(None),
+        _ => return Err(io::Error::from_raw_os_error(status).into()),
+    }
+
+    let values = policy_values(policy_key);
+    unsafe { RegCloseKey(policy_key) };
+    let mut config_lines = vec![];
+    let mut rule_lines = vec![];
+    for (name, value) in values? {
+        match value {
+            RegistryValue::Lines(lines) if name == RULES_VALUE_NAME => rule_lines = lines,
+            RegistryValue::Lines(lines) => {
+                config_lines.push(format!("{}={}", name, lines.join("")))
+            }
+            RegistryValue::Number(number) => config_lines.push(format!("{}={}", name, number)),
+        }
+    }
+
+    Ok(Some((
+        parse_config(&config_lines.join("\n"))?,
+        parse_rules(&rule_lines.join("\n"))?,
+    )))
+}
+
+enum RegistryValue {
+    Lines(Vec<String>),
+    Number(u32),
+}
+
+fn policy_values(policy_key: HKEY) -> Result<Vec<(String, RegistryValue)>, SwytError> {
+    let mut values = vec![];
+    let mut name = vec![0u16; MAX_VALUE_NAME_LENGTH + 1];
+    let mut data = vec![0u8; MAX_VALUE_DATA_LENGTH];
+    for index in 0.. {
+        let mut name_length = name.len() as DWORD;
+        let mut data_length = data.len() as DWORD;
+        let mut value_type = 0;
+        let status = unsafe {
+            RegEnumValueW(
+                policy_key,
+                index,
+                name.as_mut_ptr(),
+                &mut name_length,
+                ptr::null_mut(),
+                &mut value_type,
+                data.as_mut_ptr(),
+                &mut data_length,
+            )
+        };
+        match status as DWORD {
+            ERROR_SUCCESS => (),
+            ERROR_NO_MORE_ITEMS => break,
+            _ => return Err(io::Error::from_raw_os_error(status).into()),
+        }
+
+        let name = String::from_utf16_lossy(&name[..name_length as usize]);
+        let data = &data[..data_length as usize];
+        let value = match value_type {
+            REG_SZ | REG_MULTI_SZ => RegistryValue::Lines(wide_strings(data)),
+            REG_DWORD if data.len() == 4 => {
+                RegistryValue::Number(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+            }
+            _ => continue,
+        };
+        values.push((name, value));
+    }
+
+    Ok(values)
+}
+
+/// Decodes the null separated UTF-16 strings of a string or multi-string value.
+fn wide_strings(data: &[u8]) -> Vec<String> {
+    let characters: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|character| u16::from_le_bytes([character[0], character[1]]))
+        .collect();
+    characters
+        .split(|&character| character == 0)
+        .filter(|string| !string.is_empty())
+        .map(String::from_utf16_lossy)
+        .collect()
+}
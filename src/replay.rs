@@ -0,0 +1,145 @@
+use crate::{evaluate_rules, Rules, ScanReport, SwytError, Verdict};
+use chrono::NaiveDateTime;
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+const TRACE_DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Processes seen by a recorded scan, with the verdicts of the rules enforced then.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanTrace {
+    date_time: NaiveDateTime,
+    verdicts: Vec<(String, Verdict)>,
+}
+
+/// Process treated differently by the replayed rules than by the recorded ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerdictChange {
+    pub process_name: String,
+    pub recorded: Verdict,
+    pub replayed: Verdict,
+}
+
+impl ScanTrace {
+    pub fn date_time(&self) -> &NaiveDateTime {
+        &self.date_time
+    }
+
+    pub fn verdicts(&self) -> &[(String, Verdict)] {
+        &self.verdicts
+    }
+
+    /// Evaluates the rules for the processes of the scan at the time it happened, returning
+    /// the processes they treat differently, once per name. The conditions of the system at
+    /// that time aren't recorded, so rules with conditions never apply.
+    pub fn replay(&self, rules: &Rules) -> Vec<VerdictChange> {
+        let mut process_names = BTreeSet::new();
+        self.verdicts
+            .iter()
+            .filter(|(process_name, _)| process_names.insert(process_name))
+            .filter_map(|(process_name, recorded)| {
+                let replayed = evaluate_rules(rules, process_name, &self.date_time);
+                (replayed != *recorded).then(|| VerdictChange {
+                    process_name: process_name.clone(),
+                    recorded: *recorded,
+                    replayed,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Appends the processes of a scan and their verdicts to the trace file.
+pub fn append_scan_trace(trace_filepath: &Path, report: &ScanReport) -> Result<(), SwytError> {
+    let date_time = match report.date_time() {
+        Some(date_time) => date_time,
+        None => return Ok(()),
+    };
+
+    let mut trace = format!("scan={}\n", date_time.format(TRACE_DATE_TIME_FORMAT));
+    for (process_name, verdict) in report.verdicts() {
+        // A line break in the name would start another entry
+        if !process_name.contains('\n') {
+            trace.push_str(&format!("{}={}\n", verdict, process_name));
+        }
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(trace_filepath)?
+        .write_all(trace.as_bytes())?;
+    Ok(())
+}
+
+pub fn read_scan_traces(trace_filepath: &Path) -> Result<Vec<ScanTrace>, SwytError> {
+    let mut scan_traces: Vec<ScanTrace> = vec![];
+    for line in fs::read_to_string(trace_filepath)?.lines() {
+        let (key, value) = line.split_once('=').ok_or(SwytError::StateParseError)?;
+        let verdict = match key {
+            "scan" => {
+                scan_traces.push(ScanTrace {
+                    date_time: NaiveDateTime::parse_from_str(value, TRACE_DATE_TIME_FORMAT)
+                        .map_err(|_| SwytError::StateParseError)?,
+                    verdicts: vec![],
+                });
+                continue;
+            }
+            "unmatched" => Verdict::Unmatched,
+            "allowed" => Verdict::Allowed,
+            "blocked" => Verdict::Blocked,
+            _ => return Err(SwytError::StateParseError),
+        };
+        scan_traces
+            .last_mut()
+            .ok_or(SwytError::StateParseError)?
+            .verdicts
+            .push((value.to_string(), verdict));
+    }
+
+    Ok(scan_traces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_rules;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn replay_scan_traces() {
+        let scan_traces = read_scan_traces(Path::new("./test_data/scan_trace.jbb")).unwrap();
+        assert_eq!(scan_traces.len(), 2);
+        assert_eq!(
+            scan_traces[0].date_time(),
+            &NaiveDate::from_ymd(2020, 6, 1).and_hms_milli(18, 0, 0, 250)
+        );
+        assert_eq!(scan_traces[0].verdicts().len(), 3);
+
+        let rules = parse_rules("game=19:00~23:00;MO\nchat=08:00~20:00;MO").unwrap();
+        assert_eq!(
+            scan_traces[0].replay(&rules),
+            vec![VerdictChange {
+                process_name: "game".to_string(),
+                recorded: Verdict::Allowed,
+                replayed: Verdict::Blocked,
+            }]
+        );
+        assert_eq!(
+            scan_traces[1].replay(&rules),
+            vec![
+                VerdictChange {
+                    process_name: "game".to_string(),
+                    recorded: Verdict::Blocked,
+                    replayed: Verdict::Allowed,
+                },
+                VerdictChange {
+                    process_name: "chat".to_string(),
+                    recorded: Verdict::Unmatched,
+                    replayed: Verdict::Blocked,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{ScanErrorBehavior, SwytError, Verdict};
+use chrono::NaiveDateTime;
+use std::fmt::{Display, Formatter};
+
+/// What a scan of the processes did, for logging and metrics.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScanReport {
+    date_time: Option<NaiveDateTime>,
+    verdicts: Vec<(String, Verdict)>,
+    scanned: u32,
+    blocked: u32,
+    killed: u32,
+    minimized: u32,
+    closed: u32,
+    restrained: u32,
+    deferred: u32,
+    unreadable: u32,
+    errors: Vec<ScanError>,
+}
+
+/// Failure to enforce a rule on a process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanError {
+    pub process_name: String,
+    pub pid: u32,
+    pub reason: String,
+}
+
+impl ScanReport {
+    /// Time the rules were evaluated at.
+    pub fn date_time(&self) -> Option<&NaiveDateTime> {
+        self.date_time.as_ref()
+    }
+
+    /// Processes evaluated, with the verdict of the rules for each of them.
+    pub fn verdicts(&self) -> &[(String, Verdict)] {
+        &self.verdicts
+    }
+
+    /// Number of processes evaluated.
+    pub fn scanned(&self) -> u32 {
+        self.scanned
+    }
+
+    /// Number of processes a rule blocked.
+    pub fn blocked(&self) -> u32 {
+        self.blocked
+    }
+
+    pub fn killed(&self) -> u32 {
+        self.killed
+    }
+
+    pub fn minimized(&self) -> u32 {
+        self.minimized
+    }
+
+    pub fn closed(&self) -> u32 {
+        self.closed
+    }
+
+    /// Number of blocked processes left running, notified, throttled or suspended.
+    pub fn restrained(&self) -> u32 {
+        self.restrained
+    }
+
+    /// Number of blocked processes whose killing was deferred.
+    pub fn deferred(&self) -> u32 {
+        self.deferred
+    }
+
+    /// Number of processes skipped because they couldn't be read, see `on_scan_error`.
+    pub fn unreadable(&self) -> u32 {
+        self.unreadable
+    }
+
+    pub fn errors(&self) -> &[ScanError] {
+        &self.errors
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn record_date_time(&mut self, date_time: NaiveDateTime) {
+        self.date_time = Some(date_time);
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn record_scanned(&mut self, process_name: &str, verdict: Verdict) {
+        self.scanned += 1;
+        self.verdicts.push((process_name.to_string(), verdict));
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn record_blocked(&mut self) {
+        self.blocked += 1;
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn record_killed(&mut self) {
+        self.killed += 1;
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn record_minimized(&mut self) {
+        self.minimized += 1;
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn record_closed(&mut self) {
+        self.closed += 1;
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn record_restrained(&mut self) {
+        self.restrained += 1;
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn record_deferred(&mut self) {
+        self.deferred += 1;
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    /// Counts a process that couldn't be read, unless the scan aborts on such errors, the
+    /// error is then returned.
+    pub(crate) fn record_unreadable(
+        &mut self,
+        on_scan_error: ScanErrorBehavior,
+        err: SwytError,
+    ) -> Result<(), SwytError> {
+        match on_scan_error {
+            ScanErrorBehavior::Skip => {
+                self.unreadable += 1;
+                Ok(())
+            }
+            ScanErrorBehavior::Abort => Err(err),
+        }
+    }
+
+    /// Adds the counts and errors of the report of another part of the scan.
+    #[cfg_attr(not(feature = "native"), allow(dead_code))]
+    pub(crate) fn merge(&mut self, other: ScanReport) {
+        self.date_time = self.date_time.or(other.date_time);
+        self.verdicts.extend(other.verdicts);
+        self.scanned += other.scanned;
+        self.blocked += other.blocked;
+        self.killed += other.killed;
+        self.minimized += other.minimized;
+        self.closed += other.closed;
+        self.restrained += other.restrained;
+        self.deferred += other.deferred;
+        self.unreadable += other.unreadable;
+        self.errors.extend(other.errors);
+    }
+
+    #[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+    pub(crate) fn record_error(&mut self, process_name: &str, pid: u32, reason: String) {
+        self.errors.push(ScanError {
+            process_name: process_name.to_string(),
+            pid,
+            reason,
+        });
+    }
+}
+
+impl Display for ScanReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Scanned {} process(es), {} blocked: {} killed, {} minimized, {} closed, {} restrained, {} deferred, {} unreadable, {} error(s)",
+            self.scanned,
+            self.blocked,
+            self.killed,
+            self.minimized,
+            self.closed,
+            self.restrained,
+            self.deferred,
+            self.unreadable,
+            self.errors.len()
+        )
+    }
+}
+
+impl Display for ScanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Couldn't enforce the rule on process {} ({}): {}",
+            self.process_name, self.pid, self.reason
+        )
+    }
+}
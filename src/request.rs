@@ -0,0 +1,418 @@
+use crate::{verify_admin_pin, Configuration, SwytError, ANY_PROCESS_NAME};
+use chrono::{Duration, NaiveDateTime};
+use std::fmt::{Display, Formatter};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const OVERRIDE_REQUESTS_FILE_NAME: &str = "override_requests.jbb";
+/// Decisions of the administrator, kept apart so a user allowed to request can't approve.
+const OVERRIDE_GRANTS_FILE_NAME: &str = "override_grants.jbb";
+const OVERRIDE_DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestStatus {
+    /// Waiting for the administrator to approve or deny it.
+    Pending,
+    /// The process is allowed until then.
+    Approved {
+        until: NaiveDateTime,
+    },
+    Denied,
+    /// Not decided on in time, or approved and over.
+    Expired,
+}
+
+impl Display for RequestStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestStatus::Pending => write!(f, "pending"),
+            RequestStatus::Approved { until } => {
+                write!(f, "approved until {}", until.format("%H:%M"))
+            }
+            RequestStatus::Denied => write!(f, "denied"),
+            RequestStatus::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+/// Request of the monitored user to allow a process for a while.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverrideRequest {
+    id: u32,
+    requested_at: NaiveDateTime,
+    minutes: u32,
+    process_name: String,
+    reason: String,
+    status: RequestStatus,
+    /// When the approval ends or ended, kept once it expires for the cooldown.
+    granted_until: Option<NaiveDateTime>,
+}
+
+impl OverrideRequest {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn requested_at(&self) -> &NaiveDateTime {
+        &self.requested_at
+    }
+
+    pub fn minutes(&self) -> u32 {
+        self.minutes
+    }
+
+    pub fn process_name(&self) -> &str {
+        &self.process_name
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// The status at the time the requests were read.
+    pub fn status(&self) -> RequestStatus {
+        self.status
+    }
+}
+
+impl Display for OverrideRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {} for {} minute(s), requested at {}",
+            self.id,
+            self.process_name,
+            self.minutes,
+            self.requested_at.format(OVERRIDE_DATE_TIME_FORMAT)
+        )?;
+        if !self.reason.is_empty() {
+            write!(f, " ({})", self.reason)?;
+        }
+        write!(f, ": {}", self.status)
+    }
+}
+
+/// Queues a request to allow the process for the given minutes, once the administrator
+/// approves it. The requests are limited to `override_requests_per_day` over the last 24
+/// hours, and one pending request per process. A process can't be requested again until
+/// `override_cooldown` hours after its last approval ended.
+pub fn request_override(
+    swyt_filepath: &Path,
+    configuration: &Configuration,
+    process_name: &str,
+    minutes: u32,
+    reason: &str,
+    now: NaiveDateTime,
+) -> Result<OverrideRequest, SwytError> {
+    let limit = configuration.override_requests_per_day() as usize;
+    if limit == 0 {
+        return Err(SwytError::OverrideRequestError(
+            "override requests are disabled".to_string(),
+        ));
+    }
+    if process_name.is_empty()
+        || process_name == ANY_PROCESS_NAME
+        || process_name.contains([';', '\n'])
+        || minutes == 0
+    {
+        return Err(SwytError::OverrideRequestError(
+            "expected a process name and a positive duration".to_string(),
+        ));
+    }
+
+    let requests = read_override_requests(swyt_filepath, configuration, now)?;
+    let cooldown_until = requests
+        .iter()
+        .filter(|request| request.process_name == process_name)
+        .filter_map(|request| request.granted_until)
+        .max()
+        .map(|granted_until| {
+            granted_until + Duration::hours(configuration.override_cooldown().into())
+        });
+    if let Some(cooldown_until) = cooldown_until.filter(|&cooldown_until| cooldown_until > now) {
+        return Err(SwytError::OverrideRequestError(format!(
+            "{} can't be requested again before {}",
+            process_name,
+            cooldown_until.format(OVERRIDE_DATE_TIME_FORMAT)
+        )));
+    }
+    let recent_requests = requests
+        .iter()
+        .filter(|request| request.requested_at > now - Duration::days(1))
+        .count();
+    if recent_requests >= limit {
+        return Err(SwytError::OverrideRequestError(format!(
+            "at most {} request(s) a day, try again later",
+            limit
+        )));
+    }
+    if requests.iter().any(|request| {
+        request.process_name == process_name && request.status == RequestStatus::Pending
+    }) {
+        return Err(SwytError::OverrideRequestError(format!(
+            "a request for {} is already pending",
+            process_name
+        )));
+    }
+
+    let request = OverrideRequest {
+        id: requests.iter().map(|request| request.id).max().unwrap_or(0) + 1,
+        requested_at: now,
+        minutes,
+        process_name: process_name.to_string(),
+        reason: reason.replace('\n', " "),
+        status: RequestStatus::Pending,
+        granted_until: None,
+    };
+    append_line(
+        &get_override_requests_filepath(swyt_filepath),
+        &format!(
+            "{};{};{};{};{}",
+            request.id,
+            request.requested_at.format(OVERRIDE_DATE_TIME_FORMAT),
+            request.minutes,
+            request.process_name,
+            request.reason
+        ),
+    )?;
+    Ok(request)
+}
+
+/// Approves or denies the pending request, an approved one allowing its process from now.
+/// Approving it takes the admin PIN, for the user requesting it not to approve it alone.
+pub fn decide_override_request(
+    swyt_filepath: &Path,
+    configuration: &Configuration,
+    id: u32,
+    approved: bool,
+    admin_pin: Option<&str>,
+    now: NaiveDateTime,
+) -> Result<OverrideRequest, SwytError> {
+    let mut request = read_override_requests(swyt_filepath, configuration, now)?
+        .into_iter()
+        .find(|request| request.id == id)
+        .ok_or_else(|| SwytError::OverrideRequestError(format!("no request #{}", id)))?;
+    if request.status != RequestStatus::Pending {
+        return Err(SwytError::OverrideRequestError(format!(
+            "request #{} is {}",
+            id, request.status
+        )));
+    }
+    if approved {
+        verify_admin_pin(swyt_filepath, admin_pin.unwrap_or_default())?;
+    }
+
+    append_line(
+        &get_override_grants_filepath(swyt_filepath),
+        &format!(
+            "{};{};{};{};{}",
+            id,
+            now.format(OVERRIDE_DATE_TIME_FORMAT),
+            if approved { "approved" } else { "denied" },
+            request.minutes,
+            request.process_name
+        ),
+    )?;
+    request.granted_until = approved.then(|| now + Duration::minutes(request.minutes.into()));
+    request.status = match request.granted_until {
+        Some(until) => RequestStatus::Approved { until },
+        None => RequestStatus::Denied,
+    };
+    Ok(request)
+}
+
+/// Reads the override requests with their status at `now`, the oldest first. A request
+/// pending for longer than `override_request_expiry` minutes expires.
+pub fn read_override_requests(
+    swyt_filepath: &Path,
+    configuration: &Configuration,
+    now: NaiveDateTime,
+) -> Result<Vec<OverrideRequest>, SwytError> {
+    let parse_date_time = |date_time: &str| {
+        NaiveDateTime::parse_from_str(date_time, OVERRIDE_DATE_TIME_FORMAT)
+            .map_err(|_| SwytError::StateParseError)
+    };
+    let parse_number = |number: &str| u32::from_str(number).map_err(|_| SwytError::StateParseError);
+
+    // The decisions repeat what was approved, the requests being writable by the user
+    let grants = read_lines(&get_override_grants_filepath(swyt_filepath))?;
+    let mut decisions = vec![];
+    for line in grants.lines() {
+        match line.splitn(5, ';').collect::<Vec<_>>()[..] {
+            [id, decided_at, decision, minutes, process_name] => decisions.push((
+                parse_number(id)?,
+                parse_date_time(decided_at)?,
+                decision == "approved",
+                parse_number(minutes)?,
+                process_name,
+            )),
+            _ => return Err(SwytError::StateParseError),
+        }
+    }
+
+    let expiry = Duration::minutes(configuration.override_request_expiry().into());
+    let mut requests = vec![];
+    for line in read_lines(&get_override_requests_filepath(swyt_filepath))?.lines() {
+        let (id, requested_at, minutes, process_name, reason) =
+            match line.splitn(5, ';').collect::<Vec<_>>()[..] {
+                [id, requested_at, minutes, process_name, reason] => (
+                    parse_number(id)?,
+                    parse_date_time(requested_at)?,
+                    parse_number(minutes)?,
+                    process_name,
+                    reason,
+                ),
+                _ => return Err(SwytError::StateParseError),
+            };
+        // Only the first decision counts
+        let decision = decisions
+            .iter()
+            .find(|(decision_id, ..)| *decision_id == id);
+        let (minutes, process_name, granted_until, status) = match decision {
+            Some(&(_, decided_at, true, minutes, process_name)) => {
+                let until = decided_at + Duration::minutes(minutes.into());
+                let status = match until > now {
+                    true => RequestStatus::Approved { until },
+                    false => RequestStatus::Expired,
+                };
+                (minutes, process_name, Some(until), status)
+            }
+            Some(&(_, _, false, minutes, process_name)) => {
+                (minutes, process_name, None, RequestStatus::Denied)
+            }
+            None if requested_at + expiry > now => {
+                (minutes, process_name, None, RequestStatus::Pending)
+            }
+            None => (minutes, process_name, None, RequestStatus::Expired),
+        };
+        requests.push(OverrideRequest {
+            id,
+            requested_at,
+            minutes,
+            process_name: process_name.to_string(),
+            reason: reason.to_string(),
+            status,
+            granted_until,
+        });
+    }
+
+    Ok(requests)
+}
+
+fn read_lines(filepath: &Path) -> Result<String, SwytError> {
+    match fs::read_to_string(filepath) {
+        Ok(lines) => Ok(lines),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn append_line(filepath: &Path, line: &str) -> Result<(), SwytError> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(filepath)?
+        .write_all(format!("{}\n", line).as_bytes())?;
+    Ok(())
+}
+
+pub(crate) fn get_override_requests_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(OVERRIDE_REQUESTS_FILE_NAME)
+}
+
+pub(crate) fn get_override_grants_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(OVERRIDE_GRANTS_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_rules, load_config, parse_rules, Verdict};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn override_requests() {
+        let swyt_filepath = Path::new("./test_data/override_requests");
+        let config = load_config(swyt_filepath).unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let requests =
+            read_override_requests(swyt_filepath, &config, monday.and_hms(18, 0, 0)).unwrap();
+        let statuses: Vec<RequestStatus> = requests.iter().map(|r| r.status()).collect();
+        assert_eq!(
+            statuses,
+            [
+                RequestStatus::Approved {
+                    until: monday.and_hms(18, 5, 0)
+                },
+                RequestStatus::Denied,
+                RequestStatus::Expired,
+                RequestStatus::Pending
+            ]
+        );
+        // What was approved holds, not what the request says since
+        assert_eq!(requests[0].process_name(), "game");
+        assert_eq!(requests[0].minutes(), 60);
+        assert_eq!(
+            requests[3].to_string(),
+            "#4 game for 20 minute(s), requested at 2020-06-01T17:50:00: pending"
+        );
+
+        // Four requests over the last 24 hours, the limit being two
+        assert!(matches!(
+            request_override(
+                swyt_filepath,
+                &config,
+                "discord",
+                30,
+                "",
+                monday.and_hms(19, 0, 0)
+            ),
+            Err(SwytError::OverrideRequestError(_))
+        ));
+        // The requests are older than a day, but the approval of game ended 30 hours ago
+        assert!(matches!(
+            request_override(
+                swyt_filepath,
+                &config,
+                "game",
+                30,
+                "",
+                NaiveDate::from_ymd(2020, 6, 2).and_hms(18, 0, 0)
+            ),
+            Err(SwytError::OverrideRequestError(reason)) if reason.contains("2020-06-03T00:05:00")
+        ));
+        assert!(matches!(
+            decide_override_request(
+                swyt_filepath,
+                &config,
+                2,
+                true,
+                None,
+                monday.and_hms(18, 0, 0)
+            ),
+            Err(SwytError::OverrideRequestError(_))
+        ));
+        // Approving takes the admin PIN, which isn't set
+        assert!(matches!(
+            decide_override_request(
+                swyt_filepath,
+                &config,
+                4,
+                true,
+                Some("1234"),
+                monday.and_hms(18, 0, 0)
+            ),
+            Err(SwytError::AdminPinError(_) | SwytError::Unsupported(_))
+        ));
+
+        let rules = parse_rules("game=18:00~20:00;MO")
+            .unwrap()
+            .with_grants(&["game".to_string()]);
+        assert_eq!(
+            evaluate_rules(&rules, "game", &monday.and_hms(10, 30, 0)),
+            Verdict::Allowed
+        );
+    }
+}
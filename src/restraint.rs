@@ -0,0 +1,199 @@
+use crate::identity::ProcessIdentity;
+use crate::{Action, EnforcementState, SwytError};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::PoisonError;
+
+const RESTRAINT_JOURNAL_FILE_NAME: &str = "restraints.jbb";
+
+/// What the `notify`, `throttle` and `suspend` actions did to a process left running, each
+/// being done once. The throttling and the suspension are undone once its rule allows it again.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Restraint {
+    pub(crate) notified: bool,
+    /// Priority the process had before being throttled.
+    pub(crate) priority: Option<i32>,
+    pub(crate) suspended: bool,
+}
+
+impl Restraint {
+    /// Whether the action was already taken on the process.
+    pub(crate) fn includes(&self, action: &Action) -> bool {
+        match action {
+            Action::Notify => self.notified,
+            Action::Throttle => self.priority.is_some(),
+            Action::Suspend => self.suspended,
+            _ => false,
+        }
+    }
+}
+
+/// Returns the restraint of the process, `None` when it isn't restrained.
+pub(crate) fn restraint(enforcement_state: &EnforcementState, pid: u32) -> Option<Restraint> {
+    let mut restraints = enforcement_state
+        .restraints
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let &(identity, restraint) = restraints.get(&pid)?;
+    // The pid may have been reused by another process since
+    if identity != ProcessIdentity::of(pid) {
+        restraints.remove(&pid);
+        return None;
+    }
+    Some(restraint)
+}
+
+/// Records what was done to the process, what was done to it before included.
+pub(crate) fn restrain(enforcement_state: &EnforcementState, pid: u32, restraint: Restraint) {
+    enforcement_state
+        .restraints
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(pid, (ProcessIdentity::of(pid), restraint));
+}
+
+/// Forgets the restraint of the process, once undone or the process killed.
+pub(crate) fn release(enforcement_state: &EnforcementState, pid: u32) {
+    enforcement_state
+        .restraints
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&pid);
+}
+
+/// Writes the suspensions and the throttlings of the state to the journal, for a daemon
+/// stopped before undoing them, as when killed, to have them undone when it starts again.
+/// The journal is removed when there is nothing to undo.
+pub fn write_restraint_journal(
+    swyt_filepath: &Path,
+    enforcement_state: &EnforcementState,
+) -> Result<(), SwytError> {
+    let entries: Vec<String> = enforcement_state
+        .restraints
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .values()
+        .filter(|(_, restraint)| restraint.suspended || restraint.priority.is_some())
+        .map(|(identity, restraint)| {
+            format!(
+                "{};{};{};{}\n",
+                identity.pid(),
+                optional(identity.start_time()),
+                optional(restraint.priority),
+                restraint.suspended
+            )
+        })
+        .collect();
+    let journal_filepath = get_restraint_journal_filepath(swyt_filepath);
+    if entries.is_empty() {
+        return match fs::remove_file(journal_filepath) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        };
+    }
+
+    fs::write(journal_filepath, entries.concat())?;
+    Ok(())
+}
+
+/// Restores the restraints of the journal into the state, those of the processes that
+/// exited since, their pid being reused or not, being left out.
+pub fn restore_restraints(
+    enforcement_state: &EnforcementState,
+    swyt_filepath: &Path,
+) -> Result<(), SwytError> {
+    let journal = match fs::read_to_string(get_restraint_journal_filepath(swyt_filepath)) {
+        Ok(journal) => journal,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut restraints = enforcement_state
+        .restraints
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    for line in journal.lines() {
+        let (identity, restraint) = match line.split(';').collect::<Vec<_>>()[..] {
+            [pid, start_time, priority, suspended] => (
+                ProcessIdentity::new(parse(pid)?, parse_optional(start_time)?),
+                Restraint {
+                    notified: false,
+                    priority: parse_optional(priority)?,
+                    suspended: parse(suspended)?,
+                },
+            ),
+            _ => return Err(SwytError::StateParseError),
+        };
+        if identity == ProcessIdentity::of(identity.pid()) {
+            restraints.insert(identity.pid(), (identity, restraint));
+        }
+    }
+
+    Ok(())
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T, SwytError> {
+    value.parse().map_err(|_| SwytError::StateParseError)
+}
+
+fn parse_optional<T: FromStr>(value: &str) -> Result<Option<T>, SwytError> {
+    match value {
+        "" => Ok(None),
+        value => parse(value).map(Some),
+    }
+}
+
+pub(crate) fn get_restraint_journal_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(RESTRAINT_JOURNAL_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restraint_journal() {
+        let swyt_filepath =
+            std::env::temp_dir().join(format!("swyt-restraints-{}", std::process::id()));
+        fs::create_dir_all(&swyt_filepath).unwrap();
+        let pid = std::process::id();
+        let enforcement_state = EnforcementState::new();
+        restrain(
+            &enforcement_state,
+            pid,
+            Restraint {
+                notified: true,
+                priority: Some(5),
+                suspended: true,
+            },
+        );
+        write_restraint_journal(&swyt_filepath, &enforcement_state).unwrap();
+
+        let restored_state = EnforcementState::new();
+        restore_restraints(&restored_state, &swyt_filepath).unwrap();
+        assert_eq!(
+            restraint(&restored_state, pid),
+            Some(Restraint {
+                notified: false,
+                priority: Some(5),
+                suspended: true,
+            })
+        );
+
+        // Nothing is left to undo once the process is released
+        release(&enforcement_state, pid);
+        write_restraint_journal(&swyt_filepath, &enforcement_state).unwrap();
+        assert!(!get_restraint_journal_filepath(&swyt_filepath).exists());
+        let restored_state = EnforcementState::new();
+        restore_restraints(&restored_state, &swyt_filepath).unwrap();
+        assert_eq!(restraint(&restored_state, pid), None);
+
+        fs::remove_dir_all(&swyt_filepath).unwrap();
+    }
+}
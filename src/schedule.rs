@@ -0,0 +1,274 @@
+use crate::semantics::{
+    merge_periods, next_boundary, period_contains, period_contains_repeated, repeated_times,
+    second_of_week, subtract_periods, RepeatedPass, SECONDS_PER_DAY, SECONDS_PER_WEEK,
+};
+use crate::{Period, RepeatedTimePolicy};
+#[cfg(any(feature = "native", feature = "process"))]
+use chrono::Local;
+#[cfg(feature = "timezone")]
+use chrono::Utc;
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, NaiveTime, TimeZone, Timelike, Weekday};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[derive(Debug, Clone, Default)]
+struct DaySchedule {
+    begin_times: Vec<NaiveTime>,
+    end_times: Vec<NaiveTime>,
+    max_end_times: Vec<NaiveTime>,
+}
+
+impl DaySchedule {
+    fn new(mut periods: Vec<(NaiveTime, NaiveTime)>) -> Self {
+        periods.sort();
+        let begin_times = periods.iter().map(|&(begin_time, _)| begin_time).collect();
+        let end_times = periods.iter().map(|&(_, end_time)| end_time).collect();
+        let max_end_times = periods
+            .iter()
+            .scan(
+                None,
+                |max_end_time: &mut Option<NaiveTime>, &(_, end_time)| {
+                    let max = max_end_time.map_or(end_time, |max| max.max(end_time));
+                    *max_end_time = Some(max);
+                    Some(max)
+                },
+            )
+            .collect();
+
+        DaySchedule {
+            begin_times,
+            end_times,
+            max_end_times,
+        }
+    }
+
+    /// Returns whether the time is allowed, and whether it is for the whole minute. The
+    /// periods begin at the first second of a minute, so only the one ending in the minute
+    /// allows part of it.
+    fn contains(&self, time: NaiveTime) -> (bool, bool) {
+        let started_periods = self
+            .begin_times
+            .partition_point(|&begin_time| begin_time <= time);
+        match started_periods {
+            0 => (false, true),
+            _ => {
+                // The periods begun by then allow it as one, until the latest of their ends
+                let begin_time = self.begin_times[started_periods - 1];
+                let max_end_time = self.max_end_times[started_periods - 1];
+                let ends_in_minute =
+                    max_end_time.hour() == time.hour() && max_end_time.minute() == time.minute();
+                (
+                    period_contains(begin_time, max_end_time, time),
+                    !ends_in_minute,
+                )
+            }
+        }
+    }
+}
+
+/// Whether the schedule allows a minute of the week, the last one it was asked for, packed as
+/// `(minute + 1) << 1 | allowed` so it can be shared by the threads evaluating the rules.
+#[derive(Debug, Default)]
+struct MinuteCache(AtomicU32);
+
+impl MinuteCache {
+    fn get(&self, minute_of_week: u32) -> Option<bool> {
+        let entry = self.0.load(Ordering::Relaxed);
+        (entry >> 1 == minute_of_week + 1).then_some(entry & 1 == 1)
+    }
+
+    fn set(&self, minute_of_week: u32, allowed: bool) {
+        self.0.store(
+            (minute_of_week + 1) << 1 | u32::from(allowed),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+impl Clone for MinuteCache {
+    fn clone(&self) -> Self {
+        MinuteCache(AtomicU32::new(self.0.load(Ordering::Relaxed)))
+    }
+}
+
+/// Local time the rules are evaluated at. When the clocks fell back and the time is repeated,
+/// the pass it is in tells the occurrences of the periods apart.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EvaluationTime {
+    date_time: NaiveDateTime,
+    /// The instant, to evaluate the rules in other time zones, unknown for a time of the day.
+    #[cfg(feature = "timezone")]
+    instant: Option<DateTime<Utc>>,
+    policy: RepeatedTimePolicy,
+    repeated: Option<(RepeatedPass, (NaiveTime, NaiveTime))>,
+}
+
+impl EvaluationTime {
+    /// Time of the day, taken as is even if the clocks fell back then, in any time zone.
+    pub(crate) fn new(date_time: NaiveDateTime) -> Self {
+        EvaluationTime {
+            date_time,
+            #[cfg(feature = "timezone")]
+            instant: None,
+            policy: RepeatedTimePolicy::Both,
+            repeated: None,
+        }
+    }
+
+    pub(crate) fn at<Tz: TimeZone>(date_time: &DateTime<Tz>, policy: RepeatedTimePolicy) -> Self {
+        EvaluationTime {
+            date_time: date_time.naive_local(),
+            #[cfg(feature = "timezone")]
+            instant: Some(date_time.with_timezone(&Utc)),
+            policy,
+            repeated: repeated_times(date_time),
+        }
+    }
+
+    #[cfg(any(feature = "native", feature = "process"))]
+    pub(crate) fn now(policy: RepeatedTimePolicy) -> Self {
+        EvaluationTime::at(&Local::now(), policy)
+    }
+
+    /// Returns the same instant in the time zone, or the same time of the day when the instant
+    /// is unknown.
+    #[cfg(feature = "timezone")]
+    pub(crate) fn in_timezone(&self, timezone: &chrono_tz::Tz) -> Self {
+        match &self.instant {
+            Some(instant) => EvaluationTime::at(&instant.with_timezone(timezone), self.policy),
+            None => *self,
+        }
+    }
+
+    pub(crate) fn date_time(&self) -> &NaiveDateTime {
+        &self.date_time
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WeekSchedule {
+    days: [DaySchedule; 7],
+    /// Saves looking up the periods for every process scanned in the same minute.
+    minute_cache: MinuteCache,
+}
+
+impl WeekSchedule {
+    /// Schedule of the periods, without the times of the excepted periods.
+    pub(crate) fn new(periods: &[Period], excepted_periods: &[Period]) -> Self {
+        let days_periods = |periods: &[Period]| {
+            let mut days_periods: [Vec<(NaiveTime, NaiveTime)>; 7] = Default::default();
+            for period in periods {
+                for day in &period.days_of_week {
+                    days_periods[day.num_days_from_monday() as usize]
+                        .push((period.begin_time, period.end_time));
+                }
+            }
+            days_periods
+        };
+        let days_excepted_periods = days_periods(excepted_periods);
+
+        let mut schedule = WeekSchedule::default();
+        for (day, day_periods) in days_periods(periods).iter().enumerate() {
+            schedule.days[day] = match days_excepted_periods[day].is_empty() {
+                true => DaySchedule::new(day_periods.clone()),
+                false => {
+                    DaySchedule::new(subtract_periods(day_periods, &days_excepted_periods[day]))
+                }
+            };
+        }
+        schedule
+    }
+
+    pub(crate) fn contains(&self, time: &EvaluationTime) -> bool {
+        let date_time = &time.date_time;
+        if let Some((pass, repeated_times)) = time.repeated {
+            let schedule = &self.days[date_time.weekday().num_days_from_monday() as usize];
+            return schedule.begin_times.iter().zip(&schedule.end_times).any(
+                |(&begin_time, &end_time)| {
+                    period_contains_repeated(
+                        begin_time,
+                        end_time,
+                        date_time.time(),
+                        repeated_times,
+                        pass,
+                        time.policy,
+                    )
+                },
+            );
+        }
+
+        let minute_of_week = second_of_week(date_time) / 60;
+        if let Some(allowed) = self.minute_cache.get(minute_of_week) {
+            return allowed;
+        }
+
+        let day = date_time.weekday().num_days_from_monday() as usize;
+        let (allowed, whole_minute) = self.days[day].contains(date_time.time());
+        if whole_minute {
+            self.minute_cache.set(minute_of_week, allowed);
+        }
+        allowed
+    }
+
+    /// Returns the allowed times of the day, overlapping periods being merged.
+    pub(crate) fn day_periods(&self, weekday: Weekday) -> Vec<(NaiveTime, NaiveTime)> {
+        let schedule = &self.days[weekday.num_days_from_monday() as usize];
+        let day_periods: Vec<(NaiveTime, NaiveTime)> = schedule
+            .begin_times
+            .iter()
+            .copied()
+            .zip(schedule.end_times.iter().copied())
+            .collect();
+        merge_periods(&day_periods)
+    }
+
+    /// Returns the end of the last period before the given time, within the past week.
+    pub(crate) fn last_allowed(&self, date_time: &NaiveDateTime) -> Option<NaiveDateTime> {
+        (0..=7).find_map(|days_ago| {
+            let date = date_time.date() - Duration::days(days_ago);
+            self.days[date.weekday().num_days_from_monday() as usize]
+                .end_times
+                .iter()
+                .filter(|&&end_time| days_ago > 0 || end_time < date_time.time())
+                .max()
+                .map(|&end_time| date.and_time(end_time))
+        })
+    }
+
+    /// Seconds of the week at which the allowed state can change, a period ending at
+    /// `end_time` stops allowing one second later.
+    pub(crate) fn boundaries(&self) -> impl Iterator<Item = u32> + '_ {
+        self.days.iter().enumerate().flat_map(|(day, schedule)| {
+            let day_offset = day as u32 * SECONDS_PER_DAY;
+            let begin_boundaries = schedule
+                .begin_times
+                .iter()
+                .map(|begin_time| begin_time.num_seconds_from_midnight());
+            let end_boundaries = schedule
+                .end_times
+                .iter()
+                .map(|end_time| end_time.num_seconds_from_midnight() + 1);
+            begin_boundaries
+                .chain(end_boundaries)
+                .map(move |second_of_day| (day_offset + second_of_day) % SECONDS_PER_WEEK)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Scheduler {
+    boundaries: Vec<u32>,
+}
+
+impl Scheduler {
+    pub(crate) fn insert(&mut self, schedule: &WeekSchedule) {
+        for boundary in schedule.boundaries() {
+            if let Err(index) = self.boundaries.binary_search(&boundary) {
+                self.boundaries.insert(index, boundary);
+            }
+        }
+    }
+
+    pub(crate) fn next_boundary(&self, date_time: &NaiveDateTime) -> Option<NaiveDateTime> {
+        next_boundary(&self.boundaries, date_time)
+    }
+}
@@ -0,0 +1,265 @@
+use crate::RepeatedTimePolicy;
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDateTime, NaiveTime, Offset, TimeZone, Timelike,
+};
+
+pub const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+pub const SECONDS_PER_WEEK: u32 = 7 * SECONDS_PER_DAY;
+
+/// Returns whether the period from `begin_time` to `end_time` allows the time, both ends
+/// included. A period ending before it begins allows nothing, it doesn't span midnight.
+pub fn period_contains(begin_time: NaiveTime, end_time: NaiveTime, time: NaiveTime) -> bool {
+    begin_time <= time && time <= end_time
+}
+
+/// Pass of the local times repeated when the clocks fall back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RepeatedPass {
+    First,
+    Second,
+}
+
+/// Returns whether the period allows the time, which is repeated because the clocks fall back
+/// that day and is in the given pass of the `repeated_times`. The ends of the period that are
+/// repeated happen in the first pass with `Earliest`, in the second pass with `Latest`, or
+/// in both with `Both`, the period then being considered on each pass like on other days.
+pub fn period_contains_repeated(
+    begin_time: NaiveTime,
+    end_time: NaiveTime,
+    time: NaiveTime,
+    repeated_times: (NaiveTime, NaiveTime),
+    pass: RepeatedPass,
+    policy: RepeatedTimePolicy,
+) -> bool {
+    let end_pass = match policy {
+        RepeatedTimePolicy::Both => return period_contains(begin_time, end_time, time),
+        RepeatedTimePolicy::Earliest => RepeatedPass::First,
+        RepeatedTimePolicy::Latest => RepeatedPass::Second,
+    };
+    // Orders the times of the day as they happen, the times before the repeated ones first
+    let instant = |time_of_day: NaiveTime, pass: RepeatedPass| {
+        let (first_repeated_time, last_repeated_time) = repeated_times;
+        if time_of_day < first_repeated_time {
+            (0, time_of_day)
+        } else if time_of_day <= last_repeated_time {
+            (1 + pass as u8, time_of_day)
+        } else {
+            (3, time_of_day)
+        }
+    };
+
+    instant(begin_time, end_pass) <= instant(time, pass)
+        && instant(time, pass) <= instant(end_time, end_pass)
+}
+
+/// Returns the pass the time is in when it is repeated because the clocks fell back, with the
+/// first and last repeated times of the day. The clocks are assumed to change on a minute, as
+/// they do in the time zone database.
+pub fn repeated_times<Tz: TimeZone>(
+    date_time: &DateTime<Tz>,
+) -> Option<(RepeatedPass, (NaiveTime, NaiveTime))> {
+    let timezone = date_time.timezone();
+    let local_date_time = date_time.naive_local();
+    let first_occurrence = match timezone.from_local_datetime(&local_date_time) {
+        LocalResult::Ambiguous(first_occurrence, _) => first_occurrence,
+        _ => return None,
+    };
+    let pass = match date_time.offset().fix() == first_occurrence.offset().fix() {
+        true => RepeatedPass::First,
+        false => RepeatedPass::Second,
+    };
+
+    let date = local_date_time.date();
+    let is_repeated = |minute: &u32| {
+        matches!(
+            timezone.from_local_datetime(&date.and_hms(minute / 60, minute % 60, 0)),
+            LocalResult::Ambiguous(..)
+        )
+    };
+    let current_minute = local_date_time.hour() * 60 + local_date_time.minute();
+    let first_minute = (0..=current_minute)
+        .rev()
+        .take_while(is_repeated)
+        .last()
+        .unwrap_or(current_minute);
+    let last_minute = (current_minute..SECONDS_PER_DAY / 60)
+        .take_while(is_repeated)
+        .last()
+        .unwrap_or(current_minute);
+    Some((
+        pass,
+        (
+            NaiveTime::from_hms(first_minute / 60, first_minute % 60, 0),
+            NaiveTime::from_hms(last_minute / 60, last_minute % 60, 59),
+        ),
+    ))
+}
+
+/// Returns the times allowed by the periods, sorted and with the overlapping or adjacent ones
+/// merged.
+pub fn merge_periods(periods: &[(NaiveTime, NaiveTime)]) -> Vec<(NaiveTime, NaiveTime)> {
+    let mut periods = periods.to_vec();
+    periods.sort();
+
+    let mut merged_periods: Vec<(NaiveTime, NaiveTime)> = vec![];
+    for (begin_time, end_time) in periods {
+        match merged_periods.last_mut() {
+            Some((_, last_end_time)) if begin_time <= *last_end_time => {
+                *last_end_time = (*last_end_time).max(end_time)
+            }
+            _ => merged_periods.push((begin_time, end_time)),
+        }
+    }
+
+    merged_periods
+}
+
+/// Returns the times allowed by the periods once the excepted ones are taken out, sorted and
+/// merged. The times are excepted from the beginning of an excepted period until its end, when
+/// the periods resume, or until the end of the day for one ending at 23:59:59. An excepted
+/// period ending before it begins, such as 22:00~06:00, excepts both ends of the day.
+pub fn subtract_periods(
+    periods: &[(NaiveTime, NaiveTime)],
+    excepted_periods: &[(NaiveTime, NaiveTime)],
+) -> Vec<(NaiveTime, NaiveTime)> {
+    let end_of_day = NaiveTime::from_hms(23, 59, 59);
+    let mut remaining_periods = merge_periods(periods);
+    for &(except_begin_time, except_end_time) in excepted_periods {
+        let excepted_ranges = match except_begin_time <= except_end_time {
+            true => vec![(except_begin_time, except_end_time)],
+            false => vec![
+                (NaiveTime::from_hms(0, 0, 0), except_end_time),
+                (except_begin_time, end_of_day),
+            ],
+        };
+        for (except_begin_time, except_end_time) in excepted_ranges {
+            let resumes = except_end_time != end_of_day;
+            remaining_periods = remaining_periods
+                .into_iter()
+                .flat_map(|(begin_time, end_time)| {
+                    let overlaps =
+                        except_begin_time <= end_time && (except_end_time > begin_time || !resumes);
+                    if !overlaps {
+                        return vec![(begin_time, end_time)];
+                    }
+
+                    let mut parts = vec![];
+                    if begin_time < except_begin_time {
+                        parts.push((begin_time, except_begin_time - Duration::seconds(1)));
+                    }
+                    if resumes && except_end_time <= end_time {
+                        parts.push((except_end_time, end_time));
+                    }
+                    parts
+                })
+                .collect();
+        }
+    }
+
+    remaining_periods
+}
+
+/// Returns the second of the week of the time, from Monday at midnight.
+pub fn second_of_week(date_time: &NaiveDateTime) -> u32 {
+    date_time.weekday().num_days_from_monday() * SECONDS_PER_DAY
+        + date_time.time().num_seconds_from_midnight()
+}
+
+/// Returns the first time after `date_time` at one of the `boundaries`, seconds of the week
+/// sorted in ascending order, wrapping around to the next week. There is none without
+/// boundaries.
+pub fn next_boundary(boundaries: &[u32], date_time: &NaiveDateTime) -> Option<NaiveDateTime> {
+    let first_boundary = *boundaries.first()?;
+    let second_of_week = second_of_week(date_time);
+    let index = boundaries.partition_point(|&boundary| boundary <= second_of_week);
+    let seconds_until_boundary = match boundaries.get(index) {
+        Some(boundary) => boundary - second_of_week,
+        None => first_boundary + SECONDS_PER_WEEK - second_of_week,
+    };
+
+    Some(date_time.with_nanosecond(0)? + Duration::seconds(seconds_until_boundary as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_rules, parse_rules, Verdict};
+    use chrono::NaiveDate;
+
+    /// Xorshift generator, the inputs of the property tests being reproducible.
+    fn pseudo_random(state: &mut u64) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        (*state >> 32) as u32
+    }
+
+    #[test]
+    fn semantics_properties() {
+        let mut state = 0x5757_7974;
+        let random_time = |state: &mut u64| {
+            NaiveTime::from_num_seconds_from_midnight(pseudo_random(state) % 86_400, 0)
+        };
+        // The periods of the rules are given in minutes
+        let random_minute = |state: &mut u64| random_time(state).with_second(0).unwrap();
+
+        for _ in 0..200 {
+            let periods: Vec<(NaiveTime, NaiveTime)> = (0..pseudo_random(&mut state) % 6)
+                .map(|_| {
+                    let (begin_time, end_time) =
+                        (random_minute(&mut state), random_minute(&mut state));
+                    (begin_time.min(end_time), begin_time.max(end_time))
+                })
+                .collect();
+            let merged_periods = merge_periods(&periods);
+            assert!(merged_periods.windows(2).all(|pair| pair[0].1 < pair[1].0));
+
+            let rules_string = match periods.is_empty() {
+                true => "game=00:00~00:00;TU".to_string(),
+                false => {
+                    let period_times: Vec<String> = periods
+                        .iter()
+                        .map(|(begin_time, end_time)| {
+                            format!(
+                                "{}~{}",
+                                begin_time.format("%H:%M"),
+                                end_time.format("%H:%M")
+                            )
+                        })
+                        .collect();
+                    format!("game={};MO", period_times.join(","))
+                }
+            };
+            let rules = parse_rules(&rules_string).unwrap();
+            let monday = NaiveDate::from_ymd(2020, 6, 1);
+            for _ in 0..50 {
+                let time = random_time(&mut state);
+                let contained = periods
+                    .iter()
+                    .any(|&(begin_time, end_time)| period_contains(begin_time, end_time, time));
+                assert_eq!(
+                    merged_periods.iter().any(|&(begin_time, end_time)| {
+                        period_contains(begin_time, end_time, time)
+                    }),
+                    contained
+                );
+                let verdict = evaluate_rules(&rules, "game", &monday.and_time(time));
+                assert_eq!(verdict == Verdict::Allowed, contained);
+            }
+
+            let date_time = monday.and_time(random_time(&mut state))
+                + chrono::Duration::days(i64::from(pseudo_random(&mut state) % 7));
+            let next_boundary = match rules.next_boundary(&date_time) {
+                Some(next_boundary) => next_boundary,
+                None => continue,
+            };
+            assert!(next_boundary > date_time);
+            assert!(next_boundary <= date_time + chrono::Duration::weeks(1));
+            let allowed = |date_time: &NaiveDateTime| evaluate_rules(&rules, "game", date_time);
+            assert_eq!(
+                allowed(&date_time),
+                allowed(&(next_boundary - chrono::Duration::seconds(1)))
+            );
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::Configuration;
+use std::collections::HashSet;
+#[cfg(target_os = "linux")]
+use std::fs;
+
+/// Returns why the rules aren't enforced on the process because of its session, if they
+/// aren't. A user policy only applies to the session of the daemon, so a daemon running with
+/// the privileges to kill the processes of other users, as an administrator of a Windows
+/// terminal server, doesn't enforce it on them.
+#[cfg_attr(not(any(feature = "native", feature = "process")), allow(dead_code))]
+pub(crate) fn session_exemption(configuration: &Configuration, pid: u32) -> Option<&'static str> {
+    if configuration.interactive_sessions_only() && !is_interactive_session(pid) {
+        Some("outside of interactive sessions")
+    } else if !configuration.managed() && !is_daemon_session(pid) {
+        Some("outside of the session of the daemon")
+    } else {
+        None
+    }
+}
+
+/// Returns whether the process belongs to a local login session, as opposed to a remote
+/// (SSH) session or a system service. It is determined with the systemd-logind session of
+/// the process, processes are considered interactive on other platforms.
+#[cfg(target_os = "linux")]
+pub fn is_interactive_session(pid: u32) -> bool {
+    let session_id = match session_id(pid) {
+        Some(session_id) => session_id,
+        None => return false,
+    };
+    let session = match fs::read_to_string(format!("/run/systemd/sessions/{}", session_id)) {
+        Ok(session) => session,
+        Err(_) => return false,
+    };
+
+    !session.lines().any(|line| {
+        matches!(
+            line.split_once('='),
+            Some(("REMOTE", "1")) | Some(("SERVICE", "sshd"))
+        )
+    })
+}
+
+/// Returns whether the process belongs to a user session, local or remote (RDP), as opposed
+/// to session 0 which holds the services.
+#[cfg(all(windows, feature = "native"))]
+pub fn is_interactive_session(pid: u32) -> bool {
+    windows_session_id(pid).is_some_and(|session_id| session_id != 0)
+}
+
+#[cfg(not(any(target_os = "linux", all(windows, feature = "native"))))]
+pub fn is_interactive_session(_pid: u32) -> bool {
+    true
+}
+
+/// Returns whether the process belongs to the Windows session of the daemon. Each user logged
+/// on a multi-session machine has their own. Elsewhere, the processes of other users can only
+/// be killed by root, which enforces a managed policy on all of them.
+#[cfg(all(windows, feature = "native"))]
+pub fn is_daemon_session(pid: u32) -> bool {
+    windows_session_id(pid) == windows_session_id(std::process::id())
+}
+
+#[cfg(not(all(windows, feature = "native")))]
+pub fn is_daemon_session(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(all(windows, feature = "native"))]
+fn windows_session_id(pid: u32) -> Option<u32> {
+    use winapi::um::processthreadsapi::ProcessIdToSessionId;
+
+    let mut session_id = 0;
+    match unsafe { ProcessIdToSessionId(pid, &mut session_id) } {
+        0 => None,
+        _ => Some(session_id),
+    }
+}
+
+/// Returns the pids holding a systemd-logind inhibitor in block mode, taken for critical
+/// operations such as burning a disc or installing updates. There are none on other
+/// platforms.
+#[cfg(target_os = "linux")]
+pub fn blocking_inhibitor_pids() -> HashSet<u32> {
+    let entries = match fs::read_dir("/run/systemd/inhibit") {
+        Ok(entries) => entries,
+        Err(_) => return HashSet::new(),
+    };
+
+    // The directory also holds the FIFOs referencing the inhibitors, which would block reads
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter(|inhibitor| inhibitor.lines().any(|line| line == "MODE=block"))
+        .filter_map(|inhibitor| {
+            inhibitor
+                .lines()
+                .find_map(|line| line.strip_prefix("PID="))?
+                .parse()
+                .ok()
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn blocking_inhibitor_pids() -> HashSet<u32> {
+    HashSet::new()
+}
+
+#[cfg(target_os = "linux")]
+fn session_id(pid: u32) -> Option<String> {
+    let cgroup = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    cgroup
+        .lines()
+        .flat_map(|line| line.split('/'))
+        .find_map(|unit| unit.strip_prefix("session-")?.strip_suffix(".scope"))
+        .map(String::from)
+}
@@ -0,0 +1,210 @@
+use crate::{Configuration, Rules, ANY_PROCESS_NAME};
+
+/// Textual state of a policy, compared to the one of the previous policy on reload to log
+/// what changed. Its text is redacted by the `Redactor` of the configuration, a change of a
+/// redacted secret alone going unnoticed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicySnapshot {
+    settings: Vec<(String, String)>,
+    rules: Vec<(String, String)>,
+}
+
+impl PolicySnapshot {
+    pub fn new(configuration: &Configuration, rules: &Rules) -> Self {
+        let settings = vec![
+            ("check_interval", configuration.check_interval().to_string()),
+            (
+                "interactive_sessions_only",
+                configuration.interactive_sessions_only().to_string(),
+            ),
+            ("managed", configuration.managed().to_string()),
+            (
+                "on_reload_error",
+                configuration.on_reload_error().to_string(),
+            ),
+            ("on_scan_error", configuration.on_scan_error().to_string()),
+            ("scan_workers", configuration.scan_workers().to_string()),
+            ("low_power", configuration.low_power().to_string()),
+            (
+                "low_power_check_interval",
+                configuration.low_power_check_interval().to_string(),
+            ),
+            (
+                "dst_repeated_time",
+                configuration.dst_repeated_time().to_string(),
+            ),
+            (
+                "renamed_binaries",
+                configuration.renamed_binaries().to_string(),
+            ),
+            (
+                "lockdown_processes",
+                configuration.lockdown_processes().join(","),
+            ),
+            (
+                "unsaved_work_grace",
+                configuration.unsaved_work_grace().to_string(),
+            ),
+            (
+                "terminate_grace",
+                configuration.terminate_grace().to_string(),
+            ),
+            (
+                "inhibitor_max_deferral",
+                configuration.inhibitor_max_deferral().to_string(),
+            ),
+            (
+                "busy_calendar",
+                configuration
+                    .busy_calendar()
+                    .map_or(String::new(), |path| path.display().to_string()),
+            ),
+            ("busy_processes", configuration.busy_processes().join(",")),
+            (
+                "do_not_disturb_processes",
+                configuration.do_not_disturb_processes().join(","),
+            ),
+            (
+                "protected_processes",
+                configuration.protected_processes().join(","),
+            ),
+            (
+                "override_requests_per_day",
+                configuration.override_requests_per_day().to_string(),
+            ),
+            (
+                "override_request_expiry",
+                configuration.override_request_expiry().to_string(),
+            ),
+            (
+                "override_cooldown",
+                configuration.override_cooldown().to_string(),
+            ),
+            (
+                "time_server",
+                configuration.time_server().unwrap_or_default().to_string(),
+            ),
+            (
+                "report_command",
+                configuration
+                    .report_command()
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+            (
+                "notify_command",
+                configuration
+                    .notify_command()
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+            ("max_clock_skew", configuration.max_clock_skew().to_string()),
+            (
+                "stats_retention",
+                configuration.stats_retention().to_string(),
+            ),
+            (
+                "anonymous_stats",
+                configuration.anonymous_stats().to_string(),
+            ),
+            (
+                "audit_retention",
+                configuration.audit_retention().to_string(),
+            ),
+            (
+                "loosening_approval",
+                configuration.loosening_approval().to_string(),
+            ),
+            (
+                "redact_pattern",
+                configuration.redactor().patterns().join(" "),
+            ),
+        ];
+        let redactor = configuration.redactor();
+        let rules = rules
+            .rules
+            .iter()
+            .map(|rule| {
+                let rule_line = redactor.redact(&rule.to_string());
+                // The rules applying to any process are only told apart by their conditions
+                let key = if rule.process_name == ANY_PROCESS_NAME {
+                    rule_line.clone()
+                } else {
+                    redactor.redact(&rule.process_name)
+                };
+                (key, rule_line)
+            })
+            .collect();
+
+        PolicySnapshot {
+            settings: settings
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), redactor.redact(&value)))
+                .collect(),
+            rules,
+        }
+    }
+
+    /// Returns a digest of the policy, stable across builds to be stored. The settings left to
+    /// their default are left out, so adding a setting doesn't change the digest.
+    pub(crate) fn digest(&self) -> u64 {
+        let default_settings =
+            PolicySnapshot::new(&Configuration::default(), &Rules::new()).settings;
+        let settings = self
+            .settings
+            .iter()
+            .filter(|setting| !default_settings.contains(setting));
+        // 64-bit FNV-1a
+        let mut digest: u64 = 0xcbf2_9ce4_8422_2325;
+        for (key, value) in settings.chain(&self.rules) {
+            for byte in key
+                .bytes()
+                .chain([b'='])
+                .chain(value.bytes())
+                .chain([b'\n'])
+            {
+                digest = (digest ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        digest
+    }
+
+    /// Describes the changes from this snapshot to the given one, one per line.
+    pub fn diff(&self, snapshot: &PolicySnapshot) -> Vec<String> {
+        let mut changes = self.diff_settings(snapshot);
+        for (key, rule_line) in &snapshot.rules {
+            match find_rule_line(&self.rules, key) {
+                None => changes.push(format!("Added rule {}", rule_line)),
+                Some(old_rule_line) if old_rule_line != rule_line => {
+                    changes.push(format!("Changed rule {} to {}", old_rule_line, rule_line))
+                }
+                _ => (),
+            }
+        }
+        for (key, rule_line) in &self.rules {
+            if find_rule_line(&snapshot.rules, key).is_none() {
+                changes.push(format!("Removed rule {}", rule_line));
+            }
+        }
+
+        changes
+    }
+
+    /// Describes the changes of the settings only, see `diff`.
+    pub(crate) fn diff_settings(&self, snapshot: &PolicySnapshot) -> Vec<String> {
+        let mut changes = vec![];
+        for ((key, value), (_, new_value)) in self.settings.iter().zip(&snapshot.settings) {
+            if value != new_value {
+                changes.push(format!("Changed {} from {} to {}", key, value, new_value));
+            }
+        }
+        changes
+    }
+}
+
+fn find_rule_line<'a>(rules: &'a [(String, String)], key: &str) -> Option<&'a String> {
+    rules
+        .iter()
+        .find(|(rule_key, _)| rule_key == key)
+        .map(|(_, rule_line)| rule_line)
+}
@@ -0,0 +1,663 @@
+use crate::budget::{read_budget_usages, write_budget_usages};
+use crate::{BudgetUsage, Configuration, EnforcementEvent, SwytError};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "sqlite")]
+use std::convert::TryFrom;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::{Chars, FromStr};
+
+const ACTION_COUNTS_FILE_NAME: &str = "action_counts.jbb";
+const ACTION_COUNTS_DATE_FORMAT: &str = "%Y-%m-%d";
+const EVENTS_FILE_NAME: &str = "events.jsonl";
+const STATS_DATABASE_FILE_NAME: &str = "stats.sqlite";
+const STATS_DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Where the statistics of the daemon are kept, the actions taken on processes and the
+/// budget usage. The store is chosen at compile time, see `DefaultStatsStore`.
+pub trait StatsStore {
+    fn record_events(&mut self, events: &[EnforcementEvent]) -> Result<(), SwytError>;
+
+    /// Returns the actions taken on processes from `since`, the oldest first.
+    fn events_since(&self, since: &NaiveDateTime) -> Result<Vec<EnforcementEvent>, SwytError>;
+
+    /// Replaces the budget usage with the given one.
+    fn write_budget_usages(&mut self, usages: &[BudgetUsage]) -> Result<(), SwytError>;
+
+    /// Returns the budget usage last written, empty when none was.
+    fn read_budget_usages(&self) -> Result<Vec<BudgetUsage>, SwytError>;
+
+    /// Removes the actions taken on processes before `before`, returning how many were.
+    fn prune_events(&mut self, before: &NaiveDateTime) -> Result<usize, SwytError>;
+
+    /// Removes all the actions taken on processes, returning how many there were.
+    fn clear_events(&mut self) -> Result<usize, SwytError>;
+}
+
+/// SQLite with the `sqlite` feature, flat files otherwise.
+#[cfg(feature = "sqlite")]
+pub type DefaultStatsStore = SqliteStatsStore;
+#[cfg(not(feature = "sqlite"))]
+pub type DefaultStatsStore = FlatFileStatsStore;
+
+/// Opens the store of this build of swyt in the swyt directory.
+pub fn open_stats_store(swyt_filepath: &Path) -> Result<DefaultStatsStore, SwytError> {
+    DefaultStatsStore::open(swyt_filepath)
+}
+
+/// Opens the store the configuration chooses in the swyt directory, the anonymous one with
+/// `anonymous_stats=true`, the one of this build of swyt otherwise.
+pub fn open_configured_stats_store(
+    swyt_filepath: &Path,
+    configuration: &Configuration,
+) -> Result<Box<dyn StatsStore>, SwytError> {
+    Ok(match configuration.anonymous_stats() {
+        true => Box::new(AnonymousStatsStore::open(swyt_filepath)?),
+        false => Box::new(open_stats_store(swyt_filepath)?),
+    })
+}
+
+/// Keeps the actions taken on processes in *events.jsonl*, one JSON object a line, and the
+/// budget usage in *budget_usage.jbb*, for installations without SQLite.
+pub struct FlatFileStatsStore {
+    swyt_filepath: PathBuf,
+}
+
+impl FlatFileStatsStore {
+    pub fn open(swyt_filepath: &Path) -> Result<Self, SwytError> {
+        Ok(FlatFileStatsStore {
+            swyt_filepath: swyt_filepath.to_path_buf(),
+        })
+    }
+
+    /// Returns all the actions recorded, the oldest first.
+    fn read_events(&self) -> Result<Vec<EnforcementEvent>, SwytError> {
+        match fs::read_to_string(get_events_filepath(&self.swyt_filepath)) {
+            Ok(events_string) => events_string.lines().map(parse_event).collect(),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(vec![]),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl StatsStore for FlatFileStatsStore {
+    fn record_events(&mut self, events: &[EnforcementEvent]) -> Result<(), SwytError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let lines: Vec<String> = events
+            .iter()
+            .map(|event| format!("{}\n", event.to_json()))
+            .collect();
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(get_events_filepath(&self.swyt_filepath))?
+            .write_all(lines.concat().as_bytes())?;
+        Ok(())
+    }
+
+    fn events_since(&self, since: &NaiveDateTime) -> Result<Vec<EnforcementEvent>, SwytError> {
+        let mut events = self.read_events()?;
+        events.retain(|event| event.date_time() >= since);
+        Ok(events)
+    }
+
+    fn write_budget_usages(&mut self, usages: &[BudgetUsage]) -> Result<(), SwytError> {
+        write_budget_usages(&self.swyt_filepath, usages)
+    }
+
+    fn read_budget_usages(&self) -> Result<Vec<BudgetUsage>, SwytError> {
+        read_budget_usages(&self.swyt_filepath)
+    }
+
+    fn prune_events(&mut self, before: &NaiveDateTime) -> Result<usize, SwytError> {
+        let events = self.read_events()?;
+        let kept_lines: Vec<String> = events
+            .iter()
+            .filter(|event| event.date_time() >= before)
+            .map(|event| format!("{}\n", event.to_json()))
+            .collect();
+        let pruned = events.len() - kept_lines.len();
+        if pruned > 0 {
+            fs::write(
+                get_events_filepath(&self.swyt_filepath),
+                kept_lines.concat(),
+            )?;
+        }
+        Ok(pruned)
+    }
+
+    fn clear_events(&mut self) -> Result<usize, SwytError> {
+        let cleared = self.read_events()?.len();
+        match fs::remove_file(get_events_filepath(&self.swyt_filepath)) {
+            Ok(()) => Ok(cleared),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Only keeps how many times each action was taken on each process each day, in
+/// *action_counts.jbb*, never the times, the pids nor the command lines of the actions. The
+/// actions are read back at the start of their day, without pid. The budget usage, already
+/// counted by day, is kept in the store of this build so switching stores doesn't refill the
+/// budgets.
+pub struct AnonymousStatsStore {
+    swyt_filepath: PathBuf,
+    budget_store: DefaultStatsStore,
+}
+
+impl AnonymousStatsStore {
+    pub fn open(swyt_filepath: &Path) -> Result<Self, SwytError> {
+        Ok(AnonymousStatsStore {
+            swyt_filepath: swyt_filepath.to_path_buf(),
+            budget_store: DefaultStatsStore::open(swyt_filepath)?,
+        })
+    }
+
+    /// Returns how many times each action was taken on each process, by day, process name
+    /// and action.
+    fn read_counts(&self) -> Result<BTreeMap<(NaiveDate, String, String), usize>, SwytError> {
+        let counts_string =
+            match fs::read_to_string(get_action_counts_filepath(&self.swyt_filepath)) {
+                Ok(counts_string) => counts_string,
+                Err(err) if err.kind() == ErrorKind::NotFound => return Ok(BTreeMap::new()),
+                Err(err) => return Err(err.into()),
+            };
+
+        counts_string
+            .lines()
+            .map(|line| match line.splitn(4, ';').collect::<Vec<_>>()[..] {
+                [date, action, count, process_name] => Ok((
+                    (
+                        NaiveDate::parse_from_str(date, ACTION_COUNTS_DATE_FORMAT)
+                            .map_err(|_| SwytError::StateParseError)?,
+                        process_name.to_string(),
+                        action.to_string(),
+                    ),
+                    usize::from_str(count).map_err(|_| SwytError::StateParseError)?,
+                )),
+                _ => Err(SwytError::StateParseError),
+            })
+            .collect()
+    }
+
+    fn write_counts(
+        &self,
+        counts: &BTreeMap<(NaiveDate, String, String), usize>,
+    ) -> Result<(), SwytError> {
+        let lines: Vec<String> = counts
+            .iter()
+            .map(|((date, process_name, action), count)| {
+                format!(
+                    "{};{};{};{}\n",
+                    date.format(ACTION_COUNTS_DATE_FORMAT),
+                    action,
+                    count,
+                    process_name
+                )
+            })
+            .collect();
+        fs::write(
+            get_action_counts_filepath(&self.swyt_filepath),
+            lines.concat(),
+        )?;
+        Ok(())
+    }
+}
+
+impl StatsStore for AnonymousStatsStore {
+    fn record_events(&mut self, events: &[EnforcementEvent]) -> Result<(), SwytError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut counts = self.read_counts()?;
+        for event in events {
+            let key = (
+                event.date_time().date(),
+                event.process_name().to_string(),
+                event.action().to_string(),
+            );
+            *counts.entry(key).or_default() += 1;
+        }
+        self.write_counts(&counts)
+    }
+
+    /// The actions of the day of `since` are all returned, their times being unknown.
+    fn events_since(&self, since: &NaiveDateTime) -> Result<Vec<EnforcementEvent>, SwytError> {
+        let mut events = vec![];
+        for ((date, process_name, action), count) in self.read_counts()? {
+            if date >= since.date() {
+                let event = EnforcementEvent::new(date.and_hms(0, 0, 0), process_name, 0, action);
+                events.extend(std::iter::repeat_n(event, count));
+            }
+        }
+        Ok(events)
+    }
+
+    fn write_budget_usages(&mut self, usages: &[BudgetUsage]) -> Result<(), SwytError> {
+        self.budget_store.write_budget_usages(usages)
+    }
+
+    fn read_budget_usages(&self) -> Result<Vec<BudgetUsage>, SwytError> {
+        self.budget_store.read_budget_usages()
+    }
+
+    /// The actions of the day of `before` are all kept, their times being unknown.
+    fn prune_events(&mut self, before: &NaiveDateTime) -> Result<usize, SwytError> {
+        let mut counts = self.read_counts()?;
+        let total = counts.values().sum::<usize>();
+        counts.retain(|(date, _, _), _| *date >= before.date());
+        let pruned = total - counts.values().sum::<usize>();
+        if pruned > 0 {
+            self.write_counts(&counts)?;
+        }
+        Ok(pruned)
+    }
+
+    fn clear_events(&mut self) -> Result<usize, SwytError> {
+        let cleared = self.read_counts()?.values().sum();
+        match fs::remove_file(get_action_counts_filepath(&self.swyt_filepath)) {
+            Ok(()) => Ok(cleared),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Keeps the statistics in the SQLite database *stats.sqlite*.
+#[cfg(feature = "sqlite")]
+pub struct SqliteStatsStore {
+    connection: rusqlite::Connection,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStatsStore {
+    pub fn open(swyt_filepath: &Path) -> Result<Self, SwytError> {
+        let connection = rusqlite::Connection::open(swyt_filepath.join(STATS_DATABASE_FILE_NAME))?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS events (
+                date_time TEXT NOT NULL,
+                process_name TEXT NOT NULL,
+                pid INTEGER NOT NULL,
+                action TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS events_date_time ON events (date_time);
+            CREATE TABLE IF NOT EXISTS budget_usage (
+                process_name TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                used INTEGER NOT NULL
+            );",
+        )?;
+        // The databases created before the command lines were recorded lack their column
+        if connection
+            .prepare("SELECT command_line FROM events LIMIT 0")
+            .is_err()
+        {
+            connection.execute("ALTER TABLE events ADD COLUMN command_line TEXT", [])?;
+        }
+        Ok(SqliteStatsStore { connection })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl StatsStore for SqliteStatsStore {
+    fn record_events(&mut self, events: &[EnforcementEvent]) -> Result<(), SwytError> {
+        let transaction = self.connection.transaction()?;
+        for event in events {
+            transaction.execute(
+                "INSERT INTO events (date_time, process_name, pid, action, command_line)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![
+                    event.date_time().format(STATS_DATE_TIME_FORMAT).to_string(),
+                    event.process_name(),
+                    event.pid(),
+                    event.action(),
+                    event.command_line()
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn events_since(&self, since: &NaiveDateTime) -> Result<Vec<EnforcementEvent>, SwytError> {
+        // The dates are written so that they sort like the text
+        let mut statement = self.connection.prepare(
+            "SELECT date_time, process_name, pid, action, command_line FROM events
+            WHERE date_time >= ?1 ORDER BY date_time, rowid",
+        )?;
+        let rows =
+            statement.query_map([since.format(STATS_DATE_TIME_FORMAT).to_string()], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, u32>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?;
+
+        let mut events = vec![];
+        for row in rows {
+            let (date_time, process_name, pid, action, command_line) = row?;
+            events.push(
+                EnforcementEvent::new(parse_date_time(&date_time)?, process_name, pid, action)
+                    .with_command_line(command_line),
+            );
+        }
+        Ok(events)
+    }
+
+    fn write_budget_usages(&mut self, usages: &[BudgetUsage]) -> Result<(), SwytError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM budget_usage", [])?;
+        for usage in usages {
+            transaction.execute(
+                "INSERT INTO budget_usage (process_name, date, used) VALUES (?1, ?2, ?3)",
+                rusqlite::params![
+                    usage.process_name(),
+                    usage.date().to_string(),
+                    usage.used().as_secs() as i64
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn read_budget_usages(&self) -> Result<Vec<BudgetUsage>, SwytError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT process_name, date, used FROM budget_usage")?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+
+        let mut usages = vec![];
+        for row in rows {
+            let (process_name, date, used) = row?;
+            usages.push(BudgetUsage::new(
+                chrono::NaiveDate::from_str(&date).map_err(|_| SwytError::StateParseError)?,
+                process_name,
+                std::time::Duration::from_secs(
+                    u64::try_from(used).map_err(|_| SwytError::StateParseError)?,
+                ),
+            ));
+        }
+        Ok(usages)
+    }
+
+    fn prune_events(&mut self, before: &NaiveDateTime) -> Result<usize, SwytError> {
+        Ok(self.connection.execute(
+            "DELETE FROM events WHERE date_time < ?1",
+            [before.format(STATS_DATE_TIME_FORMAT).to_string()],
+        )?)
+    }
+
+    fn clear_events(&mut self) -> Result<usize, SwytError> {
+        Ok(self.connection.execute("DELETE FROM events", [])?)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for SwytError {
+    fn from(sqlite_error: rusqlite::Error) -> Self {
+        SwytError::StatsStoreError(sqlite_error.to_string())
+    }
+}
+
+/// Parses an event as written by `EnforcementEvent::to_json`.
+fn parse_event(json: &str) -> Result<EnforcementEvent, SwytError> {
+    let mut fields = parse_json_object(json).ok_or(SwytError::StateParseError)?;
+    let mut field = |key: &str| fields.remove(key).ok_or(SwytError::StateParseError);
+    Ok(EnforcementEvent::new(
+        parse_date_time(&field("date_time")?)?,
+        field("process_name")?,
+        u32::from_str(&field("pid")?).map_err(|_| SwytError::StateParseError)?,
+        field("action")?,
+    )
+    .with_command_line(fields.remove("command_line")))
+}
+
+fn parse_date_time(date_time: &str) -> Result<NaiveDateTime, SwytError> {
+    NaiveDateTime::parse_from_str(date_time, STATS_DATE_TIME_FORMAT)
+        .map_err(|_| SwytError::StateParseError)
+}
+
+/// Parses a JSON object of strings and integers, the integers being kept as written.
+fn parse_json_object(json: &str) -> Option<HashMap<String, String>> {
+    let mut chars = json.trim().chars().peekable();
+    let skip_whitespace = |chars: &mut Peekable<Chars>| {
+        while chars
+            .next_if(|character| character.is_whitespace())
+            .is_some()
+        {}
+    };
+
+    let mut fields = HashMap::new();
+    (chars.next()? == '{').then_some(())?;
+    skip_whitespace(&mut chars);
+    if chars.next_if_eq(&'}').is_none() {
+        loop {
+            skip_whitespace(&mut chars);
+            let key = parse_json_string(&mut chars)?;
+            skip_whitespace(&mut chars);
+            (chars.next()? == ':').then_some(())?;
+            skip_whitespace(&mut chars);
+            let value = match chars.peek()? {
+                '"' => parse_json_string(&mut chars)?,
+                _ => {
+                    let mut number = String::new();
+                    while let Some(digit) =
+                        chars.next_if(|&character| character == '-' || character.is_ascii_digit())
+                    {
+                        number.push(digit);
+                    }
+                    number
+                }
+            };
+            fields.insert(key, value);
+            skip_whitespace(&mut chars);
+            match chars.next()? {
+                ',' => continue,
+                '}' => break,
+                _ => return None,
+            }
+        }
+    }
+
+    chars.next().is_none().then_some(fields)
+}
+
+fn parse_json_string(chars: &mut Peekable<Chars>) -> Option<String> {
+    (chars.next()? == '"').then_some(())?;
+    let mut string = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(string),
+            '\\' => string.push(match chars.next()? {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'u' => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    char::from_u32(u32::from_str_radix(&code, 16).ok()?)?
+                }
+                character => character,
+            }),
+            character => string.push(character),
+        }
+    }
+}
+
+pub(crate) fn get_action_counts_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(ACTION_COUNTS_FILE_NAME)
+}
+
+pub(crate) fn get_events_filepath(swyt_filepath: &Path) -> PathBuf {
+    swyt_filepath.join(EVENTS_FILE_NAME)
+}
+
+/// The database of the SQLite store, with its rollback journal.
+pub(crate) fn get_stats_database_filepaths(swyt_filepath: &Path) -> Vec<PathBuf> {
+    vec![
+        swyt_filepath.join(STATS_DATABASE_FILE_NAME),
+        swyt_filepath.join(format!("{}-journal", STATS_DATABASE_FILE_NAME)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn assert_stats_round_trip(stats_store: &mut impl StatsStore) {
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let events = vec![
+            EnforcementEvent::new(
+                monday.and_hms(18, 0, 0),
+                "game".to_string(),
+                42,
+                "kill".to_string(),
+            ),
+            EnforcementEvent::new(
+                monday.and_hms(19, 0, 0),
+                "dis\"cord\u{1}".to_string(),
+                43,
+                "minimize".to_string(),
+            )
+            .with_command_line(Some("discord --token=[REDACTED]".to_string())),
+        ];
+        stats_store.record_events(&events).unwrap();
+        assert_eq!(
+            stats_store.events_since(&monday.and_hms(0, 0, 0)).unwrap(),
+            events
+        );
+        assert_eq!(
+            stats_store
+                .events_since(&monday.and_hms(18, 30, 0))
+                .unwrap(),
+            events[1..]
+        );
+
+        let usages = vec![BudgetUsage::new(
+            monday,
+            "game".to_string(),
+            std::time::Duration::from_secs(600),
+        )];
+        stats_store.write_budget_usages(&usages).unwrap();
+        stats_store.write_budget_usages(&usages).unwrap();
+        assert_eq!(stats_store.read_budget_usages().unwrap(), usages);
+
+        assert_eq!(
+            stats_store
+                .prune_events(&monday.and_hms(18, 30, 0))
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            stats_store.events_since(&monday.and_hms(0, 0, 0)).unwrap(),
+            events[1..]
+        );
+        assert_eq!(stats_store.clear_events().unwrap(), 1);
+        assert!(stats_store
+            .events_since(&monday.and_hms(0, 0, 0))
+            .unwrap()
+            .is_empty());
+        assert_eq!(stats_store.read_budget_usages().unwrap(), usages);
+    }
+
+    #[test]
+    fn flat_file_stats_store() {
+        let swyt_filepath = std::env::temp_dir().join(format!("swyt-stats-{}", std::process::id()));
+        std::fs::create_dir_all(&swyt_filepath).unwrap();
+        assert_stats_round_trip(&mut FlatFileStatsStore::open(&swyt_filepath).unwrap());
+        std::fs::remove_dir_all(&swyt_filepath).unwrap();
+    }
+
+    #[test]
+    fn anonymous_stats_store() {
+        let swyt_filepath =
+            std::env::temp_dir().join(format!("swyt-anonymous-{}", std::process::id()));
+        std::fs::create_dir_all(&swyt_filepath).unwrap();
+        let mut stats_store = AnonymousStatsStore::open(&swyt_filepath).unwrap();
+        let monday = NaiveDate::from_ymd(2020, 6, 1);
+        let tuesday = NaiveDate::from_ymd(2020, 6, 2);
+        let event = |date_time: NaiveDateTime, pid: u32, action: &str| {
+            EnforcementEvent::new(date_time, "ga;me".to_string(), pid, action.to_string())
+                .with_command_line(Some("game --level 3".to_string()))
+        };
+        stats_store
+            .record_events(&[
+                event(monday.and_hms(18, 0, 0), 42, "kill"),
+                event(monday.and_hms(19, 0, 0), 43, "kill"),
+            ])
+            .unwrap();
+        stats_store
+            .record_events(&[event(tuesday.and_hms(18, 0, 0), 44, "notify")])
+            .unwrap();
+
+        // Only the counts by day are kept
+        let counts = std::fs::read_to_string(get_action_counts_filepath(&swyt_filepath)).unwrap();
+        assert_eq!(
+            counts,
+            "2020-06-01;kill;2;ga;me\n2020-06-02;notify;1;ga;me\n"
+        );
+        let anonymous_event = |date: NaiveDate, action: &str| {
+            EnforcementEvent::new(
+                date.and_hms(0, 0, 0),
+                "ga;me".to_string(),
+                0,
+                action.to_string(),
+            )
+        };
+        assert_eq!(
+            stats_store.events_since(&monday.and_hms(19, 0, 0)).unwrap(),
+            vec![
+                anonymous_event(monday, "kill"),
+                anonymous_event(monday, "kill"),
+                anonymous_event(tuesday, "notify"),
+            ]
+        );
+
+        assert_eq!(
+            stats_store
+                .prune_events(&tuesday.and_hms(12, 0, 0))
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            stats_store.events_since(&monday.and_hms(0, 0, 0)).unwrap(),
+            vec![anonymous_event(tuesday, "notify")]
+        );
+        assert_eq!(stats_store.clear_events().unwrap(), 1);
+        assert!(stats_store
+            .events_since(&monday.and_hms(0, 0, 0))
+            .unwrap()
+            .is_empty());
+        std::fs::remove_dir_all(&swyt_filepath).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_stats_store() {
+        let swyt_filepath =
+            std::env::temp_dir().join(format!("swyt-sqlite-{}", std::process::id()));
+        std::fs::create_dir_all(&swyt_filepath).unwrap();
+        assert_stats_round_trip(&mut SqliteStatsStore::open(&swyt_filepath).unwrap());
+        std::fs::remove_dir_all(&swyt_filepath).unwrap();
+    }
+}
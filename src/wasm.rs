@@ -0,0 +1,20 @@
+use crate::{evaluate_rules, parse_rules};
+use chrono::NaiveDateTime;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub fn validate_rules(rules: &str) -> Result<(), JsValue> {
+    parse_rules(rules)
+        .map(|_| ())
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn evaluate(rules: &str, process_name: &str, date_time: &str) -> Result<String, JsValue> {
+    let rules = parse_rules(rules).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let date_time = NaiveDateTime::from_str(date_time)
+        .map_err(|_| JsValue::from_str("Couldn't parse date time"))?;
+
+    Ok(evaluate_rules(&rules, process_name, &date_time).to_string())
+}
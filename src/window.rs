@@ -0,0 +1,334 @@
+#[cfg(windows)]
+pub use windows::{close_windows, fullscreen_pids, minimize, unsaved_work_pids};
+#[cfg(target_os = "linux")]
+pub use x11::{close_windows, fullscreen_pids, minimize, unsaved_work_pids};
+
+/// Returns whether a window title contains the pattern, ignoring case.
+fn title_matches(title: &str, title_pattern: &str) -> bool {
+    title.to_lowercase().contains(&title_pattern.to_lowercase())
+}
+
+/// Returns whether a window title marks its document as modified, as most editors do with a
+/// leading bullet or asterisk, or a trailing asterisk.
+fn title_shows_unsaved_work(title: &str) -> bool {
+    let title = title.trim();
+    title.starts_with(['*', '•', '●']) || title.ends_with('*')
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use crate::SwytError;
+    use std::collections::HashSet;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, Window};
+    use x11rb::rust_connection::RustConnection;
+
+    /// Value of the `WM_CHANGE_STATE` client message iconifying a window.
+    const ICONIC_STATE: u32 = 3;
+
+    /// Returns the pids owning a fullscreen window, as advertised by the window manager
+    /// through the EWMH `_NET_WM_STATE` and `_NET_WM_PID` properties.
+    pub fn fullscreen_pids() -> Result<HashSet<u32>, SwytError> {
+        let (connection, root) = connect()?;
+        let wm_state = intern_atom(&connection, b"_NET_WM_STATE")?;
+        let wm_state_fullscreen = intern_atom(&connection, b"_NET_WM_STATE_FULLSCREEN")?;
+
+        let mut pids = HashSet::new();
+        for (window, pid) in client_windows(&connection, root)? {
+            let is_fullscreen = property(&connection, window, wm_state, AtomEnum::ATOM)?
+                .contains(&wm_state_fullscreen);
+            if is_fullscreen {
+                pids.insert(pid);
+            }
+        }
+
+        Ok(pids)
+    }
+
+    /// Returns the pids owning a modal dialog or a window whose title marks a modified
+    /// document.
+    pub fn unsaved_work_pids() -> Result<HashSet<u32>, SwytError> {
+        let (connection, root) = connect()?;
+        let wm_state = intern_atom(&connection, b"_NET_WM_STATE")?;
+        let wm_state_modal = intern_atom(&connection, b"_NET_WM_STATE_MODAL")?;
+
+        let mut pids = HashSet::new();
+        for (window, pid) in client_windows(&connection, root)? {
+            let is_modal =
+                property(&connection, window, wm_state, AtomEnum::ATOM)?.contains(&wm_state_modal);
+            if is_modal || super::title_shows_unsaved_work(&title(&connection, window)?) {
+                pids.insert(pid);
+            }
+        }
+
+        Ok(pids)
+    }
+
+    /// Asks the window manager to iconify the windows of the process.
+    pub fn minimize(pid: u32) -> Result<(), SwytError> {
+        let (connection, root) = connect()?;
+        let wm_change_state = intern_atom(&connection, b"WM_CHANGE_STATE")?;
+
+        for (window, window_pid) in client_windows(&connection, root)? {
+            if window_pid != pid {
+                continue;
+            }
+
+            let event =
+                ClientMessageEvent::new(32, window, wm_change_state, [ICONIC_STATE, 0, 0, 0, 0]);
+            send_to_window_manager(&connection, root, event)?;
+        }
+
+        connection.flush().map_err(|_| SwytError::WindowFetchError)
+    }
+
+    /// Asks the window manager to close the windows of the process whose title matches the
+    /// pattern.
+    pub fn close_windows(pid: u32, title_pattern: &str) -> Result<(), SwytError> {
+        let (connection, root) = connect()?;
+        let close_window = intern_atom(&connection, b"_NET_CLOSE_WINDOW")?;
+
+        for (window, window_pid) in client_windows(&connection, root)? {
+            if window_pid != pid
+                || !super::title_matches(&title(&connection, window)?, title_pattern)
+            {
+                continue;
+            }
+
+            let event = ClientMessageEvent::new(32, window, close_window, [0, 0, 0, 0, 0]);
+            send_to_window_manager(&connection, root, event)?;
+        }
+
+        connection.flush().map_err(|_| SwytError::WindowFetchError)
+    }
+
+    fn send_to_window_manager(
+        connection: &RustConnection,
+        root: Window,
+        event: ClientMessageEvent,
+    ) -> Result<(), SwytError> {
+        connection
+            .send_event(
+                false,
+                root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )
+            .map_err(|_| SwytError::WindowFetchError)?;
+        Ok(())
+    }
+
+    fn connect() -> Result<(RustConnection, Window), SwytError> {
+        let (connection, screen_number) =
+            x11rb::connect(None).map_err(|_| SwytError::WindowFetchError)?;
+        let root = connection.setup().roots[screen_number].root;
+        Ok((connection, root))
+    }
+
+    /// Returns the windows managed by the window manager along with the pid owning them.
+    fn client_windows(
+        connection: &RustConnection,
+        root: Window,
+    ) -> Result<Vec<(Window, u32)>, SwytError> {
+        let client_list = intern_atom(connection, b"_NET_CLIENT_LIST")?;
+        let wm_pid = intern_atom(connection, b"_NET_WM_PID")?;
+
+        let mut windows = vec![];
+        for window in property(connection, root, client_list, AtomEnum::WINDOW)? {
+            if let Some(&pid) = property(connection, window, wm_pid, AtomEnum::CARDINAL)?.first() {
+                windows.push((window, pid));
+            }
+        }
+
+        Ok(windows)
+    }
+
+    fn title(connection: &RustConnection, window: Window) -> Result<String, SwytError> {
+        let wm_name = intern_atom(connection, b"_NET_WM_NAME")?;
+        let utf8_string = intern_atom(connection, b"UTF8_STRING")?;
+        let title = connection
+            .get_property(false, window, wm_name, utf8_string, 0, u32::MAX)
+            .map_err(|_| SwytError::WindowFetchError)?
+            .reply()
+            .map_err(|_| SwytError::WindowFetchError)?
+            .value;
+        Ok(String::from_utf8_lossy(&title).into_owned())
+    }
+
+    fn intern_atom(connection: &RustConnection, name: &[u8]) -> Result<u32, SwytError> {
+        Ok(connection
+            .intern_atom(false, name)
+            .map_err(|_| SwytError::WindowFetchError)?
+            .reply()
+            .map_err(|_| SwytError::WindowFetchError)?
+            .atom)
+    }
+
+    fn property(
+        connection: &RustConnection,
+        window: Window,
+        property: u32,
+        property_type: AtomEnum,
+    ) -> Result<Vec<u32>, SwytError> {
+        let reply = connection
+            .get_property(false, window, property, property_type, 0, u32::MAX)
+            .map_err(|_| SwytError::WindowFetchError)?
+            .reply()
+            .map_err(|_| SwytError::WindowFetchError)?;
+        Ok(reply.value32().map(Iterator::collect).unwrap_or_default())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use crate::SwytError;
+    use std::collections::HashSet;
+    use std::ffi::OsString;
+    use std::mem;
+    use std::os::windows::ffi::OsStringExt;
+    use winapi::shared::minwindef::{BOOL, LPARAM, TRUE};
+    use winapi::shared::windef::{HWND, RECT};
+    use winapi::um::winuser::{
+        EnumWindows, GetMonitorInfoW, GetShellWindow, GetWindowRect, GetWindowTextLengthW,
+        GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindowEnabled, IsWindowVisible,
+        MonitorFromWindow, PostMessageW, ShowWindow, MONITORINFO, MONITOR_DEFAULTTONULL,
+        SW_MINIMIZE, WM_CLOSE,
+    };
+
+    /// Returns the pids owning a visible window covering a whole monitor.
+    pub fn fullscreen_pids() -> Result<HashSet<u32>, SwytError> {
+        let mut pids = HashSet::new();
+        let enumerated =
+            unsafe { EnumWindows(Some(collect_fullscreen_pid), &mut pids as *mut _ as LPARAM) };
+        if enumerated == 0 {
+            return Err(SwytError::WindowFetchError);
+        }
+
+        Ok(pids)
+    }
+
+    /// Returns the pids owning a visible window whose title marks a modified document, or
+    /// which is disabled by a modal dialog.
+    pub fn unsaved_work_pids() -> Result<HashSet<u32>, SwytError> {
+        let mut pids = HashSet::new();
+        let enumerated = unsafe {
+            EnumWindows(
+                Some(collect_unsaved_work_pid),
+                &mut pids as *mut _ as LPARAM,
+            )
+        };
+        if enumerated == 0 {
+            return Err(SwytError::WindowFetchError);
+        }
+
+        Ok(pids)
+    }
+
+    /// Minimizes the visible windows of the process.
+    pub fn minimize(pid: u32) -> Result<(), SwytError> {
+        let mut pid = pid;
+        let enumerated =
+            unsafe { EnumWindows(Some(minimize_window), &mut pid as *mut _ as LPARAM) };
+        if enumerated == 0 {
+            return Err(SwytError::WindowFetchError);
+        }
+
+        Ok(())
+    }
+
+    /// Closes the visible windows of the process whose title matches the pattern.
+    pub fn close_windows(pid: u32, title_pattern: &str) -> Result<(), SwytError> {
+        let mut target = (pid, title_pattern);
+        let enumerated =
+            unsafe { EnumWindows(Some(close_window), &mut target as *mut _ as LPARAM) };
+        if enumerated == 0 {
+            return Err(SwytError::WindowFetchError);
+        }
+
+        Ok(())
+    }
+
+    unsafe extern "system" fn collect_fullscreen_pid(window: HWND, pids: LPARAM) -> BOOL {
+        let pids = &mut *(pids as *mut HashSet<u32>);
+        // The desktop window covers every monitor without being a fullscreen application
+        if IsWindowVisible(window) == 0 || window == GetShellWindow() {
+            return TRUE;
+        }
+
+        let monitor = MonitorFromWindow(window, MONITOR_DEFAULTTONULL);
+        if monitor.is_null() {
+            return TRUE;
+        }
+
+        let mut monitor_info: MONITORINFO = mem::zeroed();
+        monitor_info.cbSize = mem::size_of::<MONITORINFO>() as u32;
+        let mut window_rect: RECT = mem::zeroed();
+        if GetMonitorInfoW(monitor, &mut monitor_info) == 0
+            || GetWindowRect(window, &mut window_rect) == 0
+        {
+            return TRUE;
+        }
+
+        let monitor_rect = monitor_info.rcMonitor;
+        if window_rect.left <= monitor_rect.left
+            && window_rect.top <= monitor_rect.top
+            && window_rect.right >= monitor_rect.right
+            && window_rect.bottom >= monitor_rect.bottom
+        {
+            let mut pid = 0;
+            GetWindowThreadProcessId(window, &mut pid);
+            pids.insert(pid);
+        }
+
+        TRUE
+    }
+
+    unsafe extern "system" fn collect_unsaved_work_pid(window: HWND, pids: LPARAM) -> BOOL {
+        let pids = &mut *(pids as *mut HashSet<u32>);
+        if IsWindowVisible(window) == 0 {
+            return TRUE;
+        }
+
+        if IsWindowEnabled(window) == 0 || super::title_shows_unsaved_work(&window_title(window)) {
+            let mut pid = 0;
+            GetWindowThreadProcessId(window, &mut pid);
+            pids.insert(pid);
+        }
+
+        TRUE
+    }
+
+    unsafe extern "system" fn minimize_window(window: HWND, pid: LPARAM) -> BOOL {
+        let pid = *(pid as *const u32);
+        let mut window_pid = 0;
+        GetWindowThreadProcessId(window, &mut window_pid);
+        if window_pid == pid && IsWindowVisible(window) != 0 && IsIconic(window) == 0 {
+            ShowWindow(window, SW_MINIMIZE);
+        }
+
+        TRUE
+    }
+
+    unsafe extern "system" fn close_window(window: HWND, target: LPARAM) -> BOOL {
+        let &(pid, title_pattern) = &*(target as *const (u32, &str));
+        let mut window_pid = 0;
+        GetWindowThreadProcessId(window, &mut window_pid);
+        if window_pid != pid || IsWindowVisible(window) == 0 {
+            return TRUE;
+        }
+
+        if super::title_matches(&window_title(window), title_pattern) {
+            PostMessageW(window, WM_CLOSE, 0, 0);
+        }
+
+        TRUE
+    }
+
+    unsafe fn window_title(window: HWND) -> String {
+        let mut title = vec![0; GetWindowTextLengthW(window) as usize + 1];
+        let title_length = GetWindowTextW(window, title.as_mut_ptr(), title.len() as i32);
+        OsString::from_wide(&title[..title_length as usize])
+            .to_string_lossy()
+            .into_owned()
+    }
+}
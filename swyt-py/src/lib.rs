@@ -0,0 +1,256 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDate, PyDateTime};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use swyt::{evaluate_rules, StatsStore as _, SwytError};
+
+fn to_py_err(error: SwytError) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// Reads a `datetime.datetime`, or any object with its attributes, to the second.
+fn from_py_date_time(date_time: &Bound<'_, PyAny>) -> PyResult<NaiveDateTime> {
+    NaiveDate::from_ymd_opt(
+        date_time.getattr("year")?.extract()?,
+        date_time.getattr("month")?.extract()?,
+        date_time.getattr("day")?.extract()?,
+    )
+    .and_then(|date| {
+        date.and_hms_opt(
+            date_time.getattr("hour").ok()?.extract().ok()?,
+            date_time.getattr("minute").ok()?.extract().ok()?,
+            date_time.getattr("second").ok()?.extract().ok()?,
+        )
+    })
+    .ok_or_else(|| PyValueError::new_err("Invalid datetime"))
+}
+
+fn to_py_date_time<'py>(
+    py: Python<'py>,
+    date_time: &NaiveDateTime,
+) -> PyResult<Bound<'py, PyDateTime>> {
+    PyDateTime::new(
+        py,
+        date_time.year(),
+        date_time.month() as u8,
+        date_time.day() as u8,
+        date_time.hour() as u8,
+        date_time.minute() as u8,
+        date_time.second() as u8,
+        0,
+        None,
+    )
+}
+
+#[pyclass]
+struct Rules(swyt::Rules);
+
+#[pymethods]
+impl Rules {
+    fn process_names(&self) -> Vec<String> {
+        let mut process_names: Vec<String> = self.0.process_names().map(String::from).collect();
+        process_names.sort();
+        process_names
+    }
+
+    /// Returns "allowed", "blocked" or "unmatched" for the process at the given datetime.
+    fn evaluate(&self, process_name: &str, date_time: &Bound<'_, PyAny>) -> PyResult<String> {
+        let date_time = from_py_date_time(date_time)?;
+        Ok(evaluate_rules(&self.0, process_name, &date_time).to_string())
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[pyclass]
+struct Configuration(swyt::Configuration);
+
+#[pymethods]
+impl Configuration {
+    #[getter]
+    fn check_interval(&self) -> u32 {
+        self.0.check_interval()
+    }
+}
+
+/// Action taken on a process by the rules, as recorded by the daemon.
+#[pyclass]
+struct EnforcementEvent(swyt::EnforcementEvent);
+
+#[pymethods]
+impl EnforcementEvent {
+    #[getter]
+    fn date_time<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDateTime>> {
+        to_py_date_time(py, self.0.date_time())
+    }
+
+    #[getter]
+    fn process_name(&self) -> &str {
+        self.0.process_name()
+    }
+
+    #[getter]
+    fn pid(&self) -> u32 {
+        self.0.pid()
+    }
+
+    /// "kill", "minimize", "close", "notify", "throttle", "suspend" or "deny_exec".
+    #[getter]
+    fn action(&self) -> &str {
+        self.0.action()
+    }
+
+    /// The redacted command line, `None` unless rules match command lines.
+    #[getter]
+    fn command_line(&self) -> Option<&str> {
+        self.0.command_line()
+    }
+}
+
+/// Time the processes of a rule ran for on a day, counted against its budget.
+#[pyclass]
+struct BudgetUsage(swyt::BudgetUsage);
+
+#[pymethods]
+impl BudgetUsage {
+    #[getter]
+    fn date<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDate>> {
+        let date = self.0.date();
+        PyDate::new(py, date.year(), date.month() as u8, date.day() as u8)
+    }
+
+    #[getter]
+    fn process_name(&self) -> &str {
+        self.0.process_name()
+    }
+
+    #[getter]
+    fn used_seconds(&self) -> u64 {
+        self.0.used().as_secs()
+    }
+}
+
+/// Statistics recorded by the daemon, only to be read from Python. The SQLite connection
+/// can't be shared between threads, Python ones included.
+#[pyclass]
+struct StatsStore(Mutex<swyt::DefaultStatsStore>);
+
+impl StatsStore {
+    fn store(&self) -> MutexGuard<'_, swyt::DefaultStatsStore> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[pymethods]
+impl StatsStore {
+    /// Returns the actions taken on processes from the given datetime, the oldest first.
+    fn events_since(&self, since: &Bound<'_, PyAny>) -> PyResult<Vec<EnforcementEvent>> {
+        let since = from_py_date_time(since)?;
+        self.store()
+            .events_since(&since)
+            .map(|events| events.into_iter().map(EnforcementEvent).collect())
+            .map_err(to_py_err)
+    }
+
+    fn budget_usages(&self) -> PyResult<Vec<BudgetUsage>> {
+        self.store()
+            .read_budget_usages()
+            .map(|usages| usages.into_iter().map(BudgetUsage).collect())
+            .map_err(to_py_err)
+    }
+}
+
+#[pyfunction]
+fn parse_rules(rules: &str) -> PyResult<Rules> {
+    swyt::parse_rules(rules).map(Rules).map_err(to_py_err)
+}
+
+#[pyfunction]
+fn load_rules(swyt_filepath: PathBuf) -> PyResult<Rules> {
+    swyt::load_rules(&swyt_filepath)
+        .map(Rules)
+        .map_err(to_py_err)
+}
+
+#[pyfunction]
+fn load_config(swyt_filepath: PathBuf) -> PyResult<Configuration> {
+    swyt::load_config(&swyt_filepath)
+        .map(Configuration)
+        .map_err(to_py_err)
+}
+
+#[pyfunction]
+fn open_stats_store(swyt_filepath: PathBuf) -> PyResult<StatsStore> {
+    swyt::open_stats_store(&swyt_filepath)
+        .map(|stats_store| StatsStore(Mutex::new(stats_store)))
+        .map_err(to_py_err)
+}
+
+#[pymodule]
+#[pyo3(name = "swyt")]
+fn swyt_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Rules>()?;
+    module.add_class::<Configuration>()?;
+    module.add_class::<EnforcementEvent>()?;
+    module.add_class::<BudgetUsage>()?;
+    module.add_class::<StatsStore>()?;
+    module.add_function(wrap_pyfunction!(parse_rules, module)?)?;
+    module.add_function(wrap_pyfunction!(load_rules, module)?)?;
+    module.add_function(wrap_pyfunction!(load_config, module)?)?;
+    module.add_function(wrap_pyfunction!(open_stats_store, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn parse_and_evaluate() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let rules = parse_rules("game=18:00~20:00;MO").unwrap();
+            assert_eq!(rules.process_names(), vec!["game".to_string()]);
+            let monday_evening = PyDateTime::new(py, 2020, 6, 1, 19, 0, 0, 0, None).unwrap();
+            let monday_morning = PyDateTime::new(py, 2020, 6, 1, 9, 0, 0, 0, None).unwrap();
+            assert_eq!(rules.evaluate("game", &monday_evening).unwrap(), "allowed");
+            assert_eq!(rules.evaluate("game", &monday_morning).unwrap(), "blocked");
+            assert_eq!(
+                rules.evaluate("chat", &monday_morning).unwrap(),
+                "unmatched"
+            );
+            assert!(rules.evaluate("game", &py.None().into_bound(py)).is_err());
+            assert!(parse_rules("game=25:00~26:00;MO").is_err());
+        });
+    }
+
+    #[test]
+    fn stats_store_read_only() {
+        pyo3::prepare_freethreaded_python();
+        let swyt_filepath = std::env::temp_dir().join(format!("swyt-py-{}", std::process::id()));
+        std::fs::create_dir_all(&swyt_filepath).unwrap();
+        let date = NaiveDate::from_ymd_opt(2020, 6, 1).unwrap();
+        let usage = swyt::BudgetUsage::new(date, "game".to_string(), Duration::from_secs(600));
+        swyt::open_stats_store(&swyt_filepath)
+            .unwrap()
+            .write_budget_usages(&[usage])
+            .unwrap();
+
+        Python::with_gil(|py| {
+            let stats_store = open_stats_store(swyt_filepath.clone()).unwrap();
+            let usages = stats_store.budget_usages().unwrap();
+            assert_eq!(usages.len(), 1);
+            assert_eq!(usages[0].process_name(), "game");
+            assert_eq!(usages[0].used_seconds(), 600);
+            assert!(usages[0].date(py).is_ok());
+            let since = PyDateTime::new(py, 2020, 1, 1, 0, 0, 0, 0, None).unwrap();
+            assert!(stats_store.events_since(&since).unwrap().is_empty());
+        });
+        std::fs::remove_dir_all(&swyt_filepath).unwrap();
+    }
+}